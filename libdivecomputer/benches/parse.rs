@@ -0,0 +1,140 @@
+//! Parsing and post-processing throughput.
+//!
+//! The `parse` group re-parses saved dive records, as written by
+//! `DownloadOptions::keep_raw` or the `dive_parser` example's inputs, and
+//! the `sample_callback` group re-parses the one with the most samples,
+//! counting throughput per sample. Both only run when pointed at some:
+//!
+//! ```text
+//! BENCH_DEVICE="Shearwater Perdix" BENCH_DIVES=dives/ cargo bench --bench parse
+//! ```
+//!
+//! The other groups use a synthetic 100 000-sample dive and always run.
+
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use libdivecomputer::{Context, Dive, DiveSample, Fingerprint, LogLevel, StandaloneParser};
+
+const SAMPLES: u32 = 100_000;
+
+/// A long freedive session: one-second samples with a temperature every
+/// tenth and pressures for two tanks.
+fn synthetic_dive() -> Dive {
+    Dive {
+        samples: (0..SAMPLES)
+            .map(|i| DiveSample {
+                time: Duration::from_secs(u64::from(i)),
+                depth: 20.0 + 20.0 * (f64::from(i) / 60.0).sin(),
+                temperature: (i % 10 == 0).then_some(18.0),
+                pressure: vec![200.0 - f64::from(i) * 1e-3, 180.0],
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn records() -> Option<(String, Vec<Vec<u8>>)> {
+    let device = std::env::var("BENCH_DEVICE").ok()?;
+    let dir = PathBuf::from(std::env::var_os("BENCH_DIVES")?);
+    let records = std::fs::read_dir(dir)
+        .expect("BENCH_DIVES is not a readable directory")
+        .map(|entry| std::fs::read(entry.unwrap().path()).unwrap())
+        .collect();
+    Some((device, records))
+}
+
+fn parse(c: &mut Criterion) {
+    let Some((device, records)) = records() else {
+        eprintln!("parse: set BENCH_DEVICE and BENCH_DIVES to benchmark parsing");
+        return;
+    };
+    let ctx = Context::builder()
+        .log_level(LogLevel::None)
+        .build()
+        .unwrap();
+    let parser = StandaloneParser::for_model(&ctx, &device).unwrap();
+    let fingerprint = Fingerprint::default();
+
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(records.len() as u64));
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            for data in &records {
+                let _ = black_box(parser.parse(&fingerprint, data));
+            }
+        });
+    });
+    group.bench_function("parse_into", |b| {
+        let mut dive = Dive::default();
+        b.iter(|| {
+            for data in &records {
+                let _ = black_box(parser.parse_into(&fingerprint, data, &mut dive));
+            }
+        });
+    });
+    group.bench_function("parse_header", |b| {
+        b.iter(|| {
+            for data in &records {
+                let _ = black_box(parser.parse_header(&fingerprint, data));
+            }
+        });
+    });
+    group.finish();
+
+    // The per-sample hot path, measured on the longest real profile.
+    let Some((data, samples)) = records
+        .iter()
+        .filter_map(|data| Some((data, parser.parse(&fingerprint, data).ok()?.samples.len())))
+        .max_by_key(|&(_, samples)| samples)
+    else {
+        return;
+    };
+    let mut group = c.benchmark_group("sample_callback");
+    group.throughput(Throughput::Elements(samples as u64));
+    group.bench_function("parse", |b| {
+        b.iter(|| parser.parse(&fingerprint, black_box(data)))
+    });
+    group.bench_function("parse_into", |b| {
+        let mut dive = Dive::default();
+        b.iter(|| parser.parse_into(&fingerprint, black_box(data), &mut dive))
+    });
+    group.finish();
+}
+
+fn profile(c: &mut Criterion) {
+    let dive = synthetic_dive();
+    let mut group = c.benchmark_group("profile");
+    group.throughput(Throughput::Elements(u64::from(SAMPLES)));
+    group.bench_function("downsample", |b| {
+        b.iter(|| dive.downsample(black_box(1000)))
+    });
+    group.bench_function("resample", |b| {
+        b.iter(|| dive.resample(black_box(Duration::from_secs(10))))
+    });
+    group.finish();
+}
+
+fn serde(c: &mut Criterion) {
+    let dive = synthetic_dive();
+    let json = serde_json::to_vec(&dive).unwrap();
+    let mut group = c.benchmark_group("serde");
+    group.throughput(Throughput::Elements(u64::from(SAMPLES)));
+    group.bench_function("to_json", |b| {
+        b.iter(|| serde_json::to_vec(black_box(&dive)).unwrap())
+    });
+    group.bench_function("from_json", |b| {
+        b.iter_batched(
+            || json.as_slice(),
+            |json| serde_json::from_slice::<Dive>(json).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse, profile, serde);
+criterion_main!(benches);
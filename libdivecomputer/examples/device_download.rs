@@ -1,9 +1,10 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use clap::{Parser as ClapParser, ValueEnum};
 use libdivecomputer::{
-    Context, Descriptor, Device, DeviceEvent, Dive, DownloadOptions, Fingerprint, IoStream,
-    LogLevel, Result, Transport, scan,
+    Context, Descriptor, DeviceEvent, Dive, DownloadOptions, Fingerprint, LogLevel, Result,
+    Transport, scan,
 };
 use serde::{Deserialize, Serialize};
 
@@ -37,6 +38,14 @@ struct Args {
     /// Device fingerprint (hex string for incremental download)
     #[arg(long)]
     fingerprint: Option<String>,
+
+    /// Index of the device to use when the scan finds several
+    #[arg(short, long, default_value_t = 0)]
+    index: usize,
+
+    /// Scan timeout in seconds
+    #[arg(long, default_value_t = 5)]
+    scan_timeout: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -61,16 +70,20 @@ fn main() -> Result<()> {
 
     // Scan for devices.
     println!("Scanning {} devices...", args.transport);
-    let devices = scan(&ctx, args.transport).execute()?;
-    let device_info = devices
-        .into_iter()
-        .next()
-        .ok_or_else(|| libdivecomputer::LibError::DeviceError("No device found".into()))?;
+    let devices = scan(&ctx, args.transport)
+        .timeout(Duration::from_secs(args.scan_timeout))
+        .exhaustive(true)
+        .execute()?;
+    for (idx, device) in devices.iter().enumerate() {
+        println!("  [{idx}] {} ({})", device.name, device.connection);
+    }
+    let device_info = devices.into_iter().nth(args.index).ok_or_else(|| {
+        libdivecomputer::LibError::DeviceError(format!("No device at index {}", args.index))
+    })?;
 
     println!("Connecting to {}...", device_info.name);
 
-    let iostream = IoStream::open(&ctx, &device_info.connection)?;
-    let dev = Device::open(&ctx, &desc, iostream)?;
+    let dev = device_info.open(&ctx, &desc)?;
 
     let fp_bytes = args
         .fingerprint
@@ -94,7 +107,7 @@ fn main() -> Result<()> {
     let result = dev.download_dives(DownloadOptions {
         fingerprint: fp_bytes.as_ref(),
         on_event: Some(&mut on_event),
-        cancel_cb: None,
+        ..Default::default()
     });
 
     if result.has_errors() {
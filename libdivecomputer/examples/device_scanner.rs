@@ -1,5 +1,5 @@
 use clap::Parser as ClapParser;
-use libdivecomputer::{Context, Descriptor, LogLevel, Result, Transport, scan};
+use libdivecomputer::{Context, Descriptor, LogLevel, Result, Transport, TransportSet, scan};
 
 #[derive(ClapParser, Debug)]
 #[command(author, version, about = "Scan for dive computers", long_about = None)]
@@ -11,6 +11,10 @@ struct Args {
     /// Device transport (Serial, USB, BLE, etc.)
     #[arg(short = 't', long)]
     transport: Option<Transport>,
+
+    /// Keep scanning BLE and print devices as they appear, until interrupted
+    #[arg(short, long)]
+    continuous: bool,
 }
 
 fn main() -> Result<()> {
@@ -25,25 +29,46 @@ fn main() -> Result<()> {
 
     let ctx = Context::builder().log_level(LogLevel::Warning).build()?;
 
+    let descriptor = match args.device {
+        Some(ref device_name) => match Descriptor::find_by_name(device_name) {
+            Ok(desc) => Some(desc),
+            Err(_) => {
+                eprintln!("Device '{}' not found in descriptor database", device_name);
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
     // Determine which transports to scan.
     let transports = if let Some(transport) = args.transport {
-        vec![transport]
-    } else if let Some(ref device_name) = args.device {
-        // Find the descriptor and use its supported transports.
-        if let Ok(desc) = Descriptor::find_by_name(device_name) {
-            desc.transport_list()
-        } else {
-            eprintln!("Device '{}' not found in descriptor database", device_name);
-            return Ok(());
-        }
+        TransportSet::from(transport)
+    } else if let Some(ref desc) = descriptor {
+        // Use the descriptor's transports that work on this host.
+        desc.transports() & ctx.supported_transports()
     } else {
         // Scan all available transports.
-        ctx.get_transports().to_vec()
+        ctx.supported_transports()
     };
 
     for transport in transports {
         println!("\nScanning {transport} devices...");
-        match scan(&ctx, transport).execute() {
+        let mut builder = scan(&ctx, transport);
+        if let Some(ref desc) = descriptor {
+            // Also catch units that leave their service UUID out of the
+            // advertisement.
+            builder = builder.descriptor(desc);
+        }
+        if args.continuous && transport == Transport::Ble {
+            for device in builder.continuous(true).stream()? {
+                match device {
+                    Ok(device) => println!("  Found: {} ({})", device.name, device.connection),
+                    Err(e) => eprintln!("  Error scanning: {e}"),
+                }
+            }
+            continue;
+        }
+        match builder.execute() {
             Ok(devices) => {
                 for device in &devices {
                     println!("  Found: {} ({})", device.name, device.connection);
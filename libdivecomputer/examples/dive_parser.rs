@@ -92,6 +92,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             &data
         });
 
+        if !Parser::can_parse(&ctx, &desc, &data) {
+            eprintln!(
+                "Skipping {}: not a valid dive for {device_name}",
+                file_path.display()
+            );
+            continue;
+        }
+
         let parser = Parser::from_descriptor(&ctx, &desc, &data)?;
         match parser.parse(&fingerprint) {
             Ok(dive) => {
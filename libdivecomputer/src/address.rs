@@ -0,0 +1,202 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::LibError;
+
+/// A Bluetooth device address (`BD_ADDR`), for classic Bluetooth and BLE
+/// alike.
+///
+/// Parses every spelling the platforms hand out — `AA:BB:CC:DD:EE:FF`,
+/// `AA-BB-CC-DD-EE-FF`, BlueZ's `hci0/dev_AA_BB_CC_DD_EE_FF`, or any of those
+/// behind an `LE:` prefix — and always prints the colon-separated form.
+/// [`BtAddress::UNKNOWN`] stands for a peripheral whose stack hides its
+/// address, as CoreBluetooth does.
+///
+/// Serialized as that string. The raw `u64` older versions stored in
+/// [`ConnectionInfo`](crate::ConnectionInfo) still deserializes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BtAddress(u64);
+
+impl BtAddress {
+    /// The all-zero address, used when the real one is not known.
+    pub const UNKNOWN: Self = Self(0);
+
+    /// An address from its 48-bit integer form, most significant octet
+    /// first, as the C library reports it. Higher bits are ignored.
+    pub const fn from_u64(raw: u64) -> Self {
+        Self(raw & 0xFFFF_FFFF_FFFF)
+    }
+
+    /// The 48-bit integer form, as the C library takes it.
+    pub const fn to_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Whether this is [`BtAddress::UNKNOWN`].
+    pub const fn is_unknown(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The six octets, most significant first.
+    pub fn octets(self) -> [u8; 6] {
+        let [_, _, octets @ ..] = self.0.to_be_bytes();
+        octets
+    }
+}
+
+impl From<u64> for BtAddress {
+    fn from(raw: u64) -> Self {
+        Self::from_u64(raw)
+    }
+}
+
+impl From<BtAddress> for u64 {
+    fn from(address: BtAddress) -> Self {
+        address.0
+    }
+}
+
+impl From<[u8; 6]> for BtAddress {
+    fn from(octets: [u8; 6]) -> Self {
+        let [a, b, c, d, e, f] = octets;
+        Self(u64::from_be_bytes([0, 0, a, b, c, d, e, f]))
+    }
+}
+
+#[cfg(feature = "ble")]
+impl From<btleplug::api::BDAddr> for BtAddress {
+    fn from(address: btleplug::api::BDAddr) -> Self {
+        Self::from(address.into_inner())
+    }
+}
+
+/// The address inside a platform peripheral id. Fails on macOS and iOS,
+/// whose ids are opaque UUIDs.
+#[cfg(feature = "ble")]
+impl TryFrom<&btleplug::platform::PeripheralId> for BtAddress {
+    type Error = LibError;
+
+    fn try_from(id: &btleplug::platform::PeripheralId) -> Result<Self, Self::Error> {
+        id.to_string().parse()
+    }
+}
+
+impl FromStr for BtAddress {
+    type Err = LibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || LibError::InvalidArguments(format!("invalid Bluetooth address '{s}'"));
+        let id = s.strip_prefix("LE:").unwrap_or(s);
+        // BlueZ object path: "hci0/dev_AA_BB_CC_DD_EE_FF".
+        let (mac, separator) = match id.split_once('/') {
+            Some((_, device)) => (device.strip_prefix("dev_").ok_or_else(invalid)?, '_'),
+            // Checked by length so that hyphenated UUIDs are not mistaken
+            // for addresses.
+            None if id.len() == 17 && id.contains('-') => (id, '-'),
+            None => (id, ':'),
+        };
+        let mut octets = [0u8; 6];
+        let mut parts = mac.split(separator);
+        for octet in &mut octets {
+            let part = parts.next().ok_or_else(invalid)?;
+            *octet = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+        }
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(Self::from(octets))
+    }
+}
+
+impl fmt::Display for BtAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.octets();
+        write!(f, "{a:02X}:{b:02X}:{c:02X}:{d:02X}:{e:02X}:{g:02X}")
+    }
+}
+
+impl Serialize for BtAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for BtAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AddressVisitor;
+
+        impl Visitor<'_> for AddressVisitor {
+            type Value = BtAddress;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a Bluetooth address string or integer")
+            }
+
+            fn visit_u64<E: de::Error>(self, raw: u64) -> Result<BtAddress, E> {
+                Ok(BtAddress::from_u64(raw))
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<BtAddress, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(AddressVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: BtAddress = BtAddress::from_u64(0xAABB_CCDD_EEFF);
+
+    #[test]
+    fn display_is_colon_separated() {
+        assert_eq!(ADDRESS.to_string(), "AA:BB:CC:DD:EE:FF");
+        assert_eq!(BtAddress::UNKNOWN.to_string(), "00:00:00:00:00:00");
+        assert_eq!(ADDRESS.octets(), [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
+    }
+
+    #[test]
+    fn parses_platform_spellings() {
+        for s in [
+            "AA:BB:CC:DD:EE:FF",
+            "aa:bb:cc:dd:ee:ff",
+            "aa-bb-cc-dd-ee-ff",
+            "hci0/dev_AA_BB_CC_DD_EE_FF",
+            "LE:AA:BB:CC:DD:EE:FF",
+        ] {
+            assert_eq!(s.parse::<BtAddress>().unwrap(), ADDRESS, "{s}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_and_opaque_ids() {
+        for s in [
+            "AA:BB:CC",
+            "AA:BB:CC:DD:EE:FF:00",
+            "GG:HH:II:JJ:KK:LL",
+            "5A1E3B2C-0000-4000-8000-00805F9B34FB",
+            "hci0/not_a_device",
+            "",
+        ] {
+            assert!(s.parse::<BtAddress>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn deserializes_strings_and_legacy_integers() {
+        use serde::de::IntoDeserializer;
+        use serde::de::value::Error;
+
+        let from_str = |s: &str| BtAddress::deserialize(s.into_deserializer());
+        let from_u64 = |raw: u64| BtAddress::deserialize(raw.into_deserializer());
+        assert_eq!(from_str("AA:BB:CC:DD:EE:FF"), Ok::<_, Error>(ADDRESS));
+        assert_eq!(from_u64(0xAABB_CCDD_EEFF), Ok::<_, Error>(ADDRESS));
+        assert!(from_str("AA:BB").is_err());
+    }
+}
@@ -1,7 +1,8 @@
 //! Shared Android JNI infrastructure.
 //!
 //! Stores the JVM reference and provides thread-attachment helpers
-//! used by both the BLE and classic Bluetooth modules.
+//! used by both the BLE and classic Bluetooth modules, plus a preflight
+//! check of the runtime permissions they need.
 
 pub static JAVAVM: std::sync::OnceLock<jni::JavaVM> = std::sync::OnceLock::new();
 
@@ -28,3 +29,139 @@ pub fn attach_current_thread()
         .ok_or("JavaVM not initialized — call init() first")?;
     Ok(vm.attach_current_thread()?)
 }
+
+/// The calling thread's JNI environment. The thread must already be
+/// attached, e.g. through [`attach_current_thread`].
+pub(crate) fn get_env() -> crate::error::Result<jni::JNIEnv<'static>> {
+    use crate::error::LibError;
+    let vm = JAVAVM
+        .get()
+        .ok_or_else(|| LibError::DeviceError("JavaVM not initialized".to_string()))?;
+    vm.get_env()
+        .map_err(|e| LibError::DeviceError(format!("Failed to get JNIEnv: {e}")))
+}
+
+/// Turn a Java exception left pending by the last JNI call into an error,
+/// clearing it so later calls work.
+pub(crate) fn check_and_clear_exception(
+    env: &jni::JNIEnv,
+    context: &str,
+) -> crate::error::Result<()> {
+    if env.exception_check().unwrap_or(false) {
+        let _ = env.exception_describe();
+        let _ = env.exception_clear();
+        return Err(crate::error::LibError::DeviceError(format!(
+            "Java exception in {context}"
+        )));
+    }
+    Ok(())
+}
+
+/// An Android runtime permission the Bluetooth transports need.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    /// `BLUETOOTH_SCAN`, for BLE scans from Android 12.
+    BluetoothScan,
+    /// `BLUETOOTH_CONNECT`, for connecting and listing paired devices from
+    /// Android 12.
+    BluetoothConnect,
+    /// `ACCESS_FINE_LOCATION`, which BLE scans need before Android 12.
+    FineLocation,
+}
+
+impl Permission {
+    /// The full permission name, as passed to `requestPermissions`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::BluetoothScan => "android.permission.BLUETOOTH_SCAN",
+            Self::BluetoothConnect => "android.permission.BLUETOOTH_CONNECT",
+            Self::FineLocation => "android.permission.ACCESS_FINE_LOCATION",
+        }
+    }
+}
+
+/// API level of Android 12, which split the Bluetooth permissions out of
+/// location.
+const ANDROID_S: i32 = 31;
+
+/// `PackageManager.PERMISSION_GRANTED`.
+const PERMISSION_GRANTED: i32 = 0;
+
+/// The permissions scanning and connecting need on API level `sdk_int`.
+pub fn required_permissions(sdk_int: i32) -> &'static [Permission] {
+    if sdk_int >= ANDROID_S {
+        &[Permission::BluetoothScan, Permission::BluetoothConnect]
+    } else {
+        &[Permission::FineLocation]
+    }
+}
+
+/// The [`required_permissions`] the app has not been granted yet, to ask for
+/// with `Activity.requestPermissions` before scanning.
+pub fn missing_permissions() -> crate::error::Result<Vec<Permission>> {
+    use crate::error::LibError;
+    use jni::objects::{JObject, JValue};
+
+    let failed = |what: &str, e: jni::errors::Error| {
+        LibError::DeviceError(format!("permission check: {what} failed: {e}"))
+    };
+    let env = get_env()?;
+    let sdk_int = env
+        .get_static_field("android/os/Build$VERSION", "SDK_INT", "I")
+        .and_then(|v| v.i())
+        .map_err(|e| failed("Build.VERSION.SDK_INT", e))?;
+    check_and_clear_exception(&env, "Build.VERSION.SDK_INT")?;
+    let context = JObject::from(ndk_context::android_context().context() as jni::sys::jobject);
+
+    let mut missing = Vec::new();
+    for &permission in required_permissions(sdk_int) {
+        let name = env
+            .new_string(permission.name())
+            .map_err(|e| failed("new_string", e))?;
+        let granted = env
+            .call_method(
+                context,
+                "checkSelfPermission",
+                "(Ljava/lang/String;)I",
+                &[JValue::Object(name.into())],
+            )
+            .and_then(|v| v.i());
+        let _ = env.delete_local_ref(name.into());
+        check_and_clear_exception(&env, "checkSelfPermission")?;
+        if granted.map_err(|e| failed("checkSelfPermission", e))? != PERMISSION_GRANTED {
+            missing.push(permission);
+        }
+    }
+    Ok(missing)
+}
+
+/// Fail with [`LibError::PermissionDenied`](crate::error::LibError::PermissionDenied)
+/// naming every [`missing_permissions`] entry, so the app can request them
+/// instead of seeing an opaque Bluetooth failure.
+pub fn check_permissions() -> crate::error::Result<()> {
+    let missing = missing_permissions()?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(crate::error::LibError::PermissionDenied(
+        missing.iter().map(|p| p.name().to_string()).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_permissions_split_at_android_12() {
+        assert_eq!(required_permissions(30), &[Permission::FineLocation]);
+        assert_eq!(
+            required_permissions(ANDROID_S),
+            &[Permission::BluetoothScan, Permission::BluetoothConnect]
+        );
+        assert_eq!(
+            required_permissions(34),
+            &[Permission::BluetoothScan, Permission::BluetoothConnect]
+        );
+    }
+}
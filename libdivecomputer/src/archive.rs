@@ -0,0 +1,110 @@
+//! Compact binary dive archives.
+//!
+//! JSON is slow and bulky for dives with tens of thousands of samples. These
+//! helpers write and read a `Vec<Dive>` as CBOR (feature `cbor`) or
+//! MessagePack (feature `msgpack`), typically a fifth of the JSON size, for
+//! apps that cache or sync dives over the network. Byte fields such as
+//! [`Dive::raw`] are stored as raw bytes rather than hex.
+//!
+//! ```no_run
+//! # #[cfg(feature = "cbor")]
+//! # fn demo(dives: &[libdivecomputer::Dive]) -> libdivecomputer::Result<()> {
+//! use libdivecomputer::archive;
+//!
+//! let file = std::fs::File::create("dives.cbor")?;
+//! archive::write_cbor(dives, std::io::BufWriter::new(file))?;
+//! let file = std::fs::File::open("dives.cbor")?;
+//! let restored = archive::read_cbor(std::io::BufReader::new(file))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{Read, Write};
+
+use crate::error::{LibError, Result};
+use crate::parser::Dive;
+
+/// Write `dives` to `writer` as CBOR.
+#[cfg(feature = "cbor")]
+pub fn write_cbor(dives: &[Dive], writer: impl Write) -> Result<()> {
+    ciborium::into_writer(dives, writer).map_err(|e| match e {
+        ciborium::ser::Error::Io(e) => LibError::Io(e),
+        e => LibError::Serialization(e.to_string()),
+    })
+}
+
+/// Read dives written by [`write_cbor`].
+#[cfg(feature = "cbor")]
+pub fn read_cbor(reader: impl Read) -> Result<Vec<Dive>> {
+    ciborium::from_reader(reader).map_err(|e| match e {
+        ciborium::de::Error::Io(e) => LibError::Io(e),
+        e => LibError::Serialization(e.to_string()),
+    })
+}
+
+/// Write `dives` to `writer` as MessagePack.
+///
+/// Structs are written as maps keyed by field name, so archives stay
+/// readable as fields are added to [`Dive`].
+#[cfg(feature = "msgpack")]
+pub fn write_msgpack(dives: &[Dive], mut writer: impl Write) -> Result<()> {
+    let mut serializer = rmp_serde::Serializer::new(&mut writer).with_struct_map();
+    serde::Serialize::serialize(dives, &mut serializer)
+        .map_err(|e| LibError::Serialization(e.to_string()))
+}
+
+/// Read dives written by [`write_msgpack`].
+#[cfg(feature = "msgpack")]
+pub fn read_msgpack(reader: impl Read) -> Result<Vec<Dive>> {
+    rmp_serde::from_read(reader).map_err(|e| LibError::Serialization(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::parser::{DiveSample, Fingerprint};
+
+    fn dives() -> Vec<Dive> {
+        vec![Dive {
+            fingerprint: Fingerprint::from(vec![0xDE, 0xAD]),
+            start: "2025-06-15T12:30:00Z".parse().unwrap(),
+            max_depth: 18.5,
+            raw: vec![1, 2, 3],
+            samples: vec![DiveSample {
+                time: Duration::from_secs(10),
+                depth: 3.0,
+                temperature: Some(20.0),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }]
+    }
+
+    fn check(restored: &[Dive]) {
+        let expected = dives();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].fingerprint, expected[0].fingerprint);
+        assert_eq!(restored[0].start, expected[0].start);
+        assert_eq!(restored[0].raw, expected[0].raw);
+        assert_eq!(restored[0].samples.len(), 1);
+        assert_eq!(restored[0].samples[0].temperature, Some(20.0));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_round_trip() {
+        let mut buf = Vec::new();
+        write_cbor(&dives(), &mut buf).unwrap();
+        check(&read_cbor(buf.as_slice()).unwrap());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_round_trip() {
+        let mut buf = Vec::new();
+        write_msgpack(&dives(), &mut buf).unwrap();
+        check(&read_msgpack(buf.as_slice()).unwrap());
+    }
+}
@@ -1,16 +1,27 @@
+mod options;
+#[cfg(target_os = "android")]
+mod pairing;
+mod runtime;
 /// Known BLE service and characteristic UUIDs for supported dive computers.
 pub mod services;
 
+pub use btleplug::platform::PeripheralId;
+pub use options::{BleOptions, BleState, ReconnectPolicy};
+pub use runtime::{runtime_builder, set_runtime};
+
 use std::collections::VecDeque;
 use std::ffi::c_void;
+use std::pin::Pin;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 use btleplug::api::{
-    Central, CharPropFlags, Characteristic, Manager as _, Peripheral as _, ScanFilter, Service,
-    ValueNotification, WriteType,
+    BDAddr, Central, CentralEvent, CharPropFlags, Characteristic, Manager as _, Peripheral as _,
+    ScanFilter, Service, ValueNotification, WriteType,
 };
 use btleplug::platform::{Adapter, Manager, Peripheral};
+use futures::Stream;
 use libdivecomputer_sys as ffi;
 use tokio::sync::{mpsc, oneshot};
 use tokio::time::Instant;
@@ -18,120 +29,308 @@ use tokio_stream::StreamExt;
 use tracing::instrument;
 use uuid::Uuid;
 
-use crate::device::{ConnectionInfo, DeviceInfo};
+use crate::address::BtAddress;
+use crate::device::{BleAdvertisement, ConnectionInfo, DeviceInfo};
 use crate::error::{LibError, Result};
 use crate::iostream::IoStream;
-use crate::scanner::mac_string_to_u64;
+use crate::scanner::{NamePrefix, ScanDedup, match_name_prefix};
 use crate::transport::Transport;
 
-use services::KNOWN_SERVICES;
+use runtime::BleRuntime;
 #[cfg(target_os = "android")]
 use services::use_random_address;
+use services::{KNOWN_SERVICES, WriteSize};
 
-type PendingReads = Vec<(usize, oneshot::Sender<std::result::Result<Vec<u8>, String>>)>;
+type PendingReads = Vec<(usize, oneshot::Sender<Result<Vec<u8>>>)>;
+type Notifications = Pin<Box<dyn Stream<Item = ValueNotification> + Send>>;
+type CentralEvents = Pin<Box<dyn Stream<Item = CentralEvent> + Send>>;
 
-/// Scan for BLE dive computer devices.
+/// Scan for BLE dive computer devices, returning as soon as at least one
+/// has been seen or `timeout` elapses.
 pub fn scan_ble(timeout: Duration) -> Result<Vec<DeviceInfo>> {
+    scan_ble_with(timeout, false, &[])
+}
+
+/// Scan for BLE dive computer devices. With `exhaustive`, keep collecting
+/// until `timeout` elapses instead of stopping at the first hit, so every
+/// computer in range gets a chance to show up. Devices advertising a name
+/// that starts with one of `name_prefixes` count as dive computers too.
+pub(crate) fn scan_ble_with(
+    timeout: Duration,
+    exhaustive: bool,
+    name_prefixes: &[NamePrefix],
+) -> Result<Vec<DeviceInfo>> {
     #[cfg(target_os = "android")]
-    let _jni_guard = android::attach_current_thread()
-        .map_err(|e| LibError::DeviceError(format!("JNI attach failed: {e}")))?;
+    let _jni_guard = android::attach_current_thread().map_err(|e| LibError::ScanFailed {
+        transport: Transport::Ble,
+        reason: format!("JNI attach failed: {e}"),
+    })?;
+    #[cfg(target_os = "android")]
+    crate::android::check_permissions()?;
 
-    let rt = tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .build()
-        .map_err(|e| LibError::DeviceError(e.to_string()))?;
+    let rt = BleRuntime::new()?;
 
-    rt.block_on(scan_ble_async(timeout))
+    rt.block_on(scan_ble_async(timeout, exhaustive, name_prefixes))
 }
 
-#[instrument(fields(timeout_ms = timeout.as_millis() as u64))]
-async fn scan_ble_async(timeout: Duration) -> Result<Vec<DeviceInfo>> {
-    let known_uuids: Vec<Uuid> = KNOWN_SERVICES.iter().map(|(uuid, _)| *uuid).collect();
+#[instrument(skip(name_prefixes), fields(timeout_ms = timeout.as_millis() as u64))]
+async fn scan_ble_async(
+    timeout: Duration,
+    exhaustive: bool,
+    name_prefixes: &[NamePrefix],
+) -> Result<Vec<DeviceInfo>> {
+    let (adapter, known_uuids) = start_known_scan(name_prefixes.is_empty()).await?;
+
+    let start = tokio::time::Instant::now();
+    let mut devices = Vec::new();
+
+    loop {
+        collect_visible(&adapter, &known_uuids, name_prefixes, &mut devices).await?;
 
+        if (!exhaustive && !devices.is_empty()) || start.elapsed() >= timeout {
+            break;
+        }
+
+        tokio::time::sleep(SCAN_POLL_INTERVAL).await;
+    }
+
+    adapter.stop_scan().await?;
+    Ok(devices)
+}
+
+/// Interval between peripheral-list checks while scanning.
+const SCAN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long to wait for CoreBluetooth to report the adapter powered on. A new
+/// central manager starts out in an unknown state — on iOS the first one also
+/// raises the Bluetooth permission prompt — and scans started before it
+/// settles are dropped by the OS.
+#[cfg(target_vendor = "apple")]
+const ADAPTER_READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Open the first Bluetooth adapter, ready to scan.
+async fn first_adapter() -> Result<Adapter> {
     let manager = Manager::new().await?;
-    let adapters = manager.adapters().await?;
-    let adapter = adapters
+    let adapter = manager
+        .adapters()
+        .await?
         .into_iter()
         .next()
         .ok_or(LibError::NoBluetoothAdapter)?;
+    #[cfg(target_vendor = "apple")]
+    wait_powered_on(&adapter).await?;
+    Ok(adapter)
+}
 
-    let scan_filter = ScanFilter {
-        services: known_uuids.clone(),
-    };
+#[cfg(target_vendor = "apple")]
+async fn wait_powered_on(adapter: &Adapter) -> Result<()> {
+    use btleplug::api::CentralState;
 
-    adapter.start_scan(scan_filter).await?;
+    let start = Instant::now();
+    loop {
+        match adapter.adapter_state().await? {
+            CentralState::PoweredOn => return Ok(()),
+            CentralState::PoweredOff => {
+                return Err(LibError::BleError("Bluetooth is turned off".into()));
+            }
+            _ if start.elapsed() >= ADAPTER_READY_TIMEOUT => {
+                return Err(LibError::BleError(
+                    "Bluetooth did not become ready; the app may lack Bluetooth permission".into(),
+                ));
+            }
+            _ => tokio::time::sleep(SCAN_POLL_INTERVAL).await,
+        }
+    }
+}
+
+/// Scan on a worker thread for [`ScanBuilder::stream`](crate::scanner::ScanBuilder::stream),
+/// sending devices to `tx` as they appear. Runs until `timeout` elapses
+/// (forever if `None`), `cancel` is set, or the receiver is dropped. A
+/// failure is sent down the channel as the last item.
+pub(crate) fn stream_ble(
+    timeout: Option<Duration>,
+    dedup: ScanDedup,
+    name_prefixes: &[NamePrefix],
+    tx: &std::sync::mpsc::Sender<Result<DeviceInfo>>,
+    cancel: &AtomicBool,
+) {
+    let result = (|| -> Result<()> {
+        #[cfg(target_os = "android")]
+        let _jni_guard = android::attach_current_thread().map_err(|e| LibError::ScanFailed {
+            transport: Transport::Ble,
+            reason: format!("JNI attach failed: {e}"),
+        })?;
+        #[cfg(target_os = "android")]
+        crate::android::check_permissions()?;
+
+        let rt = BleRuntime::new()?;
+
+        rt.block_on(stream_ble_async(timeout, dedup, name_prefixes, tx, cancel))
+    })();
+
+    if let Err(e) = result {
+        let _ = tx.send(Err(e));
+    }
+}
+
+#[instrument(skip(name_prefixes, tx, cancel))]
+async fn stream_ble_async(
+    timeout: Option<Duration>,
+    dedup: ScanDedup,
+    name_prefixes: &[NamePrefix],
+    tx: &std::sync::mpsc::Sender<Result<DeviceInfo>>,
+    cancel: &AtomicBool,
+) -> Result<()> {
+    let (adapter, known_uuids) = start_known_scan(name_prefixes.is_empty()).await?;
 
     let start = tokio::time::Instant::now();
     let mut devices = Vec::new();
+    let mut reported: Vec<DeviceInfo> = Vec::new();
 
-    loop {
-        let peripherals = adapter.peripherals().await?;
+    let result = 'scan: loop {
+        if let Err(e) = collect_visible(&adapter, &known_uuids, name_prefixes, &mut devices).await {
+            break Err(e);
+        }
 
-        for peripheral in peripherals {
-            if let Ok(Some(props)) = peripheral.properties().await {
-                for service_uuid in &props.services {
-                    if let Some(idx) = known_uuids.iter().position(|u| u == service_uuid) {
-                        let service_name = KNOWN_SERVICES[idx].1;
-                        let peripheral_id = peripheral.id();
-                        let address_string = peripheral_id.to_string();
-                        let address = peripheral_id_to_address(&address_string).unwrap_or(0);
-
-                        let device = DeviceInfo {
-                            name: props
-                                .local_name
-                                .as_ref()
-                                .map(|n| format!("{n} - {service_name}"))
-                                .unwrap_or_else(|| service_name.to_string()),
-                            transport: Transport::Ble,
-                            connection: ConnectionInfo::Ble {
-                                address,
-                                address_string,
-                                service_name: service_name.to_string(),
-                                local_name: props.local_name.clone(),
-                            },
-                        };
-
-                        if !devices.iter().any(|d: &DeviceInfo| d.name == device.name) {
-                            devices.push(device);
-                        }
-                    }
+        for device in &devices {
+            let key = device.connection.connection_string();
+            let previous = reported
+                .iter_mut()
+                .find(|d| d.connection.connection_string() == key);
+            let report = match previous {
+                None => {
+                    reported.push(device.clone());
+                    true
                 }
+                Some(previous) if dedup == ScanDedup::Updates && previous != device => {
+                    *previous = device.clone();
+                    true
+                }
+                Some(_) => false,
+            };
+            if report && tx.send(Ok(device.clone())).is_err() {
+                // Nobody is listening any more.
+                break 'scan Ok(());
             }
         }
 
-        if !devices.is_empty() || start.elapsed() >= timeout {
-            break;
+        if cancel.load(Ordering::Relaxed) || timeout.is_some_and(|t| start.elapsed() >= t) {
+            break Ok(());
         }
 
-        tokio::time::sleep(Duration::from_millis(100)).await;
-    }
+        tokio::time::sleep(SCAN_POLL_INTERVAL).await;
+    };
 
     adapter.stop_scan().await?;
-    Ok(devices)
+    result
 }
 
-fn peripheral_id_to_address(id_str: &str) -> Option<u64> {
-    // Linux/BlueZ: "hci0/dev_XX_XX_XX_XX_XX_XX"
-    if id_str.contains("/dev_") {
-        let parts: Vec<&str> = id_str.split('/').collect();
-        if parts.len() == 2 {
-            let mac_part = parts[1].strip_prefix("dev_")?;
-            let mac_with_colons = mac_part.replace('_', ":");
-            return mac_string_to_u64(&mac_with_colons);
-        }
-    }
+/// Open the first adapter and start scanning for the services in
+/// [`KNOWN_SERVICES`]. Without `filter_services` the OS reports every
+/// peripheral, which name-prefix matching needs to see devices that leave
+/// the service UUID out of their advertisements.
+async fn start_known_scan(filter_services: bool) -> Result<(Adapter, Vec<Uuid>)> {
+    let known_uuids: Vec<Uuid> = KNOWN_SERVICES.iter().map(|(uuid, _)| *uuid).collect();
 
-    // Standard MAC: "AA:BB:CC:DD:EE:FF"
-    if id_str.contains(':') {
-        return mac_string_to_u64(id_str);
-    }
+    let adapter = first_adapter().await?;
 
-    // Hyphen format: "AA-BB-CC-DD-EE-FF"
-    if id_str.contains('-') {
-        return mac_string_to_u64(&id_str.replace('-', ":"));
-    }
+    let scan_filter = ScanFilter {
+        services: if filter_services {
+            known_uuids.clone()
+        } else {
+            Vec::new()
+        },
+    };
 
-    None
+    adapter.start_scan(scan_filter).await?;
+    Ok((adapter, known_uuids))
+}
+
+/// Merge the dive computers currently known to `adapter` into `devices`.
+/// A peripheral qualifies by advertising a known service or, failing that,
+/// a name matching one of `name_prefixes`.
+async fn collect_visible(
+    adapter: &Adapter,
+    known_uuids: &[Uuid],
+    name_prefixes: &[NamePrefix],
+    devices: &mut Vec<DeviceInfo>,
+) -> Result<()> {
+    let peripherals = adapter.peripherals().await?;
+
+    for peripheral in peripherals {
+        let Ok(Some(props)) = peripheral.properties().await else {
+            continue;
+        };
+        let by_service = props
+            .services
+            .iter()
+            .find_map(|uuid| known_uuids.iter().position(|u| u == uuid))
+            .map(|idx| KNOWN_SERVICES[idx].1);
+        let by_name = || {
+            let name = props.local_name.as_deref()?;
+            let matched = match_name_prefix(name, name_prefixes)?;
+            Some(
+                matched
+                    .vendor
+                    .as_deref()
+                    .map_or("", services::service_name_for_vendor),
+            )
+        };
+        let Some(service_name) = by_service.or_else(by_name) else {
+            continue;
+        };
+
+        // CoreBluetooth never reveals the MAC (`props.address` is all
+        // zeroes); the peripheral id is the only stable handle there.
+        let peripheral_id = peripheral.id().to_string();
+        let address_string = if props.address == BDAddr::default() {
+            peripheral_id.clone()
+        } else {
+            props.address.to_string()
+        };
+        let address = BtAddress::from(props.address);
+
+        let connection = ConnectionInfo::Ble {
+            address,
+            address_string,
+            service_name: service_name.to_string(),
+            local_name: props.local_name.clone(),
+            peripheral_id: Some(peripheral_id),
+        };
+        let device = DeviceInfo {
+            name: connection.display_name().into_owned(),
+            transport: Transport::Ble,
+            connection,
+            advertisement: Some(BleAdvertisement {
+                rssi: props.rssi,
+                tx_power: props.tx_power_level,
+                manufacturer_data: props
+                    .manufacturer_data
+                    .iter()
+                    .map(|(id, data)| (*id, data.clone()))
+                    .collect(),
+                services: props.services.iter().map(Uuid::to_string).collect(),
+            }),
+        };
+
+        // Key on the peripheral, not the name: several computers of the same
+        // model advertise identical names. Prefer the entry that carries a
+        // local name, which may only arrive with a later advertisement, but
+        // always take the latest advertisement data.
+        match devices.iter_mut().find(|d: &&mut DeviceInfo| {
+            d.connection.connection_string() == device.connection.connection_string()
+        }) {
+            Some(existing) => {
+                if props.local_name.is_some() {
+                    *existing = device;
+                } else {
+                    existing.advertisement = device.advertisement;
+                }
+            }
+            None => devices.push(device),
+        }
+    }
+    Ok(())
 }
 
 // --- BLE Transport (iostream implementation) ---
@@ -139,11 +338,11 @@ fn peripheral_id_to_address(id_str: &str) -> Option<u64> {
 enum BleEvent {
     Write {
         data: Vec<u8>,
-        response: oneshot::Sender<std::result::Result<usize, String>>,
+        response: oneshot::Sender<Result<usize>>,
     },
     Read {
         size: usize,
-        response: oneshot::Sender<std::result::Result<Vec<u8>, String>>,
+        response: oneshot::Sender<Result<Vec<u8>>>,
     },
     Poll {
         timeout: Duration,
@@ -151,7 +350,7 @@ enum BleEvent {
     },
     ReadCharacteristic {
         uuid: Uuid,
-        response: oneshot::Sender<std::result::Result<Vec<u8>, String>>,
+        response: oneshot::Sender<Result<Vec<u8>>>,
     },
     SetTimeout {
         timeout: Duration,
@@ -227,6 +426,14 @@ struct BleTransport {
 /// shutdown.
 const BLE_EVENT_CHANNEL_CAPACITY: usize = 8;
 
+/// A [`LibError::ConnectFailed`] for the BLE transport.
+fn connect_failed(reason: impl Into<String>) -> LibError {
+    LibError::ConnectFailed {
+        transport: Transport::Ble,
+        reason: reason.into(),
+    }
+}
+
 /// Render a `catch_unwind` panic payload as a human-readable string. Panics
 /// that cross the FFI boundary come back as `Box<dyn Any + Send>` whose payload
 /// is almost always a `&'static str` (bare `panic!("msg")`) or `String`
@@ -272,22 +479,193 @@ fn buffer_push(buffer: &mut VecDeque<Vec<u8>>, packet: Vec<u8>) {
     buffer.push_back(packet);
 }
 
+/// The characteristic outgoing data goes to, and how much of it fits in one
+/// write.
+struct Writer {
+    characteristic: Characteristic,
+    size: WriteSize,
+}
+
+/// A live GATT session with the dive computer, set up afresh on every
+/// (re)connect.
+struct Session {
+    service: Service,
+    writer: Writer,
+    notifications: Notifications,
+}
+
+impl Session {
+    /// Connect, discover services and subscribe to notifications.
+    async fn establish(
+        peripheral: &Peripheral,
+        write_size: WriteSize,
+        options: &BleOptions,
+    ) -> Result<Self> {
+        tracing::debug!("ble: connecting");
+        options.report(BleState::Connecting);
+        peripheral.connect().await?;
+
+        tracing::debug!("ble: discovering services");
+        options.report(BleState::Discovering);
+        peripheral.discover_services().await?;
+
+        let (service, write_char, read_char) =
+            BleTransport::find_preferred_service_and_characteristics(peripheral).await?;
+
+        // IMPORTANT: get the notification stream BEFORE enabling the GATT
+        // subscription. If we subscribe first, any notification that arrives
+        // in the window before we obtain the stream can be dropped on backends
+        // whose internal channel buffers nothing for a zero-subscriber
+        // broadcast — which is exactly the kind of single-packet loss that can
+        // wedge a Shearwater first-sync handshake.
+        let notifications = peripheral.notifications().await?;
+
+        tracing::debug!("ble: subscribing to notifications");
+        peripheral.subscribe(&read_char).await?;
+
+        // Let the CCCD descriptor write fully complete before the first
+        // protocol command goes out. Cheap; only matters on the first session
+        // for a given physical connection.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        Ok(Self {
+            service,
+            writer: Writer {
+                characteristic: write_char,
+                size: write_size,
+            },
+            notifications,
+        })
+    }
+}
+
+/// The worker's end of a BLE session: the peripheral, its current GATT
+/// session, and what to do when the link drops.
+struct Link {
+    peripheral: Peripheral,
+    session: Session,
+    options: BleOptions,
+    /// Reconnect attempts made before giving up. Set once the link is lost
+    /// for good; every request after that fails.
+    lost_after: Option<u32>,
+}
+
+impl Link {
+    fn lost_error(&self) -> Option<LibError> {
+        self.lost_after
+            .map(|attempts| LibError::BleDisconnected { attempts })
+    }
+
+    async fn is_connected(&self) -> bool {
+        self.peripheral.is_connected().await.unwrap_or(false)
+    }
+
+    /// Send `data`, split to the link's write size.
+    async fn write(&self, data: &[u8]) -> Result<()> {
+        if let Some(err) = self.lost_error() {
+            return Err(err);
+        }
+        // A write longer than the link's MTU is rejected outright, so split
+        // it; see `services::WriteSize`.
+        let writer = &self.session.writer;
+        for chunk in data.chunks(writer.size.get()) {
+            self.peripheral
+                .write(&writer.characteristic, chunk, WriteType::WithoutResponse)
+                .await
+                .map_err(|err| LibError::TransportIo {
+                    transport: Transport::Ble,
+                    reason: format!("write failed: {err}"),
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Re-establish the session after the link dropped, following the
+    /// [`ReconnectPolicy`]. Marks the link lost if every attempt fails.
+    async fn recover(&mut self) -> Result<()> {
+        let policy = self.options.reconnect;
+        for attempt in 1..=policy.max_attempts {
+            tokio::time::sleep(policy.delay(attempt)).await;
+            tracing::warn!(
+                attempt,
+                max_attempts = policy.max_attempts,
+                "ble: link lost; reconnecting"
+            );
+            self.options.report(BleState::Reconnecting);
+            match Session::establish(
+                &self.peripheral,
+                self.session.writer.size.reset(),
+                &self.options,
+            )
+            .await
+            {
+                Ok(session) => {
+                    self.session = session;
+                    self.options.report(BleState::Ready);
+                    tracing::debug!(attempt, "ble: reconnected");
+                    return Ok(());
+                }
+                Err(err) => {
+                    tracing::warn!(attempt, error = %err, "ble: reconnect attempt failed");
+                    let _ = self.peripheral.disconnect().await;
+                }
+            }
+        }
+        tracing::error!(attempts = policy.max_attempts, "ble: link lost; giving up");
+        self.lost_after = Some(policy.max_attempts);
+        Err(LibError::BleDisconnected {
+            attempts: policy.max_attempts,
+        })
+    }
+}
+
+/// Peripheral to open in [`BleTransport::connect`].
+enum BleTarget<'a> {
+    /// Platform id from a scan, resolved without going through an address.
+    /// The only handle that works on macOS/iOS, where CoreBluetooth hides
+    /// the MAC behind an opaque UUID.
+    Id(&'a PeripheralId),
+    /// MAC address or id string, e.g. from a saved [`ConnectionInfo::Ble`].
+    Address(&'a str),
+}
+
+impl BleTarget<'_> {
+    async fn matches(&self, peripheral: &Peripheral) -> bool {
+        match self {
+            Self::Id(id) => peripheral.id() == **id,
+            Self::Address(address) => {
+                BleTransport::peripheral_matches(peripheral, &address.to_lowercase()).await
+            }
+        }
+    }
+}
+
+/// Android ids display as the MAC, which bonding and the direct lookup need.
+impl std::fmt::Display for BleTarget<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Id(id) => id.fmt(f),
+            Self::Address(address) => f.write_str(address),
+        }
+    }
+}
+
 impl BleTransport {
     /// Find the peripheral once, then retry only the session-open portion.
     /// Rescanning on every retry (the previous behavior) ate ~5s of every
     /// attempt for no benefit.
-    #[instrument(skip_all, fields(mac_address = %mac_address, service_name = %service_name))]
-    async fn connect(mac_address: &str, service_name: &str) -> Result<Self> {
+    #[instrument(skip_all, fields(target = %target, service_name = %service_name))]
+    async fn connect(
+        target: &BleTarget<'_>,
+        service_name: &str,
+        options: &BleOptions,
+    ) -> Result<Self> {
         tracing::debug!("ble: scanning for peripheral");
+        options.report(BleState::Searching);
 
-        let manager = Manager::new().await?;
-        let adapters = manager.adapters().await?;
-        let adapter = adapters
-            .into_iter()
-            .next()
-            .ok_or(LibError::NoBluetoothAdapter)?;
+        let adapter = first_adapter().await?;
 
-        let peripheral = Self::find_peripheral(&adapter, mac_address, service_name).await?;
+        let peripheral = Self::find_peripheral(&adapter, target, service_name).await?;
         let device_name = peripheral
             .properties()
             .await?
@@ -295,7 +673,29 @@ impl BleTransport {
             .local_name
             .unwrap_or_else(|| "Unknown".to_string());
 
-        tracing::debug!(device_name = %device_name, "ble: found peripheral, opening session");
+        let write_size = options
+            .max_write_size
+            .map_or_else(|| services::write_size(service_name), WriteSize::fixed);
+        tracing::debug!(
+            device_name = %device_name,
+            write_size = write_size.get(),
+            "ble: found peripheral, opening session"
+        );
+
+        // Bond before the first GATT connection so the PIN exchange doesn't
+        // race the session-open retries below.
+        #[cfg(target_os = "android")]
+        {
+            let (address, name, options) =
+                (target.to_string(), device_name.clone(), options.clone());
+            tokio::task::spawn_blocking(move || {
+                let _jni_guard = crate::android::attach_current_thread()
+                    .map_err(|e| connect_failed(format!("JNI attach failed: {e}")))?;
+                pairing::ensure_bonded(&address, &name, &options)
+            })
+            .await
+            .map_err(|e| connect_failed(format!("bonding task failed: {e}")))??;
+        }
 
         let mut last_err = None;
         for attempt in 1..=BLE_CONNECT_MAX_ATTEMPTS {
@@ -308,8 +708,20 @@ impl BleTransport {
                 );
                 tokio::time::sleep(BLE_CONNECT_RETRY_DELAY).await;
             }
-            match Self::open_session(&peripheral, device_name.clone(), attempt).await {
-                Ok(transport) => return Ok(transport),
+            match Self::open_session(
+                &peripheral,
+                &adapter,
+                device_name.clone(),
+                write_size,
+                attempt,
+                options,
+            )
+            .await
+            {
+                Ok(transport) => {
+                    options.report(BleState::Ready);
+                    return Ok(transport);
+                }
                 Err(err) => {
                     tracing::warn!(
                         attempt,
@@ -325,52 +737,43 @@ impl BleTransport {
             }
         }
 
-        Err(last_err
-            .unwrap_or_else(|| LibError::DeviceError("BLE session open failed".to_string())))
+        Err(last_err.unwrap_or_else(|| connect_failed("session open failed")))
     }
 
     /// One pass at connect → discover services → subscribe → spawn event loop.
     /// Called from the retry loop in [`Self::connect`].
-    #[instrument(skip(peripheral), fields(device_name = %device_name, attempt = attempt))]
+    #[instrument(
+        skip(peripheral, adapter, options),
+        fields(device_name = %device_name, attempt = attempt)
+    )]
     async fn open_session(
         peripheral: &Peripheral,
+        adapter: &Adapter,
         device_name: String,
+        write_size: WriteSize,
         attempt: u32,
+        options: &BleOptions,
     ) -> Result<Self> {
         let started = Instant::now();
-        tracing::debug!("ble: connecting");
-        peripheral.connect().await?;
-
-        tracing::debug!("ble: discovering services");
-        peripheral.discover_services().await?;
-
-        let (service, write_char, read_char) =
-            Self::find_preferred_service_and_characteristics(peripheral).await?;
-
-        // IMPORTANT: get the notification stream BEFORE enabling the GATT
-        // subscription. If we subscribe first, any notification that arrives
-        // in the window before we obtain the stream can be dropped on backends
-        // whose internal channel buffers nothing for a zero-subscriber
-        // broadcast — which is exactly the kind of single-packet loss that can
-        // wedge a Shearwater first-sync handshake.
-        let (event_tx, event_rx) = mpsc::channel::<BleEvent>(BLE_EVENT_CHANNEL_CAPACITY);
-        let notification_stream = peripheral.notifications().await?;
-
-        tracing::debug!("ble: subscribing to notifications");
-        peripheral.subscribe(&read_char).await?;
-
-        // Let the CCCD descriptor write fully complete before the first
-        // protocol command goes out. Cheap; only matters on the first session
-        // for a given physical connection.
-        tokio::time::sleep(Duration::from_millis(200)).await;
+        // Listen for adapter events before connecting so that a drop right
+        // after the session opens still triggers a reconnect.
+        let central_events = adapter.events().await?;
+        let session = Session::establish(peripheral, write_size, options).await?;
 
         tracing::debug!(
             elapsed_ms = started.elapsed().as_millis() as u64,
             "ble: session ready"
         );
 
-        // Clone what the spawned thread needs.
-        let peripheral_owned = peripheral.clone();
+        let (event_tx, event_rx) = mpsc::channel::<BleEvent>(BLE_EVENT_CHANNEL_CAPACITY);
+
+        // Move what the spawned thread needs.
+        let link = Link {
+            peripheral: peripheral.clone(),
+            session,
+            options: options.clone(),
+            lost_after: None,
+        };
 
         // Startup handshake: the spawned thread must confirm it attached JNI
         // and built its runtime before we hand a `BleTransport` back to the
@@ -392,22 +795,16 @@ impl BleTransport {
                 let _jni_guard = match android::attach_current_thread() {
                     Ok(g) => g,
                     Err(e) => {
-                        let _ = startup_tx.send(Err(LibError::DeviceError(format!(
-                            "JNI attach failed: {e}"
-                        ))));
+                        let _ =
+                            startup_tx.send(Err(connect_failed(format!("JNI attach failed: {e}"))));
                         return;
                     }
                 };
 
-                let rt = match tokio::runtime::Builder::new_current_thread()
-                    .enable_all()
-                    .build()
-                {
+                let rt = match BleRuntime::new() {
                     Ok(rt) => rt,
                     Err(e) => {
-                        let _ = startup_tx.send(Err(LibError::DeviceError(format!(
-                            "failed to build BLE runtime: {e}"
-                        ))));
+                        let _ = startup_tx.send(Err(e));
                         return;
                     }
                 };
@@ -417,13 +814,7 @@ impl BleTransport {
                     return;
                 }
 
-                rt.block_on(Self::event_loop(
-                    service,
-                    peripheral_owned,
-                    event_rx,
-                    notification_stream,
-                    write_char,
-                ));
+                rt.block_on(Self::event_loop(link, event_rx, central_events));
             }));
 
             if let Err(payload) = result {
@@ -438,8 +829,8 @@ impl BleTransport {
             Ok(Ok(())) => {}
             Ok(Err(e)) => return Err(e),
             Err(_) => {
-                return Err(LibError::DeviceError(
-                    "BLE event loop thread exited before signalling startup".to_string(),
+                return Err(connect_failed(
+                    "event loop thread exited before signalling startup",
                 ));
             }
         }
@@ -451,21 +842,21 @@ impl BleTransport {
         })
     }
 
-    #[instrument(skip_all, fields(peripheral_id = %peripheral.id()))]
+    #[instrument(skip_all, fields(peripheral_id = %link.peripheral.id()))]
     async fn event_loop(
-        service: Service,
-        peripheral: Peripheral,
+        mut link: Link,
         mut event_rx: mpsc::Receiver<BleEvent>,
-        mut notification_stream: impl StreamExt<Item = ValueNotification> + Unpin,
-        write_char: Characteristic,
+        mut central_events: CentralEvents,
     ) {
+        let peripheral_id = link.peripheral.id();
         let mut received_packets: VecDeque<Vec<u8>> = VecDeque::new();
         let mut pending_reads: PendingReads = Vec::new();
         let mut poll_manager = PollManager::new();
 
         loop {
             tokio::select! {
-                Some(ValueNotification { value, .. }) = notification_stream.next() => {
+                Some(ValueNotification { value, .. }) = link.session.notifications.next() => {
+                    link.session.writer.size.observe(value.len());
                     if let Some((size, response)) = pending_reads.pop() {
                         if value.len() <= size {
                             let _ = response.send(Ok(value));
@@ -481,6 +872,19 @@ impl BleTransport {
                     poll_manager.notify_all();
                 },
 
+                Some(CentralEvent::DeviceDisconnected(id)) = central_events.next(),
+                    if link.lost_after.is_none() =>
+                {
+                    // Disconnects issued while reconnecting land here too;
+                    // only a link that is actually down needs recovering.
+                    if id == peripheral_id
+                        && !link.is_connected().await
+                        && link.recover().await.is_err()
+                    {
+                        Self::fail_pending(&link, &mut pending_reads, &mut poll_manager);
+                    }
+                },
+
                 event = event_rx.recv() => {
                     // `None` means the parent `BleTransport` was dropped
                     // without sending `Disconnect` — treat channel close as
@@ -490,9 +894,7 @@ impl BleTransport {
                     let Some(event) = event else { break };
                     if !Self::handle_event(
                         event,
-                        &service,
-                        &peripheral,
-                        &write_char,
+                        &mut link,
                         &mut received_packets,
                         &mut pending_reads,
                         &mut poll_manager,
@@ -510,23 +912,26 @@ impl BleTransport {
 
     async fn handle_event(
         event: BleEvent,
-        service: &Service,
-        peripheral: &Peripheral,
-        write_char: &Characteristic,
+        link: &mut Link,
         received_packets: &mut VecDeque<Vec<u8>>,
         pending_reads: &mut PendingReads,
         poll_manager: &mut PollManager,
     ) -> bool {
         match event {
             BleEvent::Write { data, response } => {
-                let result = match peripheral
-                    .write(write_char, &data, WriteType::WithoutResponse)
-                    .await
-                {
-                    Ok(_) => Ok(data.len()),
-                    Err(err) => Err(format!("Write error: {err}")),
-                };
-                let _ = response.send(result);
+                let mut result = link.write(&data).await;
+                // The write found the link down: bring it back and send the
+                // write again, so the download carries on where it was.
+                if result.is_err() && link.lost_after.is_none() && !link.is_connected().await {
+                    result = match link.recover().await {
+                        Ok(()) => link.write(&data).await,
+                        Err(err) => {
+                            Self::fail_pending(link, pending_reads, poll_manager);
+                            Err(err)
+                        }
+                    };
+                }
+                let _ = response.send(result.map(|()| data.len()));
             }
 
             BleEvent::Read { size, response } => {
@@ -539,13 +944,17 @@ impl BleTransport {
                         received_packets.push_front(remainder);
                         let _ = response.send(Ok(result));
                     }
+                } else if let Some(err) = link.lost_error() {
+                    let _ = response.send(Err(err));
                 } else {
                     pending_reads.push((size, response));
                 }
             }
 
             BleEvent::Poll { timeout, response } => {
-                if !received_packets.is_empty() {
+                // A lost link reports readable so the driver's next read
+                // fails at once instead of waiting out its timeout.
+                if !received_packets.is_empty() || link.lost_after.is_some() {
                     let _ = response.send(true);
                 } else {
                     poll_manager.add_poll(timeout, response);
@@ -557,44 +966,76 @@ impl BleTransport {
             }
 
             BleEvent::ReadCharacteristic { uuid, response } => {
-                if let Some(c) = service.characteristics.iter().find(|c| c.uuid == uuid) {
-                    match peripheral.read(c).await {
-                        Ok(data) => {
-                            let _ = response.send(Ok(data));
-                        }
-                        Err(err) => {
-                            let _ = response.send(Err(format!("Read characteristic error: {err}")));
-                        }
-                    }
+                let characteristics = &link.session.service.characteristics;
+                let result = if let Some(err) = link.lost_error() {
+                    Err(err)
+                } else if let Some(c) = characteristics.iter().find(|c| c.uuid == uuid) {
+                    link.peripheral
+                        .read(c)
+                        .await
+                        .map_err(|err| LibError::TransportIo {
+                            transport: Transport::Ble,
+                            reason: format!("reading characteristic {uuid} failed: {err}"),
+                        })
                 } else {
-                    let _ = response.send(Err("Characteristic not found".to_string()));
-                }
+                    Err(LibError::BleServiceNotFound(format!(
+                        "characteristic {uuid}"
+                    )))
+                };
+                let _ = response.send(result);
             }
 
             BleEvent::Disconnect => {
-                let _ = peripheral.disconnect().await;
+                let _ = link.peripheral.disconnect().await;
                 return false;
             }
         }
         true
     }
 
-    #[instrument(skip(adapter), fields(mac_address = %mac_address, service_name = %service_name))]
+    /// Fail every waiting read with the lost link's error and wake pending
+    /// polls so the driver sees it straight away.
+    fn fail_pending(link: &Link, pending_reads: &mut PendingReads, poll_manager: &mut PollManager) {
+        for (_, response) in pending_reads.drain(..) {
+            if let Some(err) = link.lost_error() {
+                let _ = response.send(Err(err));
+            }
+        }
+        poll_manager.notify_all();
+    }
+
+    #[instrument(skip(adapter), fields(target = %target, service_name = %service_name))]
     async fn find_peripheral(
         adapter: &Adapter,
-        mac_address: &str,
+        target: &BleTarget<'_>,
         service_name: &str,
     ) -> Result<Peripheral> {
-        let target = mac_address.to_lowercase();
         let _ = service_name; // only read on Android below; silence warnings elsewhere
 
+        // Handles cached by a scan carry whatever address type the scan saw,
+        // so on Android a random-address computer must skip them and go
+        // through the address-type aware lookup in tier 2.
+        #[cfg(target_os = "android")]
+        let use_cached = !use_random_address(service_name);
+        #[cfg(not(target_os = "android"))]
+        let use_cached = true;
+
+        // A scanned id resolves directly while the manager still knows it.
+        if use_cached
+            && let BleTarget::Id(id) = target
+            && let Ok(peripheral) = adapter.peripheral(id).await
+        {
+            tracing::debug!("ble: resolved via peripheral id (no scan)");
+            return Ok(peripheral);
+        }
+
         // Tier 1: cached peripherals already known to this Manager session.
         // After the first sync of a process this is enough to avoid the
         // 5-second scan; on droidplug the GLOBAL_ADAPTER persists peripherals
         // across calls within the same process.
-        if let Ok(peripherals) = adapter.peripherals().await {
+        if use_cached && let Ok(peripherals) = adapter.peripherals().await {
             for peripheral in peripherals {
-                if Self::peripheral_matches(&peripheral, &target).await {
+                if target.matches(&peripheral).await {
                     tracing::debug!("ble: resolved via cached peripheral lookup (no scan)");
                     return Ok(peripheral);
                 }
@@ -617,7 +1058,7 @@ impl BleTransport {
         // `core/qt-ble.cpp` and prevents `connectGatt()` from racing service
         // discovery with the wrong link-layer address type.
         #[cfg(target_os = "android")]
-        if let Ok(addr) = mac_address.parse::<btleplug::api::BDAddr>() {
+        if let Ok(addr) = target.to_string().parse::<BDAddr>() {
             let id: btleplug::platform::PeripheralId = addr.into();
             let result = if use_random_address(service_name) {
                 tracing::debug!(
@@ -659,13 +1100,13 @@ impl BleTransport {
 
         let peripherals = adapter.peripherals().await?;
         for peripheral in peripherals {
-            if Self::peripheral_matches(&peripheral, &target).await {
+            if target.matches(&peripheral).await {
                 return Ok(peripheral);
             }
         }
 
         Err(LibError::BleDeviceNotFound(format!(
-            "device {mac_address} not found after cached lookup and 5s scan"
+            "device {target} not found after cached lookup and 5s scan"
         )))
     }
 
@@ -673,15 +1114,26 @@ impl BleTransport {
     /// trying both the platform peripheral id (needed on iOS where
     /// CoreBluetooth uses opaque UUIDs instead of MAC addresses) and the
     /// underlying BD address advertised in `properties()` (Linux/Android).
+    ///
+    /// MAC-like targets are also compared numerically, so an address saved
+    /// from a scan in one spelling (`hci0/dev_AA_BB_…`, `AA-BB-…`) still
+    /// finds the peripheral when the platform reports it in another.
     async fn peripheral_matches(peripheral: &Peripheral, target: &str) -> bool {
-        if peripheral.id().to_string().to_lowercase() == target {
+        let id = peripheral.id().to_string().to_lowercase();
+        if id == target {
             return true;
         }
-        if let Ok(Some(props)) = peripheral.properties().await
-            && props.address.to_string().to_lowercase() == target
+        let target_address = target.parse::<BtAddress>().ok();
+        if target_address.is_some() && BtAddress::try_from(&peripheral.id()).ok() == target_address
         {
             return true;
         }
+        if let Ok(Some(props)) = peripheral.properties().await {
+            let address = props.address.to_string().to_lowercase();
+            if address == target || target_address == Some(BtAddress::from(props.address)) {
+                return true;
+            }
+        }
         false
     }
 
@@ -729,22 +1181,21 @@ impl BleTransport {
     /// carries a `oneshot::Sender` for the reply, send it to the worker, and
     /// block on the response. Collapses the three failure axes (channel
     /// closed on send, channel closed on recv, worker-side error) into a
-    /// single `LibError::DeviceError`.
+    /// single `Result`.
     ///
     /// `BleEvent::Poll` doesn't fit this shape because its reply is `bool`
     /// rather than `Result<_, String>`, so `poll_blocking` stays custom.
     fn request<R, F>(&self, make_event: F) -> Result<R>
     where
-        F: FnOnce(oneshot::Sender<std::result::Result<R, String>>) -> BleEvent,
+        F: FnOnce(oneshot::Sender<Result<R>>) -> BleEvent,
     {
         let (tx, rx) = oneshot::channel();
         self.event_tx
             .blocking_send(make_event(tx))
-            .map_err(|_| LibError::DeviceError("BLE event channel closed".to_string()))?;
+            .map_err(|_| LibError::BleError("event channel closed".to_string()))?;
         match rx.blocking_recv() {
-            Ok(Ok(v)) => Ok(v),
-            Ok(Err(err)) => Err(LibError::DeviceError(err)),
-            Err(_) => Err(LibError::DeviceError("BLE channel closed".to_string())),
+            Ok(result) => result,
+            Err(_) => Err(LibError::BleError("worker dropped the reply".to_string())),
         }
     }
 
@@ -779,13 +1230,15 @@ impl BleTransport {
                 timeout,
                 response: tx,
             })
-            .map_err(|_| LibError::DeviceError("BLE event channel closed".to_string()))?;
+            .map_err(|_| LibError::BleError("event channel closed".to_string()))?;
         rx.blocking_recv()
-            .map_err(|_| LibError::DeviceError("BLE channel closed".to_string()))
+            .map_err(|_| LibError::BleError("worker dropped the reply".to_string()))
     }
 
     fn set_timeout(&self, timeout: Duration) {
-        let _ = self.event_tx.blocking_send(BleEvent::SetTimeout { timeout });
+        let _ = self
+            .event_tx
+            .blocking_send(BleEvent::SetTimeout { timeout });
     }
 
     fn get_name(&self) -> &str {
@@ -990,25 +1443,72 @@ extern "C" fn ble_ioctl(
 /// `service_name` is the stored service name from [`services::KNOWN_SERVICES`]
 /// and is used to pick the LE address type on Android — see
 /// [`services::use_random_address`].
-#[instrument(skip(ctx), fields(mac_address = %mac_address, service_name = %service_name))]
 pub fn ble_iostream_open(
     ctx: &crate::context::Context,
     mac_address: &str,
     service_name: &str,
+) -> Result<IoStream> {
+    ble_iostream_open_with(ctx, mac_address, service_name, &BleOptions::default())
+}
+
+/// [`ble_iostream_open`] with connection [`BleOptions`]: progress through
+/// the [`BleState`]s and a PIN callback for computers that require bonding.
+#[instrument(skip(ctx, options), fields(mac_address = %mac_address, service_name = %service_name))]
+pub fn ble_iostream_open_with(
+    ctx: &crate::context::Context,
+    mac_address: &str,
+    service_name: &str,
+    options: &BleOptions,
+) -> Result<IoStream> {
+    let addr = mac_address.strip_prefix("LE:").unwrap_or(mac_address);
+    open_target(ctx, &BleTarget::Address(addr), service_name, options)
+}
+
+/// Open a BLE iostream for a peripheral by its platform id, as reported by a
+/// scan. Needed on macOS/iOS, where CoreBluetooth exposes an opaque UUID
+/// instead of the MAC and [`ble_iostream_open`] has no address to match.
+#[instrument(skip(ctx, options), fields(peripheral_id = %id, service_name = %service_name))]
+pub fn ble_iostream_open_peripheral(
+    ctx: &crate::context::Context,
+    id: &PeripheralId,
+    service_name: &str,
+    options: &BleOptions,
+) -> Result<IoStream> {
+    open_target(ctx, &BleTarget::Id(id), service_name, options)
+}
+
+/// Rebuild a [`PeripheralId`] from its string form, where the platform
+/// allows it. BlueZ ids wrap a D-Bus path btleplug doesn't let us construct;
+/// its address-based lookup works there anyway.
+pub(crate) fn parse_peripheral_id(id: &str) -> Option<PeripheralId> {
+    #[cfg(target_vendor = "apple")]
+    let parsed = id.parse::<Uuid>().ok().map(PeripheralId::from);
+    #[cfg(any(target_os = "android", target_os = "windows"))]
+    let parsed = id.parse::<BDAddr>().ok().map(PeripheralId::from);
+    #[cfg(not(any(target_vendor = "apple", target_os = "android", target_os = "windows")))]
+    let parsed = {
+        let _ = id;
+        None
+    };
+    parsed
+}
+
+fn open_target(
+    ctx: &crate::context::Context,
+    target: &BleTarget<'_>,
+    service_name: &str,
+    options: &BleOptions,
 ) -> Result<IoStream> {
     #[cfg(target_os = "android")]
     let _jni_guard = android::attach_current_thread()
-        .map_err(|e| LibError::DeviceError(format!("JNI attach failed: {e}")))?;
-
-    // Create a temporary runtime for the async connection.
-    let rt = tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .build()
-        .map_err(|e| LibError::DeviceError(e.to_string()))?;
+        .map_err(|e| connect_failed(format!("JNI attach failed: {e}")))?;
+    #[cfg(target_os = "android")]
+    crate::android::check_permissions()?;
 
-    let addr = mac_address.strip_prefix("LE:").unwrap_or(mac_address);
+    // Shared or temporary runtime for the async connection.
+    let rt = BleRuntime::new()?;
 
-    let transport = rt.block_on(BleTransport::connect(addr, service_name))?;
+    let transport = rt.block_on(BleTransport::connect(target, service_name, options))?;
     let io_ptr = Box::into_raw(Box::new(transport)) as *mut c_void;
 
     let callbacks = ffi::dc_custom_cbs_t {
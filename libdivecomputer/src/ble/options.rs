@@ -0,0 +1,218 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Stage of opening a BLE session, reported through [`BleOptions::on_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BleState {
+    /// Looking for the peripheral (cached lookup, then an active scan).
+    Searching,
+    /// Bonding with the dive computer; a PIN may be requested through
+    /// [`BleOptions::pin`].
+    Pairing,
+    /// Establishing the GATT connection. Reported once per attempt.
+    Connecting,
+    /// Discovering services and subscribing to notifications.
+    Discovering,
+    /// Session is open and ready for the driver.
+    Ready,
+    /// The link dropped mid-session and is being re-established under the
+    /// [`ReconnectPolicy`]. Reported once per attempt; followed by
+    /// `Connecting`, `Discovering` and `Ready` on success.
+    Reconnecting,
+}
+
+/// How an open BLE session recovers when the link drops, e.g. when the diver
+/// walks away from the phone mid-download.
+///
+/// Each attempt waits, reconnects, rediscovers services and resubscribes;
+/// the write that found the link down is then sent again. Bytes the computer
+/// sent while disconnected are lost, which the drivers' own timeouts and
+/// retries already cope with. Once every attempt has failed, the session
+/// fails with [`LibError::BleDisconnected`](crate::error::LibError::BleDisconnected).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Reconnect attempts before giving up; `0` disables reconnecting.
+    pub max_attempts: u32,
+    /// Wait before the first attempt, doubled for each one after it.
+    pub initial_delay: Duration,
+    /// Cap on the wait between attempts.
+    pub max_delay: Duration,
+}
+
+impl ReconnectPolicy {
+    /// Never reconnect: the first dropped link fails the session.
+    pub const fn none() -> Self {
+        Self {
+            max_attempts: 0,
+            initial_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        }
+    }
+
+    /// Wait before reconnect `attempt` (starting at 1).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let doublings = attempt.saturating_sub(1).min(16);
+        self.initial_delay
+            .saturating_mul(1 << doublings)
+            .min(self.max_delay)
+    }
+}
+
+/// Three attempts, 0.5 s apart at first and backing off to 4 s: enough to
+/// ride out a brief dropout without leaving a UI hanging on a computer that
+/// has gone to sleep.
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(4),
+        }
+    }
+}
+
+type StateCallback = Arc<dyn Fn(BleState) + Send + Sync>;
+type PinCallback = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Options for opening a BLE session with
+/// [`ble_iostream_open_with`](super::ble_iostream_open_with).
+///
+/// ```no_run
+/// use libdivecomputer::ble::{BleOptions, BleState};
+///
+/// let options = BleOptions::new()
+///     .on_state(|state| {
+///         if state == BleState::Pairing {
+///             println!("check the dive computer for a PIN");
+///         }
+///     })
+///     .pin(|device_name| {
+///         println!("PIN shown on {device_name}:");
+///         let mut line = String::new();
+///         std::io::stdin().read_line(&mut line).ok()?;
+///         Some(line.trim().to_string())
+///     });
+/// ```
+#[derive(Clone, Default)]
+pub struct BleOptions {
+    pub(crate) on_state: Option<StateCallback>,
+    pub(crate) pin: Option<PinCallback>,
+    pub(crate) max_write_size: Option<usize>,
+    pub(crate) reconnect: ReconnectPolicy,
+}
+
+impl BleOptions {
+    /// Options with no callbacks; equivalent to plain
+    /// [`ble_iostream_open`](super::ble_iostream_open).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report each [`BleState`] the connection passes through, e.g. to show
+    /// "pairing…" in a UI.
+    #[must_use]
+    pub fn on_state(mut self, f: impl Fn(BleState) + Send + Sync + 'static) -> Self {
+        self.on_state = Some(Arc::new(f));
+        self
+    }
+
+    /// Supply the PIN when the dive computer asks to bond (Shearwater Tern,
+    /// some Mares units). Called with the device name once the computer is
+    /// showing its PIN; return `None` to abort.
+    ///
+    /// The callback may block, e.g. on a channel fed by the UI. It is only
+    /// used on Android; desktop stacks route PIN entry through the system's
+    /// pairing agent (the BlueZ agent, Windows or macOS pairing dialogs), so
+    /// bond once there before connecting.
+    #[must_use]
+    pub fn pin(mut self, f: impl Fn(&str) -> Option<String> + Send + Sync + 'static) -> Self {
+        self.pin = Some(Arc::new(f));
+        self
+    }
+
+    /// Split outgoing writes into packets of at most `size` bytes, replacing
+    /// the per-vendor default from
+    /// [`services::max_write_size`](super::services::max_write_size).
+    ///
+    /// btleplug does not report the negotiated MTU, so the default starts
+    /// at the 23-byte minimum and only grows once the computer sends larger
+    /// notifications. Set it to `MTU - 3` when the platform is known to
+    /// negotiate more (iOS and recent Android usually do) to cut the number
+    /// of writes from the start, e.g. for firmware uploads. A size set here
+    /// is used as is.
+    #[must_use]
+    pub fn max_write_size(mut self, size: usize) -> Self {
+        self.max_write_size = Some(size.max(1));
+        self
+    }
+
+    /// Recover dropped links with `policy` instead of
+    /// [`ReconnectPolicy::default`].
+    #[must_use]
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = policy;
+        self
+    }
+
+    pub(crate) fn report(&self, state: BleState) {
+        tracing::debug!(?state, "ble: state change");
+        if let Some(on_state) = &self.on_state {
+            on_state(state);
+        }
+    }
+}
+
+impl fmt::Debug for BleOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BleOptions")
+            .field("on_state", &self.on_state.is_some())
+            .field("pin", &self.pin.is_some())
+            .field("max_write_size", &self.max_write_size)
+            .field("reconnect", &self.reconnect)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn reports_states_and_asks_for_pin() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&seen);
+        let options = BleOptions::new()
+            .on_state(move |state| recorder.lock().unwrap().push(state))
+            .pin(|name| (name == "Tern").then(|| "123456".to_string()));
+
+        options.report(BleState::Pairing);
+        options.report(BleState::Ready);
+        assert_eq!(*seen.lock().unwrap(), [BleState::Pairing, BleState::Ready]);
+
+        let pin = options.pin.as_ref().unwrap();
+        assert_eq!(pin("Tern").as_deref(), Some("123456"));
+        assert_eq!(pin("Perdix"), None);
+
+        assert_eq!(BleOptions::new().max_write_size(0).max_write_size, Some(1));
+
+        // Without callbacks, reporting is a no-op.
+        BleOptions::default().report(BleState::Searching);
+    }
+    #[test]
+    fn reconnect_delay_backs_off_to_cap() {
+        let policy = ReconnectPolicy::default();
+        let delays: Vec<_> = (1..=6).map(|attempt| policy.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            [500, 1000, 2000, 4000, 4000, 4000].map(Duration::from_millis)
+        );
+        assert_eq!(policy.delay(u32::MAX), policy.max_delay);
+
+        let options = BleOptions::new().reconnect(ReconnectPolicy::none());
+        assert_eq!(options.reconnect.max_attempts, 0);
+    }
+}
@@ -0,0 +1,150 @@
+//! Android bonding with PIN entry for BLE dive computers.
+//!
+//! btleplug has no pairing API, so this drives `android.bluetooth.BluetoothDevice`
+//! over JNI: start bonding, ask the application for the PIN the dive computer
+//! is displaying, hand it to the stack, and wait for the bond to settle.
+
+use std::time::{Duration, Instant};
+
+use jni::JNIEnv;
+use jni::objects::{JObject, JValue};
+
+use super::connect_failed;
+use super::options::{BleOptions, BleState};
+use crate::android::{check_and_clear_exception, get_env};
+use crate::error::{LibError, Result};
+
+/// `BluetoothDevice.BOND_NONE`.
+const BOND_NONE: i32 = 10;
+/// `BluetoothDevice.BOND_BONDED`.
+const BOND_BONDED: i32 = 12;
+
+/// How long to wait for the bond after the PIN has been supplied.
+const BOND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Interval between bond-state checks and `setPin` retries.
+const BOND_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Bond with the device at `address` unless it already is. A no-op without a
+/// [`BleOptions::pin`] callback, leaving any bonding to the system dialog.
+///
+/// Blocks for as long as the user takes to enter the PIN and the stack takes
+/// to bond, so it runs on a blocking thread attached to the JVM, never on a
+/// runtime worker. The bond state is polled: `ACTION_BOND_STATE_CHANGED`
+/// can only be received by a `BroadcastReceiver` subclass, which JNI cannot
+/// define without shipping Java code.
+pub(crate) fn ensure_bonded(address: &str, device_name: &str, options: &BleOptions) -> Result<()> {
+    let Some(pin) = options.pin.as_ref() else {
+        return Ok(());
+    };
+
+    let env = get_env()?;
+    let device = remote_device(&env, address)?;
+    if bond_state(&env, device)? == BOND_BONDED {
+        return Ok(());
+    }
+
+    options.report(BleState::Pairing);
+    tracing::debug!(address, "ble: bonding");
+
+    // Starting the bond is what makes the dive computer show its PIN.
+    let started = env
+        .call_method(device, "createBond", "()Z", &[])
+        .and_then(|v| v.z())
+        .map_err(|e| connect_failed(format!("createBond failed: {e}")))?;
+    check_and_clear_exception(&env, "createBond")?;
+    if !started && bond_state(&env, device)? == BOND_NONE {
+        return Err(connect_failed(format!(
+            "could not start bonding with {address}"
+        )));
+    }
+
+    let pin = pin(device_name).ok_or(LibError::Cancelled)?;
+    let pin_bytes = env
+        .byte_array_from_slice(pin.as_bytes())
+        .map_err(|e| connect_failed(format!("byte_array_from_slice failed: {e}")))?;
+
+    // `setPin` only succeeds once the stack has raised its pairing request,
+    // which may lag `createBond`; keep offering the PIN until it is taken.
+    let deadline = Instant::now() + BOND_TIMEOUT;
+    let mut pin_accepted = false;
+    loop {
+        if !pin_accepted {
+            pin_accepted = env
+                .call_method(
+                    device,
+                    "setPin",
+                    "([B)Z",
+                    &[JValue::Object(JObject::from(pin_bytes))],
+                )
+                .and_then(|v| v.z())
+                .map_err(|e| connect_failed(format!("setPin failed: {e}")))?;
+            check_and_clear_exception(&env, "setPin")?;
+        }
+
+        match bond_state(&env, device)? {
+            BOND_BONDED => {
+                tracing::debug!(address, "ble: bonded");
+                return Ok(());
+            }
+            BOND_NONE if pin_accepted => {
+                return Err(connect_failed(format!(
+                    "{device_name} rejected the pairing PIN"
+                )));
+            }
+            _ => {}
+        }
+        if Instant::now() >= deadline {
+            return Err(LibError::Status(
+                crate::status::Status::Timeout,
+                Some(format!("bonding with {device_name} did not complete")),
+            ));
+        }
+        std::thread::sleep(BOND_POLL_INTERVAL);
+    }
+}
+
+fn remote_device<'a>(env: &JNIEnv<'a>, address: &str) -> Result<JObject<'a>> {
+    // BluetoothAdapter adapter = BluetoothAdapter.getDefaultAdapter();
+    let adapter = env
+        .call_static_method(
+            "android/bluetooth/BluetoothAdapter",
+            "getDefaultAdapter",
+            "()Landroid/bluetooth/BluetoothAdapter;",
+            &[],
+        )
+        .and_then(|v| v.l())
+        .map_err(|e| connect_failed(format!("getDefaultAdapter failed: {e}")))?;
+    check_and_clear_exception(env, "getDefaultAdapter")?;
+    if adapter.is_null() {
+        return Err(LibError::NoBluetoothAdapter);
+    }
+
+    // BluetoothDevice device = adapter.getRemoteDevice(address);
+    let j_address = env
+        .new_string(address.to_uppercase())
+        .map_err(|e| connect_failed(format!("new_string failed: {e}")))?;
+    let device = env
+        .call_method(
+            adapter,
+            "getRemoteDevice",
+            "(Ljava/lang/String;)Landroid/bluetooth/BluetoothDevice;",
+            &[JValue::Object(j_address.into())],
+        )
+        .and_then(|v| v.l())
+        .map_err(|e| connect_failed(format!("getRemoteDevice failed: {e}")))?;
+    check_and_clear_exception(env, "getRemoteDevice")?;
+    if device.is_null() {
+        return Err(LibError::BleDeviceNotFound(address.to_string()));
+    }
+    Ok(device)
+}
+
+fn bond_state(env: &JNIEnv, device: JObject) -> Result<i32> {
+    let state = env
+        .call_method(device, "getBondState", "()I", &[])
+        .and_then(|v| v.i())
+        .map_err(|e| connect_failed(format!("getBondState failed: {e}")))?;
+    check_and_clear_exception(env, "getBondState")?;
+    Ok(state)
+}
@@ -0,0 +1,100 @@
+use std::future::Future;
+use std::sync::OnceLock;
+
+use tokio::runtime::{Builder, Handle, Runtime};
+
+use crate::error::{LibError, Result};
+
+static SHARED: OnceLock<Handle> = OnceLock::new();
+
+/// Run BLE work on the application's tokio runtime instead of a private
+/// runtime built for each scan and connection.
+///
+/// Call once at startup, before any BLE scan or connection. The BLE entry
+/// points are blocking and drive their futures with
+/// [`Handle::block_on`], so they must still be called from outside async
+/// code, e.g. from [`tokio::task::spawn_blocking`]. The handle must belong
+/// to a multi-threaded runtime with I/O and time enabled; a current-thread
+/// runtime would not make progress while the caller blocks.
+///
+/// On Android, btleplug polls its futures on the runtime's worker threads,
+/// and those must be attached to the JVM. Build the runtime from
+/// [`runtime_builder`], which attaches each worker as it starts; BLE
+/// operations on a shared runtime whose workers are not attached fail with
+/// [`LibError::InvalidArguments`].
+///
+/// Returns [`LibError::InvalidArguments`] if a runtime was already set.
+pub fn set_runtime(handle: Handle) -> Result<()> {
+    SHARED
+        .set(handle)
+        .map_err(|_| LibError::InvalidArguments("BLE runtime already set".into()))
+}
+
+/// A multi-threaded runtime builder with I/O and time enabled, suitable for
+/// [`set_runtime`]. On Android its worker threads attach to the JVM when
+/// they start and detach when they stop.
+pub fn runtime_builder() -> Builder {
+    let mut builder = Builder::new_multi_thread();
+    builder.enable_all();
+    #[cfg(target_os = "android")]
+    builder
+        .on_thread_start(|| match crate::android::attach_current_thread() {
+            Ok(guard) => crate::android::JNI_ENV.with(|env| *env.borrow_mut() = Some(guard)),
+            Err(e) => tracing::error!("failed to attach BLE runtime worker to the JVM: {e}"),
+        })
+        .on_thread_stop(|| crate::android::JNI_ENV.with(|env| drop(env.borrow_mut().take())));
+    builder
+}
+
+/// Whether the shared runtime's workers can call into the JVM. Checked once
+/// by running a task on a worker.
+#[cfg(target_os = "android")]
+fn workers_attached(handle: &Handle) -> bool {
+    static ATTACHED: OnceLock<bool> = OnceLock::new();
+    *ATTACHED.get_or_init(|| {
+        handle.block_on(async {
+            tokio::spawn(async {
+                crate::android::JAVAVM
+                    .get()
+                    .is_some_and(|vm| vm.get_env().is_ok())
+            })
+            .await
+            .unwrap_or(false)
+        })
+    })
+}
+
+/// The runtime a BLE operation runs on: the one given to [`set_runtime`],
+/// or a current-thread runtime of its own.
+pub(super) enum BleRuntime {
+    Shared(Handle),
+    Owned(Runtime),
+}
+
+impl BleRuntime {
+    pub(super) fn new() -> Result<Self> {
+        if let Some(handle) = SHARED.get() {
+            #[cfg(target_os = "android")]
+            if !workers_attached(handle) {
+                return Err(LibError::InvalidArguments(
+                    "BLE runtime workers are not attached to the JVM; build the runtime \
+                     with ble::runtime_builder"
+                        .into(),
+                ));
+            }
+            return Ok(Self::Shared(handle.clone()));
+        }
+        Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map(Self::Owned)
+            .map_err(|e| LibError::BleError(format!("failed to build runtime: {e}")))
+    }
+
+    pub(super) fn block_on<F: Future>(&self, future: F) -> F::Output {
+        match self {
+            Self::Shared(handle) => handle.block_on(future),
+            Self::Owned(rt) => rt.block_on(future),
+        }
+    }
+}
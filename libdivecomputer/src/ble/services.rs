@@ -46,6 +46,27 @@ pub const KNOWN_SERVICES: &[(Uuid, &str)] = &[
     ),
 ];
 
+/// Catalog service name for `vendor` (a [`Descriptor`](crate::Descriptor)
+/// vendor such as "Shearwater" or "Heinrichs Weikamp"), so devices found by
+/// advertised name get the same quirks as those found by service UUID.
+/// Falls back to `vendor` itself when no catalog entry belongs to it.
+///
+/// Compares letters and digits only, ignoring case, since the catalog and
+/// the descriptors spell some vendors differently.
+pub fn service_name_for_vendor(vendor: &str) -> &str {
+    fn key(s: &str) -> impl Iterator<Item = char> + '_ {
+        s.chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_lowercase())
+    }
+    let vendor_len = key(vendor).count();
+    KNOWN_SERVICES
+        .iter()
+        .map(|(_, name)| *name)
+        .find(|name| vendor_len > 0 && key(name).take(vendor_len).eq(key(vendor)))
+        .unwrap_or(vendor)
+}
+
 /// Whether a BLE dive computer advertises with a random static LE address.
 ///
 /// Mirrors Subsurface's `use_random_address()` helper in `core/qt-ble.cpp`:
@@ -60,3 +81,151 @@ pub const KNOWN_SERVICES: &[(Uuid, &str)] = &[
 pub fn use_random_address(service_name: &str) -> bool {
     service_name.contains("Shearwater") || service_name.contains("Garmin")
 }
+
+/// Largest write every BLE link accepts: the default ATT MTU of 23 bytes
+/// minus the 3-byte ATT header. Used until something says otherwise.
+pub const DEFAULT_WRITE_SIZE: usize = 20;
+
+/// Longest attribute value ATT allows, used for computers whose writes must
+/// not be split at all.
+const MAX_ATTRIBUTE_LEN: usize = 512;
+
+/// Per-vendor write size overrides, matched by service-name substring like
+/// [`use_random_address`].
+///
+/// Most protocols are byte streams and survive being split at any size. The
+/// Pelagic, Divesoft and Halcyon protocols are packet-oriented instead:
+/// each write is one frame that the driver already sizes for the link, and
+/// splitting it corrupts the frame.
+pub const WRITE_SIZE_OVERRIDES: &[(&str, usize)] = &[
+    ("Pelagic", MAX_ATTRIBUTE_LEN),
+    ("Divesoft", MAX_ATTRIBUTE_LEN),
+    ("Halcyon", MAX_ATTRIBUTE_LEN),
+];
+
+/// Size outgoing writes are split into for the computer behind
+/// `service_name`, from [`WRITE_SIZE_OVERRIDES`] or [`DEFAULT_WRITE_SIZE`].
+///
+/// btleplug does not report the negotiated MTU, so the default assumes the
+/// smallest one. An open connection raises it as notifications show the
+/// link carries more; use
+/// [`BleOptions::max_write_size`](super::BleOptions::max_write_size) to fix
+/// it up front.
+pub fn max_write_size(service_name: &str) -> usize {
+    write_size(service_name).current
+}
+
+/// Write size for the computer behind `service_name`: fixed for the
+/// packet-oriented vendors in [`WRITE_SIZE_OVERRIDES`], otherwise
+/// [`DEFAULT_WRITE_SIZE`] raised as the link proves it carries more.
+pub(crate) fn write_size(service_name: &str) -> WriteSize {
+    WRITE_SIZE_OVERRIDES
+        .iter()
+        .find(|(vendor, _)| service_name.contains(vendor))
+        .map_or(WriteSize::negotiated(), |(_, size)| WriteSize::fixed(*size))
+}
+
+/// How large outgoing writes on a link may be.
+///
+/// btleplug has no MTU query on any platform, but a notification can carry
+/// at most `MTU - 3` bytes, the same limit a write without response has. So
+/// the largest notification the computer has sent is a lower bound on what
+/// the negotiated MTU lets us write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WriteSize {
+    initial: usize,
+    current: usize,
+    negotiated: bool,
+}
+
+impl WriteSize {
+    /// Always `size`, whatever the link reports.
+    pub(crate) fn fixed(size: usize) -> Self {
+        Self {
+            initial: size,
+            current: size,
+            negotiated: false,
+        }
+    }
+
+    /// [`DEFAULT_WRITE_SIZE`], raised by [`observe`](Self::observe).
+    pub(crate) fn negotiated() -> Self {
+        Self {
+            negotiated: true,
+            ..Self::fixed(DEFAULT_WRITE_SIZE)
+        }
+    }
+
+    /// Bytes a single write may carry right now.
+    pub(crate) fn get(self) -> usize {
+        self.current
+    }
+
+    /// Account for a notification of `len` bytes received on the link.
+    pub(crate) fn observe(&mut self, len: usize) {
+        if self.negotiated && len > self.current {
+            self.current = len.min(MAX_ATTRIBUTE_LEN);
+            tracing::debug!(
+                write_size = self.current,
+                "ble: link carries larger packets"
+            );
+        }
+    }
+
+    /// The size to start a new connection with, which negotiates its MTU
+    /// afresh.
+    pub(crate) fn reset(self) -> Self {
+        Self {
+            current: self.initial,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_size_overrides_by_vendor() {
+        assert_eq!(max_write_size("Shearwater"), DEFAULT_WRITE_SIZE);
+        assert_eq!(max_write_size("Pelagic (i330R, DSX)"), MAX_ATTRIBUTE_LEN);
+        assert_eq!(max_write_size("Divesoft"), MAX_ATTRIBUTE_LEN);
+        assert_eq!(max_write_size(""), DEFAULT_WRITE_SIZE);
+    }
+
+    #[test]
+    fn write_size_grows_with_notifications() {
+        let mut size = write_size("Shearwater (Perdix/Teric/Peregrine/Tern)");
+        size.observe(12);
+        assert_eq!(size.get(), DEFAULT_WRITE_SIZE);
+        size.observe(244);
+        assert_eq!(size.get(), 244);
+        size.observe(100);
+        assert_eq!(size.get(), 244);
+        size.observe(4096);
+        assert_eq!(size.get(), MAX_ATTRIBUTE_LEN);
+        assert_eq!(size.reset().get(), DEFAULT_WRITE_SIZE);
+
+        let mut fixed = WriteSize::fixed(64);
+        fixed.observe(244);
+        assert_eq!(fixed.get(), 64);
+        assert_eq!(write_size("Divesoft").get(), MAX_ATTRIBUTE_LEN);
+    }
+
+    #[test]
+    fn vendor_resolves_to_catalog_service() {
+        assert_eq!(
+            service_name_for_vendor("Shearwater"),
+            "Shearwater (Perdix/Teric/Peregrine/Tern)"
+        );
+        assert_eq!(
+            service_name_for_vendor("Heinrichs Weikamp"),
+            "Heinrichs-Weikamp (Telit/Stollmann)"
+        );
+        assert_eq!(service_name_for_vendor("Scubapro"), "ScubaPro (G2, G3)");
+        assert_eq!(service_name_for_vendor("Oceanic"), "Oceanic");
+        assert_eq!(service_name_for_vendor(""), "");
+        assert!(use_random_address(service_name_for_vendor("Shearwater")));
+    }
+}
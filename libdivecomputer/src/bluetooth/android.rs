@@ -5,11 +5,10 @@
 //! and open RFCOMM connections without any Kotlin helper classes.
 
 use jni::objects::{GlobalRef, JObject, JValue};
-use jni::JNIEnv;
 
+use crate::android::{check_and_clear_exception, get_env};
 use crate::device::{ConnectionInfo, DeviceInfo};
 use crate::error::{LibError, Result};
-use crate::scanner::{format_bluetooth_address, mac_string_to_u64};
 use crate::transport::Transport;
 
 /// Standard Serial Port Profile UUID used by dive computers.
@@ -28,23 +27,25 @@ pub struct BluetoothSocket {
 #[expect(unsafe_code)]
 unsafe impl Send for BluetoothSocket {}
 
-fn get_env() -> Result<JNIEnv<'static>> {
-    let vm = crate::android::JAVAVM
-        .get()
-        .ok_or_else(|| LibError::DeviceError("JavaVM not initialized".to_string()))?;
-    vm.get_env()
-        .map_err(|e| LibError::DeviceError(format!("Failed to get JNIEnv: {e}")))
+fn scan_failed(reason: impl Into<String>) -> LibError {
+    LibError::ScanFailed {
+        transport: Transport::Bluetooth,
+        reason: reason.into(),
+    }
 }
 
-fn check_and_clear_exception(env: &JNIEnv, context: &str) -> Result<()> {
-    if env.exception_check().unwrap_or(false) {
-        let _ = env.exception_describe();
-        let _ = env.exception_clear();
-        return Err(LibError::DeviceError(format!(
-            "Java exception in {context}"
-        )));
+fn connect_failed(reason: impl Into<String>) -> LibError {
+    LibError::ConnectFailed {
+        transport: Transport::Bluetooth,
+        reason: reason.into(),
+    }
+}
+
+fn io_failed(reason: impl Into<String>) -> LibError {
+    LibError::TransportIo {
+        transport: Transport::Bluetooth,
+        reason: reason.into(),
     }
-    Ok(())
 }
 
 /// Return paired/bonded classic Bluetooth devices via JNI.
@@ -52,6 +53,7 @@ fn check_and_clear_exception(env: &JNIEnv, context: &str) -> Result<()> {
 /// Calls `BluetoothAdapter.getDefaultAdapter().getBondedDevices()` and
 /// filters to devices with classic BT support (`getType() & 1 != 0`).
 pub fn get_bonded_devices() -> Result<Vec<DeviceInfo>> {
+    crate::android::check_permissions()?;
     let env = get_env()?;
     let mut devices = Vec::new();
 
@@ -63,23 +65,21 @@ pub fn get_bonded_devices() -> Result<Vec<DeviceInfo>> {
             "()Landroid/bluetooth/BluetoothAdapter;",
             &[],
         )
-        .map_err(|e| LibError::DeviceError(format!("getDefaultAdapter failed: {e}")))?
+        .map_err(|e| scan_failed(format!("getDefaultAdapter failed: {e}")))?
         .l()
-        .map_err(|e| LibError::DeviceError(format!("getDefaultAdapter result: {e}")))?;
+        .map_err(|e| scan_failed(format!("getDefaultAdapter result: {e}")))?;
     check_and_clear_exception(&env, "getDefaultAdapter")?;
 
     if adapter.is_null() {
-        return Err(LibError::DeviceError(
-            "No Bluetooth adapter available".to_string(),
-        ));
+        return Err(LibError::NoBluetoothAdapter);
     }
 
     // Set<BluetoothDevice> bonded = adapter.getBondedDevices();
     let bonded_set = env
         .call_method(adapter, "getBondedDevices", "()Ljava/util/Set;", &[])
-        .map_err(|e| LibError::DeviceError(format!("getBondedDevices failed: {e}")))?
+        .map_err(|e| scan_failed(format!("getBondedDevices failed: {e}")))?
         .l()
-        .map_err(|e| LibError::DeviceError(format!("getBondedDevices result: {e}")))?;
+        .map_err(|e| scan_failed(format!("getBondedDevices result: {e}")))?;
     check_and_clear_exception(&env, "getBondedDevices")?;
 
     if bonded_set.is_null() {
@@ -89,9 +89,9 @@ pub fn get_bonded_devices() -> Result<Vec<DeviceInfo>> {
     // int size = bonded.size();
     let size = env
         .call_method(bonded_set, "size", "()I", &[])
-        .map_err(|e| LibError::DeviceError(format!("Set.size failed: {e}")))?
+        .map_err(|e| scan_failed(format!("Set.size failed: {e}")))?
         .i()
-        .map_err(|e| LibError::DeviceError(format!("Set.size result: {e}")))?;
+        .map_err(|e| scan_failed(format!("Set.size result: {e}")))?;
     check_and_clear_exception(&env, "Set.size")?;
 
     if size == 0 {
@@ -101,18 +101,18 @@ pub fn get_bonded_devices() -> Result<Vec<DeviceInfo>> {
     // Iterator<BluetoothDevice> iter = bonded.iterator();
     let iterator = env
         .call_method(bonded_set, "iterator", "()Ljava/util/Iterator;", &[])
-        .map_err(|e| LibError::DeviceError(format!("Set.iterator failed: {e}")))?
+        .map_err(|e| scan_failed(format!("Set.iterator failed: {e}")))?
         .l()
-        .map_err(|e| LibError::DeviceError(format!("Set.iterator result: {e}")))?;
+        .map_err(|e| scan_failed(format!("Set.iterator result: {e}")))?;
     check_and_clear_exception(&env, "Set.iterator")?;
 
     for _ in 0..size {
         // BluetoothDevice dev = iter.next();
         let dev = env
             .call_method(iterator, "next", "()Ljava/lang/Object;", &[])
-            .map_err(|e| LibError::DeviceError(format!("Iterator.next failed: {e}")))?
+            .map_err(|e| scan_failed(format!("Iterator.next failed: {e}")))?
             .l()
-            .map_err(|e| LibError::DeviceError(format!("Iterator.next result: {e}")))?;
+            .map_err(|e| scan_failed(format!("Iterator.next result: {e}")))?;
         check_and_clear_exception(&env, "Iterator.next")?;
 
         if dev.is_null() {
@@ -123,7 +123,7 @@ pub fn get_bonded_devices() -> Result<Vec<DeviceInfo>> {
         // 1 = DEVICE_TYPE_CLASSIC, 2 = DEVICE_TYPE_LE, 3 = DEVICE_TYPE_DUAL
         let bt_type = env
             .call_method(dev, "getType", "()I", &[])
-            .map_err(|e| LibError::DeviceError(format!("getType failed: {e}")))?
+            .map_err(|e| scan_failed(format!("getType failed: {e}")))?
             .i()
             .unwrap_or(0);
         check_and_clear_exception(&env, "getType")?;
@@ -136,22 +136,22 @@ pub fn get_bonded_devices() -> Result<Vec<DeviceInfo>> {
         // String address = dev.getAddress();
         let address_jstr = env
             .call_method(dev, "getAddress", "()Ljava/lang/String;", &[])
-            .map_err(|e| LibError::DeviceError(format!("getAddress failed: {e}")))?
+            .map_err(|e| scan_failed(format!("getAddress failed: {e}")))?
             .l()
-            .map_err(|e| LibError::DeviceError(format!("getAddress result: {e}")))?;
+            .map_err(|e| scan_failed(format!("getAddress result: {e}")))?;
         check_and_clear_exception(&env, "getAddress")?;
 
         let address_string: String = env
             .get_string(address_jstr.into())
-            .map_err(|e| LibError::DeviceError(format!("getAddress string: {e}")))?
+            .map_err(|e| scan_failed(format!("getAddress string: {e}")))?
             .into();
 
         // String name = dev.getName();
         let name_jstr = env
             .call_method(dev, "getName", "()Ljava/lang/String;", &[])
-            .map_err(|e| LibError::DeviceError(format!("getName failed: {e}")))?
+            .map_err(|e| scan_failed(format!("getName failed: {e}")))?
             .l()
-            .map_err(|e| LibError::DeviceError(format!("getName result: {e}")))?;
+            .map_err(|e| scan_failed(format!("getName result: {e}")))?;
         check_and_clear_exception(&env, "getName")?;
 
         let name = if name_jstr.is_null() {
@@ -159,12 +159,12 @@ pub fn get_bonded_devices() -> Result<Vec<DeviceInfo>> {
         } else {
             let s: String = env
                 .get_string(name_jstr.into())
-                .map_err(|e| LibError::DeviceError(format!("getName string: {e}")))?
+                .map_err(|e| scan_failed(format!("getName string: {e}")))?
                 .into();
             s
         };
 
-        let address = mac_string_to_u64(&address_string).unwrap_or(0);
+        let address = address_string.parse().unwrap_or_default();
 
         devices.push(DeviceInfo {
             name,
@@ -173,6 +173,7 @@ pub fn get_bonded_devices() -> Result<Vec<DeviceInfo>> {
                 address,
                 address_string,
             },
+            advertisement: None,
         });
     }
 
@@ -183,6 +184,7 @@ pub fn get_bonded_devices() -> Result<Vec<DeviceInfo>> {
 ///
 /// The device must already be paired via Android Settings.
 pub fn connect(address: &str) -> Result<BluetoothSocket> {
+    crate::android::check_permissions()?;
     let env = get_env()?;
 
     // BluetoothAdapter adapter = BluetoothAdapter.getDefaultAdapter();
@@ -193,21 +195,19 @@ pub fn connect(address: &str) -> Result<BluetoothSocket> {
             "()Landroid/bluetooth/BluetoothAdapter;",
             &[],
         )
-        .map_err(|e| LibError::DeviceError(format!("getDefaultAdapter failed: {e}")))?
+        .map_err(|e| connect_failed(format!("getDefaultAdapter failed: {e}")))?
         .l()
-        .map_err(|e| LibError::DeviceError(format!("getDefaultAdapter result: {e}")))?;
+        .map_err(|e| connect_failed(format!("getDefaultAdapter result: {e}")))?;
     check_and_clear_exception(&env, "getDefaultAdapter")?;
 
     if adapter.is_null() {
-        return Err(LibError::DeviceError(
-            "No Bluetooth adapter available".to_string(),
-        ));
+        return Err(LibError::NoBluetoothAdapter);
     }
 
     // BluetoothDevice device = adapter.getRemoteDevice(address);
     let j_address = env
         .new_string(address)
-        .map_err(|e| LibError::DeviceError(format!("new_string failed: {e}")))?;
+        .map_err(|e| connect_failed(format!("new_string failed: {e}")))?;
     let device = env
         .call_method(
             adapter,
@@ -215,13 +215,13 @@ pub fn connect(address: &str) -> Result<BluetoothSocket> {
             "(Ljava/lang/String;)Landroid/bluetooth/BluetoothDevice;",
             &[JValue::Object(j_address.into())],
         )
-        .map_err(|e| LibError::DeviceError(format!("getRemoteDevice failed: {e}")))?
+        .map_err(|e| connect_failed(format!("getRemoteDevice failed: {e}")))?
         .l()
-        .map_err(|e| LibError::DeviceError(format!("getRemoteDevice result: {e}")))?;
+        .map_err(|e| connect_failed(format!("getRemoteDevice result: {e}")))?;
     check_and_clear_exception(&env, "getRemoteDevice")?;
 
     if device.is_null() {
-        return Err(LibError::DeviceError(format!(
+        return Err(connect_failed(format!(
             "Remote device not found: {address}"
         )));
     }
@@ -229,7 +229,7 @@ pub fn connect(address: &str) -> Result<BluetoothSocket> {
     // UUID uuid = UUID.fromString(SPP_UUID);
     let j_uuid_str = env
         .new_string(SPP_UUID)
-        .map_err(|e| LibError::DeviceError(format!("new_string UUID failed: {e}")))?;
+        .map_err(|e| connect_failed(format!("new_string UUID failed: {e}")))?;
     let uuid = env
         .call_static_method(
             "java/util/UUID",
@@ -237,9 +237,9 @@ pub fn connect(address: &str) -> Result<BluetoothSocket> {
             "(Ljava/lang/String;)Ljava/util/UUID;",
             &[JValue::Object(j_uuid_str.into())],
         )
-        .map_err(|e| LibError::DeviceError(format!("UUID.fromString failed: {e}")))?
+        .map_err(|e| connect_failed(format!("UUID.fromString failed: {e}")))?
         .l()
-        .map_err(|e| LibError::DeviceError(format!("UUID.fromString result: {e}")))?;
+        .map_err(|e| connect_failed(format!("UUID.fromString result: {e}")))?;
     check_and_clear_exception(&env, "UUID.fromString")?;
 
     // BluetoothSocket socket = device.createRfcommSocketToServiceRecord(uuid);
@@ -250,19 +250,13 @@ pub fn connect(address: &str) -> Result<BluetoothSocket> {
             "(Ljava/util/UUID;)Landroid/bluetooth/BluetoothSocket;",
             &[JValue::Object(uuid)],
         )
-        .map_err(|e| {
-            LibError::DeviceError(format!("createRfcommSocketToServiceRecord failed: {e}"))
-        })?
+        .map_err(|e| connect_failed(format!("createRfcommSocketToServiceRecord failed: {e}")))?
         .l()
-        .map_err(|e| {
-            LibError::DeviceError(format!("createRfcommSocketToServiceRecord result: {e}"))
-        })?;
+        .map_err(|e| connect_failed(format!("createRfcommSocketToServiceRecord result: {e}")))?;
     check_and_clear_exception(&env, "createRfcommSocketToServiceRecord")?;
 
     if socket.is_null() {
-        return Err(LibError::DeviceError(
-            "Failed to create RFCOMM socket".to_string(),
-        ));
+        return Err(connect_failed("Failed to create RFCOMM socket".to_string()));
     }
 
     // Cancel discovery before connecting (Android recommendation).
@@ -271,40 +265,35 @@ pub fn connect(address: &str) -> Result<BluetoothSocket> {
 
     // socket.connect();  (blocking)
     env.call_method(socket, "connect", "()V", &[])
-        .map_err(|e| LibError::DeviceError(format!("BluetoothSocket.connect failed: {e}")))?;
+        .map_err(|e| connect_failed(format!("BluetoothSocket.connect failed: {e}")))?;
     check_and_clear_exception(&env, "BluetoothSocket.connect")?;
 
     // InputStream in = socket.getInputStream();
     let input_stream = env
         .call_method(socket, "getInputStream", "()Ljava/io/InputStream;", &[])
-        .map_err(|e| LibError::DeviceError(format!("getInputStream failed: {e}")))?
+        .map_err(|e| connect_failed(format!("getInputStream failed: {e}")))?
         .l()
-        .map_err(|e| LibError::DeviceError(format!("getInputStream result: {e}")))?;
+        .map_err(|e| connect_failed(format!("getInputStream result: {e}")))?;
     check_and_clear_exception(&env, "getInputStream")?;
 
     // OutputStream out = socket.getOutputStream();
     let output_stream = env
-        .call_method(
-            socket,
-            "getOutputStream",
-            "()Ljava/io/OutputStream;",
-            &[],
-        )
-        .map_err(|e| LibError::DeviceError(format!("getOutputStream failed: {e}")))?
+        .call_method(socket, "getOutputStream", "()Ljava/io/OutputStream;", &[])
+        .map_err(|e| connect_failed(format!("getOutputStream failed: {e}")))?
         .l()
-        .map_err(|e| LibError::DeviceError(format!("getOutputStream result: {e}")))?;
+        .map_err(|e| connect_failed(format!("getOutputStream result: {e}")))?;
     check_and_clear_exception(&env, "getOutputStream")?;
 
     // Store as global references so they survive across JNI frames.
     let socket_ref = env
         .new_global_ref(socket)
-        .map_err(|e| LibError::DeviceError(format!("GlobalRef socket: {e}")))?;
+        .map_err(|e| connect_failed(format!("GlobalRef socket: {e}")))?;
     let input_ref = env
         .new_global_ref(input_stream)
-        .map_err(|e| LibError::DeviceError(format!("GlobalRef input: {e}")))?;
+        .map_err(|e| connect_failed(format!("GlobalRef input: {e}")))?;
     let output_ref = env
         .new_global_ref(output_stream)
-        .map_err(|e| LibError::DeviceError(format!("GlobalRef output: {e}")))?;
+        .map_err(|e| connect_failed(format!("GlobalRef output: {e}")))?;
 
     Ok(BluetoothSocket {
         socket: socket_ref,
@@ -321,11 +310,8 @@ impl BluetoothSocket {
 
         // Create a Java byte[] to receive data.
         let j_buf = env
-            .new_byte_array(
-                i32::try_from(buf.len())
-                    .map_err(|_| LibError::DeviceError("buffer too large".to_string()))?,
-            )
-            .map_err(|e| LibError::DeviceError(format!("new_byte_array: {e}")))?;
+            .new_byte_array(i32::try_from(buf.len()).map_err(|_| io_failed("buffer too large"))?)
+            .map_err(|e| io_failed(format!("new_byte_array: {e}")))?;
 
         let input: JObject = self.input_stream.as_obj();
 
@@ -341,9 +327,9 @@ impl BluetoothSocket {
                     JValue::Int(i32::try_from(buf.len()).unwrap_or(i32::MAX)),
                 ],
             )
-            .map_err(|e| LibError::DeviceError(format!("InputStream.read: {e}")))?
+            .map_err(|e| io_failed(format!("InputStream.read: {e}")))?
             .i()
-            .map_err(|e| LibError::DeviceError(format!("InputStream.read result: {e}")))?;
+            .map_err(|e| io_failed(format!("InputStream.read result: {e}")))?;
         check_and_clear_exception(&env, "InputStream.read")?;
 
         if n < 0 {
@@ -363,7 +349,7 @@ impl BluetoothSocket {
                 &mut *(std::ptr::from_mut::<[u8]>(&mut buf[..n]) as *mut [u8] as *mut [i8])
             },
         )
-        .map_err(|e| LibError::DeviceError(format!("get_byte_array_region: {e}")))?;
+        .map_err(|e| io_failed(format!("get_byte_array_region: {e}")))?;
 
         Ok(n)
     }
@@ -373,18 +359,15 @@ impl BluetoothSocket {
         let env = get_env()?;
 
         let j_buf = env
-            .new_byte_array(
-                i32::try_from(data.len())
-                    .map_err(|_| LibError::DeviceError("buffer too large".to_string()))?,
-            )
-            .map_err(|e| LibError::DeviceError(format!("new_byte_array: {e}")))?;
+            .new_byte_array(i32::try_from(data.len()).map_err(|_| io_failed("buffer too large"))?)
+            .map_err(|e| io_failed(format!("new_byte_array: {e}")))?;
 
         // Copy Rust data into Java byte[].
         // SAFETY: reinterpreting &[u8] as &[i8] — same layout.
         #[expect(unsafe_code)]
         let data_i8 = unsafe { &*(std::ptr::from_ref::<[u8]>(data) as *const [u8] as *const [i8]) };
         env.set_byte_array_region(j_buf, 0, data_i8)
-            .map_err(|e| LibError::DeviceError(format!("set_byte_array_region: {e}")))?;
+            .map_err(|e| io_failed(format!("set_byte_array_region: {e}")))?;
 
         let output: JObject = self.output_stream.as_obj();
 
@@ -399,7 +382,7 @@ impl BluetoothSocket {
                 JValue::Int(i32::try_from(data.len()).unwrap_or(i32::MAX)),
             ],
         )
-        .map_err(|e| LibError::DeviceError(format!("OutputStream.write: {e}")))?;
+        .map_err(|e| io_failed(format!("OutputStream.write: {e}")))?;
         check_and_clear_exception(&env, "OutputStream.write")?;
 
         Ok(data.len())
@@ -412,9 +395,9 @@ impl BluetoothSocket {
 
         let n = env
             .call_method(input, "available", "()I", &[])
-            .map_err(|e| LibError::DeviceError(format!("InputStream.available: {e}")))?
+            .map_err(|e| io_failed(format!("InputStream.available: {e}")))?
             .i()
-            .map_err(|e| LibError::DeviceError(format!("InputStream.available result: {e}")))?;
+            .map_err(|e| io_failed(format!("InputStream.available result: {e}")))?;
         check_and_clear_exception(&env, "InputStream.available")?;
 
         Ok(usize::try_from(n).unwrap_or(0))
@@ -25,6 +25,8 @@ use crate::device::DeviceInfo;
 use crate::error::{LibError, Result};
 #[cfg(target_os = "android")]
 use crate::iostream::IoStream;
+#[cfg(target_os = "android")]
+use crate::transport::Transport;
 
 // ---------------------------------------------------------------------------
 // Scanning
@@ -37,8 +39,10 @@ use crate::iostream::IoStream;
 #[cfg(target_os = "android")]
 #[tracing::instrument]
 pub fn scan_bluetooth_android() -> Result<Vec<DeviceInfo>> {
-    let _guard = crate::android::attach_current_thread()
-        .map_err(|e| LibError::DeviceError(format!("JNI attach failed: {e}")))?;
+    let _guard = crate::android::attach_current_thread().map_err(|e| LibError::ScanFailed {
+        transport: Transport::Bluetooth,
+        reason: format!("JNI attach failed: {e}"),
+    })?;
     android::get_bonded_devices()
 }
 
@@ -308,8 +312,10 @@ extern "C" fn bt_purge(io: *mut c_void, _direction: ffi::dc_direction_t) -> ffi:
 #[cfg(target_os = "android")]
 #[tracing::instrument(skip(ctx), fields(address = %address))]
 pub fn bt_iostream_open(ctx: &crate::context::Context, address: &str) -> Result<IoStream> {
-    let _guard = crate::android::attach_current_thread()
-        .map_err(|e| LibError::DeviceError(format!("JNI attach failed: {e}")))?;
+    let _guard = crate::android::attach_current_thread().map_err(|e| LibError::ConnectFailed {
+        transport: Transport::Bluetooth,
+        reason: format!("JNI attach failed: {e}"),
+    })?;
 
     let socket = android::connect(address)?;
     let transport = BtTransport {
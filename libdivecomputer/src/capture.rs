@@ -0,0 +1,439 @@
+//! Recording and replaying the traffic of a [`CustomTransport`].
+//!
+//! Wrap the transport a download runs over in a [`RecordingTransport`] and
+//! every read, write and line-control call is logged to a capture file. A
+//! [`ReplayTransport`] later plays that file back to the same driver, so a
+//! protocol bug seen once on a user's computer becomes a test that runs
+//! without the hardware.
+//!
+//! Only transports driven from Rust can be captured: [`TcpTransport`],
+//! `SerialPortTransport`, `HidTransport`, or an application's own. The C
+//! library's built-in serial, USB and IrDA code bypasses the callbacks.
+//!
+//! # Format
+//!
+//! One line per call: microseconds since the capture started, the operation,
+//! and its arguments, separated by spaces. Data is hex, `-` when empty. A
+//! failed call is written as the operation followed by `!` and the status
+//! code.
+//!
+//! ```text
+//! 0 timeout 3000
+//! 152 write 1B
+//! 20931 read 6E0B
+//! 41220 read! -7
+//! ```
+//!
+//! [`TcpTransport`]: crate::tcp::TcpTransport
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::custom::{CustomTransport, error_status};
+use crate::error::{LibError, Result};
+use crate::iostream::{Direction, SerialConfig};
+use crate::parser::Fingerprint;
+use crate::status::Status;
+
+/// A [`CustomTransport`] that passes every call through to `inner` and logs
+/// it to `log`. See the [module docs](self) for the format.
+pub struct RecordingTransport<T, W: Write> {
+    inner: T,
+    log: W,
+    started: Instant,
+}
+
+impl<T: CustomTransport> RecordingTransport<T, BufWriter<File>> {
+    /// Record to a new file at `path`, replacing any existing one.
+    pub fn to_file(inner: T, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(inner, BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<T: CustomTransport, W: Write + Send + 'static> RecordingTransport<T, W> {
+    /// Record the traffic of `inner` to `log`.
+    pub fn new(inner: T, log: W) -> Self {
+        Self {
+            inner,
+            log,
+            started: Instant::now(),
+        }
+    }
+
+    /// The wrapped transport.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn record(&mut self, op: &str, args: std::fmt::Arguments<'_>) {
+        let micros = self.started.elapsed().as_micros();
+        // Flush per line so a crash still leaves the capture that led to it.
+        // A capture that cannot be written must not break the download.
+        let _ = writeln!(self.log, "{micros} {op} {args}").and_then(|()| self.log.flush());
+    }
+
+    fn record_result<R>(
+        &mut self,
+        op: &str,
+        result: Result<R>,
+        ok: impl FnOnce(&R) -> String,
+    ) -> Result<R> {
+        match &result {
+            Ok(value) => self.record(op, format_args!("{}", ok(value))),
+            Err(e) => self.record(&format!("{op}!"), format_args!("{}", error_status(e))),
+        }
+        result
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    if data.is_empty() {
+        "-".to_string()
+    } else {
+        Fingerprint::from(data).to_string()
+    }
+}
+
+impl<T: CustomTransport, W: Write + Send + 'static> CustomTransport for RecordingTransport<T, W> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let result = self.inner.read(buf);
+        self.record_result("read", result, |&n| hex(&buf[..n.min(buf.len())]))
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize> {
+        let result = self.inner.write(data);
+        self.record_result("write", result, |&n| hex(&data[..n.min(data.len())]))
+    }
+
+    fn poll(&mut self, timeout_ms: i32) -> Result<bool> {
+        let result = self.inner.poll(timeout_ms);
+        self.record_result("poll", result, |&ready| u8::from(ready).to_string())
+    }
+
+    fn set_timeout(&mut self, timeout_ms: i32) -> Result<()> {
+        self.record("timeout", format_args!("{timeout_ms}"));
+        self.inner.set_timeout(timeout_ms)
+    }
+
+    fn configure(&mut self, config: &SerialConfig) -> Result<()> {
+        self.record(
+            "configure",
+            format_args!(
+                "{} {} {:?} {:?} {:?}",
+                config.baudrate,
+                config.databits,
+                config.parity,
+                config.stopbits,
+                config.flowcontrol
+            ),
+        );
+        self.inner.configure(config)
+    }
+
+    fn set_break(&mut self, value: bool) -> Result<()> {
+        self.record("break", format_args!("{}", u8::from(value)));
+        self.inner.set_break(value)
+    }
+
+    fn set_dtr(&mut self, value: bool) -> Result<()> {
+        self.record("dtr", format_args!("{}", u8::from(value)));
+        self.inner.set_dtr(value)
+    }
+
+    fn set_rts(&mut self, value: bool) -> Result<()> {
+        self.record("rts", format_args!("{}", u8::from(value)));
+        self.inner.set_rts(value)
+    }
+
+    fn get_lines(&mut self) -> Result<u32> {
+        let result = self.inner.get_lines();
+        self.record_result("lines", result, u32::to_string)
+    }
+
+    fn get_available(&mut self) -> Result<usize> {
+        let result = self.inner.get_available();
+        self.record_result("available", result, usize::to_string)
+    }
+
+    fn ioctl(&mut self, request: u32, data: &mut [u8]) -> Result<()> {
+        let result = self.inner.ioctl(request, data);
+        self.record_result("ioctl", result, |()| format!("{request} {}", hex(data)))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.record("flush", format_args!(""));
+        self.inner.flush()
+    }
+
+    fn purge(&mut self, direction: Direction) -> Result<()> {
+        self.record("purge", format_args!("{direction:?}"));
+        self.inner.purge(direction)
+    }
+
+    fn sleep(&mut self, duration: Duration) -> Result<()> {
+        self.record("sleep", format_args!("{}", duration.as_millis()));
+        self.inner.sleep(duration)
+    }
+}
+
+/// One replayable call from a capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Entry {
+    Read(Vec<u8>),
+    Write(Vec<u8>),
+    Poll(bool),
+    Lines(u32),
+    Available(usize),
+    Ioctl(Vec<u8>),
+    Failed(&'static str, Status),
+}
+
+impl Entry {
+    fn op(&self) -> &'static str {
+        match self {
+            Entry::Read(_) => "read",
+            Entry::Write(_) => "write",
+            Entry::Poll(_) => "poll",
+            Entry::Lines(_) => "lines",
+            Entry::Available(_) => "available",
+            Entry::Ioctl(_) => "ioctl",
+            Entry::Failed(op, _) => op,
+        }
+    }
+}
+
+/// A [`CustomTransport`] that plays back a capture written by
+/// [`RecordingTransport`].
+///
+/// Reads return the recorded data and writes are checked against what the
+/// driver sent at the time: a driver that takes a different path than the
+/// recorded session gets [`Status::Protocol`]. Line control, timeouts and
+/// sleeps are accepted without waiting, so a replay runs as fast as the
+/// driver can parse.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    entries: VecDeque<Entry>,
+    /// Rest of a recorded read the driver asked for in smaller pieces.
+    pending: VecDeque<u8>,
+}
+
+impl ReplayTransport {
+    /// Load the capture at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse a capture.
+    pub fn parse(capture: &str) -> Result<Self> {
+        let mut entries = VecDeque::new();
+        for (idx, line) in capture.lines().enumerate() {
+            let invalid = || LibError::InvalidArguments(format!("capture line {}", idx + 1));
+            let mut fields = line.split_whitespace().skip(1);
+            let Some(op) = fields.next() else {
+                continue;
+            };
+            let mut arg = || fields.next().ok_or_else(invalid);
+            let data = |arg: &str| -> Result<Vec<u8>> {
+                if arg == "-" {
+                    Ok(Vec::new())
+                } else {
+                    Fingerprint::from_hex(arg).map(|fp| fp.as_bytes().to_vec())
+                }
+            };
+            let entry = match op {
+                "read" => Entry::Read(data(arg()?)?),
+                "write" => Entry::Write(data(arg()?)?),
+                "poll" => Entry::Poll(arg()? == "1"),
+                "lines" => Entry::Lines(arg()?.parse().map_err(|_| invalid())?),
+                "available" => Entry::Available(arg()?.parse().map_err(|_| invalid())?),
+                "ioctl" => {
+                    arg()?;
+                    Entry::Ioctl(data(arg()?)?)
+                }
+                failed if failed.ends_with('!') => {
+                    let op = match failed.trim_end_matches('!') {
+                        "read" => "read",
+                        "write" => "write",
+                        "poll" => "poll",
+                        "lines" => "lines",
+                        "available" => "available",
+                        "ioctl" => "ioctl",
+                        _ => return Err(invalid()),
+                    };
+                    let code: i32 = arg()?.parse().map_err(|_| invalid())?;
+                    Entry::Failed(op, Status::try_from(code).map_err(|_| invalid())?)
+                }
+                // Not replayed: the driver's requests are accepted as made.
+                _ => continue,
+            };
+            entries.push_back(entry);
+        }
+        Ok(Self {
+            entries,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Whether every recorded call has been replayed. A test can assert this
+    /// after the download to check the driver did not stop early.
+    pub fn is_finished(&self) -> bool {
+        self.entries.is_empty() && self.pending.is_empty()
+    }
+
+    fn next(&mut self, op: &'static str) -> Result<Entry> {
+        let Some(entry) = self.entries.pop_front() else {
+            return Err(LibError::Status(
+                Status::Timeout,
+                Some(format!("capture exhausted at {op}")),
+            ));
+        };
+        match entry {
+            Entry::Failed(failed, status) if failed == op => {
+                Err(LibError::Status(status, Some("replayed failure".into())))
+            }
+            entry if entry.op() == op => Ok(entry),
+            entry => Err(LibError::Status(
+                Status::Protocol,
+                Some(format!(
+                    "replay expected {}, driver called {op}",
+                    entry.op()
+                )),
+            )),
+        }
+    }
+}
+
+impl CustomTransport for ReplayTransport {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.pending.is_empty() {
+            let Entry::Read(data) = self.next("read")? else {
+                unreachable!("next returns the requested op")
+            };
+            self.pending.extend(data);
+        }
+        let n = buf.len().min(self.pending.len());
+        for (dst, src) in buf.iter_mut().zip(self.pending.drain(..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize> {
+        let Entry::Write(expected) = self.next("write")? else {
+            unreachable!("next returns the requested op")
+        };
+        if expected != data {
+            return Err(LibError::Status(
+                Status::Protocol,
+                Some(format!(
+                    "replay expected write {}, driver wrote {}",
+                    hex(&expected),
+                    hex(data)
+                )),
+            ));
+        }
+        Ok(data.len())
+    }
+
+    fn poll(&mut self, _timeout_ms: i32) -> Result<bool> {
+        if !self.pending.is_empty() {
+            return Ok(true);
+        }
+        match self.next("poll")? {
+            Entry::Poll(ready) => Ok(ready),
+            _ => unreachable!("next returns the requested op"),
+        }
+    }
+
+    fn get_lines(&mut self) -> Result<u32> {
+        match self.next("lines")? {
+            Entry::Lines(lines) => Ok(lines),
+            _ => unreachable!("next returns the requested op"),
+        }
+    }
+
+    fn get_available(&mut self) -> Result<usize> {
+        match self.next("available")? {
+            Entry::Available(available) => Ok(available),
+            _ => unreachable!("next returns the requested op"),
+        }
+    }
+
+    fn ioctl(&mut self, _request: u32, data: &mut [u8]) -> Result<()> {
+        let Entry::Ioctl(recorded) = self.next("ioctl")? else {
+            unreachable!("next returns the requested op")
+        };
+        let n = data.len().min(recorded.len());
+        data[..n].copy_from_slice(&recorded[..n]);
+        Ok(())
+    }
+
+    fn sleep(&mut self, _duration: Duration) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers every write with the bytes reversed; times out when idle.
+    struct Echo(Vec<u8>);
+
+    impl CustomTransport for Echo {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            if self.0.is_empty() {
+                return Err(LibError::Status(Status::Timeout, None));
+            }
+            let n = buf.len().min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0.drain(..n);
+            Ok(n)
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<usize> {
+            self.0.extend(data.iter().rev());
+            Ok(data.len())
+        }
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let mut recorder = RecordingTransport::new(Echo(Vec::new()), Vec::new());
+        recorder.set_timeout(1000).unwrap();
+        recorder.write(&[1, 2, 3]).unwrap();
+        let mut buf = [0; 3];
+        assert_eq!(recorder.read(&mut buf).unwrap(), 3);
+        assert!(recorder.read(&mut buf).is_err());
+        let capture = String::from_utf8(recorder.log.clone()).unwrap();
+        assert!(capture.lines().any(|line| line.ends_with(" read 030201")));
+        assert!(capture.lines().any(|line| line.ends_with(" read! -7")));
+
+        let mut replay = ReplayTransport::parse(&capture).unwrap();
+        replay.set_timeout(1000).unwrap();
+        replay.write(&[1, 2, 3]).unwrap();
+        // Split reads are served from the recorded one.
+        let mut small = [0; 2];
+        assert_eq!(replay.read(&mut small).unwrap(), 2);
+        assert_eq!(small, [3, 2]);
+        assert_eq!(replay.read(&mut small).unwrap(), 1);
+        assert_eq!(small[0], 1);
+        assert!(matches!(
+            replay.read(&mut buf),
+            Err(LibError::Status(Status::Timeout, _))
+        ));
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn replay_rejects_diverging_writes() {
+        let mut replay = ReplayTransport::parse("0 write 0102\n").unwrap();
+        assert!(matches!(
+            replay.write(&[9]),
+            Err(LibError::Status(Status::Protocol, _))
+        ));
+    }
+}
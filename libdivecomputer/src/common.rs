@@ -308,6 +308,70 @@ impl SampleFlag {
     }
 }
 
+/// Severity of an event, decoded from the [`SampleFlag`] severity field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Severity {
+    /// The driver did not say.
+    #[default]
+    None,
+    /// Informational state, no user action required.
+    State,
+    /// Informational.
+    Info,
+    /// Warning.
+    Warning,
+    /// Alarm — diver should act.
+    Alarm,
+}
+
+impl From<SampleFlag> for Severity {
+    fn from(flags: SampleFlag) -> Self {
+        match flags.severity() {
+            1 => Self::State,
+            2 => Self::Info,
+            3 => Self::Warning,
+            4 => Self::Alarm,
+            _ => Self::None,
+        }
+    }
+}
+
+/// What an event is about, decoded from the [`SampleFlag`] event-type field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum EventCategory {
+    /// The driver did not say.
+    #[default]
+    None,
+    /// Point of interest.
+    Interest,
+    /// Navigation point.
+    Navpoint,
+    /// Hazard.
+    Danger,
+    /// Animal sighting.
+    Animal,
+    /// Equipment or procedural issue.
+    Issue,
+    /// Injury.
+    Injury,
+}
+
+impl From<SampleFlag> for EventCategory {
+    fn from(flags: SampleFlag) -> Self {
+        match flags.event_type() {
+            1 => Self::Interest,
+            2 => Self::Navpoint,
+            3 => Self::Danger,
+            4 => Self::Animal,
+            5 => Self::Issue,
+            6 => Self::Injury,
+            _ => Self::None,
+        }
+    }
+}
+
 impl std::fmt::Display for SampleFlag {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         bitflags::parser::to_writer(self, f)
@@ -324,6 +388,15 @@ impl From<u32> for SampleFlag {
 mod tests {
     use super::*;
 
+    #[test]
+    fn severity_and_category_from_flags() {
+        let flags = SampleFlag::BEGIN | SampleFlag::SEVERITY_ALARM | SampleFlag::TYPE_ISSUE;
+        assert_eq!(Severity::from(flags), Severity::Alarm);
+        assert_eq!(EventCategory::from(flags), EventCategory::Issue);
+        assert_eq!(Severity::from(SampleFlag::BEGIN), Severity::None);
+        assert!(Severity::Warning > Severity::Info);
+    }
+
     #[test]
     fn sample_flag_combine() {
         let flags = SampleFlag::BEGIN | SampleFlag::SEVERITY_WARN;
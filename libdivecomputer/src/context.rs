@@ -1,7 +1,15 @@
 use std::{
     ffi::{CStr, c_char, c_uint, c_void},
     fmt::Display,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
     ptr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+        mpsc,
+    },
 };
 
 use libdivecomputer_sys as ffi;
@@ -12,40 +20,56 @@ use crate::{
     common::ffi_guard,
     error::{LibError, Result},
     status::Status,
-    transport::TransportSet,
+    transport::{Transport, TransportSet},
 };
 
 type LogCallback = dyn Fn(LogLevel, &str) + Send + Sync;
 
 /// Named wrapper so the FFI `*mut c_void` userdata is a thin pointer, and the
 /// intent of the double-box is spelled out in the type system instead of
-/// hidden behind `Box<Box<dyn ...>>`.
-#[repr(transparent)]
-struct LogCallbackHandle(Box<LogCallback>);
+/// hidden behind `Box<Box<dyn ...>>`. Carries the context's level filter so
+/// the callback can check it without going through the context.
+struct LogCallbackHandle {
+    callback: Box<LogCallback>,
+    level: Arc<AtomicU32>,
+}
 
 /// Wrapper around `dc_context_t`.
 ///
 /// Field drop order matters: `ptr` is declared first, so it is dropped first
 /// (calling `dc_context_free` and detaching any log callback in the C
-/// library), and only then is `_log_callback` dropped. Reversing the order
+/// library), and only then is `log_callback` dropped. Reversing the order
 /// would let the C library call a freed closure during context teardown.
 pub struct Context {
     pub(crate) ptr: *mut ffi::dc_context_t,
     /// Stored so the closure is freed on drop.
-    _log_callback: Option<Box<LogCallbackHandle>>,
+    log_callback: Option<Box<LogCallbackHandle>>,
+    /// Most verbose level passed on to the log callback; see
+    /// [`set_loglevel`](Self::set_loglevel).
+    log_level: Arc<AtomicU32>,
 }
 
 impl Context {
     /// Create a new context. Prefer `Context::builder()` for configuration.
+    ///
+    /// With the `log` feature, the C library's messages go to the `log`
+    /// crate under the `libdivecomputer` target instead of stderr, until
+    /// [`set_logfunc`](Self::set_logfunc) installs another callback.
     #[must_use = "the created Context owns a C allocation"]
     pub fn new() -> Result<Self> {
         let mut ptr = ptr::null_mut();
         let status = unsafe { ffi::dc_context_new(&mut ptr) };
         Status::check(status, "failed to create context")?;
-        Ok(Self {
+        #[cfg_attr(not(feature = "log"), allow(unused_mut))]
+        let mut ctx = Self {
             ptr,
-            _log_callback: None,
-        })
+            log_callback: None,
+            // The C library's own default.
+            log_level: Arc::new(AtomicU32::new(ffi::DC_LOGLEVEL_WARNING)),
+        };
+        #[cfg(feature = "log")]
+        ctx.set_logfunc(forward_to_log)?;
+        Ok(ctx)
     }
 
     /// Create a context builder for configuration.
@@ -59,11 +83,23 @@ impl Context {
     }
 
     /// Set the log level.
-    pub fn set_loglevel(&mut self, loglevel: LogLevel) -> Result<()> {
+    ///
+    /// Works through `&self`, so it can be changed while the context is in
+    /// use, e.g. from a settings screen while a download runs on another
+    /// thread; the change applies from the next message on.
+    pub fn set_loglevel(&self, loglevel: LogLevel) -> Result<()> {
+        self.log_level.store(loglevel as u32, Ordering::Relaxed);
+        // The C library keeps the level in a single int it reads per
+        // message; a message racing the change is logged at either level.
         let status = unsafe { ffi::dc_context_set_loglevel(self.ptr, loglevel as _) };
         Status::check(status, "failed to set loglevel")
     }
 
+    /// The current log level.
+    pub fn log_level(&self) -> LogLevel {
+        LogLevel::from_raw(self.log_level.load(Ordering::Relaxed))
+    }
+
     /// Set the log callback function.
     pub fn set_logfunc<F>(&mut self, callback: F) -> Result<()>
     where
@@ -73,7 +109,10 @@ impl Context {
     }
 
     fn set_logfunc_boxed(&mut self, callback: Box<LogCallback>) -> Result<()> {
-        let handle = Box::new(LogCallbackHandle(callback));
+        let handle = Box::new(LogCallbackHandle {
+            callback,
+            level: Arc::clone(&self.log_level),
+        });
         let raw = Box::into_raw(handle);
 
         let status = unsafe {
@@ -91,7 +130,7 @@ impl Context {
 
         // Keep the handle alive — C holds `raw` as userdata until the context
         // is freed (or another call replaces it).
-        self._log_callback = Some(unsafe { Box::from_raw(raw) });
+        self.log_callback = Some(unsafe { Box::from_raw(raw) });
 
         Ok(())
     }
@@ -104,6 +143,38 @@ impl Context {
         let bits = unsafe { ffi::dc_context_get_transports(self.ptr as *mut _) };
         TransportSet::from_bits(bits)
     }
+
+    /// Transports this build can actually use on this platform: those of
+    /// [`get_transports`](Self::get_transports), which only reflects the C
+    /// library, adjusted for the ones this crate drives itself. BLE needs the
+    /// `ble` feature, IrDA the `irda` feature; `hidapi` and `serialport`
+    /// provide USB HID and serial, and the `bluetooth` feature classic
+    /// Bluetooth on Android.
+    ///
+    /// Pass it to [`Descriptor::vendors_for`](crate::Descriptor::vendors_for)
+    /// and [`Descriptor::by_transports`](crate::Descriptor::by_transports) so
+    /// a UI only offers models it can talk to.
+    pub fn supported_transports(&self) -> TransportSet {
+        let mut transports = self.get_transports();
+        if cfg!(feature = "ble") {
+            transports.insert(Transport::Ble);
+        } else {
+            transports.remove(Transport::Ble);
+        }
+        if !cfg!(feature = "irda") {
+            transports.remove(Transport::Irda);
+        }
+        if cfg!(feature = "hidapi") {
+            transports.insert(Transport::UsbHid);
+        }
+        if cfg!(feature = "serialport") {
+            transports.insert(Transport::Serial);
+        }
+        if cfg!(all(target_os = "android", feature = "bluetooth")) {
+            transports.insert(Transport::Bluetooth);
+        }
+        transports
+    }
 }
 
 impl std::fmt::Debug for Context {
@@ -134,6 +205,7 @@ unsafe impl Sync for Context {}
 pub struct ContextBuilder {
     log_level: Option<LogLevel>,
     log_fn: Option<Box<LogCallback>>,
+    log_file: Option<(PathBuf, LogRotation)>,
 }
 
 impl std::fmt::Debug for ContextBuilder {
@@ -141,6 +213,7 @@ impl std::fmt::Debug for ContextBuilder {
         f.debug_struct("ContextBuilder")
             .field("log_level", &self.log_level)
             .field("log_fn", &self.log_fn.as_ref().map(|_| ".."))
+            .field("log_file", &self.log_file)
             .finish()
     }
 }
@@ -166,12 +239,23 @@ impl ContextBuilder {
         self
     }
 
+    /// Also write every C-library log message at or below the configured
+    /// [`log_level`](Self::log_level) to the file at `path`, for users to
+    /// attach to bug reports. See [`log_file`] for the format; the file is
+    /// opened by [`build`](Self::build).
+    #[must_use]
+    pub fn log_file(mut self, path: impl Into<PathBuf>, rotation: LogRotation) -> Self {
+        self.log_file = Some((path.into(), rotation));
+        self
+    }
+
     /// Consume the builder and construct a [`Context`].
     ///
     /// # Errors
     ///
     /// Returns an error if the C library fails to allocate the context, set
-    /// the log level, or install the log callback.
+    /// the log level, or install the log callback, or if the
+    /// [`log_file`](Self::log_file) cannot be opened.
     #[must_use = "the constructed Context owns a C allocation"]
     pub fn build(self) -> Result<Context> {
         let mut ctx = Context::new()?;
@@ -180,8 +264,18 @@ impl ContextBuilder {
             ctx.set_loglevel(level)?;
         }
 
-        if let Some(callback) = self.log_fn {
-            ctx.set_logfunc_boxed(callback)?;
+        let file = match self.log_file {
+            Some((path, rotation)) => Some(log_file(path, rotation)?),
+            None => None,
+        };
+        match (self.log_fn, file) {
+            (Some(callback), Some(file)) => ctx.set_logfunc(move |level, message| {
+                callback(level, message);
+                file(level, message);
+            })?,
+            (Some(callback), None) => ctx.set_logfunc_boxed(callback)?,
+            (None, Some(file)) => ctx.set_logfunc(file)?,
+            (None, None) => {}
         }
 
         Ok(ctx)
@@ -208,6 +302,19 @@ pub enum LogLevel {
     All = ffi::DC_LOGLEVEL_ALL,
 }
 
+impl LogLevel {
+    fn from_raw(level: ffi::dc_loglevel_t) -> Self {
+        match level {
+            ffi::DC_LOGLEVEL_ERROR => Self::Error,
+            ffi::DC_LOGLEVEL_WARNING => Self::Warning,
+            ffi::DC_LOGLEVEL_INFO => Self::Info,
+            ffi::DC_LOGLEVEL_DEBUG => Self::Debug,
+            ffi::DC_LOGLEVEL_ALL => Self::All,
+            _ => Self::None,
+        }
+    }
+}
+
 impl Display for LogLevel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -221,6 +328,206 @@ impl Display for LogLevel {
     }
 }
 
+/// One log message from the C library, as delivered by [`log_channel`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogRecord {
+    /// Severity of the message.
+    pub level: LogLevel,
+    /// The message text, without a trailing newline.
+    pub message: String,
+}
+
+impl Display for LogRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.level, self.message)
+    }
+}
+
+/// A callback for [`ContextBuilder::log_fn`] that forwards every C-library
+/// log message to the returned receiver, so an app can route them into its
+/// own logging from any thread:
+///
+/// ```no_run
+/// # use libdivecomputer::{Context, LogLevel, context::log_channel};
+/// # fn demo() -> libdivecomputer::Result<()> {
+/// let (log_fn, records) = log_channel();
+/// let ctx = Context::builder()
+///     .log_level(LogLevel::Warning)
+///     .log_fn(log_fn)
+///     .build()?;
+/// std::thread::spawn(move || {
+///     for record in records {
+///         eprintln!("libdivecomputer: {record}");
+///     }
+/// });
+/// # Ok(())
+/// # }
+/// ```
+pub fn log_channel() -> (
+    impl Fn(LogLevel, &str) + Send + Sync + 'static,
+    mpsc::Receiver<LogRecord>,
+) {
+    let (tx, rx) = mpsc::channel();
+    let forward = move |level, message: &str| {
+        // Nobody listening any more is not worth failing a download over.
+        let _ = tx.send(LogRecord {
+            level,
+            message: message.trim_end().to_string(),
+        });
+    };
+    (forward, rx)
+}
+
+/// When a [`log_file`] moves on to a fresh file. The full file is renamed to
+/// `<path>.1`, earlier ones shift up to `<path>.<keep>`, and any older one
+/// is deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    /// Once the current file would grow past `max_bytes`.
+    Size {
+        /// Largest size of one file, in bytes.
+        max_bytes: u64,
+        /// Number of rotated files kept besides the current one.
+        keep: usize,
+    },
+    /// At the first message of each day in the host's time zone.
+    Daily {
+        /// Number of rotated files kept besides the current one.
+        keep: usize,
+    },
+}
+
+impl LogRotation {
+    fn keep(self) -> usize {
+        match self {
+            Self::Size { keep, .. } | Self::Daily { keep } => keep,
+        }
+    }
+}
+
+/// A callback for [`ContextBuilder::log_fn`] that appends every C-library
+/// log message to the file at `path`, one line each with a UTC timestamp and
+/// the level, rotating the file as `rotation` says:
+///
+/// ```text
+/// 2025-06-01T09:30:00.125Z [Debug] Write: size=1, data=1B
+/// ```
+///
+/// [`ContextBuilder::log_file`] installs it alongside any other callback.
+/// Failures to write are ignored, like a full disk should not fail a
+/// download.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened for appending.
+pub fn log_file(
+    path: impl AsRef<Path>,
+    rotation: LogRotation,
+) -> Result<impl Fn(LogLevel, &str) + Send + Sync + 'static> {
+    let file = Mutex::new(RotatingFile::open(path.as_ref().to_path_buf(), rotation)?);
+    Ok(move |level, message: &str| {
+        let line = format!(
+            "{:.3} [{level}] {}\n",
+            jiff::Timestamp::now(),
+            message.trim_end()
+        );
+        if let Ok(mut file) = file.lock() {
+            let _ = file.write(&line);
+        }
+    })
+}
+
+/// The file behind [`log_file`].
+struct RotatingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    /// `None` only while rotating, so the file is closed before it is
+    /// renamed, which Windows requires.
+    file: Option<File>,
+    len: u64,
+    /// Local date the current file was started on.
+    day: jiff::civil::Date,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, rotation: LogRotation) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let meta = file.metadata()?;
+        // Continue an existing file only within the day it was written.
+        let day = meta
+            .modified()
+            .ok()
+            .and_then(|time| jiff::Timestamp::try_from(time).ok())
+            .map_or_else(today, |time| {
+                time.to_zoned(jiff::tz::TimeZone::system()).date()
+            });
+        Ok(Self {
+            path,
+            rotation,
+            file: Some(file),
+            len: meta.len(),
+            day,
+        })
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        let full = match self.rotation {
+            LogRotation::Size { max_bytes, .. } => {
+                self.len > 0 && self.len + line.len() as u64 > max_bytes
+            }
+            LogRotation::Daily { .. } => today() != self.day,
+        };
+        if full || self.file.is_none() {
+            self.rotate()?;
+        }
+        let file = self.file.as_mut().expect("rotate reopens the file");
+        file.write_all(line.as_bytes())?;
+        self.len += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        let numbered = |n: usize| {
+            let mut path = self.path.clone().into_os_string();
+            path.push(format!(".{n}"));
+            PathBuf::from(path)
+        };
+        let keep = self.rotation.keep();
+        if keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            // Gaps in the numbering are fine, so missing files are skipped.
+            let _ = fs::remove_file(numbered(keep));
+            for n in (1..keep).rev() {
+                let _ = fs::rename(numbered(n), numbered(n + 1));
+            }
+            fs::rename(&self.path, numbered(1))?;
+        }
+        self.file = Some(File::create(&self.path)?);
+        self.len = 0;
+        self.day = today();
+        Ok(())
+    }
+}
+
+fn today() -> jiff::civil::Date {
+    jiff::Zoned::now().date()
+}
+
+/// Log callback installed by [`Context::new`] with the `log` feature.
+#[cfg(feature = "log")]
+fn forward_to_log(level: LogLevel, message: &str) {
+    let level = match level {
+        LogLevel::Error => log::Level::Error,
+        LogLevel::Warning => log::Level::Warn,
+        LogLevel::Info => log::Level::Info,
+        LogLevel::Debug => log::Level::Debug,
+        LogLevel::None | LogLevel::All => log::Level::Trace,
+    };
+    log::log!(target: "libdivecomputer", level, "{}", message.trim_end());
+}
+
 extern "C" fn log_callback_wrapper(
     _context: *mut ffi::dc_context_t,
     loglevel: ffi::dc_loglevel_t,
@@ -232,16 +539,16 @@ extern "C" fn log_callback_wrapper(
 ) {
     ffi_guard(|| unsafe {
         let handle = &*(userdata as *const LogCallbackHandle);
-        let level = match loglevel {
-            ffi::DC_LOGLEVEL_ERROR => LogLevel::Error,
-            ffi::DC_LOGLEVEL_WARNING => LogLevel::Warning,
-            ffi::DC_LOGLEVEL_INFO => LogLevel::Info,
-            ffi::DC_LOGLEVEL_DEBUG => LogLevel::Debug,
-            _ => LogLevel::None,
+        if loglevel > handle.level.load(Ordering::Relaxed) {
+            return;
+        }
+        let level = match LogLevel::from_raw(loglevel) {
+            LogLevel::All => LogLevel::None,
+            level => level,
         };
 
         if let Ok(msg) = CStr::from_ptr(message).to_str() {
-            (handle.0)(level, msg);
+            (handle.callback)(level, msg);
         }
     })
 }
@@ -278,6 +585,84 @@ mod tests {
         assert_eq!(LogLevel::None.to_string(), "");
     }
 
+    #[test]
+    fn log_channel_forwards_records() {
+        let (log_fn, records) = log_channel();
+        log_fn(LogLevel::Warning, "battery low\n");
+        drop(log_fn);
+        let received: Vec<_> = records.iter().collect();
+        assert_eq!(
+            received,
+            vec![LogRecord {
+                level: LogLevel::Warning,
+                message: "battery low".into(),
+            }]
+        );
+        assert_eq!(received[0].to_string(), "[Warning] battery low");
+    }
+
+    #[test]
+    fn log_file_rotates_by_size() {
+        let dir = std::env::temp_dir().join(format!("libdivecomputer-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("dc.log");
+
+        let rotation = LogRotation::Size {
+            max_bytes: 100,
+            keep: 2,
+        };
+        let log = log_file(&path, rotation).unwrap();
+        for n in 0..12 {
+            log(LogLevel::Debug, &format!("message {n}\n"));
+        }
+
+        let current = fs::read_to_string(&path).unwrap();
+        assert!(current.ends_with("[Debug] message 11\n"), "{current}");
+        assert!(current.len() <= 100);
+        // Millisecond UTC timestamps, e.g. `2025-06-01T09:30:00.125Z`.
+        assert!(current.lines().all(|line| line.find(' ') == Some(24)));
+        assert!(dir.join("dc.log.1").exists());
+        assert!(dir.join("dc.log.2").exists());
+        assert!(!dir.join("dc.log.3").exists());
+        let mut files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        files.sort_by_key(|f| f.as_ref().unwrap().file_name());
+        assert_eq!(files.len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn log_level_filters_callback() {
+        let (log_fn, records) = log_channel();
+        let handle = LogCallbackHandle {
+            callback: Box::new(log_fn),
+            level: Arc::new(AtomicU32::new(ffi::DC_LOGLEVEL_WARNING)),
+        };
+        let userdata = &handle as *const LogCallbackHandle as *mut c_void;
+        let log = |level, message: &std::ffi::CStr| {
+            log_callback_wrapper(
+                ptr::null_mut(),
+                level,
+                ptr::null(),
+                0,
+                ptr::null(),
+                message.as_ptr(),
+                userdata,
+            )
+        };
+
+        log(ffi::DC_LOGLEVEL_DEBUG, c"hidden");
+        log(ffi::DC_LOGLEVEL_ERROR, c"shown");
+        handle
+            .level
+            .store(LogLevel::Debug as u32, Ordering::Relaxed);
+        log(ffi::DC_LOGLEVEL_DEBUG, c"now shown");
+        drop(handle);
+
+        let messages: Vec<_> = records.iter().map(|r| r.message).collect();
+        assert_eq!(messages, ["shown", "now shown"]);
+    }
+
     #[test]
     fn context_get_transports() {
         let ctx = Context::new().unwrap();
@@ -285,4 +670,17 @@ mod tests {
         // On a real system, at least serial should be available
         let _ = transports.to_vec();
     }
+
+    #[test]
+    fn supported_transports_follow_features() {
+        let ctx = Context::new().unwrap();
+        let supported = ctx.supported_transports();
+        assert_eq!(supported.contains(Transport::Ble), cfg!(feature = "ble"));
+        if !cfg!(feature = "irda") {
+            assert!(!supported.contains(Transport::Irda));
+        }
+        if cfg!(feature = "serialport") {
+            assert!(supported.contains(Transport::Serial));
+        }
+    }
 }
@@ -0,0 +1,570 @@
+use std::ffi::{c_int, c_uint, c_void};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::ptr;
+use std::time::{Duration, Instant};
+
+use libdivecomputer_sys as ffi;
+
+use crate::context::Context;
+use crate::descriptor::Descriptor;
+use crate::device::Device;
+use crate::error::{LibError, Result};
+use crate::iostream::{Direction, FlowControl, IoStream, Parity, SerialConfig, StopBits};
+use crate::status::Status;
+use crate::transport::Transport;
+
+/// Application-provided I/O for a dive computer, registered with the C
+/// library through `dc_custom_open` — the same mechanism the built-in BLE
+/// and Android Bluetooth transports use.
+///
+/// Only [`read`](Self::read) and [`write`](Self::write) are required. The
+/// line-control methods default to no-ops, which suits packet transports;
+/// [`poll`](Self::poll), [`ioctl`](Self::ioctl) and the line/queue queries
+/// default to reporting [`Status::Unsupported`]. Return [`LibError::Status`] to hand a specific
+/// status (e.g. [`Status::Timeout`]) back to the driver; other errors are
+/// reported as [`Status::Io`].
+pub trait CustomTransport: Send + 'static {
+    /// Read up to `buf.len()` bytes, returning how many were read.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Write `data`, returning how many bytes were written.
+    fn write(&mut self, data: &[u8]) -> Result<usize>;
+
+    /// Wait up to `timeout_ms` for data to become readable. Returns
+    /// `Ok(false)` on timeout. Negative means wait indefinitely.
+    fn poll(&mut self, timeout_ms: i32) -> Result<bool> {
+        let _ = timeout_ms;
+        Err(LibError::Status(Status::Unsupported, None))
+    }
+
+    /// Set the read timeout in milliseconds, with [`IoStream::set_timeout`]
+    /// semantics.
+    fn set_timeout(&mut self, timeout_ms: i32) -> Result<()> {
+        let _ = timeout_ms;
+        Ok(())
+    }
+
+    /// Apply serial line settings requested by the driver.
+    fn configure(&mut self, config: &SerialConfig) -> Result<()> {
+        let _ = config;
+        Ok(())
+    }
+
+    /// Assert (`true`) or release a break condition on the line.
+    fn set_break(&mut self, value: bool) -> Result<()> {
+        let _ = value;
+        Ok(())
+    }
+
+    /// Drive the DTR line.
+    fn set_dtr(&mut self, value: bool) -> Result<()> {
+        let _ = value;
+        Ok(())
+    }
+
+    /// Drive the RTS line.
+    fn set_rts(&mut self, value: bool) -> Result<()> {
+        let _ = value;
+        Ok(())
+    }
+
+    /// State of the modem input lines, as a mask of `DC_LINE_*` bits.
+    fn get_lines(&mut self) -> Result<u32> {
+        Err(LibError::Status(Status::Unsupported, None))
+    }
+
+    /// Number of bytes waiting to be read.
+    fn get_available(&mut self) -> Result<usize> {
+        Err(LibError::Status(Status::Unsupported, None))
+    }
+
+    /// Transport-specific request, such as `DC_IOCTL_BLE_GET_NAME`.
+    fn ioctl(&mut self, request: u32, data: &mut [u8]) -> Result<()> {
+        let _ = (request, data);
+        Err(LibError::Status(Status::Unsupported, None))
+    }
+
+    /// Flush pending output.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Discard buffered data in the given direction.
+    fn purge(&mut self, direction: Direction) -> Result<()> {
+        let _ = direction;
+        Ok(())
+    }
+
+    /// Pause for `duration`; drivers use this between protocol steps.
+    fn sleep(&mut self, duration: Duration) -> Result<()> {
+        std::thread::sleep(duration);
+        Ok(())
+    }
+}
+
+impl IoStream {
+    /// Wrap an application-provided transport in an iostream.
+    ///
+    /// `transport` is what the stream reports to drivers; some families
+    /// change their protocol depending on it (e.g. BLE framing), so it
+    /// should match the link `io` actually speaks.
+    #[must_use = "the opened IoStream must be passed to Device::open"]
+    pub fn custom<T: CustomTransport>(ctx: &Context, transport: Transport, io: T) -> Result<Self> {
+        let io_ptr = Box::into_raw(Box::new(io)).cast::<c_void>();
+
+        let callbacks = ffi::dc_custom_cbs_t {
+            set_timeout: Some(custom_set_timeout::<T>),
+            set_break: Some(custom_set_break::<T>),
+            set_dtr: Some(custom_set_dtr::<T>),
+            set_rts: Some(custom_set_rts::<T>),
+            get_lines: Some(custom_get_lines::<T>),
+            get_available: Some(custom_get_available::<T>),
+            configure: Some(custom_configure::<T>),
+            poll: Some(custom_poll::<T>),
+            read: Some(custom_read::<T>),
+            write: Some(custom_write::<T>),
+            ioctl: Some(custom_ioctl::<T>),
+            flush: Some(custom_flush::<T>),
+            purge: Some(custom_purge::<T>),
+            sleep: Some(custom_sleep::<T>),
+            close: Some(custom_close::<T>),
+        };
+
+        let mut iostream_ptr = ptr::null_mut();
+        let status = unsafe {
+            ffi::dc_custom_open(
+                &mut iostream_ptr,
+                ctx.ptr(),
+                transport as _,
+                &callbacks,
+                io_ptr,
+            )
+        };
+
+        if status != ffi::DC_STATUS_SUCCESS {
+            // SAFETY: `dc_custom_open` does not retain `userdata` on failure,
+            // so this is still the unique owner of the Box created above.
+            unsafe { drop(Box::from_raw(io_ptr.cast::<T>())) };
+            return Err(LibError::status_with_context(
+                status,
+                "failed to open custom iostream",
+            ));
+        }
+
+        Ok(Self::from_raw(iostream_ptr))
+    }
+}
+
+impl Device {
+    /// Open a device over an application-provided transport. Shorthand for
+    /// [`IoStream::custom`] followed by [`Device::open`].
+    #[must_use = "the opened Device owns the iostream and must be used or explicitly dropped"]
+    pub fn open_custom<T: CustomTransport>(
+        ctx: &Context,
+        desc: &Descriptor,
+        transport: Transport,
+        io: T,
+    ) -> Result<Self> {
+        let iostream = IoStream::custom(ctx, transport, io)?;
+        Self::open(ctx, desc, iostream)
+    }
+}
+
+/// Fill `buf` completely, the way the C serial backend reads, for stream
+/// transports built on a `read_some` that takes the time left (`None` to
+/// block) and returns whatever arrived.
+///
+/// At least one read is attempted even when `timeout` is zero. If the buffer
+/// is still short when the time is up, the bytes received are kept in
+/// `pending`, handed out first by the next call, and [`Status::Timeout`] is
+/// returned, so a timeout never drops bytes from the stream.
+pub(crate) fn read_full(
+    pending: &mut Vec<u8>,
+    buf: &mut [u8],
+    timeout: Option<Duration>,
+    mut read_some: impl FnMut(&mut [u8], Option<Duration>) -> std::io::Result<usize>,
+) -> Result<usize> {
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let mut filled = pending.len().min(buf.len());
+    buf[..filled].copy_from_slice(&pending[..filled]);
+    pending.drain(..filled);
+    while filled < buf.len() {
+        let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        match read_some(&mut buf[filled..], remaining) {
+            Ok(n) => filled += n,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock
+                        | std::io::ErrorKind::TimedOut
+                        | std::io::ErrorKind::Interrupted
+                ) => {}
+            Err(e) => {
+                pending.splice(0..0, buf[..filled].iter().copied());
+                return Err(e.into());
+            }
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+    }
+    if filled < buf.len() {
+        pending.splice(0..0, buf[..filled].iter().copied());
+        return Err(LibError::Status(Status::Timeout, None));
+    }
+    Ok(filled)
+}
+
+/// Status code handed back to the C library for a transport error.
+pub(crate) fn error_status(err: &LibError) -> ffi::dc_status_t {
+    match err {
+        LibError::Status(status, _) => *status as _,
+        LibError::Cancelled => ffi::DC_STATUS_CANCELLED,
+        LibError::InvalidArguments(_) => ffi::DC_STATUS_INVALIDARGS,
+        LibError::TransportNotSupported(_) => ffi::DC_STATUS_UNSUPPORTED,
+        LibError::Io(e) if e.kind() == std::io::ErrorKind::TimedOut => ffi::DC_STATUS_TIMEOUT,
+        _ => ffi::DC_STATUS_IO,
+    }
+}
+
+fn to_status(result: Result<()>) -> ffi::dc_status_t {
+    match result {
+        Ok(()) => ffi::DC_STATUS_SUCCESS,
+        Err(e) => error_status(&e),
+    }
+}
+
+/// Run a callback body against the transport behind `io`. Panics must not
+/// unwind into C, and are reported as I/O errors.
+fn with_transport<T: CustomTransport>(
+    io: *mut c_void,
+    f: impl FnOnce(&mut T) -> ffi::dc_status_t,
+) -> ffi::dc_status_t {
+    if io.is_null() {
+        return ffi::DC_STATUS_INVALIDARGS;
+    }
+    // SAFETY: `io` is the `Box<T>` handed to `dc_custom_open`, which lives
+    // until `custom_close`, and the C library never calls back concurrently.
+    let transport = unsafe { &mut *io.cast::<T>() };
+    catch_unwind(AssertUnwindSafe(|| f(transport))).unwrap_or(ffi::DC_STATUS_IO)
+}
+
+fn serial_config(
+    baudrate: c_uint,
+    databits: c_uint,
+    parity: ffi::dc_parity_t,
+    stopbits: ffi::dc_stopbits_t,
+    flowcontrol: ffi::dc_flowcontrol_t,
+) -> Option<SerialConfig> {
+    let parity = match parity {
+        ffi::DC_PARITY_NONE => Parity::None,
+        ffi::DC_PARITY_ODD => Parity::Odd,
+        ffi::DC_PARITY_EVEN => Parity::Even,
+        ffi::DC_PARITY_MARK => Parity::Mark,
+        ffi::DC_PARITY_SPACE => Parity::Space,
+        _ => return None,
+    };
+    let stopbits = match stopbits {
+        ffi::DC_STOPBITS_ONE => StopBits::One,
+        ffi::DC_STOPBITS_ONEPOINTFIVE => StopBits::OneAndHalf,
+        ffi::DC_STOPBITS_TWO => StopBits::Two,
+        _ => return None,
+    };
+    let flowcontrol = match flowcontrol {
+        ffi::DC_FLOWCONTROL_NONE => FlowControl::None,
+        ffi::DC_FLOWCONTROL_HARDWARE => FlowControl::Hardware,
+        ffi::DC_FLOWCONTROL_SOFTWARE => FlowControl::Software,
+        _ => return None,
+    };
+    Some(SerialConfig {
+        baudrate,
+        databits,
+        parity,
+        stopbits,
+        flowcontrol,
+    })
+}
+
+extern "C" fn custom_read<T: CustomTransport>(
+    io: *mut c_void,
+    data: *mut c_void,
+    size: usize,
+    actual: *mut usize,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| {
+        if data.is_null() {
+            return ffi::DC_STATUS_INVALIDARGS;
+        }
+        let buf = unsafe { std::slice::from_raw_parts_mut(data.cast::<u8>(), size) };
+        match transport.read(buf) {
+            Ok(n) => {
+                if !actual.is_null() {
+                    unsafe { *actual = n.min(size) };
+                }
+                ffi::DC_STATUS_SUCCESS
+            }
+            Err(e) => error_status(&e),
+        }
+    })
+}
+
+extern "C" fn custom_write<T: CustomTransport>(
+    io: *mut c_void,
+    data: *const c_void,
+    size: usize,
+    actual: *mut usize,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| {
+        if data.is_null() {
+            return ffi::DC_STATUS_INVALIDARGS;
+        }
+        let buf = unsafe { std::slice::from_raw_parts(data.cast::<u8>(), size) };
+        match transport.write(buf) {
+            Ok(n) => {
+                if !actual.is_null() {
+                    unsafe { *actual = n.min(size) };
+                }
+                ffi::DC_STATUS_SUCCESS
+            }
+            Err(e) => error_status(&e),
+        }
+    })
+}
+
+extern "C" fn custom_poll<T: CustomTransport>(io: *mut c_void, timeout: c_int) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| match transport.poll(timeout) {
+        Ok(true) => ffi::DC_STATUS_SUCCESS,
+        Ok(false) => ffi::DC_STATUS_TIMEOUT,
+        Err(e) => error_status(&e),
+    })
+}
+
+extern "C" fn custom_set_timeout<T: CustomTransport>(
+    io: *mut c_void,
+    timeout: c_int,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| to_status(transport.set_timeout(timeout)))
+}
+
+extern "C" fn custom_set_break<T: CustomTransport>(
+    io: *mut c_void,
+    value: c_uint,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| to_status(transport.set_break(value != 0)))
+}
+
+extern "C" fn custom_set_dtr<T: CustomTransport>(
+    io: *mut c_void,
+    value: c_uint,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| to_status(transport.set_dtr(value != 0)))
+}
+
+extern "C" fn custom_set_rts<T: CustomTransport>(
+    io: *mut c_void,
+    value: c_uint,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| to_status(transport.set_rts(value != 0)))
+}
+
+extern "C" fn custom_get_lines<T: CustomTransport>(
+    io: *mut c_void,
+    value: *mut c_uint,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| match transport.get_lines() {
+        Ok(lines) => {
+            if !value.is_null() {
+                unsafe { *value = lines };
+            }
+            ffi::DC_STATUS_SUCCESS
+        }
+        Err(e) => error_status(&e),
+    })
+}
+
+extern "C" fn custom_get_available<T: CustomTransport>(
+    io: *mut c_void,
+    value: *mut usize,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| match transport.get_available() {
+        Ok(available) => {
+            if !value.is_null() {
+                unsafe { *value = available };
+            }
+            ffi::DC_STATUS_SUCCESS
+        }
+        Err(e) => error_status(&e),
+    })
+}
+
+extern "C" fn custom_configure<T: CustomTransport>(
+    io: *mut c_void,
+    baudrate: c_uint,
+    databits: c_uint,
+    parity: ffi::dc_parity_t,
+    stopbits: ffi::dc_stopbits_t,
+    flowcontrol: ffi::dc_flowcontrol_t,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| {
+        match serial_config(baudrate, databits, parity, stopbits, flowcontrol) {
+            Some(config) => to_status(transport.configure(&config)),
+            None => ffi::DC_STATUS_INVALIDARGS,
+        }
+    })
+}
+
+extern "C" fn custom_ioctl<T: CustomTransport>(
+    io: *mut c_void,
+    request: c_uint,
+    data: *mut c_void,
+    size: usize,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| {
+        let buf: &mut [u8] = if data.is_null() {
+            &mut []
+        } else {
+            unsafe { std::slice::from_raw_parts_mut(data.cast::<u8>(), size) }
+        };
+        to_status(transport.ioctl(request, buf))
+    })
+}
+
+extern "C" fn custom_flush<T: CustomTransport>(io: *mut c_void) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| to_status(transport.flush()))
+}
+
+extern "C" fn custom_purge<T: CustomTransport>(
+    io: *mut c_void,
+    direction: ffi::dc_direction_t,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| {
+        let direction = match direction {
+            ffi::DC_DIRECTION_INPUT => Direction::Input,
+            ffi::DC_DIRECTION_OUTPUT => Direction::Output,
+            ffi::DC_DIRECTION_ALL => Direction::All,
+            _ => return ffi::DC_STATUS_INVALIDARGS,
+        };
+        to_status(transport.purge(direction))
+    })
+}
+
+extern "C" fn custom_sleep<T: CustomTransport>(
+    io: *mut c_void,
+    milliseconds: c_uint,
+) -> ffi::dc_status_t {
+    with_transport::<T>(io, |transport| {
+        to_status(transport.sleep(Duration::from_millis(u64::from(milliseconds))))
+    })
+}
+
+extern "C" fn custom_close<T: CustomTransport>(io: *mut c_void) -> ffi::dc_status_t {
+    if io.is_null() {
+        return ffi::DC_STATUS_SUCCESS;
+    }
+    // SAFETY: libdivecomputer calls `close` exactly once, with the pointer
+    // produced by `Box::into_raw` in `IoStream::custom`.
+    let result = catch_unwind(AssertUnwindSafe(|| unsafe {
+        drop(Box::from_raw(io.cast::<T>()));
+    }));
+    match result {
+        Ok(()) => ffi::DC_STATUS_SUCCESS,
+        Err(_) => ffi::DC_STATUS_IO,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_status_keeps_driver_status() {
+        assert_eq!(
+            error_status(&LibError::Status(Status::Timeout, None)),
+            ffi::DC_STATUS_TIMEOUT
+        );
+        assert_eq!(
+            error_status(&LibError::Io(std::io::ErrorKind::TimedOut.into())),
+            ffi::DC_STATUS_TIMEOUT
+        );
+        assert_eq!(error_status(&LibError::Unknown), ffi::DC_STATUS_IO);
+    }
+
+    #[test]
+    fn serial_config_from_ffi() {
+        let config = serial_config(
+            9600,
+            8,
+            ffi::DC_PARITY_EVEN,
+            ffi::DC_STOPBITS_TWO,
+            ffi::DC_FLOWCONTROL_NONE,
+        )
+        .unwrap();
+        assert_eq!(config.baudrate, 9600);
+        assert_eq!(config.parity, Parity::Even);
+        assert_eq!(config.stopbits, StopBits::Two);
+        assert_eq!(config.flowcontrol, FlowControl::None);
+
+        assert!(
+            serial_config(9600, 8, 99, ffi::DC_STOPBITS_ONE, ffi::DC_FLOWCONTROL_NONE).is_none()
+        );
+    }
+
+    struct Loopback(Vec<u8>);
+
+    impl CustomTransport for Loopback {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let n = buf.len().min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0.drain(..n);
+            Ok(n)
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<usize> {
+            self.0.extend_from_slice(data);
+            Ok(data.len())
+        }
+    }
+
+    #[test]
+    fn read_full_keeps_bytes_from_a_short_read() {
+        let mut pending = Vec::new();
+        let mut chunks = vec![b"ab".to_vec()];
+        let mut read_some = |buf: &mut [u8], _: Option<Duration>| match chunks.pop() {
+            Some(chunk) => {
+                buf[..chunk.len()].copy_from_slice(&chunk);
+                Ok(chunk.len())
+            }
+            None => Err(std::io::ErrorKind::TimedOut.into()),
+        };
+
+        // A zero timeout still reads once.
+        let mut buf = [0u8; 4];
+        assert!(matches!(
+            read_full(&mut pending, &mut buf, Some(Duration::ZERO), &mut read_some),
+            Err(LibError::Status(Status::Timeout, _))
+        ));
+        assert_eq!(pending, b"ab");
+
+        let mut buf = [0u8; 2];
+        let n = read_full(&mut pending, &mut buf, Some(Duration::ZERO), &mut read_some).unwrap();
+        assert_eq!(&buf[..n], b"ab");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn callbacks_drive_the_transport() {
+        let io = Box::into_raw(Box::new(Loopback(Vec::new()))).cast::<c_void>();
+
+        let mut actual = 0;
+        let status = custom_write::<Loopback>(io, b"ping".as_ptr().cast(), 4, &mut actual);
+        assert_eq!((status, actual), (ffi::DC_STATUS_SUCCESS, 4));
+
+        let mut buf = [0u8; 8];
+        let status = custom_read::<Loopback>(io, buf.as_mut_ptr().cast(), buf.len(), &mut actual);
+        assert_eq!((status, actual), (ffi::DC_STATUS_SUCCESS, 4));
+        assert_eq!(&buf[..4], b"ping");
+
+        assert_eq!(custom_poll::<Loopback>(io, 0), ffi::DC_STATUS_UNSUPPORTED);
+        assert_eq!(custom_close::<Loopback>(io), ffi::DC_STATUS_SUCCESS);
+    }
+}
@@ -1,13 +1,30 @@
 use libdivecomputer_sys as ffi;
 
+/// The wall-clock date and time of a `dc_datetime_t`. Fails rather than
+/// panicking on fields out of range, which is what a header decoded with
+/// the wrong model's layout usually holds.
+pub(crate) fn ffi_to_civil(dt: &ffi::dc_datetime_t) -> Result<jiff::civil::DateTime, jiff::Error> {
+    fn field<T: TryFrom<i32>>(name: &str, value: i32) -> Result<T, jiff::Error> {
+        T::try_from(value)
+            .map_err(|_| jiff::Error::from_args(format_args!("{name} {value} is out of range")))
+    }
+    let date = jiff::civil::Date::new(
+        field("year", dt.year)?,
+        field("month", dt.month)?,
+        field("day", dt.day)?,
+    )?;
+    let time = jiff::civil::Time::new(
+        field("hour", dt.hour)?,
+        field("minute", dt.minute)?,
+        field("second", dt.second)?,
+        0,
+    )?;
+    Ok(date.to_datetime(time))
+}
+
 /// Convert a `dc_datetime_t` to a `jiff::Timestamp`.
 pub(crate) fn ffi_to_timestamp(dt: &ffi::dc_datetime_t) -> Result<jiff::Timestamp, jiff::Error> {
-    let civil = jiff::civil::date(dt.year as i16, dt.month as i8, dt.day as i8).at(
-        dt.hour as i8,
-        dt.minute as i8,
-        dt.second as i8,
-        0,
-    );
+    let civil = ffi_to_civil(dt)?;
     if dt.timezone == i32::MIN {
         // DC_TIMEZONE_NONE — treat as UTC
         Ok(civil.to_zoned(jiff::tz::TimeZone::UTC)?.timestamp())
@@ -17,11 +34,46 @@ pub(crate) fn ffi_to_timestamp(dt: &ffi::dc_datetime_t) -> Result<jiff::Timestam
     }
 }
 
-/// Convert a `jiff::Timestamp` to a `dc_datetime_t` in local time.
+/// Convert a `dc_datetime_t` to a `jiff::Zoned`, reading the wall-clock
+/// time in `fallback` when the computer recorded no UTC offset.
+pub(crate) fn ffi_to_zoned(
+    dt: &ffi::dc_datetime_t,
+    fallback: &jiff::tz::TimeZone,
+) -> Result<jiff::Zoned, jiff::Error> {
+    let civil = ffi_to_civil(dt)?;
+    match ffi_utc_offset(dt) {
+        Some(secs) => {
+            let tz = jiff::tz::TimeZone::fixed(jiff::tz::Offset::from_seconds(secs)?);
+            civil.to_zoned(tz)
+        }
+        None => civil.to_zoned(fallback.clone()),
+    }
+}
+
+/// UTC offset in seconds recorded in a `dc_datetime_t`, or `None` for
+/// `DC_TIMEZONE_NONE`.
+pub(crate) fn ffi_utc_offset(dt: &ffi::dc_datetime_t) -> Option<i32> {
+    (dt.timezone != i32::MIN).then_some(dt.timezone)
+}
+
+/// Convert a `jiff::Timestamp` to a `dc_datetime_t` in the host's local
+/// time, which is what a dive computer's clock is expected to show.
 pub(crate) fn timestamp_to_ffi(ts: jiff::Timestamp) -> ffi::dc_datetime_t {
-    let mut dt: ffi::dc_datetime_t = unsafe { std::mem::zeroed() };
-    unsafe { ffi::dc_datetime_localtime(&mut dt, ts.as_second()) };
-    dt
+    zoned_to_ffi(&ts.to_zoned(jiff::tz::TimeZone::system()))
+}
+
+/// Convert a `jiff::Zoned` to a `dc_datetime_t` holding its wall-clock time
+/// and UTC offset.
+pub(crate) fn zoned_to_ffi(zoned: &jiff::Zoned) -> ffi::dc_datetime_t {
+    ffi::dc_datetime_t {
+        year: zoned.year().into(),
+        month: zoned.month().into(),
+        day: zoned.day().into(),
+        hour: zoned.hour().into(),
+        minute: zoned.minute().into(),
+        second: zoned.second().into(),
+        timezone: zoned.offset().seconds(),
+    }
 }
 
 #[cfg(test)]
@@ -48,6 +100,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ffi_to_zoned_uses_fallback_only_without_offset() {
+        let tz = jiff::tz::TimeZone::fixed(jiff::tz::offset(2));
+        let dt = make_dt(2025, 6, 15, 12, 30, 45, i32::MIN);
+        let zoned = ffi_to_zoned(&dt, &tz).unwrap();
+        assert_eq!(zoned.timestamp().to_string(), "2025-06-15T10:30:45Z");
+
+        let dt = make_dt(2025, 6, 15, 12, 30, 45, -3600);
+        let zoned = ffi_to_zoned(&dt, &tz).unwrap();
+        assert_eq!(zoned.timestamp().to_string(), "2025-06-15T13:30:45Z");
+    }
+
+    #[test]
+    fn ffi_to_zoned_rejects_invalid_fields() {
+        let tz = jiff::tz::TimeZone::UTC;
+        assert!(ffi_to_zoned(&make_dt(2025, 13, 15, 12, 0, 0, i32::MIN), &tz).is_err());
+        assert!(ffi_to_zoned(&make_dt(2025, 6, 15, 12, 60, 0, 3600), &tz).is_err());
+    }
+
     #[test]
     fn ffi_to_timestamp_utc() {
         // timezone == i32::MIN means UTC (DC_TIMEZONE_NONE)
@@ -91,6 +162,31 @@ mod tests {
         assert_eq!(ts.to_string(), "2025-01-01T00:30:00Z");
     }
 
+    #[test]
+    fn ffi_to_timestamp_rejects_invalid_fields() {
+        for dt in [
+            make_dt(2025, 0, 15, 12, 0, 0, i32::MIN),
+            make_dt(2025, 13, 15, 12, 0, 0, 0),
+            make_dt(2025, 6, 32, 12, 0, 0, 0),
+            make_dt(2025, 6, 15, 25, 0, 0, 0),
+            make_dt(2025, 256 + 6, 15, 12, 0, 0, 0),
+        ] {
+            assert!(ffi_to_timestamp(&dt).is_err());
+        }
+    }
+
+    #[test]
+    fn ffi_utc_offset_none_and_some() {
+        assert_eq!(
+            ffi_utc_offset(&make_dt(2025, 1, 1, 0, 0, 0, i32::MIN)),
+            None
+        );
+        assert_eq!(
+            ffi_utc_offset(&make_dt(2025, 1, 1, 0, 0, 0, -1800)),
+            Some(-1800)
+        );
+    }
+
     #[test]
     fn timestamp_to_ffi_roundtrip() {
         let ts = jiff::Timestamp::from_second(1750000000).unwrap();
@@ -107,4 +203,19 @@ mod tests {
         let ts2 = ffi_to_timestamp(&dt).unwrap();
         assert_eq!(ts.as_second(), ts2.as_second());
     }
+
+    #[test]
+    fn zoned_to_ffi_keeps_wall_clock_and_offset() {
+        let offset = jiff::tz::Offset::from_seconds(-(3 * 3600 + 30 * 60)).unwrap();
+        let zoned = jiff::Timestamp::from_second(1750000000)
+            .unwrap()
+            .to_zoned(jiff::tz::TimeZone::fixed(offset));
+        let dt = zoned_to_ffi(&zoned);
+        assert_eq!(
+            (dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second),
+            (2025, 6, 15, 11, 36, 40)
+        );
+        assert_eq!(dt.timezone, -(3 * 3600 + 30 * 60));
+        assert_eq!(ffi_to_timestamp(&dt).unwrap(), zoned.timestamp());
+    }
 }
@@ -3,6 +3,7 @@ use std::{fmt, ptr};
 
 use libdivecomputer_sys as ffi;
 
+use crate::device::{ConnectionInfo, DeviceInfo};
 use crate::error::{LibError, Result};
 use crate::family::Family;
 use crate::status::Status;
@@ -39,18 +40,75 @@ impl Descriptor {
         Ok(None)
     }
 
-    /// Find a descriptor by full name ("Vendor Product").
+    /// Find a descriptor by full name ("Vendor Product") or product name
+    /// alone, ignoring ASCII case and surrounding whitespace, so names typed
+    /// by a user match.
     #[must_use = "look-up result should be inspected"]
     pub fn find_by_name(name: &str) -> Result<Descriptor> {
+        let name = name.trim();
         for desc in Self::iter()? {
             let full_name = format!("{} {}", desc.vendor(), desc.product());
-            if full_name == name || desc.product() == name {
+            if full_name.eq_ignore_ascii_case(name) || desc.product().eq_ignore_ascii_case(name) {
                 return Ok(desc);
             }
         }
         Err(LibError::DescriptorNotFound(name.to_string()))
     }
 
+    /// Vendor names in the catalog, each once, in catalog order.
+    pub fn vendors() -> Result<Vec<String>> {
+        let mut vendors: Vec<String> = Vec::new();
+        for desc in Self::iter()? {
+            if !vendors.iter().any(|vendor| vendor == desc.vendor()) {
+                vendors.push(desc.vendor().to_string());
+            }
+        }
+        Ok(vendors)
+    }
+
+    /// Like [`vendors`](Self::vendors), but only vendors with at least one
+    /// model reachable over `transports`. Pass
+    /// [`Context::supported_transports`](crate::Context::supported_transports)
+    /// to leave out vendors whose models this host cannot talk to.
+    pub fn vendors_for(transports: TransportSet) -> Result<Vec<String>> {
+        let mut vendors: Vec<String> = Vec::new();
+        for desc in Self::by_transports(transports)? {
+            if !vendors.iter().any(|vendor| vendor == desc.vendor()) {
+                vendors.push(desc.vendor().to_string());
+            }
+        }
+        Ok(vendors)
+    }
+
+    /// All models of `vendor`, ignoring ASCII case.
+    pub fn by_vendor(vendor: &str) -> Result<Vec<Descriptor>> {
+        Ok(Self::iter()?
+            .filter(|desc| desc.vendor().eq_ignore_ascii_case(vendor))
+            .collect())
+    }
+
+    /// All models reachable over `transport`.
+    pub fn by_transport(transport: Transport) -> Result<Vec<Descriptor>> {
+        Ok(Self::iter()?
+            .filter(|desc| desc.supports(transport))
+            .collect())
+    }
+
+    /// All models reachable over at least one of `transports`, e.g.
+    /// [`Context::supported_transports`](crate::Context::supported_transports).
+    pub fn by_transports(transports: TransportSet) -> Result<Vec<Descriptor>> {
+        Ok(Self::iter()?
+            .filter(|desc| desc.supports_any(transports))
+            .collect())
+    }
+
+    /// All models of the device family `family`, i.e. sharing one driver.
+    pub fn by_family(family: Family) -> Result<Vec<Descriptor>> {
+        Ok(Self::iter()?
+            .filter(|desc| desc.family() == family)
+            .collect())
+    }
+
     /// Vendor name.
     pub fn vendor(&self) -> &str {
         if self.ptr.is_null() {
@@ -99,12 +157,153 @@ impl Descriptor {
         unsafe { TransportSet::from_bits(ffi::dc_descriptor_get_transports(self.ptr)) }
     }
 
+    /// Whether the model can be reached over `transport`.
+    pub fn supports(&self, transport: Transport) -> bool {
+        self.transports().contains(transport)
+    }
+
+    /// Whether the model can be reached over at least one of `transports`.
+    pub fn supports_any(&self, transports: TransportSet) -> bool {
+        !(self.transports() & transports).is_empty()
+    }
+
+    /// Whether `device`, as found by a scan, may be this model: USB and USB
+    /// HID devices are checked by vendor and product ID, Bluetooth and BLE
+    /// devices by the name they advertise, which must start with the
+    /// product name or its first word, or by a BLE service belonging to the
+    /// vendor.
+    ///
+    /// libdivecomputer's own filter for this (`dc_descriptor_filter`) is
+    /// private to the C library, so the USB IDs come from a per-model table
+    /// kept here. Models missing from it accept any ID no listed model uses.
+    /// Devices that carry nothing to check, such as serial ports and IrDA
+    /// devices, always match.
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        if self.ptr.is_null() || !self.supports(device.transport) {
+            return false;
+        }
+        match &device.connection {
+            // A serial port behind a USB bridge; nothing to check, as for
+            // other serial ports.
+            ConnectionInfo::Usb { ftdi: true, .. } => true,
+            ConnectionInfo::Usb {
+                vendor_id,
+                product_id,
+                ..
+            } => self.matches_usb(Transport::Usb, *vendor_id, *product_id),
+            ConnectionInfo::UsbHid {
+                vendor_id,
+                product_id,
+            } => self.matches_usb(Transport::UsbHid, *vendor_id, *product_id),
+            ConnectionInfo::Ble { .. } | ConnectionInfo::Bluetooth { .. } => {
+                crate::scanner::match_rank(device, self.vendor(), self.product()) < 3
+            }
+            _ => true,
+        }
+    }
+
+    /// Whether the USB ID belongs to this model according to [`USB_MODELS`]:
+    /// listed together, or neither the model nor the ID listed at all.
+    fn matches_usb(&self, transport: Transport, vendor_id: u16, product_id: u16) -> bool {
+        let mut listed = false;
+        for model in USB_MODELS
+            .iter()
+            .filter(|model| model.transport == transport)
+        {
+            let same_model = model.vendor == self.vendor() && model.product == self.product();
+            let same_id = model.vendor_id == vendor_id && model.product_id == product_id;
+            if same_model && same_id {
+                return true;
+            }
+            listed |= same_model || same_id;
+        }
+        !listed
+    }
+
     /// Supported transports as a Vec.
     pub fn transport_list(&self) -> Vec<Transport> {
         self.transports().to_vec()
     }
 }
 
+/// A dive computer model and the USB ID it enumerates with.
+pub(crate) struct UsbModel {
+    pub(crate) transport: Transport,
+    pub(crate) vendor_id: u16,
+    pub(crate) product_id: u16,
+    /// Vendor and product as named in the descriptor catalog.
+    pub(crate) vendor: &'static str,
+    pub(crate) product: &'static str,
+}
+
+/// USB and USB HID IDs of the models that connect over them, one entry per
+/// model. Models sharing an ID each get an entry.
+pub(crate) const USB_MODELS: &[UsbModel] = &[
+    usb_model(Transport::UsbHid, 0x1493, 0x0030, "Suunto", "EON Steel"),
+    usb_model(Transport::UsbHid, 0x1493, 0x0033, "Suunto", "EON Core"),
+    usb_model(Transport::UsbHid, 0x1493, 0x0035, "Suunto", "D5"),
+    usb_model(
+        Transport::UsbHid,
+        0x1493,
+        0x0036,
+        "Suunto",
+        "EON Steel Black",
+    ),
+    usb_model(Transport::UsbHid, 0x2E6C, 0x3201, "Scubapro", "G2"),
+    usb_model(Transport::UsbHid, 0x2E6C, 0x3211, "Scubapro", "G2 Console"),
+    usb_model(Transport::UsbHid, 0x2E6C, 0x4201, "Scubapro", "G2 HUD"),
+    usb_model(
+        Transport::UsbHid,
+        0xC251,
+        0x2006,
+        "Scubapro",
+        "Aladin Square",
+    ),
+    usb_model(Transport::Usb, 0x0471, 0x0888, "Atomic Aquatics", "Cobalt"),
+    usb_model(
+        Transport::Usb,
+        0x0471,
+        0x0888,
+        "Atomic Aquatics",
+        "Cobalt 2",
+    ),
+];
+
+const fn usb_model(
+    transport: Transport,
+    vendor_id: u16,
+    product_id: u16,
+    vendor: &'static str,
+    product: &'static str,
+) -> UsbModel {
+    UsbModel {
+        transport,
+        vendor_id,
+        product_id,
+        vendor,
+        product,
+    }
+}
+
+/// Display name for a USB or USB HID device, from the model listed with its
+/// ID in [`USB_MODELS`], e.g. "Suunto EON Steel"; the first one listed for
+/// an ID several models share. IDs no supported model uses get a generic
+/// "USB Device 1234:5678".
+pub(crate) fn usb_device_name(transport: Transport, vendor_id: u16, product_id: u16) -> String {
+    let model = USB_MODELS.iter().find(|model| {
+        model.transport == transport
+            && model.vendor_id == vendor_id
+            && model.product_id == product_id
+    });
+    match model {
+        Some(model) => format!("{} {}", model.vendor, model.product),
+        None if transport == Transport::UsbHid => {
+            format!("USB HID Device {vendor_id:04X}:{product_id:04X}")
+        }
+        None => format!("USB Device {vendor_id:04X}:{product_id:04X}"),
+    }
+}
+
 impl fmt::Display for Descriptor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} {}", self.vendor(), self.product())
@@ -120,7 +319,7 @@ impl fmt::Debug for Descriptor {
             self.product(),
             self.model(),
             self.family(),
-            self.transport_list(),
+            self.transports(),
         )
     }
 }
@@ -205,6 +404,93 @@ mod test {
         assert_eq!(desc.product(), "EON Steel");
     }
 
+    #[test]
+    fn find_by_name_ignores_case() {
+        let desc = Descriptor::find_by_name(" suunto eon steel ").unwrap();
+        assert_eq!(desc.product(), "EON Steel");
+    }
+
+    #[test]
+    fn filter_by_vendor_transport_and_family() {
+        let vendors = Descriptor::vendors().unwrap();
+        assert!(vendors.iter().any(|vendor| vendor == "Shearwater"));
+        let shearwater = Descriptor::by_vendor("shearwater").unwrap();
+        assert!(!shearwater.is_empty());
+        assert!(shearwater.iter().all(|desc| desc.vendor() == "Shearwater"));
+
+        let ble = Descriptor::by_transport(Transport::Ble).unwrap();
+        assert!(ble.iter().all(|desc| desc.supports(Transport::Ble)));
+
+        let serial = Descriptor::by_transports(Transport::Serial.into()).unwrap();
+        assert!(serial.iter().all(|desc| desc.supports(Transport::Serial)));
+        let either = Descriptor::by_transports(Transport::Serial | Transport::Ble).unwrap();
+        assert!(either.len() >= serial.len().max(ble.len()));
+        assert!(
+            Descriptor::by_transports(TransportSet::empty())
+                .unwrap()
+                .is_empty()
+        );
+        let ble_vendors = Descriptor::vendors_for(Transport::Ble.into()).unwrap();
+        assert!(ble_vendors.iter().all(|vendor| vendors.contains(vendor)));
+        assert!(ble_vendors.len() < vendors.len());
+
+        let family = shearwater[0].family();
+        let same_family = Descriptor::by_family(family).unwrap();
+        assert!(same_family.iter().all(|desc| desc.family() == family));
+    }
+
+    #[test]
+    fn matches_usbhid_ids() {
+        let eon_steel = Descriptor::find("Suunto", "EON Steel").unwrap().unwrap();
+        let device = |product_id| DeviceInfo {
+            name: "EON Steel".to_string(),
+            transport: Transport::UsbHid,
+            connection: ConnectionInfo::UsbHid {
+                vendor_id: 0x1493,
+                product_id,
+            },
+            advertisement: None,
+        };
+        assert!(eon_steel.matches(&device(0x0030)));
+        assert!(!eon_steel.matches(&device(0xffff)));
+        // The EON Core's ID is another model's.
+        assert!(!eon_steel.matches(&device(0x0033)));
+    }
+
+    #[test]
+    fn usb_models_name_catalog_entries() {
+        for model in USB_MODELS {
+            let desc = Descriptor::find(model.vendor, model.product)
+                .unwrap()
+                .unwrap_or_else(|| panic!("{} {} not in the catalog", model.vendor, model.product));
+            assert!(desc.supports(model.transport), "{desc}");
+        }
+    }
+
+    #[test]
+    fn usb_device_names_come_from_the_model_table() {
+        assert_eq!(
+            usb_device_name(Transport::UsbHid, 0x1493, 0x0030),
+            "Suunto EON Steel"
+        );
+        assert_eq!(
+            usb_device_name(Transport::UsbHid, 0x1493, 0x0033),
+            "Suunto EON Core"
+        );
+        assert_eq!(
+            usb_device_name(Transport::Usb, 0x0471, 0x0888),
+            "Atomic Aquatics Cobalt"
+        );
+        assert_eq!(
+            usb_device_name(Transport::UsbHid, 0x0001, 0x0002),
+            "USB HID Device 0001:0002"
+        );
+        assert_eq!(
+            usb_device_name(Transport::Usb, 0x0001, 0x0002),
+            "USB Device 0001:0002"
+        );
+    }
+
     #[test]
     fn find_by_name_unknown() {
         let err = Descriptor::find_by_name("Nonexistent Device 9999").unwrap_err();
@@ -226,7 +512,7 @@ mod test {
         // At least some descriptors should have transports
         let has_transports = Descriptor::iter()
             .unwrap()
-            .any(|d| !d.transports().to_vec().is_empty());
+            .any(|d| !d.transports().is_empty());
         assert!(has_transports);
     }
 
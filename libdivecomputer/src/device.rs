@@ -1,7 +1,18 @@
 use std::{
     borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashSet, VecDeque},
     ffi::{c_int, c_uchar, c_uint, c_void},
-    fmt, ptr,
+    fmt,
+    hash::{Hash, Hasher},
+    ptr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
 use libdivecomputer_sys as ffi;
@@ -9,13 +20,15 @@ use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
 use crate::{
+    address::BtAddress,
     buffer::Buffer,
     common::{as_void_ptr, ffi_guard, from_void_ptr},
     context::Context,
-    descriptor::Descriptor,
-    error::{LibError, Result},
+    descriptor::{Descriptor, usb_device_name},
+    error::{ErrorKind, LibError, Result, combined_kind},
+    fingerprint_store::{FingerprintKey, FingerprintStore},
     iostream::IoStream,
-    parser::{Dive, Fingerprint, Parser},
+    parser::{Dive, Fingerprint, ParseOptions, Parser},
     status::Status,
     transport::Transport,
 };
@@ -23,7 +36,10 @@ use crate::{
 /// Description of a device returned by [`scan`](crate::scan) or constructed
 /// manually before calling [`IoStream::open`]. Bundles a human-readable name,
 /// the transport kind, and the transport-specific connection details.
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Equality and hashing ignore [`advertisement`](Self::advertisement), so
+/// the same computer compares equal from one scan to the next.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceInfo {
     /// Display name for the device (e.g. the advertised BLE local name,
     /// serial-port friendly name, or vendor/product label).
@@ -32,6 +48,256 @@ pub struct DeviceInfo {
     pub transport: Transport,
     /// Transport-specific connection parameters.
     pub connection: ConnectionInfo,
+    /// What the device advertised when it was found, for BLE scan results.
+    /// Changes from scan to scan, so it is left out of saved entries that
+    /// carry none; use [`connection`](Self::connection) to identify a device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub advertisement: Option<BleAdvertisement>,
+}
+
+impl PartialEq for DeviceInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.transport == other.transport
+            && self.connection == other.connection
+    }
+}
+
+impl Eq for DeviceInfo {}
+
+impl Hash for DeviceInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.transport.hash(state);
+        self.connection.hash(state);
+    }
+}
+
+/// Advertisement data seen for a BLE device during the scan that found it,
+/// e.g. to sort a device picker by signal strength or label entries by
+/// manufacturer.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BleAdvertisement {
+    /// Received signal strength in dBm; closer to zero is stronger.
+    pub rssi: Option<i16>,
+    /// Advertised transmit power in dBm.
+    pub tx_power: Option<i16>,
+    /// Manufacturer-specific data, keyed by Bluetooth SIG company identifier.
+    pub manufacturer_data: BTreeMap<u16, Vec<u8>>,
+    /// Every service UUID in the advertisement, not just the one that
+    /// matched a known dive computer service. Lowercase, hyphenated.
+    pub services: Vec<String>,
+}
+
+impl DeviceInfo {
+    /// Signal strength from the scan that found this device, if known.
+    pub fn rssi(&self) -> Option<i16> {
+        self.advertisement.as_ref().and_then(|adv| adv.rssi)
+    }
+
+    /// Connect to exactly this device and bind it to the model `desc`.
+    ///
+    /// Intended for the scan-then-pick flow: run [`scan`](crate::scan) with
+    /// [`exhaustive`](crate::scanner::ScanBuilder::exhaustive), let the user
+    /// choose an entry, then open it here. A `DeviceInfo` saved from an
+    /// earlier scan works as well; BLE addresses are matched regardless of
+    /// how the platform formatted them.
+    ///
+    /// Fails with [`LibError::TransportNotSupported`] before touching the
+    /// hardware if `desc` cannot talk over this device's transport. Line
+    /// settings for serial ports (baud rate, parity, flow control) are not
+    /// needed here: the family driver applies them in [`Device::open`].
+    #[must_use = "the opened Device owns the iostream and must be used or explicitly dropped"]
+    #[instrument(skip_all, fields(device = %self.name))]
+    pub fn open(&self, ctx: &Context, desc: &Descriptor) -> Result<Device> {
+        let transport = Transport::from(&self.connection);
+        if !desc.transports().contains(transport) {
+            return Err(LibError::TransportNotSupported(format!(
+                "{desc} cannot be reached over {transport}"
+            )));
+        }
+        let iostream = IoStream::open(ctx, &self.connection)?;
+        let mut device = Device::open(ctx, desc, iostream)?;
+        device.connection = Some(self.connection.clone());
+        Ok(device)
+    }
+
+    /// Connect to a device saved from an earlier scan, finding it again if
+    /// it has moved.
+    ///
+    /// Tries [`open`](Self::open) first. If that fails with a
+    /// [retriable](LibError::is_retriable) error — the computer is not where
+    /// it was — `desc`'s transport is scanned again and the entry that is
+    /// [the same device](Self::same_device) is opened instead: a USB computer
+    /// plugged into another port, or a BLE computer whose platform id
+    /// changed. USB IDs name a model rather than a unit, and an advertised
+    /// name may be shared too, so a match on either must be unique among the
+    /// results. Otherwise the original error is returned.
+    #[must_use = "the opened Device owns the iostream and must be used or explicitly dropped"]
+    #[instrument(skip_all, fields(device = %self.name))]
+    pub fn open_saved(&self, ctx: &Context, desc: &Descriptor) -> Result<Device> {
+        let err = match self.open(ctx, desc) {
+            Ok(device) => return Ok(device),
+            Err(e) if !e.is_retriable() => return Err(e),
+            Err(e) => e,
+        };
+        tracing::debug!(error = %err, "saved device not reachable, rescanning");
+        let found = crate::scanner::scan(ctx, self.transport)
+            .descriptor(desc)
+            .exhaustive(true)
+            .execute()?;
+        match self.find_saved(&found) {
+            Some(device) => device.open(ctx, desc),
+            None => Err(err),
+        }
+    }
+
+    /// The entry of a new scan that is this saved device, if it can be told
+    /// apart from the others.
+    fn find_saved<'a>(&self, found: &'a [DeviceInfo]) -> Option<&'a DeviceInfo> {
+        fn unique<'a>(mut matches: impl Iterator<Item = &'a DeviceInfo>) -> Option<&'a DeviceInfo> {
+            matches.next().filter(|_| matches.next().is_none())
+        }
+        let mut by_id = found.iter().filter(|device| self.same_device(device));
+        let by_id = match self.connection {
+            // Two identical computers plugged in share their IDs, and
+            // nothing tells which one was saved.
+            ConnectionInfo::Usb { .. } | ConnectionInfo::UsbHid { .. } => unique(by_id),
+            _ => by_id.next(),
+        };
+        by_id.or_else(|| unique(found.iter().filter(|device| self.same_name(device))))
+    }
+
+    /// A copy for saving, e.g. to reconnect with
+    /// [`open_saved`](Self::open_saved) after a restart. Leaves out the
+    /// [`advertisement`](Self::advertisement), which describes one scan
+    /// rather than the device.
+    #[must_use]
+    pub fn to_saved(&self) -> DeviceInfo {
+        DeviceInfo {
+            advertisement: None,
+            ..self.clone()
+        }
+    }
+
+    /// Whether `other`, e.g. from a new scan, has this device's identity:
+    /// the same USB vendor and product ID, BLE platform id or address,
+    /// Bluetooth or IrDA address, or path. Advertised names are not compared,
+    /// since several computers of one model may share them.
+    ///
+    /// USB and USB HID devices carry no serial number or port here, so any
+    /// unit of the same model matches.
+    pub fn same_device(&self, other: &DeviceInfo) -> bool {
+        use ConnectionInfo as C;
+        match (&self.connection, &other.connection) {
+            (
+                C::Usb {
+                    vendor_id,
+                    product_id,
+                    ..
+                },
+                C::Usb {
+                    vendor_id: other_vid,
+                    product_id: other_pid,
+                    ..
+                },
+            )
+            | (
+                C::UsbHid {
+                    vendor_id,
+                    product_id,
+                },
+                C::UsbHid {
+                    vendor_id: other_vid,
+                    product_id: other_pid,
+                },
+            ) => (vendor_id, product_id) == (other_vid, other_pid),
+            (
+                C::Ble {
+                    address,
+                    address_string,
+                    peripheral_id,
+                    ..
+                },
+                C::Ble {
+                    address: other_address,
+                    address_string: other_string,
+                    peripheral_id: other_id,
+                    ..
+                },
+            ) => {
+                (peripheral_id.is_some() && peripheral_id == other_id)
+                    || (!address.is_unknown() && address == other_address)
+                    || (!address_string.is_empty()
+                        && address_string.eq_ignore_ascii_case(other_string))
+            }
+            (
+                C::Bluetooth {
+                    address,
+                    address_string,
+                },
+                C::Bluetooth {
+                    address: other_address,
+                    address_string: other_string,
+                },
+            ) => {
+                (!address.is_unknown() && address == other_address)
+                    || (!address_string.is_empty()
+                        && address_string.eq_ignore_ascii_case(other_string))
+            }
+            (C::Irda { address }, C::Irda { address: other }) => address == other,
+            (C::Serial { path }, C::Serial { path: other })
+            | (C::UsbStorage { path }, C::UsbStorage { path: other })
+            | (C::Simulator { capture: path, .. }, C::Simulator { capture: other, .. }) => {
+                path == other
+            }
+            (
+                C::Tcp { host, port },
+                C::Tcp {
+                    host: other_host,
+                    port: other_port,
+                },
+            ) => host.eq_ignore_ascii_case(other_host) && port == other_port,
+            _ => false,
+        }
+    }
+
+    /// Same transport and advertised name, the fallback of
+    /// [`open_saved`](Self::open_saved).
+    fn same_name(&self, other: &DeviceInfo) -> bool {
+        let name = |device: &DeviceInfo| match &device.connection {
+            ConnectionInfo::Ble {
+                local_name: Some(name),
+                ..
+            } => name.clone(),
+            _ => device.name.clone(),
+        };
+        self.transport == other.transport && !self.name.is_empty() && name(self) == name(other)
+    }
+
+    /// Connect, set the computer's clock to the host's current time, and
+    /// disconnect again. For repeated operations on one connection use
+    /// [`PersistentConnection::sync_clock`].
+    ///
+    /// # Errors
+    ///
+    /// Anything [`open`](Self::open) or [`Device::timesync`] can fail with.
+    #[instrument(skip_all, fields(device = %self.name))]
+    pub fn sync_clock(&self, ctx: &Context, desc: &Descriptor) -> Result<()> {
+        self.open(ctx, desc)?.timesync(jiff::Timestamp::now())
+    }
+
+    /// Connect, read the computer's model, firmware and serial number, and
+    /// disconnect again without downloading any dives. See
+    /// [`Device::identify`].
+    ///
+    /// # Errors
+    ///
+    /// Anything [`open`](Self::open) or [`Device::identify`] can fail with.
+    #[instrument(skip_all, fields(device = %self.name))]
+    pub fn identify(&self, ctx: &Context, desc: &Descriptor) -> Result<DeviceIdentity> {
+        self.open(ctx, desc)?.identify()
+    }
 }
 
 /// Transport-specific parameters needed to open a connection. Variants match
@@ -50,6 +316,17 @@ pub enum ConnectionInfo {
         vendor_id: u16,
         /// USB product ID.
         product_id: u16,
+        /// Position among the attached devices sharing these IDs, in
+        /// enumeration order. Tells identical computers plugged in side by
+        /// side apart; `0` when only one is attached.
+        #[serde(default)]
+        index: usize,
+        /// The device is an FTDI USB-serial cable for a serial computer,
+        /// e.g. an Oceanic or Cressi one, to be driven from Rust instead of
+        /// through a kernel tty (see [`FtdiTransport`](crate::ftdi::FtdiTransport)).
+        /// Linux and Android only.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        ftdi: bool,
     },
     /// USB HID — identified by vendor/product IDs.
     UsbHid {
@@ -60,21 +337,30 @@ pub enum ConnectionInfo {
     },
     /// Classic Bluetooth (RFCOMM / SPP).
     Bluetooth {
-        /// BT MAC as a raw `u64`.
-        address: u64,
+        /// BT MAC. [`BtAddress::UNKNOWN`] means "parse `address_string`".
+        address: BtAddress,
         /// MAC formatted as `AA:BB:CC:DD:EE:FF` — handy for logging and JNI.
         address_string: String,
     },
     /// Bluetooth Low Energy (GATT).
     Ble {
-        /// BLE MAC as a raw `u64`.
-        address: u64,
+        /// BLE MAC, or [`BtAddress::UNKNOWN`] where the platform hides it.
+        address: BtAddress,
         /// Advertised local name, if the peripheral provided one.
         local_name: Option<String>,
-        /// Matched service name from the known-services catalog.
+        /// Matched service name from the known-services catalog. Empty for
+        /// devices found only by an advertised-name prefix with no vendor.
         service_name: String,
-        /// MAC formatted as `AA:BB:CC:DD:EE:FF`.
+        /// MAC formatted as `AA:BB:CC:DD:EE:FF`, or the platform peripheral
+        /// id where the stack hides the MAC (CoreBluetooth on macOS/iOS).
         address_string: String,
+        /// Platform peripheral id (`btleplug`'s `PeripheralId`) as reported by
+        /// the scan. On macOS/iOS this opaque UUID is the only handle that
+        /// reconnects, so [`IoStream::open`](crate::iostream::IoStream::open)
+        /// prefers it over `address_string`. `None` in entries saved before
+        /// it was recorded.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        peripheral_id: Option<String>,
     },
     /// IrDA (infrared) — mostly legacy Uwatec / early Suunto.
     Irda {
@@ -87,6 +373,25 @@ pub enum ConnectionInfo {
         /// Filesystem path to the mounted volume.
         path: String,
     },
+    /// Serial port exported over the network by a raw TCP bridge (e.g.
+    /// `ser2net` on a Raspberry Pi). Drivers see it as [`Transport::Serial`].
+    Tcp {
+        /// Bridge host name or IP address.
+        host: String,
+        /// Bridge TCP port.
+        port: u16,
+    },
+    /// A capture written by a [`RecordingTransport`](crate::capture::RecordingTransport),
+    /// played back to the driver as a stand-in for the computer it was
+    /// recorded from. Found by scans with simulated devices (see
+    /// [`ScanBuilder::simulated`](crate::scanner::ScanBuilder::simulated))
+    /// and opened like any other connection with the `simulator` feature.
+    Simulator {
+        /// Path of the capture file.
+        capture: String,
+        /// Transport the capture was recorded over, which the driver sees.
+        transport: Transport,
+    },
 }
 
 impl ConnectionInfo {
@@ -98,7 +403,10 @@ impl ConnectionInfo {
                 Some(Cow::Borrowed(address_string))
             }
             Self::Irda { address, .. } => Some(Cow::Owned(format!("0x{address:08X}"))),
-            Self::UsbStorage { path, .. } => Some(Cow::Borrowed(path)),
+            Self::UsbStorage { path, .. } | Self::Simulator { capture: path, .. } => {
+                Some(Cow::Borrowed(path))
+            }
+            Self::Tcp { host, port } => Some(Cow::Owned(format!("{host}:{port}"))),
             Self::Usb { .. } | Self::UsbHid { .. } => None,
         }
     }
@@ -110,7 +418,9 @@ impl ConnectionInfo {
     #[must_use]
     pub fn display_name(&self) -> Cow<'_, str> {
         match self {
-            Self::Serial { path } | Self::UsbStorage { path } => Cow::Borrowed(
+            Self::Serial { path }
+            | Self::UsbStorage { path }
+            | Self::Simulator { capture: path, .. } => Cow::Borrowed(
                 path.rsplit(['/', '\\'])
                     .next()
                     .filter(|s| !s.is_empty())
@@ -119,21 +429,31 @@ impl ConnectionInfo {
             Self::Usb {
                 vendor_id,
                 product_id,
+                index,
+                ..
+            } => {
+                let name = usb_device_name(Transport::Usb, *vendor_id, *product_id);
+                Cow::Owned(match index {
+                    0 => name,
+                    _ => format!("{name} #{}", index + 1),
+                })
             }
-            | Self::UsbHid {
+            Self::UsbHid {
                 vendor_id,
                 product_id,
-            } => Cow::Owned(format!("USB Device {vendor_id:04X}:{product_id:04X}")),
+            } => Cow::Owned(usb_device_name(Transport::UsbHid, *vendor_id, *product_id)),
             Self::Bluetooth { address_string, .. } => Cow::Borrowed(address_string),
             Self::Ble {
                 local_name,
                 service_name,
                 ..
-            } => local_name
-                .as_ref()
-                .map(|name| Cow::Owned(format!("{name} - {service_name}")))
-                .unwrap_or(Cow::Borrowed(service_name)),
+            } => match local_name {
+                Some(name) if service_name.is_empty() => Cow::Borrowed(name),
+                Some(name) => Cow::Owned(format!("{name} - {service_name}")),
+                None => Cow::Borrowed(service_name),
+            },
             Self::Irda { address } => Cow::Owned(format!("IrDA 0x{address:08X}")),
+            Self::Tcp { host, port } => Cow::Owned(format!("{host}:{port}")),
         }
     }
 }
@@ -147,13 +467,15 @@ impl fmt::Display for ConnectionInfo {
 impl From<&ConnectionInfo> for Transport {
     fn from(value: &ConnectionInfo) -> Self {
         match value {
-            ConnectionInfo::Serial { .. } => Self::Serial,
+            ConnectionInfo::Serial { .. } | ConnectionInfo::Tcp { .. } => Self::Serial,
+            ConnectionInfo::Usb { ftdi: true, .. } => Self::Serial,
             ConnectionInfo::Usb { .. } => Self::Usb,
             ConnectionInfo::UsbHid { .. } => Self::UsbHid,
             ConnectionInfo::Bluetooth { .. } => Self::Bluetooth,
             ConnectionInfo::Ble { .. } => Self::Ble,
             ConnectionInfo::Irda { .. } => Self::Irda,
             ConnectionInfo::UsbStorage { .. } => Self::UsbStorage,
+            ConnectionInfo::Simulator { transport, .. } => *transport,
         }
     }
 }
@@ -195,17 +517,274 @@ pub enum DeviceEvent {
     },
 }
 
+/// A reading of the computer's clock next to the host's, from
+/// [`DeviceEvent::Clock`]. Returned in [`DownloadResult::clock`].
+///
+/// Drivers that report one (Uwatec, Reefnet, and others whose computers
+/// count ticks rather than keep a calendar) already anchor parsed dive times
+/// to the host clock through this pair, so the dives of a single download are
+/// consistent with each other. Comparing readings from two downloads shows
+/// how far the computer's clock drifted in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceClock {
+    /// Device clock value, in the driver's ticks.
+    pub devtime: u32,
+    /// Host time when the device clock was read.
+    pub systime: jiff::Timestamp,
+}
+
+impl DeviceClock {
+    /// How much the device clock ran fast (positive) or slow (negative)
+    /// relative to the host since `earlier`, for a device whose clock
+    /// advances by `tick` per unit of [`devtime`](Self::devtime).
+    ///
+    /// `None` if the device clock went backwards, e.g. after a battery
+    /// change reset it.
+    pub fn drift_since(
+        &self,
+        earlier: &DeviceClock,
+        tick: std::time::Duration,
+    ) -> Option<jiff::SignedDuration> {
+        let ticks = self.devtime.checked_sub(earlier.devtime)?;
+        let device_elapsed = jiff::SignedDuration::try_from(tick.checked_mul(ticks)?).ok()?;
+        let host_elapsed = self.systime.duration_since(earlier.systime);
+        device_elapsed.checked_sub(host_elapsed)
+    }
+}
+
+/// Download progress, delivered to [`DownloadOptions::on_progress`] from the
+/// driver's [`DeviceEvent::Progress`] events and again as each dive arrives.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DownloadProgress {
+    /// Position in the driver's progress units: bytes for most computers,
+    /// whose drivers count the memory read, but dives or records for some.
+    pub current: u32,
+    /// Total progress units expected for this download.
+    pub maximum: u32,
+    /// Dives received so far in this download, including any that failed to
+    /// parse, for "dive 3 of 17" displays.
+    #[serde(default)]
+    pub dives_done: u32,
+    /// Dives the computer is about to send, when it says. libdivecomputer
+    /// reports no such count for real computers yet, so this is `None` there
+    /// and only [`SimulatedDevice`](crate::simulator::SimulatedDevice) fills
+    /// it in.
+    #[serde(default)]
+    pub dives_total: Option<u32>,
+    /// Progress units covered so far, summed from the driver's progress
+    /// reports; bytes only when the driver counts bytes. Unlike `current`,
+    /// it keeps counting across retries.
+    #[serde(default)]
+    pub units: u64,
+    /// Time since the download started.
+    #[serde(default, with = "crate::parser::types::seconds")]
+    pub elapsed: Duration,
+    /// Progress units per second over the last few seconds, once two
+    /// progress reports have come in. A stalled link sends no reports at
+    /// all, so a UI should also watch how long ago the last one arrived
+    /// ([`SessionStatus::progress_at`] in a session).
+    #[serde(default)]
+    pub units_per_second: Option<u64>,
+}
+
+impl DownloadProgress {
+    /// Progress as a fraction in `0.0..=1.0`: progress units covered, or
+    /// dives received when the driver reports no total but the dive count is
+    /// known. `None` while neither total is known.
+    pub fn fraction(&self) -> Option<f64> {
+        let ratio = |done: u32, total: u32| (f64::from(done) / f64::from(total)).min(1.0);
+        if self.maximum > 0 {
+            return Some(ratio(self.current, self.maximum));
+        }
+        self.dives_total
+            .filter(|&total| total > 0)
+            .map(|total| ratio(self.dives_done, total))
+    }
+
+    /// Estimated time until the download finishes: the progress units still
+    /// to come at the current [`units_per_second`](Self::units_per_second),
+    /// or, when the driver reports no total, the dives still to come at the
+    /// average time per dive so far. `None` until there is enough to go on.
+    ///
+    /// The estimate is as of this report, and a stalled link sends no new
+    /// ones; [`SessionStatus::eta`] also gives up once the last report is
+    /// older than a few seconds.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.maximum > 0 {
+            let remaining = self.maximum.saturating_sub(self.current);
+            let rate = self.units_per_second.filter(|&rate| rate > 0)?;
+            return Some(Duration::from_secs_f64(f64::from(remaining) / rate as f64));
+        }
+        let total = self.dives_total?;
+        if self.dives_done == 0 {
+            return None;
+        }
+        let per_dive = self.elapsed / self.dives_done;
+        Some(per_dive * total.saturating_sub(self.dives_done))
+    }
+}
+
+/// How far back [`RateMeter`] looks. Long enough to smooth over BLE
+/// packets arriving in bursts, short enough to show a stall within seconds.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Turns the driver's progress positions into a running count of progress
+/// units and a rolling rate.
+#[derive(Default)]
+struct RateMeter {
+    last: u32,
+    units: u64,
+    /// `(when, units)` of the reports within [`RATE_WINDOW`], oldest first,
+    /// plus the last one before it so a gap in the reports lowers the rate.
+    window: VecDeque<(Instant, u64)>,
+}
+
+impl RateMeter {
+    fn record(&mut self, now: Instant, current: u32) -> (u64, Option<u64>) {
+        // A position going backwards is a retry starting over.
+        let delta = current.checked_sub(self.last).unwrap_or(current);
+        self.last = current;
+        self.units += u64::from(delta);
+        while let Some(&(when, _)) = self.window.get(1)
+            && now.duration_since(when) > RATE_WINDOW
+        {
+            self.window.pop_front();
+        }
+        let rate = self.window.front().and_then(|&(when, units)| {
+            let secs = now.duration_since(when).as_secs_f64();
+            (secs > 0.0).then(|| ((self.units - units) as f64 / secs) as u64)
+        });
+        self.window.push_back((now, self.units));
+        (self.units, rate)
+    }
+}
+
+/// A callback for [`DownloadOptions::on_event`] (or
+/// [`FirmwareOptions::on_event`](crate::FirmwareOptions::on_event)) that
+/// forwards every [`DeviceEvent`] to the returned receiver.
+///
+/// Downloads block, so a GUI typically runs them on a worker thread and
+/// drains the receiver from its own loop:
+///
+/// ```no_run
+/// # use libdivecomputer::{Device, DeviceEvent, DownloadOptions, device::event_channel};
+/// # fn sync(device: Device) {
+/// let (mut on_event, events) = event_channel();
+/// let worker = std::thread::spawn(move || {
+///     device.download_dives(DownloadOptions {
+///         on_event: Some(&mut on_event),
+///         ..Default::default()
+///     })
+/// });
+/// // Ends once the download finishes and drops the sender.
+/// for event in events {
+///     if let DeviceEvent::Progress { current, maximum } = event {
+///         println!("{current}/{maximum}");
+///     }
+/// }
+/// let result = worker.join().unwrap();
+/// # }
+/// ```
+pub fn event_channel() -> (
+    impl FnMut(DeviceEvent) + Send + 'static,
+    mpsc::Receiver<DeviceEvent>,
+) {
+    let (tx, rx) = mpsc::channel();
+    let forward = move |event| {
+        // A receiver that went away just stops listening; the download
+        // carries on.
+        let _ = tx.send(event);
+    };
+    (forward, rx)
+}
+
+/// What a download learned about the computer it talked to. Returned in
+/// [`DownloadResult::identity`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceIdentity {
+    /// Model code (vendor-specific), from [`DeviceEvent::DevInfo`].
+    pub model: u32,
+    /// Firmware version word (vendor-specific encoding).
+    pub firmware: u32,
+    /// Device serial number.
+    pub serial: u32,
+    /// Battery state at the end of the newest downloaded dive, for computers
+    /// that record one.
+    pub battery: Option<BatteryLevel>,
+}
+
+/// Battery state as reported by the computer. Drivers report a voltage, a
+/// charge percentage, or both; which one depends on the model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatteryLevel {
+    /// Battery voltage, in volts.
+    pub voltage: Option<f64>,
+    /// Remaining charge, in percent.
+    pub percent: Option<f64>,
+}
+
+impl BatteryLevel {
+    /// Extract the battery state from a dive's string fields
+    /// ([`Dive::metadata`]), e.g. Shearwater's `"Battery at end"` or a
+    /// `"Battery"` percentage. Readings taken at the end of the dive win over
+    /// ones taken at the start.
+    pub fn from_metadata<S: std::hash::BuildHasher>(
+        metadata: &std::collections::HashMap<String, String, S>,
+    ) -> Option<Self> {
+        let mut fields: Vec<_> = metadata
+            .iter()
+            .filter(|(key, _)| key.to_ascii_lowercase().contains("battery"))
+            .collect();
+        // Keys sort "at end" before anything else, then alphabetically so the
+        // result does not depend on hash order.
+        fields.sort_by_key(|(key, _)| (!key.to_ascii_lowercase().contains("end"), *key));
+
+        let mut level = Self::default();
+        for (_, value) in fields {
+            let value = value.trim();
+            let number = value
+                .trim_end_matches(|c: char| !c.is_ascii_digit())
+                .parse::<f64>()
+                .ok();
+            if value.ends_with('%') {
+                level.percent = level.percent.or(number);
+            } else if value.ends_with('V') || value.ends_with('v') {
+                level.voltage = level.voltage.or(number);
+            }
+        }
+        (level.voltage.is_some() || level.percent.is_some()).then_some(level)
+    }
+}
+
+/// Events forwarded to [`DeviceEvent`] callbacks.
+const DEVICE_EVENTS: c_uint = ffi::DC_EVENT_WAITING
+    | ffi::DC_EVENT_PROGRESS
+    | ffi::DC_EVENT_DEVINFO
+    | ffi::DC_EVENT_CLOCK
+    | ffi::DC_EVENT_VENDOR;
+
 /// Callback data passed to the FFI during foreach.
-struct ForeachData<'d, 'e, 'c> {
-    dive_cb: &'d mut dyn FnMut(&[u8], &Fingerprint) -> bool,
-    event_cb: Option<&'e mut dyn FnMut(DeviceEvent)>,
-    cancel_cb: Option<&'c dyn Fn() -> bool>,
+pub(crate) struct ForeachData<'d, 'e, 'c> {
+    pub(crate) dive_cb: &'d mut dyn FnMut(&[u8], &Fingerprint) -> bool,
+    pub(crate) event_cb: Option<&'e mut dyn FnMut(DeviceEvent)>,
+    pub(crate) cancel_cb: Option<&'c dyn Fn() -> bool>,
 }
 
 /// Connected dive computer device. Wraps `dc_device_t`.
+///
+/// Dropping it closes the device and then its transport, releasing
+/// everything the connection holds; [`disconnect`](Self::disconnect) does
+/// the same and reports errors.
 pub struct Device {
-    ptr: *mut ffi::dc_device_t,
-    _iostream: IoStream,
+    // Both swapped by `reopen`.
+    ptr: Cell<*mut ffi::dc_device_t>,
+    vendor: String,
+    product: String,
+    connection: Option<ConnectionInfo>,
+    // Declared last so it is dropped after `Drop for Device` has closed
+    // `ptr`, which still uses it.
+    iostream: RefCell<IoStream>,
 }
 
 // SAFETY: dc_device_t operations are serialized through the C library.
@@ -222,16 +801,59 @@ impl Device {
         let status = unsafe { ffi::dc_device_open(&mut ptr, ctx.ptr(), desc.ptr, iostream.ptr) };
         Status::check(status, "failed to open device")?;
         Ok(Self {
-            ptr,
-            _iostream: iostream,
+            ptr: Cell::new(ptr),
+            vendor: desc.vendor().to_string(),
+            product: desc.product().to_string(),
+            connection: None,
+            iostream: RefCell::new(iostream),
         })
     }
 
+    /// Close the connection and open it again, to start over after the link
+    /// dropped. Only a device opened through [`DeviceInfo::open`] knows its
+    /// connection; others fail with [`LibError::InvalidArguments`].
+    ///
+    /// The driver is opened afresh, so a fingerprint set earlier has to be
+    /// set again.
+    pub fn reopen(&self, ctx: &Context) -> Result<()> {
+        let connection = self.connection.as_ref().ok_or_else(|| {
+            LibError::InvalidArguments("device was not opened from a DeviceInfo".into())
+        })?;
+        let desc = Descriptor::find(&self.vendor, &self.product)?.ok_or_else(|| {
+            LibError::DescriptorNotFound(format!("{} {}", self.vendor, self.product))
+        })?;
+        // The old link is presumed dead; failing to close it cleanly is
+        // expected.
+        let old = self.ptr.replace(ptr::null_mut());
+        if !old.is_null() {
+            let status = unsafe { ffi::dc_device_close(old) };
+            if let Err(e) = Status::check(status, "failed to close device") {
+                tracing::debug!(error = %e, "closing device before reopening");
+            }
+        }
+        if let Err(e) = self.iostream.borrow_mut().close_in_place() {
+            tracing::debug!(error = %e, "closing iostream before reopening");
+        }
+        let iostream = IoStream::open(ctx, connection)?;
+        let mut ptr = ptr::null_mut();
+        let status = unsafe { ffi::dc_device_open(&mut ptr, ctx.ptr(), desc.ptr, iostream.ptr) };
+        Status::check(status, "failed to reopen device")?;
+        *self.iostream.borrow_mut() = iostream;
+        self.ptr.set(ptr);
+        Ok(())
+    }
+
+    /// The connection this device was opened over, if it was opened through
+    /// [`DeviceInfo::open`] rather than from a bare [`IoStream`].
+    pub fn connection(&self) -> Option<&ConnectionInfo> {
+        self.connection.as_ref()
+    }
+
     /// Set the fingerprint for incremental downloads.
     pub fn set_fingerprint(&self, fingerprint: &Fingerprint) -> Result<()> {
         let bytes = fingerprint.as_bytes();
         let status = unsafe {
-            ffi::dc_device_set_fingerprint(self.ptr, bytes.as_ptr(), bytes.len() as c_uint)
+            ffi::dc_device_set_fingerprint(self.ptr.get(), bytes.as_ptr(), bytes.len() as c_uint)
         };
         Status::check(status, "failed to set fingerprint")
     }
@@ -265,15 +887,9 @@ impl Device {
         let has_cancel = data.cancel_cb.is_some();
 
         unsafe {
-            let events = ffi::DC_EVENT_WAITING
-                | ffi::DC_EVENT_PROGRESS
-                | ffi::DC_EVENT_DEVINFO
-                | ffi::DC_EVENT_CLOCK
-                | ffi::DC_EVENT_VENDOR;
-
             let status = ffi::dc_device_set_events(
-                self.ptr,
-                events,
+                self.ptr.get(),
+                DEVICE_EVENTS,
                 Some(event_callback),
                 as_void_ptr(&mut data),
             );
@@ -281,7 +897,7 @@ impl Device {
 
             if has_cancel {
                 let status = ffi::dc_device_set_cancel(
-                    self.ptr,
+                    self.ptr.get(),
                     Some(cancel_callback),
                     as_void_ptr(&mut data),
                 );
@@ -289,43 +905,180 @@ impl Device {
             }
 
             let status =
-                ffi::dc_device_foreach(self.ptr, Some(dive_callback), as_void_ptr(&mut data));
+                ffi::dc_device_foreach(self.ptr.get(), Some(dive_callback), as_void_ptr(&mut data));
+
+            // `data` lives on this stack frame; detach the callbacks so a
+            // later operation on the same device (another download, a clock
+            // sync) cannot call back into it.
+            ffi::dc_device_set_events(self.ptr.get(), 0, None, ptr::null_mut());
+            if has_cancel {
+                ffi::dc_device_set_cancel(self.ptr.get(), None, ptr::null_mut());
+            }
+
             Status::check(status, "failed to download dives")?;
         }
 
         Ok(())
     }
 
+    /// Run `op` with the device's events going to `event_cb`, for operations
+    /// other than a download that report progress (firmware updates).
+    pub(crate) fn with_events<R>(
+        &self,
+        event_cb: Option<&mut dyn FnMut(DeviceEvent)>,
+        op: impl FnOnce() -> Result<R>,
+    ) -> Result<R> {
+        let mut dive_cb = |_: &[u8], _: &Fingerprint| true;
+        let mut data = ForeachData {
+            dive_cb: &mut dive_cb,
+            event_cb,
+            cancel_cb: None,
+        };
+        unsafe {
+            let status = ffi::dc_device_set_events(
+                self.ptr.get(),
+                DEVICE_EVENTS,
+                Some(event_callback),
+                as_void_ptr(&mut data),
+            );
+            Status::check(status, "failed to set event handler")?;
+        }
+        let result = op();
+        // As in `foreach_internal`: `data` dies with this frame.
+        unsafe { ffi::dc_device_set_events(self.ptr.get(), 0, None, ptr::null_mut()) };
+        result
+    }
+
+    /// Read the computer's model, firmware and serial number without
+    /// downloading dives — enough for a "is this the right computer?" check.
+    ///
+    /// Drivers only report [`DeviceEvent::DevInfo`] once a download starts,
+    /// so this starts one and cancels it as soon as the identity arrives.
+    /// Drivers that read the whole memory before reporting it still take as
+    /// long as a full transfer.
+    /// [`DeviceIdentity::battery`] is always `None`: it comes from dive data.
+    ///
+    /// # Errors
+    ///
+    /// [`Status::Unsupported`] if the driver never reports device info, or
+    /// the driver's status if the download fails before it does.
+    #[instrument(skip_all)]
+    pub fn identify(&self) -> Result<DeviceIdentity> {
+        let mut identity = None;
+        let found = std::cell::Cell::new(false);
+        let outcome = {
+            let mut dive_cb = |_: &[u8], _: &Fingerprint| false;
+            let mut event_cb = |event: DeviceEvent| {
+                if let DeviceEvent::DevInfo {
+                    model,
+                    firmware,
+                    serial,
+                } = event
+                {
+                    identity = Some(DeviceIdentity {
+                        model,
+                        firmware,
+                        serial,
+                        battery: None,
+                    });
+                    found.set(true);
+                }
+            };
+            let cancel_cb = || found.get();
+            self.foreach_internal(ForeachData {
+                dive_cb: &mut dive_cb,
+                event_cb: Some(&mut event_cb),
+                cancel_cb: Some(&cancel_cb),
+            })
+        };
+        match identity {
+            // The cancellation that got us here is expected.
+            Some(identity) => Ok(identity),
+            None => {
+                outcome?;
+                Err(LibError::Status(
+                    Status::Unsupported,
+                    Some(format!("{} driver reported no device info", self.family())),
+                ))
+            }
+        }
+    }
+
     /// Read memory from the device at the given address.
     pub fn read(&self, address: u32, buf: &mut [u8]) -> Result<()> {
         let status = unsafe {
-            ffi::dc_device_read(self.ptr, address, buf.as_mut_ptr(), buf.len() as c_uint)
+            ffi::dc_device_read(
+                self.ptr.get(),
+                address,
+                buf.as_mut_ptr(),
+                buf.len() as c_uint,
+            )
         };
         Status::check(status, "failed to read from device")
     }
 
     /// Write memory to the device at the given address.
     pub fn write(&self, address: u32, data: &[u8]) -> Result<()> {
-        let status =
-            unsafe { ffi::dc_device_write(self.ptr, address, data.as_ptr(), data.len() as c_uint) };
+        let status = unsafe {
+            ffi::dc_device_write(self.ptr.get(), address, data.as_ptr(), data.len() as c_uint)
+        };
         Status::check(status, "failed to write to device")
     }
 
     /// Dump the full device memory.
     pub fn dump(&self) -> Result<Vec<u8>> {
         let buffer = Buffer::new(0);
-        let status = unsafe { ffi::dc_device_dump(self.ptr, buffer.ptr) };
+        let status = unsafe { ffi::dc_device_dump(self.ptr.get(), buffer.ptr) };
         Status::check(status, "failed to dump device memory")?;
         Ok(buffer.to_vec())
     }
 
-    /// Synchronize the device clock.
+    /// Set the device clock to `timestamp` in the host's local time zone.
+    ///
+    /// # Errors
+    ///
+    /// [`Status::Unsupported`] for computers whose clock cannot be set from
+    /// a PC, or the transport error if the command fails.
     pub fn timesync(&self, timestamp: jiff::Timestamp) -> Result<()> {
-        let ffi_dt = crate::datetime::timestamp_to_ffi(timestamp);
-        let status = unsafe { ffi::dc_device_timesync(self.ptr, &ffi_dt) };
+        self.timesync_ffi(&crate::datetime::timestamp_to_ffi(timestamp))
+    }
+
+    /// Set the device clock to the wall-clock time of `time`, e.g. the
+    /// destination's time zone before a dive trip. Computers that store a
+    /// UTC offset receive `time`'s; the rest just take the local time.
+    ///
+    /// # Errors
+    ///
+    /// As for [`timesync`](Self::timesync).
+    pub fn timesync_zoned(&self, time: &jiff::Zoned) -> Result<()> {
+        self.timesync_ffi(&crate::datetime::zoned_to_ffi(time))
+    }
+
+    fn timesync_ffi(&self, datetime: &ffi::dc_datetime_t) -> Result<()> {
+        let status = unsafe { ffi::dc_device_timesync(self.ptr.get(), datetime) };
         Status::check(status, "failed to sync device time")
     }
 
+    /// Close the connection: the C library's device handle first, letting
+    /// the driver send any goodbye command, then the transport, which for
+    /// BLE disconnects from the peripheral and stops the event-loop thread.
+    ///
+    /// Dropping the `Device` does the same but ignores errors; call this to
+    /// know the computer was released, e.g. before showing "safe to unplug"
+    /// or starting a scan for the next one. Both steps run even if the first
+    /// fails, and the first error is returned.
+    pub fn disconnect(mut self) -> Result<()> {
+        let ptr = self.ptr.replace(ptr::null_mut());
+        let device = if ptr.is_null() {
+            Ok(())
+        } else {
+            let status = unsafe { ffi::dc_device_close(ptr) };
+            Status::check(status, "failed to close device")
+        };
+        let iostream = self.iostream.get_mut().close_in_place();
+        device.and(iostream)
+    }
+
     /// Create a parser for dive data from this device.
     #[must_use = "the created Parser owns a C allocation"]
     pub fn parser(&self, data: &[u8]) -> Result<Parser> {
@@ -338,100 +1091,1195 @@ impl Device {
     /// For streaming or custom control flow, use the lower-level `foreach` method.
     ///
     /// Returns successfully parsed dives and any parse errors that occurred.
+    ///
+    /// With a [`DownloadOptions::checkpoint`], a download that was cut short
+    /// picks up where it left off on the next call; see
+    /// [`DownloadCheckpoint`].
     #[must_use = "downloaded dives and errors should not be silently discarded"]
     pub fn download_dives(&self, options: DownloadOptions<'_>) -> DownloadResult {
-        if let Some(fp) = options.fingerprint
-            && let Err(e) = self.set_fingerprint(fp)
-        {
-            return DownloadResult {
-                dives: Vec::new(),
-                errors: vec![e],
-            };
+        download_from(self, options)
+    }
+
+    /// Download dives on a background thread, yielding each one as it is
+    /// parsed.
+    ///
+    /// Unlike [`download_dives`](Self::download_dives), a dive that fails to
+    /// parse shows up as an `Err` in its place in the sequence, so an app can
+    /// tell the user which dive was lost and keep going. A failure of the
+    /// download itself is the last item. Only dives newer than `since` are
+    /// downloaded.
+    ///
+    /// The stream owns the device; the connection closes when the download
+    /// ends or the stream is dropped.
+    pub fn into_dive_stream(self, since: Option<Fingerprint>) -> Result<DiveStream> {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = Arc::clone(&cancel);
+        let worker = std::thread::Builder::new()
+            .name("dive-download".into())
+            .spawn(move || {
+                let device = self;
+                if let Some(fp) = &since
+                    && let Err(e) = device.set_fingerprint(fp)
+                {
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+                let mut dive_cb = |data: &[u8], fingerprint: &Fingerprint| {
+                    let dive = Parser::from_device(&device, data)
+                        .and_then(|parser| parser.parse(fingerprint));
+                    // Stop downloading once nobody is listening.
+                    tx.send(dive).is_ok()
+                };
+                let cancel_cb = || worker_cancel.load(Ordering::Relaxed);
+                if let Err(e) = device.foreach(&mut dive_cb, None, Some(&cancel_cb)) {
+                    let _ = tx.send(Err(e));
+                }
+            })?;
+        Ok(DiveStream {
+            rx,
+            cancel,
+            worker: Some(worker),
+        })
+    }
+
+    /// Download dives on a background thread that the caller polls for
+    /// progress, for apps that keep a download alive in an Android foreground
+    /// service or report it to another process.
+    ///
+    /// `options` apply as for [`download_dives`](Self::download_dives).
+    /// Its [`checkpoint`](DownloadOptions::checkpoint) is where the download
+    /// starts; pass the one saved from an earlier, interrupted session to
+    /// resume it. The session works on a copy, published through
+    /// [`DownloadSession::status`]. Callbacks, the fingerprint store and the
+    /// reconnect context cannot follow the download onto its thread, and are
+    /// rejected with [`LibError::InvalidArguments`]. See [`DownloadSession`].
+    pub fn into_download_session(self, options: DownloadOptions<'_>) -> Result<DownloadSession> {
+        let connection = self.connection.clone();
+        DownloadSession::spawn(self, connection, options)
+    }
+
+    /// Get the device family (type).
+    pub fn family(&self) -> crate::family::Family {
+        let raw = unsafe { ffi::dc_device_get_type(self.ptr.get()) };
+        crate::family::Family::from(raw)
+    }
+
+    /// Get the raw device pointer (for vendor-specific APIs).
+    pub(crate) fn raw_ptr(&self) -> *mut ffi::dc_device_t {
+        self.ptr.get()
+    }
+}
+
+/// Where [`download_from`] gets dives: a real [`Device`] or a stand-in such
+/// as the simulator.
+pub(crate) trait DiveSource {
+    fn vendor(&self) -> &str;
+    fn product(&self) -> &str;
+    fn set_fingerprint(&self, fingerprint: &Fingerprint) -> Result<()>;
+    fn parser(&self, data: &[u8]) -> Result<Parser>;
+    fn set_timeout(&self, timeout: Duration) -> Result<()>;
+    fn run_foreach(&self, data: ForeachData<'_, '_, '_>) -> Result<()>;
+    /// Start over on a fresh connection before a retry. Sources without a
+    /// link to lose have nothing to do.
+    fn reopen(&self, _ctx: &Context) -> Result<()> {
+        Ok(())
+    }
+    /// How many dives [`run_foreach`](Self::run_foreach) is about to hand
+    /// over, if known.
+    fn dive_count(&self) -> Option<u32> {
+        None
+    }
+}
+
+impl DiveSource for Device {
+    fn vendor(&self) -> &str {
+        &self.vendor
+    }
+
+    fn product(&self) -> &str {
+        &self.product
+    }
+
+    fn set_fingerprint(&self, fingerprint: &Fingerprint) -> Result<()> {
+        Device::set_fingerprint(self, fingerprint)
+    }
+
+    fn parser(&self, data: &[u8]) -> Result<Parser> {
+        Parser::from_device(self, data)
+    }
+
+    fn set_timeout(&self, timeout: Duration) -> Result<()> {
+        let ms = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+        self.iostream.borrow().set_timeout(ms)
+    }
+
+    fn run_foreach(&self, data: ForeachData<'_, '_, '_>) -> Result<()> {
+        self.foreach_internal(data)
+    }
+
+    fn reopen(&self, ctx: &Context) -> Result<()> {
+        Device::reopen(self, ctx)
+    }
+}
+
+/// The [`Device::download_dives`] loop, shared with the simulator.
+pub(crate) fn download_from(
+    source: &impl DiveSource,
+    options: DownloadOptions<'_>,
+) -> DownloadResult {
+    let DownloadOptions {
+        fingerprint,
+        on_event,
+        cancel_cb,
+        mut checkpoint,
+        fingerprint_store,
+        on_progress,
+        mut on_dive,
+        clock_correction,
+        headers_only,
+        not_before,
+        max_dives,
+        keep_raw,
+        parse_options,
+        timeout,
+        retries,
+        reconnect,
+    } = options;
+
+    let failed = |e| DownloadResult {
+        dives: Vec::new(),
+        errors: vec![e],
+        identity: None,
+        clock: None,
+        vendor_events: Vec::new(),
+        failed_dives: Vec::new(),
+    };
+    if let Some(timeout) = timeout
+        && let Err(e) = source.set_timeout(timeout)
+    {
+        return failed(e);
+    }
+
+    let since = match checkpoint.as_deref_mut() {
+        Some(checkpoint) => {
+            checkpoint.begin(fingerprint);
+            checkpoint.since.clone()
+        }
+        None => fingerprint.cloned(),
+    };
+    if let Some(fp) = &since
+        && let Err(e) = source.set_fingerprint(fp)
+    {
+        return failed(e);
+    }
+
+    let mut dives = Vec::new();
+    let mut errors: Vec<LibError> = Vec::new();
+    let mut identity = None;
+    // Shared by both callbacks: the dive callback must know whether the
+    // driver already anchored dive times to the host clock.
+    let clock = Cell::new(None);
+    let mut vendor_events = Vec::new();
+    let mut failed_dives = Vec::new();
+    let mut truncated = false;
+    // Dives the computer handed over, parsed or not, for `max_dives`.
+    let mut received = 0;
+    let mut store = fingerprint_store.map(|store| (store, None));
+    let mut store_error = None;
+    // Dives handed over by failed attempts, which a retry skips, and those
+    // of the attempt under way. Only filled when retrying is possible.
+    let delivered = RefCell::new(HashSet::new());
+    let attempt_delivered = RefCell::new(Vec::new());
+    // Shared by the event and dive callbacks, which both report progress.
+    let progress = Cell::new(DownloadProgress::default());
+    let on_progress = RefCell::new(on_progress);
+    let started = Instant::now();
+    let mut meter = RateMeter::default();
+    let report = |update: DownloadProgress| {
+        let update = DownloadProgress {
+            dives_total: source.dive_count(),
+            elapsed: started.elapsed(),
+            ..update
+        };
+        progress.set(update);
+        if let Some(cb) = on_progress.borrow_mut().as_deref_mut() {
+            cb(update);
+        }
+    };
+
+    let outcome = {
+        let mut on_event = on_event;
+        let mut event_cb = |event: DeviceEvent| {
+            if let DeviceEvent::DevInfo {
+                model,
+                firmware,
+                serial,
+            } = event
+            {
+                identity = Some(DeviceIdentity {
+                    model,
+                    firmware,
+                    serial,
+                    battery: None,
+                });
+                if let Some((store, store_key)) = &mut store {
+                    let key = FingerprintKey {
+                        vendor: source.vendor().to_string(),
+                        product: source.product().to_string(),
+                        serial,
+                    };
+                    // Drivers accept a fingerprint from the DEVINFO
+                    // handler: it is raised before the first dive is
+                    // read.
+                    if since.is_none()
+                        && let Err(e) = store
+                            .load(&key)
+                            .and_then(|fp| fp.map_or(Ok(()), |fp| source.set_fingerprint(&fp)))
+                    {
+                        store_error = Some(e);
+                    }
+                    *store_key = Some(key);
+                }
+            }
+            if let DeviceEvent::Clock { devtime, systime } = event {
+                clock.set(
+                    jiff::Timestamp::from_second(systime)
+                        .ok()
+                        .map(|systime| DeviceClock { devtime, systime }),
+                );
+            }
+            if let DeviceEvent::Vendor { data } = &event {
+                vendor_events.push(data.clone());
+            }
+            if let DeviceEvent::Progress { current, maximum } = event {
+                let (units, units_per_second) = meter.record(Instant::now(), current);
+                report(DownloadProgress {
+                    current,
+                    maximum,
+                    units,
+                    units_per_second,
+                    ..progress.get()
+                });
+            }
+            if let Some(cb) = on_event.as_deref_mut() {
+                cb(event);
+            }
+        };
+        let mut dive_cb = |data: &[u8], fingerprint: &Fingerprint| -> bool {
+            if checkpoint
+                .as_deref()
+                .is_some_and(|checkpoint| checkpoint.contains(fingerprint))
+            {
+                return true;
+            }
+            // Checked when the next dive arrives, so a download that
+            // stops here really did leave dives behind.
+            if max_dives.is_some_and(|max| received >= max) {
+                truncated = true;
+                return false;
+            }
+            // Some drivers give no fingerprint; such dives cannot be told
+            // apart and are never skipped.
+            if !fingerprint.is_empty() {
+                if delivered.borrow().contains(fingerprint) {
+                    return true;
+                }
+                if retries > 0 {
+                    attempt_delivered.borrow_mut().push(fingerprint.clone());
+                }
+            }
+            received += 1;
+            let parsed = source
+                .parser(data)
+                .map(|parser| parser.with_options(parse_options.clone()))
+                .and_then(|parser| {
+                    if headers_only {
+                        parser.parse_header(fingerprint)
+                    } else {
+                        parser.parse(fingerprint)
+                    }
+                })
+                .and_then(|mut dive| {
+                    // The parser already maps device ticks to host time
+                    // through the reported clock; correcting again would
+                    // count the offset twice.
+                    if let Some(correction) = clock_correction
+                        && clock.get().is_none()
+                    {
+                        dive.start = dive.start.checked_add(correction)?;
+                    }
+                    if keep_raw {
+                        dive.raw = data.to_vec();
+                    }
+                    Ok(dive)
+                });
+            match parsed {
+                // Dives arrive newest first: everything from here on is
+                // older still.
+                Ok(dive) if not_before.is_some_and(|cutoff| dive.start < cutoff) => return false,
+                Ok(dive) => {
+                    if let Some(checkpoint) = checkpoint.as_deref_mut()
+                        && !headers_only
+                    {
+                        checkpoint.record(fingerprint);
+                    }
+                    if let Some(cb) = on_dive.as_deref_mut() {
+                        cb(&dive);
+                    }
+                    dives.push(dive);
+                }
+                Err(e) => {
+                    if keep_raw {
+                        failed_dives.push(FailedDive {
+                            fingerprint: fingerprint.clone(),
+                            raw: data.to_vec(),
+                        });
+                    }
+                    errors.push(e);
+                }
+            }
+            let last = progress.get();
+            report(DownloadProgress {
+                dives_done: last.dives_done + 1,
+                ..last
+            });
+            true
+        };
+
+        let mut attempt = 0;
+        loop {
+            let result = source.run_foreach(ForeachData {
+                dive_cb: &mut dive_cb,
+                event_cb: Some(&mut event_cb),
+                cancel_cb,
+            });
+            match result {
+                Err(e) if attempt < retries && e.is_retriable() => {
+                    attempt += 1;
+                    tracing::warn!(attempt, error = %e, "download failed, retrying");
+                    delivered
+                        .borrow_mut()
+                        .extend(attempt_delivered.borrow_mut().drain(..));
+                    // A dropped link stays dropped: start over on a fresh
+                    // connection, set up as the first one was.
+                    if let Some(ctx) = reconnect {
+                        let reopened = source
+                            .reopen(ctx)
+                            .and_then(|()| timeout.map_or(Ok(()), |t| source.set_timeout(t)))
+                            .and_then(|()| {
+                                since
+                                    .as_ref()
+                                    .map_or(Ok(()), |fp| source.set_fingerprint(fp))
+                            });
+                        if let Err(e) = reopened {
+                            break Err(e);
+                        }
+                    }
+                }
+                result => break result,
+            }
+        }
+    };
+
+    // Dives arrive newest first.
+    if let Some(identity) = &mut identity {
+        identity.battery = dives
+            .first()
+            .and_then(|dive| BatteryLevel::from_metadata(&dive.metadata));
+    }
+
+    match outcome {
+        Ok(()) => {
+            if let Some(checkpoint) = checkpoint
+                && !headers_only
+                && !truncated
+            {
+                checkpoint.complete = true;
+            }
         }
+        Err(e) => errors.push(e),
+    }
+    errors.extend(store_error);
+
+    // Only a clean, full run moves the stored fingerprint forward;
+    // otherwise the next download would skip dives that failed this time
+    // or were never fetched.
+    if let Some((store, Some(key))) = store
+        && errors.is_empty()
+        && !headers_only
+        && !truncated
+        && let Some(newest) = dives.first()
+        && let Err(e) = store.store(&key, &newest.fingerprint)
+    {
+        errors.push(e);
+    }
+
+    DownloadResult {
+        dives,
+        errors,
+        identity,
+        clock: clock.get(),
+        vendor_events,
+        failed_dives,
+    }
+}
+
+/// A device connection that stays open across several operations.
+///
+/// Opening a connection is the slow part of a download — a BLE session alone
+/// costs a peripheral scan plus GATT discovery. A kiosk downloading the same
+/// computer repeatedly can open it once with [`PersistentConnection::open`],
+/// then call [`download`](Self::download) and [`sync_clock`](Self::sync_clock)
+/// as often as needed; the underlying transport (e.g. the BLE session) is
+/// only torn down by [`close`](Self::close) or drop.
+///
+/// Whether a computer accepts a second download on the same session is up to
+/// its driver and firmware; if it does not, the error surfaces in
+/// [`DownloadResult::errors`] and the caller should reopen.
+#[derive(Debug)]
+pub struct PersistentConnection {
+    device: Device,
+    info: DeviceInfo,
+}
+
+impl PersistentConnection {
+    /// Open the transport for `info` and bind it to the device model `desc`.
+    #[must_use = "the opened connection must be used or explicitly closed"]
+    #[instrument(skip_all, fields(device = %info.name))]
+    pub fn open(ctx: &Context, desc: &Descriptor, info: &DeviceInfo) -> Result<Self> {
+        Ok(Self {
+            device: info.open(ctx, desc)?,
+            info: info.clone(),
+        })
+    }
+
+    /// The scan result this connection was opened from.
+    pub fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    /// The open device, for operations not wrapped here.
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// Download and parse dives over the open connection. See
+    /// [`Device::download_dives`].
+    #[must_use = "downloaded dives and errors should not be silently discarded"]
+    pub fn download(&self, options: DownloadOptions<'_>) -> DownloadResult {
+        self.device.download_dives(options)
+    }
+
+    /// Set the device clock to the host's current time.
+    pub fn sync_clock(&self) -> Result<()> {
+        self.device.timesync(jiff::Timestamp::now())
+    }
+
+    /// Close the device and its transport, reporting errors that dropping
+    /// the connection would ignore. See [`Device::disconnect`].
+    pub fn close(self) -> Result<()> {
+        self.device.disconnect()
+    }
+}
+
+/// Options for downloading and parsing dives.
+#[derive(Default)]
+pub struct DownloadOptions<'a> {
+    /// Fingerprint for incremental downloads. Only dives newer than this will be downloaded.
+    pub fingerprint: Option<&'a Fingerprint>,
+    /// Optional callback for device events (progress, device info, etc.).
+    pub on_event: Option<&'a mut dyn FnMut(DeviceEvent)>,
+    /// Optional callback to cancel the download. Return `true` to cancel.
+    ///
+    /// The C driver polls this between packets, so it may be backed by an
+    /// `AtomicBool` flipped from another thread. Dives parsed before the
+    /// cancellation is noticed are kept in [`DownloadResult::dives`]; see
+    /// [`DownloadResult::is_cancelled`].
+    pub cancel_cb: Option<&'a dyn Fn() -> bool>,
+    /// Progress record that lets an interrupted download resume. When set,
+    /// it decides where the download starts and `fingerprint` only seeds a
+    /// fresh checkpoint.
+    pub checkpoint: Option<&'a mut DownloadCheckpoint>,
+    /// Keeps the newest dive's fingerprint per computer, so callers do not
+    /// have to. Looked up by vendor, product and the serial number the
+    /// computer reports, and used as the start point unless `fingerprint` or
+    /// `checkpoint` gives one. Updated after a download without errors.
+    pub fingerprint_store: Option<&'a mut dyn FingerprintStore>,
+    /// Optional callback for download progress, for callers that only care
+    /// about [`DeviceEvent::Progress`] and not the other events.
+    pub on_progress: Option<&'a mut dyn FnMut(DownloadProgress)>,
+    /// Optional callback for each dive as soon as it is parsed, before the
+    /// download ends, e.g. to save dives one by one so that a process killed
+    /// halfway loses nothing already fetched.
+    pub on_dive: Option<&'a mut dyn FnMut(&Dive)>,
+    /// Added to every parsed dive's [`start`](Dive::start), to correct for a
+    /// computer whose clock is known to be off. A clock running fast records
+    /// starts too late, so pass the negated [`DeviceClock::drift_since`].
+    ///
+    /// Ignored when the driver reports a [`DeviceClock`]: its parser then
+    /// already anchors dive times to the host clock through that reading
+    /// (`systime - devtime`), and correcting again would count the offset
+    /// twice.
+    pub clock_correction: Option<jiff::SignedDuration>,
+    /// Parse only the dive headers (start time, duration, depths, gases, …)
+    /// and leave [`Dive::samples`] empty, for quickly listing dives before
+    /// the user picks which profiles to fetch.
+    ///
+    /// The computer still transfers each dive in full; what is saved is the
+    /// sample parsing. Such a download does not advance
+    /// [`checkpoint`](Self::checkpoint) or
+    /// [`fingerprint_store`](Self::fingerprint_store).
+    pub headers_only: bool,
+    /// Stop at the first dive that started before this time. Computers hand
+    /// dives over newest first, so older memory is not read at all.
+    pub not_before: Option<jiff::Timestamp>,
+    /// Stop after the computer has handed over this many dives, counting
+    /// those that fail to parse.
+    ///
+    /// A download cut short this way is not complete: it leaves
+    /// [`checkpoint`](Self::checkpoint) open and does not advance
+    /// [`fingerprint_store`](Self::fingerprint_store), so with a checkpoint
+    /// each call fetches the next `max_dives` older dives.
+    pub max_dives: Option<usize>,
+    /// Keep each dive's undecoded record in [`Dive::raw`], and the records
+    /// of dives that fail to parse in [`DownloadResult::failed_dives`], so
+    /// they can be parsed again with a fixed driver.
+    pub keep_raw: bool,
+    /// Options for parsing each dive, such as the time zone assumed for
+    /// computers that record no UTC offset.
+    pub parse_options: ParseOptions,
+    /// Read timeout for the device's I/O stream during the download, in
+    /// place of the transport's default. Computers that go quiet while
+    /// preparing a large dive may need a longer one.
+    ///
+    /// libdivecomputer cannot report a stream's timeout, so the driver's
+    /// own value cannot be put back afterwards: the device keeps this one
+    /// until it is [reopened](Device::reopen).
+    pub timeout: Option<Duration>,
+    /// How many more times to run the download after it fails with a
+    /// [retriable](LibError::is_retriable) error such as a timeout or a
+    /// dropped link. A retry skips the dives failed attempts already
+    /// delivered. Defaults to none.
+    pub retries: u32,
+    /// Context to [reopen](Device::reopen) the connection with before each
+    /// retry, so a retry after a dropped link starts on a live one. Without
+    /// it, retries run over the same connection, which only helps when the
+    /// link itself survived, e.g. after a garbled packet.
+    pub reconnect: Option<&'a Context>,
+}
+
+/// Progress of a download, kept so that one cut short (a BLE link dying at
+/// 80%, a cancelled sync) resumes instead of starting over.
+///
+/// Computers hand dives over newest first and stop at the dive matching the
+/// fingerprint, so the dives still missing after an interruption are the
+/// *oldest* ones. A resumed download therefore starts again from the same
+/// fingerprint as the interrupted one, and dives this checkpoint has already
+/// seen are skipped rather than parsed and returned twice. Only once a
+/// download completes does the newest dive become the starting point for
+/// the next one ([`latest`](Self::latest)).
+///
+/// The driver still transfers the skipped dives: libdivecomputer has no way
+/// to ask a computer for dives older than a given one.
+///
+/// Serializable, so it can be saved to survive an app restart:
+///
+/// ```no_run
+/// # use libdivecomputer::{Device, DownloadCheckpoint, DownloadOptions};
+/// # fn sync(device: &Device, saved: Option<DownloadCheckpoint>) {
+/// let mut checkpoint = saved.unwrap_or_default();
+/// let result = device.download_dives(DownloadOptions {
+///     checkpoint: Some(&mut checkpoint),
+///     ..Default::default()
+/// });
+/// // Persist `checkpoint` whether or not `result` has errors; the next
+/// // call only delivers the dives this one did not.
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadCheckpoint {
+    since: Option<Fingerprint>,
+    received: Vec<Fingerprint>,
+    complete: bool,
+}
+
+impl DownloadCheckpoint {
+    /// A checkpoint whose first download fetches the dives newer than
+    /// `since`, or every dive for `None`.
+    pub fn new(since: Option<Fingerprint>) -> Self {
+        Self {
+            since,
+            ..Self::default()
+        }
+    }
 
-        let mut dives = Vec::new();
-        let mut errors: Vec<LibError> = Vec::new();
+    /// Fingerprint the current download started from.
+    pub fn since(&self) -> Option<&Fingerprint> {
+        self.since.as_ref()
+    }
+
+    /// Dives delivered so far by the current download, newest first.
+    pub fn received(&self) -> &[Fingerprint] {
+        &self.received
+    }
+
+    /// Whether the last download ran to the end.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Newest dive known to have been downloaded: the fingerprint to store
+    /// once the download is complete.
+    pub fn latest(&self) -> Option<&Fingerprint> {
+        self.received.first().or(self.since.as_ref())
+    }
+
+    /// Prepare for a download. After a completed one, start a new round from
+    /// its newest dive; a fresh checkpoint adopts `fingerprint`.
+    fn begin(&mut self, fingerprint: Option<&Fingerprint>) {
+        if self.complete {
+            *self = Self::new(self.latest().cloned());
+        } else if self.since.is_none() && self.received.is_empty() {
+            self.since = fingerprint.cloned();
+        }
+    }
+
+    fn contains(&self, fingerprint: &Fingerprint) -> bool {
+        self.received.contains(fingerprint)
+    }
+
+    fn record(&mut self, fingerprint: &Fingerprint) {
+        self.received.push(fingerprint.clone());
+    }
+}
+
+/// Result of a dive download operation.
+///
+/// Contains both successfully parsed dives and any errors encountered during parsing.
+/// This allows partial success: some dives may parse correctly while others fail.
+pub struct DownloadResult {
+    /// Successfully parsed dives.
+    pub dives: Vec<Dive>,
+    /// Errors encountered during download or parsing.
+    pub errors: Vec<LibError>,
+    /// Model, firmware, serial and battery state of the computer, if its
+    /// driver reported a [`DeviceEvent::DevInfo`].
+    pub identity: Option<DeviceIdentity>,
+    /// Device and host clock reading, if the driver reported a
+    /// [`DeviceEvent::Clock`].
+    pub clock: Option<DeviceClock>,
+    /// Raw payloads of the driver's [`DeviceEvent::Vendor`] events, in the
+    /// order received. Some drivers pass device headers or settings blocks
+    /// through here that this crate does not decode.
+    pub vendor_events: Vec<Vec<u8>>,
+    /// Undecoded records of the dives that failed to parse, in the order
+    /// received. Only filled with [`DownloadOptions::keep_raw`].
+    pub failed_dives: Vec<FailedDive>,
+}
+
+/// A dive the computer handed over but that failed to parse, kept by
+/// [`DownloadOptions::keep_raw`]. Feed [`raw`](Self::raw) to a
+/// [`StandaloneParser`](crate::parser::StandaloneParser) to retry it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedDive {
+    /// Fingerprint the driver reported for the dive.
+    pub fingerprint: Fingerprint,
+    /// The dive's record as the computer sent it.
+    pub raw: Vec<u8>,
+}
+
+impl DownloadResult {
+    /// Returns `true` if all dives were parsed successfully (no errors).
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns `true` if any errors occurred during parsing.
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// Returns `true` if the download stopped because the cancel callback
+    /// asked it to. Everything parsed up to that point is still in `dives`,
+    /// so callers can keep the partial result.
+    pub fn is_cancelled(&self) -> bool {
+        self.errors.iter().any(|e| {
+            matches!(
+                e,
+                LibError::Status(Status::Cancelled, _) | LibError::Cancelled
+            )
+        })
+    }
+
+    /// Consume this result, returning the dives if successful, the first error if no dives
+    /// were parsed, or a `PartialDownload` error if some dives succeeded but errors occurred.
+    pub fn into_result(self) -> Result<Vec<Dive>> {
+        match (self.dives.is_empty(), self.errors.is_empty()) {
+            (_, true) => Ok(self.dives),
+            (true, false) => {
+                let mut errors = self.errors;
+                // Safe: !errors.is_empty() was just checked.
+                Err(errors.swap_remove(0))
+            }
+            (false, false) => Err(LibError::PartialDownload {
+                dives: self.dives,
+                errors: self.errors,
+            }),
+        }
+    }
+}
+
+/// Dives downloaded by [`Device::into_dive_stream`]. Iterating blocks until
+/// the next dive is parsed and ends when the download finishes.
+///
+/// Dropping the stream cancels the download and waits for it to stop.
+pub struct DiveStream {
+    rx: mpsc::Receiver<Result<Dive>>,
+    cancel: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl DiveStream {
+    /// Stop downloading. Dives already parsed are still yielded, followed by
+    /// the cancellation error.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Wait at most `timeout` for the next dive, so a UI thread can poll
+    /// between redraws.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Next<Result<Dive>> {
+        Next::recv(&self.rx, timeout)
+    }
+}
+
+/// What [`DiveStream::next_timeout`] and
+/// [`ScanStream::next_timeout`](crate::ScanStream::next_timeout) found.
+#[derive(Debug)]
+pub enum Next<T> {
+    /// The next item arrived in time.
+    Item(T),
+    /// Nothing arrived in time; the stream is still running.
+    TimedOut,
+    /// The stream has ended, as [`next`](Iterator::next) returning `None`
+    /// does.
+    Finished,
+}
+
+impl<T> Next<T> {
+    pub(crate) fn recv(rx: &mpsc::Receiver<T>, timeout: Duration) -> Self {
+        match rx.recv_timeout(timeout) {
+            Ok(item) => Self::Item(item),
+            Err(mpsc::RecvTimeoutError::Timeout) => Self::TimedOut,
+            Err(mpsc::RecvTimeoutError::Disconnected) => Self::Finished,
+        }
+    }
+}
+
+impl Iterator for DiveStream {
+    type Item = Result<Dive>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+impl Drop for DiveStream {
+    fn drop(&mut self) {
+        self.cancel();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Where a [`DownloadSession`] is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SessionState {
+    /// Still downloading.
+    Running,
+    /// Finished without errors.
+    Completed,
+    /// Stopped by [`DownloadSession::cancel`].
+    Cancelled,
+    /// Finished with errors; [`SessionStatus::error`] says why and
+    /// [`DownloadSession::wait`] returns them all.
+    Failed,
+}
+
+/// Why a [`DownloadSession`] failed, as a plain value that can be
+/// serialized with its [`SessionStatus`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SessionError {
+    /// What kind of failure it was; decides whether retrying makes sense.
+    pub kind: ErrorKind,
+    /// The error message, or the first of several.
+    pub message: String,
+}
+
+impl SessionError {
+    fn from_errors(errors: &[LibError]) -> Option<Self> {
+        let first = errors.first()?;
+        let message = match errors.len() {
+            1 => first.to_string(),
+            n => format!("{first} (and {} more)", n - 1),
+        };
+        Some(Self {
+            kind: combined_kind(errors),
+            message,
+        })
+    }
+}
+
+impl From<&LibError> for SessionError {
+    fn from(error: &LibError) -> Self {
+        Self {
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
 
-        {
-            let mut dive_cb = |data: &[u8], fingerprint: &Fingerprint| -> bool {
-                match Parser::from_device(self, data).and_then(|parser| parser.parse(fingerprint)) {
-                    Ok(dive) => dives.push(dive),
-                    Err(e) => errors.push(e),
-                }
-                true
-            };
+impl std::error::Error for SessionError {}
 
-            if let Err(e) = self.foreach_internal(ForeachData {
-                dive_cb: &mut dive_cb,
-                event_cb: options.on_event,
-                cancel_cb: options.cancel_cb,
-            }) {
-                errors.push(e);
-            }
-        }
+/// A snapshot of a [`DownloadSession`].
+///
+/// Serializable, so a foreground service can hand it to its UI process or
+/// write it to disk as often as it likes. `checkpoint` is updated as dives
+/// are [taken](DownloadSession::take_dives): saving it and passing it to the
+/// next session resumes a download whose process was killed halfway.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SessionStatus {
+    /// Whether the download is still running, and how it ended.
+    pub state: SessionState,
+    /// When the session entered `state`.
+    pub state_since: jiff::Timestamp,
+    /// The connection being downloaded from, if the device was opened
+    /// through [`DeviceInfo::open`].
+    pub connection: Option<ConnectionInfo>,
+    /// Why the download failed. Set exactly when `state` is
+    /// [`SessionState::Failed`].
+    pub error: Option<SessionError>,
+    /// The driver's latest progress report, if any.
+    pub progress: Option<DownloadProgress>,
+    /// When `progress` was reported.
+    pub progress_at: Option<jiff::Timestamp>,
+    /// Dives parsed so far.
+    pub dives: usize,
+    /// Resume point, as of the last dive taken.
+    pub checkpoint: DownloadCheckpoint,
+}
 
-        DownloadResult { dives, errors }
+impl SessionStatus {
+    /// Whether the download has ended, one way or another.
+    pub fn is_finished(&self) -> bool {
+        self.state != SessionState::Running
     }
 
-    /// Get the device family (type).
-    pub fn family(&self) -> crate::family::Family {
-        let raw = unsafe { ffi::dc_device_get_type(self.ptr) };
-        crate::family::Family::from(raw)
+    /// Estimated time until the download finishes, while it is running.
+    /// See [`DownloadProgress::eta`]. `None` once the latest progress report
+    /// is more than a few seconds old, since the link has stalled.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.is_finished() {
+            return None;
+        }
+        let age = jiff::Timestamp::now().duration_since(self.progress_at?);
+        if Duration::try_from(age).is_ok_and(|age| age > RATE_WINDOW) {
+            return None;
+        }
+        self.progress.as_ref()?.eta()
     }
 
-    /// Get the raw device pointer (for vendor-specific APIs).
-    pub(crate) fn raw_ptr(&self) -> *mut ffi::dc_device_t {
-        self.ptr
+    fn enter(&mut self, state: SessionState, error: Option<SessionError>) {
+        self.state = state;
+        self.state_since = jiff::Timestamp::now();
+        self.error = error;
     }
 }
 
-/// Options for downloading and parsing dives.
-#[derive(Default)]
-pub struct DownloadOptions<'a> {
-    /// Fingerprint for incremental downloads. Only dives newer than this will be downloaded.
-    pub fingerprint: Option<&'a Fingerprint>,
-    /// Optional callback for device events (progress, device info, etc.).
-    pub on_event: Option<&'a mut dyn FnMut(DeviceEvent)>,
-    /// Optional callback to cancel the download. Return `true` to cancel.
-    pub cancel_cb: Option<&'a dyn Fn() -> bool>,
+/// Marks the session failed if its worker unwinds before publishing how the
+/// download ended, so the status never shows a dead session as running.
+struct FailOnPanic<'a>(&'a std::sync::Mutex<SessionShared>);
+
+impl Drop for FailOnPanic<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            let mut shared = self
+                .0
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            shared.status.enter(
+                SessionState::Failed,
+                Some(SessionError {
+                    kind: ErrorKind::Other,
+                    message: "download thread panicked".into(),
+                }),
+            );
+        }
+    }
 }
 
-/// Result of a dive download operation.
+/// A download running on its own thread, started by
+/// [`Device::into_download_session`].
 ///
-/// Contains both successfully parsed dives and any errors encountered during parsing.
-/// This allows partial success: some dives may parse correctly while others fail.
-pub struct DownloadResult {
-    /// Successfully parsed dives.
-    pub dives: Vec<Dive>,
-    /// Errors encountered during download or parsing.
-    pub errors: Vec<LibError>,
+/// Nothing about the session is tied to the thread that started it: any
+/// thread may call [`status`](Self::status) or [`cancel`](Self::cancel),
+/// and neither touches JNI. On Android the worker thread attaches itself
+/// to the JVM once for the whole download, so transports do not attach and
+/// detach around every read, and the session survives the activity that
+/// started it going away.
+///
+/// ```no_run
+/// # use libdivecomputer::{Device, DownloadCheckpoint, DownloadOptions};
+/// # fn save(_: &[libdivecomputer::Dive]) {}
+/// # fn sync(device: Device, mut saved: DownloadCheckpoint) -> libdivecomputer::Result<()> {
+/// let session = device.into_download_session(DownloadOptions {
+///     checkpoint: Some(&mut saved),
+///     ..Default::default()
+/// })?;
+/// while !session.is_finished() {
+///     save(&session.take_dives());
+///     let status = session.status();
+///     // Post `status.progress` and `status.eta()` to the service
+///     // notification and persist `status.checkpoint`.
+///     std::thread::sleep(std::time::Duration::from_millis(500));
+/// }
+/// let result = session.wait();
+/// save(&result.dives);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Dropping the session cancels the download and waits for it to stop.
+pub struct DownloadSession {
+    shared: Arc<std::sync::Mutex<SessionShared>>,
+    cancel: Arc<AtomicBool>,
+    worker: Option<JoinHandle<DownloadResult>>,
 }
 
-impl DownloadResult {
-    /// Returns `true` if all dives were parsed successfully (no errors).
-    pub fn is_ok(&self) -> bool {
-        self.errors.is_empty()
+/// What a [`DownloadSession`]'s worker hands to its owner.
+struct SessionShared {
+    status: SessionStatus,
+    /// Dives parsed but not yet taken, newest first.
+    dives: VecDeque<Dive>,
+    /// The worker's final checkpoint, published once every dive is taken.
+    finished: Option<DownloadCheckpoint>,
+    /// Header-only downloads do not advance the checkpoint.
+    headers_only: bool,
+}
+
+impl DownloadSession {
+    fn spawn<S: DiveSource + Send + 'static>(
+        source: S,
+        connection: Option<ConnectionInfo>,
+        options: DownloadOptions<'_>,
+    ) -> Result<Self> {
+        let DownloadOptions {
+            fingerprint,
+            on_event,
+            cancel_cb,
+            checkpoint,
+            fingerprint_store,
+            on_progress,
+            on_dive,
+            clock_correction,
+            headers_only,
+            not_before,
+            max_dives,
+            keep_raw,
+            parse_options,
+            timeout,
+            retries,
+            reconnect,
+        } = options;
+        if on_event.is_some()
+            || cancel_cb.is_some()
+            || fingerprint_store.is_some()
+            || on_progress.is_some()
+            || on_dive.is_some()
+            || reconnect.is_some()
+        {
+            return Err(LibError::InvalidArguments(
+                "a download session takes no callbacks, fingerprint store or reconnect context"
+                    .into(),
+            ));
+        }
+        // Settle the start point now, so the published checkpoint is already
+        // the one this download resumes from.
+        let mut checkpoint = checkpoint.map_or_else(DownloadCheckpoint::default, |c| c.clone());
+        checkpoint.begin(fingerprint);
+        let shared = Arc::new(std::sync::Mutex::new(SessionShared {
+            status: SessionStatus {
+                state: SessionState::Running,
+                state_since: jiff::Timestamp::now(),
+                connection,
+                error: None,
+                progress: None,
+                progress_at: None,
+                dives: 0,
+                checkpoint: checkpoint.clone(),
+            },
+            dives: VecDeque::new(),
+            finished: None,
+            headers_only,
+        }));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_shared = Arc::clone(&shared);
+        let worker_cancel = Arc::clone(&cancel);
+        let worker = std::thread::Builder::new()
+            .name("dive-session".into())
+            .spawn(move || {
+                #[cfg(target_os = "android")]
+                let _jni_guard = crate::android::attach_current_thread()
+                    .inspect_err(|e| tracing::warn!(error = %e, "JNI attach failed"));
+                let _fail_on_panic = FailOnPanic(&worker_shared);
+
+                let update = |f: &mut dyn FnMut(&mut SessionShared)| {
+                    if let Ok(mut shared) = worker_shared.lock() {
+                        f(&mut shared);
+                    }
+                };
+                let mut on_progress = |progress: DownloadProgress| {
+                    update(&mut |shared| {
+                        shared.status.progress = Some(progress);
+                        shared.status.progress_at = Some(jiff::Timestamp::now());
+                    });
+                };
+                // The checkpoint only moves past a dive once it is taken.
+                let mut on_dive = |dive: &Dive| {
+                    update(&mut |shared| {
+                        shared.status.dives += 1;
+                        shared.dives.push_back(dive.clone());
+                    });
+                };
+                let cancel_cb = || worker_cancel.load(Ordering::Relaxed);
+                let result = download_from(
+                    &source,
+                    DownloadOptions {
+                        checkpoint: Some(&mut checkpoint),
+                        on_progress: Some(&mut on_progress),
+                        on_dive: Some(&mut on_dive),
+                        cancel_cb: Some(&cancel_cb),
+                        clock_correction,
+                        headers_only,
+                        not_before,
+                        max_dives,
+                        keep_raw,
+                        parse_options,
+                        timeout,
+                        retries,
+                        ..Default::default()
+                    },
+                );
+                let (state, error) = if result.is_cancelled() {
+                    (SessionState::Cancelled, None)
+                } else if result.has_errors() {
+                    (
+                        SessionState::Failed,
+                        SessionError::from_errors(&result.errors),
+                    )
+                } else {
+                    (SessionState::Completed, None)
+                };
+                update(&mut |shared| {
+                    shared.status.enter(state, error.clone());
+                    if shared.dives.is_empty() {
+                        shared.status.checkpoint = checkpoint.clone();
+                    } else {
+                        shared.finished = Some(checkpoint.clone());
+                    }
+                });
+                result
+            })?;
+        Ok(Self {
+            shared,
+            cancel,
+            worker: Some(worker),
+        })
     }
 
-    /// Returns `true` if any errors occurred during parsing.
-    pub fn has_errors(&self) -> bool {
-        !self.errors.is_empty()
+    fn lock(&self) -> std::sync::MutexGuard<'_, SessionShared> {
+        self.shared
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
     }
 
-    /// Consume this result, returning the dives if successful, the first error if no dives
-    /// were parsed, or a `PartialDownload` error if some dives succeeded but errors occurred.
-    pub fn into_result(self) -> Result<Vec<Dive>> {
-        match (self.dives.is_empty(), self.errors.is_empty()) {
-            (_, true) => Ok(self.dives),
-            (true, false) => {
-                let mut errors = self.errors;
-                // Safe: !errors.is_empty() was just checked.
-                Err(errors.swap_remove(0))
+    /// The session's current state, progress and checkpoint.
+    pub fn status(&self) -> SessionStatus {
+        self.lock().status.clone()
+    }
+
+    /// Dives parsed since the last call, newest first, to save as the
+    /// download goes.
+    ///
+    /// Taking a dive is what moves [`status`](Self::status)'s checkpoint
+    /// past it, so save the dives before persisting the checkpoint: a
+    /// process killed in between downloads them again rather than losing
+    /// them.
+    pub fn take_dives(&self) -> Vec<Dive> {
+        let mut shared = self.lock();
+        let shared = &mut *shared;
+        let dives: Vec<Dive> = shared.dives.drain(..).collect();
+        if !shared.headers_only {
+            for dive in &dives {
+                shared.status.checkpoint.record(&dive.fingerprint);
             }
-            (false, false) => Err(LibError::PartialDownload {
-                dives: self.dives,
-                errors: self.errors,
-            }),
+        }
+        if let Some(finished) = shared.finished.take() {
+            shared.status.checkpoint = finished;
+        }
+        dives
+    }
+
+    /// Whether the download has ended. [`wait`](Self::wait) then returns
+    /// without blocking.
+    pub fn is_finished(&self) -> bool {
+        self.worker.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Stop downloading. The session ends as [`SessionState::Cancelled`],
+    /// keeping the dives parsed so far.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Wait for the download to end and return its result, whose
+    /// [`dives`](DownloadResult::dives) are those not
+    /// [taken](Self::take_dives) yet. Save [`status`](Self::status)'s
+    /// checkpoint first to resume it later.
+    pub fn wait(mut self) -> DownloadResult {
+        let mut result = match self.worker.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            None => unreachable!("only wait and drop take the worker"),
+        };
+        result.dives = self.take_dives();
+        result
+    }
+}
+
+impl fmt::Debug for DownloadSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DownloadSession")
+            .field("status", &self.status())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for DownloadSession {
+    fn drop(&mut self) {
+        self.cancel();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
         }
     }
 }
@@ -439,16 +2287,18 @@ impl DownloadResult {
 impl std::fmt::Debug for Device {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Device")
-            .field("open", &!self.ptr.is_null())
+            .field("open", &!self.ptr.get().is_null())
             .finish()
     }
 }
 
 impl Drop for Device {
     fn drop(&mut self) {
-        unsafe {
-            if !self.ptr.is_null() {
-                ffi::dc_device_close(self.ptr);
+        let ptr = self.ptr.get();
+        if !ptr.is_null() {
+            let status = unsafe { ffi::dc_device_close(ptr) };
+            if let Err(e) = Status::check(status, "failed to close device") {
+                tracing::warn!(error = %e, "closing dropped device");
             }
         }
     }
@@ -593,6 +2443,8 @@ mod tests {
         let ci = ConnectionInfo::Usb {
             vendor_id: 0x1234,
             product_id: 0x5678,
+            index: 0,
+            ftdi: false,
         };
         assert!(ci.connection_string().is_none());
     }
@@ -600,10 +2452,11 @@ mod tests {
     #[test]
     fn connection_info_connection_string_ble() {
         let ci = ConnectionInfo::Ble {
-            address: 0,
+            address: BtAddress::UNKNOWN,
             local_name: Some("MyDevice".into()),
             service_name: "svc".into(),
             address_string: "AA:BB:CC:DD:EE:FF".into(),
+            peripheral_id: None,
         };
         assert_eq!(
             ci.connection_string().unwrap().as_ref(),
@@ -611,6 +2464,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn connection_info_connection_string_tcp() {
+        let ci = ConnectionInfo::Tcp {
+            host: "raspberrypi.local".into(),
+            port: 3001,
+        };
+        assert_eq!(
+            ci.connection_string().unwrap().as_ref(),
+            "raspberrypi.local:3001"
+        );
+    }
+
     #[test]
     fn connection_info_display_name_serial() {
         let ci = ConnectionInfo::Serial {
@@ -624,17 +2489,31 @@ mod tests {
         let ci = ConnectionInfo::Usb {
             vendor_id: 0x1234,
             product_id: 0x5678,
+            index: 0,
+            ftdi: false,
         };
         assert_eq!(ci.display_name().as_ref(), "USB Device 1234:5678");
     }
 
+    #[test]
+    fn connection_info_display_name_usb_second_of_two() {
+        let ci = ConnectionInfo::Usb {
+            vendor_id: 0x1234,
+            product_id: 0x5678,
+            index: 1,
+            ftdi: false,
+        };
+        assert_eq!(ci.display_name().as_ref(), "USB Device 1234:5678 #2");
+    }
+
     #[test]
     fn connection_info_display_name_ble_with_name() {
         let ci = ConnectionInfo::Ble {
-            address: 0,
+            address: BtAddress::UNKNOWN,
             local_name: Some("MyDevice".into()),
             service_name: "svc".into(),
             address_string: "".into(),
+            peripheral_id: None,
         };
         assert_eq!(ci.display_name().as_ref(), "MyDevice - svc");
     }
@@ -642,25 +2521,121 @@ mod tests {
     #[test]
     fn connection_info_display_name_ble_without_name() {
         let ci = ConnectionInfo::Ble {
-            address: 0,
+            address: BtAddress::UNKNOWN,
             local_name: None,
             service_name: "svc".into(),
             address_string: "".into(),
+            peripheral_id: None,
         };
         assert_eq!(ci.display_name().as_ref(), "svc");
     }
 
+    #[test]
+    fn connection_info_display_name_ble_without_service() {
+        let ci = ConnectionInfo::Ble {
+            address: BtAddress::UNKNOWN,
+            local_name: Some("Perdix 2 0A1B".into()),
+            service_name: String::new(),
+            address_string: "".into(),
+            peripheral_id: None,
+        };
+        assert_eq!(ci.display_name().as_ref(), "Perdix 2 0A1B");
+    }
+
+    #[test]
+    fn rssi_comes_from_advertisement() {
+        let mut device = DeviceInfo {
+            name: "Perdix".into(),
+            transport: Transport::Ble,
+            connection: ConnectionInfo::Ble {
+                address: BtAddress::UNKNOWN,
+                address_string: "AA:BB:CC:DD:EE:FF".into(),
+                service_name: String::new(),
+                local_name: None,
+                peripheral_id: None,
+            },
+            advertisement: None,
+        };
+        assert_eq!(device.rssi(), None);
+        let first_scan = device.clone();
+
+        device.advertisement = Some(BleAdvertisement {
+            rssi: Some(-67),
+            ..Default::default()
+        });
+        assert_eq!(device.rssi(), Some(-67));
+
+        // Scan-to-scan noise does not make it a different device.
+        assert_eq!(device, first_scan);
+        let set: HashSet<_> = [device, first_scan].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn same_device_ignores_scan_specific_fields() {
+        let ble = |peripheral_id: Option<&str>, address_string: &str| DeviceInfo {
+            name: "Perdix".into(),
+            transport: Transport::Ble,
+            connection: ConnectionInfo::Ble {
+                address: BtAddress::UNKNOWN,
+                address_string: address_string.into(),
+                service_name: String::new(),
+                local_name: Some("Perdix".into()),
+                peripheral_id: peripheral_id.map(Into::into),
+            },
+            advertisement: None,
+        };
+        let saved = ble(Some("id-1"), "AA:BB:CC:DD:EE:FF");
+        assert!(saved.same_device(&ble(None, "aa:bb:cc:dd:ee:ff")));
+        assert!(saved.same_device(&ble(Some("id-1"), "")));
+        assert!(!saved.same_device(&ble(Some("id-2"), "11:22:33:44:55:66")));
+        assert!(saved.same_name(&ble(Some("id-2"), "11:22:33:44:55:66")));
+
+        let usb = |index| DeviceInfo {
+            name: "USB".into(),
+            transport: Transport::Usb,
+            connection: ConnectionInfo::Usb {
+                vendor_id: 0x1493,
+                product_id: 0x0030,
+                index,
+                ftdi: false,
+            },
+            advertisement: None,
+        };
+        assert!(usb(0).same_device(&usb(1)));
+        assert!(usb(0).find_saved(&[usb(0)]).is_some());
+        assert!(usb(0).find_saved(&[usb(0), usb(1)]).is_none());
+
+        let mut scanned = saved.clone();
+        scanned.advertisement = Some(BleAdvertisement::default());
+        assert_eq!(scanned.to_saved(), saved);
+    }
+
     #[test]
     fn transport_from_connection_info() {
         let cases: Vec<(ConnectionInfo, Transport)> = vec![
-            (ConnectionInfo::Serial { path: "".into() }, Transport::Serial),
+            (
+                ConnectionInfo::Serial { path: "".into() },
+                Transport::Serial,
+            ),
             (
                 ConnectionInfo::Usb {
                     vendor_id: 0,
                     product_id: 0,
+                    index: 0,
+                    ftdi: false,
                 },
                 Transport::Usb,
             ),
+            (
+                ConnectionInfo::Usb {
+                    vendor_id: 0x0403,
+                    product_id: 0x6001,
+                    index: 0,
+                    ftdi: true,
+                },
+                Transport::Serial,
+            ),
             (
                 ConnectionInfo::UsbHid {
                     vendor_id: 0,
@@ -670,17 +2645,18 @@ mod tests {
             ),
             (
                 ConnectionInfo::Bluetooth {
-                    address: 0,
+                    address: BtAddress::UNKNOWN,
                     address_string: "".into(),
                 },
                 Transport::Bluetooth,
             ),
             (
                 ConnectionInfo::Ble {
-                    address: 0,
+                    address: BtAddress::UNKNOWN,
                     local_name: None,
                     service_name: "".into(),
                     address_string: "".into(),
+                    peripheral_id: None,
                 },
                 Transport::Ble,
             ),
@@ -689,6 +2665,13 @@ mod tests {
                 ConnectionInfo::UsbStorage { path: "".into() },
                 Transport::UsbStorage,
             ),
+            (
+                ConnectionInfo::Tcp {
+                    host: "".into(),
+                    port: 0,
+                },
+                Transport::Serial,
+            ),
         ];
         for (ci, expected) in &cases {
             assert_eq!(Transport::from(ci), *expected);
@@ -700,12 +2683,20 @@ mod tests {
         let ok_result = DownloadResult {
             dives: vec![],
             errors: vec![],
+            identity: None,
+            clock: None,
+            vendor_events: Vec::new(),
+            failed_dives: Vec::new(),
         };
         assert!(ok_result.is_ok());
         assert!(!ok_result.has_errors());
 
         let err_result = DownloadResult {
             dives: vec![],
+            identity: None,
+            clock: None,
+            vendor_events: Vec::new(),
+            failed_dives: Vec::new(),
             errors: vec![LibError::Unknown],
         };
         assert!(!err_result.is_ok());
@@ -716,6 +2707,10 @@ mod tests {
     fn download_result_into_result_empty_with_errors() {
         let result = DownloadResult {
             dives: vec![],
+            identity: None,
+            clock: None,
+            vendor_events: Vec::new(),
+            failed_dives: Vec::new(),
             errors: vec![LibError::Unknown],
         };
         assert!(result.into_result().is_err());
@@ -725,6 +2720,10 @@ mod tests {
     fn download_result_into_result_has_dives_and_errors() {
         let result = DownloadResult {
             dives: vec![Dive::default()],
+            identity: None,
+            clock: None,
+            vendor_events: Vec::new(),
+            failed_dives: Vec::new(),
             errors: vec![LibError::Unknown],
         };
         match result.into_result() {
@@ -740,17 +2739,548 @@ mod tests {
     fn download_result_into_result_empty_no_errors() {
         let result = DownloadResult {
             dives: vec![],
+            identity: None,
+            clock: None,
+            vendor_events: Vec::new(),
+            failed_dives: Vec::new(),
             errors: vec![],
         };
         let dives = result.into_result().unwrap();
         assert!(dives.is_empty());
     }
 
+    #[test]
+    fn download_result_is_cancelled_keeps_dives() {
+        let result = DownloadResult {
+            dives: vec![Dive::default(), Dive::default()],
+            identity: None,
+            clock: None,
+            vendor_events: Vec::new(),
+            failed_dives: Vec::new(),
+            errors: vec![LibError::status_with_context(
+                ffi::DC_STATUS_CANCELLED,
+                "failed to download dives",
+            )],
+        };
+        assert!(result.is_cancelled());
+        assert_eq!(result.dives.len(), 2);
+
+        let result = DownloadResult {
+            dives: vec![],
+            identity: None,
+            clock: None,
+            vendor_events: Vec::new(),
+            failed_dives: Vec::new(),
+            errors: vec![LibError::Unknown],
+        };
+        assert!(!result.is_cancelled());
+    }
+
+    #[test]
+    fn device_clock_drift() {
+        let earlier = DeviceClock {
+            devtime: 1000,
+            systime: jiff::Timestamp::from_second(1_700_000_000).unwrap(),
+        };
+        // Half-second ticks: 7210 ticks is 3605 s against 3600 s on the host.
+        let later = DeviceClock {
+            devtime: 1000 + 7210,
+            systime: jiff::Timestamp::from_second(1_700_003_600).unwrap(),
+        };
+        let tick = std::time::Duration::from_millis(500);
+        assert_eq!(
+            later.drift_since(&earlier, tick),
+            Some(jiff::SignedDuration::from_secs(5))
+        );
+        assert_eq!(earlier.drift_since(&later, tick), None);
+    }
+
+    #[test]
+    fn download_progress_fraction() {
+        let progress = |current, maximum| DownloadProgress {
+            current,
+            maximum,
+            ..Default::default()
+        };
+        assert_eq!(progress(0, 0).fraction(), None);
+        assert_eq!(progress(25, 100).fraction(), Some(0.25));
+        assert_eq!(progress(120, 100).fraction(), Some(1.0));
+        let dives = DownloadProgress {
+            dives_done: 3,
+            dives_total: Some(12),
+            ..Default::default()
+        };
+        assert_eq!(dives.fraction(), Some(0.25));
+    }
+
+    #[test]
+    fn download_progress_eta() {
+        let units = DownloadProgress {
+            current: 4000,
+            maximum: 10000,
+            units_per_second: Some(2000),
+            ..Default::default()
+        };
+        assert_eq!(units.eta(), Some(Duration::from_secs(3)));
+        let stalled = DownloadProgress {
+            units_per_second: Some(0),
+            ..units
+        };
+        assert_eq!(stalled.eta(), None);
+        let dives = DownloadProgress {
+            dives_done: 2,
+            dives_total: Some(5),
+            elapsed: Duration::from_secs(10),
+            ..Default::default()
+        };
+        assert_eq!(dives.eta(), Some(Duration::from_secs(15)));
+        assert_eq!(DownloadProgress::default().eta(), None);
+    }
+
+    #[test]
+    fn session_eta_gives_up_on_stale_progress() {
+        let now = jiff::Timestamp::now();
+        let status = |progress_at| SessionStatus {
+            state: SessionState::Running,
+            state_since: now,
+            connection: None,
+            error: None,
+            progress: Some(DownloadProgress {
+                current: 4000,
+                maximum: 10000,
+                units_per_second: Some(2000),
+                ..Default::default()
+            }),
+            progress_at: Some(progress_at),
+            dives: 0,
+            checkpoint: DownloadCheckpoint::new(None),
+        };
+        assert_eq!(status(now).eta(), Some(Duration::from_secs(3)));
+        let stale = now - jiff::SignedDuration::from_secs(10);
+        assert_eq!(status(stale).eta(), None);
+    }
+
+    #[test]
+    fn rate_meter_counts_units_and_rate() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut meter = RateMeter::default();
+        assert_eq!(meter.record(at(0), 1000), (1000, None));
+        assert_eq!(meter.record(at(1000), 3000), (3000, Some(2000)));
+        assert_eq!(meter.record(at(2000), 6000), (6000, Some(2500)));
+        // After a gap, the rate covers it rather than older reports.
+        assert_eq!(meter.record(at(8000), 6600), (6600, Some(100)));
+        // A retry starts over from zero; the count keeps going.
+        assert_eq!(meter.record(at(9000), 400), (7000, Some(142)));
+        assert_eq!(meter.record(at(15000), 400), (7000, Some(0)));
+    }
+
+    #[test]
+    fn event_channel_forwards_events() {
+        let (mut on_event, events) = event_channel();
+        on_event(DeviceEvent::Waiting);
+        on_event(DeviceEvent::Progress {
+            current: 1,
+            maximum: 2,
+        });
+        drop(on_event);
+        let received: Vec<_> = events.iter().collect();
+        assert!(matches!(
+            received.as_slice(),
+            [
+                DeviceEvent::Waiting,
+                DeviceEvent::Progress {
+                    current: 1,
+                    maximum: 2
+                }
+            ]
+        ));
+    }
+
+    #[test]
+    fn battery_level_from_metadata() {
+        let metadata: std::collections::HashMap<String, String> = [
+            ("Battery at start", "4.02 V"),
+            ("Battery at end", "3.87 V"),
+            ("Battery type", "Li-Ion 3.7V"),
+            ("Serial", "12345"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let level = BatteryLevel::from_metadata(&metadata).unwrap();
+        assert_eq!(level.voltage, Some(3.87));
+        assert_eq!(level.percent, None);
+
+        let metadata = [("Battery".to_string(), "85 %".to_string())].into();
+        let level = BatteryLevel::from_metadata(&metadata).unwrap();
+        assert_eq!(level.percent, Some(85.0));
+
+        assert!(BatteryLevel::from_metadata(&std::collections::HashMap::new()).is_none());
+    }
+
     #[test]
     fn download_options_default() {
         let opts = DownloadOptions::default();
         assert!(opts.fingerprint.is_none());
         assert!(opts.on_event.is_none());
         assert!(opts.cancel_cb.is_none());
+        assert!(opts.checkpoint.is_none());
+        assert!(opts.fingerprint_store.is_none());
+        assert!(opts.on_progress.is_none());
+        assert!(opts.on_dive.is_none());
+        assert!(opts.clock_correction.is_none());
+        assert!(!opts.headers_only);
+        assert!(opts.not_before.is_none());
+        assert!(opts.max_dives.is_none());
+        assert!(!opts.keep_raw);
+        assert!(opts.timeout.is_none());
+        assert_eq!(opts.retries, 0);
+    }
+
+    /// A source holding no dives whose first `failures` downloads time out.
+    struct FlakySource {
+        failures: std::cell::Cell<u32>,
+        runs: std::cell::Cell<u32>,
+        /// Handed over by every run, before it fails.
+        records: Vec<Fingerprint>,
+    }
+
+    impl DiveSource for FlakySource {
+        fn vendor(&self) -> &str {
+            "Test"
+        }
+
+        fn product(&self) -> &str {
+            "Flaky"
+        }
+
+        fn set_fingerprint(&self, _: &Fingerprint) -> Result<()> {
+            Ok(())
+        }
+
+        fn parser(&self, _: &[u8]) -> Result<Parser> {
+            Err(LibError::Unknown)
+        }
+
+        fn set_timeout(&self, _: Duration) -> Result<()> {
+            Ok(())
+        }
+
+        fn run_foreach(&self, data: ForeachData<'_, '_, '_>) -> Result<()> {
+            self.runs.set(self.runs.get() + 1);
+            for fingerprint in &self.records {
+                (data.dive_cb)(&[], fingerprint);
+            }
+            match self.failures.get() {
+                0 => Ok(()),
+                n => {
+                    self.failures.set(n - 1);
+                    Err(LibError::Status(Status::Timeout, None))
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn download_retries_retriable_failures() {
+        let source = FlakySource {
+            failures: 2.into(),
+            runs: 0.into(),
+            records: Vec::new(),
+        };
+        let result = download_from(
+            &source,
+            DownloadOptions {
+                retries: 3,
+                ..Default::default()
+            },
+        );
+        assert!(result.is_ok());
+        assert_eq!(source.runs.get(), 3);
+
+        let source = FlakySource {
+            failures: 2.into(),
+            runs: 0.into(),
+            records: Vec::new(),
+        };
+        let result = download_from(
+            &source,
+            DownloadOptions {
+                retries: 1,
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            result.errors[..],
+            [LibError::Status(Status::Timeout, _)]
+        ));
+        assert_eq!(source.runs.get(), 2);
+    }
+
+    #[test]
+    fn retries_skip_only_dives_of_failed_attempts() {
+        let fp = Fingerprint::from(&[1u8][..]);
+        let source = FlakySource {
+            failures: 1.into(),
+            runs: 0.into(),
+            records: vec![fp.clone(), fp.clone(), Fingerprint::default()],
+        };
+        let result = download_from(
+            &source,
+            DownloadOptions {
+                retries: 1,
+                keep_raw: true,
+                ..Default::default()
+            },
+        );
+        let handled: Vec<_> = result
+            .failed_dives
+            .iter()
+            .map(|dive| dive.fingerprint.as_bytes().len())
+            .collect();
+        // The first attempt handles the repeated fingerprint twice; the
+        // retry skips it but still hands over the dive without one.
+        assert_eq!(handled, [1, 1, 0, 0]);
+    }
+
+    /// A source handing over `records`, none of which parse.
+    struct UnparsableSource {
+        records: Vec<(Fingerprint, Vec<u8>)>,
+    }
+
+    impl DiveSource for UnparsableSource {
+        fn vendor(&self) -> &str {
+            "Test"
+        }
+
+        fn product(&self) -> &str {
+            "Unparsable"
+        }
+
+        fn set_fingerprint(&self, _: &Fingerprint) -> Result<()> {
+            Ok(())
+        }
+
+        fn parser(&self, _: &[u8]) -> Result<Parser> {
+            Err(LibError::Unknown)
+        }
+
+        fn set_timeout(&self, _: Duration) -> Result<()> {
+            Ok(())
+        }
+
+        fn run_foreach(&self, data: ForeachData<'_, '_, '_>) -> Result<()> {
+            for (fingerprint, raw) in &self.records {
+                if !(data.dive_cb)(raw, fingerprint) {
+                    break;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn keep_raw_keeps_dives_that_fail_to_parse() {
+        let source = UnparsableSource {
+            records: vec![
+                (Fingerprint::from(&[1u8][..]), vec![0xAA, 0xBB]),
+                (Fingerprint::from(&[2u8][..]), vec![0xCC]),
+            ],
+        };
+        let result = download_from(&source, DownloadOptions::default());
+        assert_eq!(result.errors.len(), 2);
+        assert!(result.failed_dives.is_empty());
+
+        let result = download_from(
+            &source,
+            DownloadOptions {
+                keep_raw: true,
+                ..Default::default()
+            },
+        );
+        assert!(result.dives.is_empty());
+        assert_eq!(result.errors.len(), 2);
+        assert_eq!(
+            result.failed_dives,
+            [
+                FailedDive {
+                    fingerprint: Fingerprint::from(&[1u8][..]),
+                    raw: vec![0xAA, 0xBB],
+                },
+                FailedDive {
+                    fingerprint: Fingerprint::from(&[2u8][..]),
+                    raw: vec![0xCC],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn checkpoint_resumes_from_same_start_until_complete() {
+        let fp = |b: u8| Fingerprint::from(&[b][..]);
+        let mut checkpoint = DownloadCheckpoint::default();
+
+        // Fresh: seeded from the caller's fingerprint.
+        checkpoint.begin(Some(&fp(1)));
+        assert_eq!(checkpoint.since(), Some(&fp(1)));
+
+        // Interrupted after the two newest dives.
+        checkpoint.record(&fp(9));
+        checkpoint.record(&fp(8));
+        assert!(!checkpoint.is_complete());
+
+        // Resuming keeps the start and remembers what was delivered.
+        checkpoint.begin(Some(&fp(5)));
+        assert_eq!(checkpoint.since(), Some(&fp(1)));
+        assert!(checkpoint.contains(&fp(9)));
+        assert!(!checkpoint.contains(&fp(7)));
+        checkpoint.record(&fp(7));
+        checkpoint.complete = true;
+        assert_eq!(checkpoint.latest(), Some(&fp(9)));
+
+        // The next download starts from the newest dive of the last one.
+        checkpoint.begin(None);
+        assert_eq!(checkpoint.since(), Some(&fp(9)));
+        assert!(checkpoint.received().is_empty());
+        assert!(!checkpoint.is_complete());
+    }
+
+    #[test]
+    fn download_session_reports_outcome() {
+        let fp = Fingerprint::from(&[1u8][..]);
+        let mut saved = DownloadCheckpoint::new(Some(fp.clone()));
+        saved.complete = true;
+        let session = DownloadSession::spawn(
+            FlakySource {
+                failures: 0.into(),
+                runs: 0.into(),
+                records: Vec::new(),
+            },
+            None,
+            DownloadOptions {
+                checkpoint: Some(&mut saved),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(session.wait().is_ok());
+
+        let connection = ConnectionInfo::Serial {
+            path: "/dev/ttyUSB0".into(),
+        };
+        let started = jiff::Timestamp::now();
+        let session = DownloadSession::spawn(
+            FlakySource {
+                failures: 1.into(),
+                runs: 0.into(),
+                records: Vec::new(),
+            },
+            Some(connection.clone()),
+            DownloadOptions {
+                fingerprint: Some(&fp),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        while !session.is_finished() {
+            std::thread::yield_now();
+        }
+        let status = session.status();
+        assert_eq!(status.state, SessionState::Failed);
+        assert!(status.state_since >= started);
+        assert_eq!(status.connection, Some(connection));
+        assert_eq!(status.error.unwrap().kind, ErrorKind::Timeout);
+        assert_eq!(status.dives, 0);
+        assert_eq!(status.checkpoint.since(), Some(&fp));
+        assert!(!status.checkpoint.is_complete());
+        assert!(session.wait().has_errors());
+
+        let mut on_dive = |_: &Dive| {};
+        let rejected = DownloadSession::spawn(
+            FlakySource {
+                failures: 0.into(),
+                runs: 0.into(),
+                records: Vec::new(),
+            },
+            None,
+            DownloadOptions {
+                on_dive: Some(&mut on_dive),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(rejected, Err(LibError::InvalidArguments(_))));
+    }
+
+    #[test]
+    fn download_session_checkpoints_taken_dives() {
+        let dive = |fp: u8| Dive {
+            fingerprint: Fingerprint::from(vec![fp]),
+            ..Default::default()
+        };
+        let mut finished = DownloadCheckpoint::new(None);
+        finished.record(&Fingerprint::from(vec![2]));
+        finished.record(&Fingerprint::from(vec![1]));
+        finished.complete = true;
+        let session = DownloadSession {
+            shared: Arc::new(std::sync::Mutex::new(SessionShared {
+                status: SessionStatus {
+                    state: SessionState::Completed,
+                    state_since: jiff::Timestamp::now(),
+                    connection: None,
+                    error: None,
+                    progress: None,
+                    progress_at: None,
+                    dives: 2,
+                    checkpoint: DownloadCheckpoint::new(None),
+                },
+                dives: VecDeque::from([dive(2)]),
+                finished: None,
+                headers_only: false,
+            })),
+            cancel: Arc::new(AtomicBool::new(false)),
+            worker: None,
+        };
+        assert!(session.status().checkpoint.received().is_empty());
+        assert_eq!(session.take_dives(), [dive(2)]);
+        assert_eq!(
+            session.status().checkpoint.received(),
+            [Fingerprint::from(vec![2])]
+        );
+
+        // The final checkpoint waits for the last dive to be taken.
+        {
+            let mut shared = session.lock();
+            shared.dives.push_back(dive(1));
+            shared.finished = Some(finished.clone());
+        }
+        assert!(!session.status().checkpoint.is_complete());
+        assert_eq!(session.take_dives(), [dive(1)]);
+        assert_eq!(session.status().checkpoint, finished);
+        assert!(session.take_dives().is_empty());
+    }
+
+    #[test]
+    fn dive_stream_next_timeout() {
+        let (tx, rx) = mpsc::channel();
+        let mut stream = DiveStream {
+            rx,
+            cancel: Arc::new(AtomicBool::new(false)),
+            worker: None,
+        };
+        assert!(matches!(
+            stream.next_timeout(Duration::ZERO),
+            Next::TimedOut
+        ));
+        tx.send(Ok(Dive::default())).unwrap();
+        assert!(matches!(
+            stream.next_timeout(Duration::ZERO),
+            Next::Item(Ok(_))
+        ));
+        drop(tx);
+        assert!(matches!(
+            stream.next_timeout(Duration::from_secs(1)),
+            Next::Finished
+        ));
     }
 }
@@ -1,4 +1,7 @@
+use serde::{Deserialize, Serialize};
+
 use crate::status::Status;
+use crate::transport::Transport;
 
 /// The main error type for this crate.
 #[derive(Debug, thiserror::Error)]
@@ -15,6 +18,33 @@ pub enum LibError {
     #[error("device error: {0}")]
     DeviceError(String),
 
+    /// Scanning for devices on a transport failed.
+    #[error("{transport} scan failed: {reason}")]
+    ScanFailed {
+        /// Transport that was being scanned.
+        transport: Transport,
+        /// What went wrong.
+        reason: String,
+    },
+
+    /// Opening a connection to a device failed.
+    #[error("{transport} connect failed: {reason}")]
+    ConnectFailed {
+        /// Transport the connection was attempted over.
+        transport: Transport,
+        /// What went wrong.
+        reason: String,
+    },
+
+    /// Reading from or writing to an open transport failed.
+    #[error("{transport} I/O error: {reason}")]
+    TransportIo {
+        /// Transport the transfer was attempted over.
+        transport: Transport,
+        /// What went wrong.
+        reason: String,
+    },
+
     /// Parse error when reading dive data.
     #[error("parse error: {0}")]
     ParseError(String),
@@ -39,6 +69,12 @@ pub enum LibError {
     #[error("BLE service not found: {0}")]
     BleServiceNotFound(String),
 
+    /// The app lacks runtime permissions the operation needs, e.g.
+    /// `android.permission.BLUETOOTH_SCAN`. Holds the missing permissions'
+    /// names, ready to request.
+    #[error("missing permissions: {}", .0.join(", "))]
+    PermissionDenied(Vec<String>),
+
     /// I/O error.
     #[error(transparent)]
     Io(#[from] std::io::Error),
@@ -48,6 +84,30 @@ pub enum LibError {
     #[error(transparent)]
     Btleplug(#[from] btleplug::Error),
 
+    /// The BLE stack failed outside of a scan, connect or transfer, e.g.
+    /// its worker thread or runtime went away.
+    #[cfg(feature = "ble")]
+    #[error("BLE error: {0}")]
+    BleError(String),
+
+    /// The BLE link dropped mid-session and could not be re-established
+    /// under the session's [`ReconnectPolicy`](crate::ble::ReconnectPolicy).
+    #[cfg(feature = "ble")]
+    #[error("BLE connection lost; gave up after {attempts} reconnect attempts")]
+    BleDisconnected {
+        /// Reconnect attempts made before giving up.
+        attempts: u32,
+    },
+
+    /// SQLite error from a [`Logbook`](crate::logbook::Logbook).
+    #[cfg(feature = "logbook")]
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+
+    /// Encoding or decoding a dive archive failed.
+    #[error("serialization error: {0}")]
+    Serialization(String),
+
     /// Integer parse error.
     #[error("parse error: {0}")]
     ParseInt(#[from] std::num::ParseIntError),
@@ -121,6 +181,151 @@ impl LibError {
     }
 }
 
+/// Broad category of a [`LibError`], from [`LibError::kind`].
+///
+/// Download frontends use it to decide whether to retry: see
+/// [`ErrorKind::is_retriable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The device did not answer in time.
+    Timeout,
+    /// The device or port is in use by someone else right now.
+    Busy,
+    /// The link failed: a scan, connect, read or write went wrong, or the
+    /// connection dropped.
+    Connection,
+    /// The device is not there, e.g. asleep, out of range or unplugged.
+    DeviceNotFound,
+    /// The device sent something the protocol did not expect, usually a
+    /// corrupted packet.
+    Protocol,
+    /// The transport, device or operation is not supported, or the hardware
+    /// it needs (a Bluetooth adapter) is missing.
+    Unsupported,
+    /// Permission to use the device was denied.
+    AccessDenied,
+    /// The caller passed something invalid, e.g. an unknown model name.
+    InvalidInput,
+    /// Dive data could not be parsed, encoded or stored.
+    Data,
+    /// The operation was cancelled.
+    Cancelled,
+    /// Anything else.
+    Other,
+}
+
+impl ErrorKind {
+    /// Whether the failure is transient, so running the same operation again
+    /// may succeed: timeouts, busy devices, dropped links, a device that was
+    /// not yet awake, and garbled packets. Unsupported operations, invalid
+    /// input, bad dive data and cancellation are permanent.
+    pub fn is_retriable(self) -> bool {
+        matches!(
+            self,
+            Self::Timeout | Self::Busy | Self::Connection | Self::DeviceNotFound | Self::Protocol
+        )
+    }
+}
+
+impl LibError {
+    /// The broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Status(status, _) => status_kind(*status),
+            Self::InvalidArguments(_) | Self::DescriptorNotFound(_) => ErrorKind::InvalidInput,
+            Self::ScanFailed { .. } | Self::ConnectFailed { .. } | Self::TransportIo { .. } => {
+                ErrorKind::Connection
+            }
+            Self::TransportNotSupported(_)
+            | Self::NoBluetoothAdapter
+            | Self::BleServiceNotFound(_)
+            | Self::InvalidVersion { .. } => ErrorKind::Unsupported,
+            Self::BleDeviceNotFound(_) => ErrorKind::DeviceNotFound,
+            Self::PermissionDenied(_) => ErrorKind::AccessDenied,
+            Self::Io(e) => io_kind(e.kind()),
+            #[cfg(feature = "ble")]
+            Self::Btleplug(e) => btleplug_kind(e),
+            #[cfg(feature = "ble")]
+            Self::BleError(_) | Self::BleDisconnected { .. } => ErrorKind::Connection,
+            #[cfg(feature = "logbook")]
+            Self::Sqlite(_) => ErrorKind::Data,
+            Self::ParseError(_)
+            | Self::Serialization(_)
+            | Self::ParseInt(_)
+            | Self::Utf8(_)
+            | Self::Jiff(_) => ErrorKind::Data,
+            Self::Cancelled => ErrorKind::Cancelled,
+            Self::PartialDownload { errors, .. } => combined_kind(errors),
+            Self::DeviceError(_) | Self::NullPointer | Self::Unknown => ErrorKind::Other,
+        }
+    }
+
+    /// Whether retrying the failed operation may succeed. Shorthand for
+    /// `self.kind().is_retriable()`.
+    pub fn is_retriable(&self) -> bool {
+        self.kind().is_retriable()
+    }
+}
+
+/// The kind of a download that failed with `errors`. The first retriable
+/// error decides, so a download cut short by a dropped link is retried even
+/// if some dives also failed to parse.
+pub(crate) fn combined_kind(errors: &[LibError]) -> ErrorKind {
+    errors
+        .iter()
+        .map(LibError::kind)
+        .find(|kind| kind.is_retriable())
+        .or_else(|| errors.first().map(LibError::kind))
+        .unwrap_or(ErrorKind::Other)
+}
+
+fn status_kind(status: Status) -> ErrorKind {
+    match status {
+        Status::Timeout => ErrorKind::Timeout,
+        Status::Io => ErrorKind::Connection,
+        Status::NoDevice => ErrorKind::DeviceNotFound,
+        Status::Protocol => ErrorKind::Protocol,
+        Status::Unsupported => ErrorKind::Unsupported,
+        Status::NoAccess => ErrorKind::AccessDenied,
+        Status::InvalidArgs => ErrorKind::InvalidInput,
+        Status::DataFormat => ErrorKind::Data,
+        Status::Cancelled => ErrorKind::Cancelled,
+        Status::Success | Status::Done | Status::NoMemory => ErrorKind::Other,
+    }
+}
+
+fn io_kind(kind: std::io::ErrorKind) -> ErrorKind {
+    use std::io::ErrorKind as Io;
+    match kind {
+        Io::TimedOut => ErrorKind::Timeout,
+        Io::ResourceBusy => ErrorKind::Busy,
+        // A dropped link, or a system call cut short by a signal.
+        Io::ConnectionReset | Io::ConnectionAborted | Io::BrokenPipe | Io::Interrupted => {
+            ErrorKind::Connection
+        }
+        Io::PermissionDenied => ErrorKind::AccessDenied,
+        Io::Unsupported => ErrorKind::Unsupported,
+        Io::InvalidInput => ErrorKind::InvalidInput,
+        Io::InvalidData | Io::UnexpectedEof => ErrorKind::Data,
+        // Includes NotFound: a missing port or file stays missing.
+        _ => ErrorKind::Other,
+    }
+}
+
+#[cfg(feature = "ble")]
+fn btleplug_kind(err: &btleplug::Error) -> ErrorKind {
+    use btleplug::Error as Ble;
+    match err {
+        Ble::TimedOut(_) => ErrorKind::Timeout,
+        Ble::DeviceNotFound => ErrorKind::DeviceNotFound,
+        Ble::PermissionDenied => ErrorKind::AccessDenied,
+        Ble::NotSupported(_) | Ble::NoSuchCharacteristic => ErrorKind::Unsupported,
+        Ble::Uuid(_) | Ble::InvalidBDAddr(_) => ErrorKind::InvalidInput,
+        _ => ErrorKind::Connection,
+    }
+}
+
 impl From<Status> for LibError {
     fn from(status: Status) -> Self {
         Self::Status(status, None)
@@ -163,6 +368,22 @@ mod tests {
     fn test_error_display() {
         let error = LibError::DeviceError("Test device error".to_string());
         assert_eq!(error.to_string(), "device error: Test device error");
+
+        let error = LibError::ConnectFailed {
+            transport: Transport::Ble,
+            reason: "no response".to_string(),
+        };
+        assert_eq!(error.to_string(), "BLE connect failed: no response");
+
+        let error = LibError::PermissionDenied(vec![
+            "android.permission.BLUETOOTH_SCAN".into(),
+            "android.permission.BLUETOOTH_CONNECT".into(),
+        ]);
+        assert_eq!(
+            error.to_string(),
+            "missing permissions: android.permission.BLUETOOTH_SCAN, android.permission.BLUETOOTH_CONNECT"
+        );
+        assert_eq!(error.kind(), ErrorKind::AccessDenied);
     }
 
     #[test]
@@ -204,4 +425,69 @@ mod tests {
         let error = LibError::from(nul_err);
         assert!(matches!(error, LibError::InvalidArguments(_)));
     }
+
+    #[test]
+    fn transient_errors_are_retriable() {
+        assert!(LibError::from(Status::Timeout).is_retriable());
+        assert!(LibError::from(Status::Io).is_retriable());
+        let busy = std::io::Error::from(std::io::ErrorKind::ResourceBusy);
+        assert_eq!(LibError::from(busy).kind(), ErrorKind::Busy);
+        let lost = LibError::TransportIo {
+            transport: Transport::Serial,
+            reason: "read failed".to_string(),
+        };
+        assert!(lost.is_retriable());
+    }
+
+    #[test]
+    fn io_errors_are_retriable_only_when_transient() {
+        use std::io::ErrorKind as Io;
+        for kind in [
+            Io::ConnectionReset,
+            Io::ConnectionAborted,
+            Io::BrokenPipe,
+            Io::TimedOut,
+            Io::Interrupted,
+        ] {
+            assert!(
+                LibError::from(std::io::Error::from(kind)).is_retriable(),
+                "{kind:?}"
+            );
+        }
+        for kind in [
+            Io::NotFound,
+            Io::PermissionDenied,
+            Io::InvalidData,
+            Io::Other,
+        ] {
+            assert!(
+                !LibError::from(std::io::Error::from(kind)).is_retriable(),
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn permanent_errors_are_not_retriable() {
+        for error in [
+            LibError::from(Status::DataFormat),
+            LibError::TransportNotSupported("IrDA".to_string()),
+            LibError::ParseError("bad header".to_string()),
+            LibError::Cancelled,
+        ] {
+            assert!(!error.is_retriable(), "{error:?}");
+        }
+    }
+
+    #[test]
+    fn partial_download_retriable_if_any_error_is() {
+        let error = LibError::PartialDownload {
+            dives: Vec::new(),
+            errors: vec![
+                LibError::ParseError("bad dive".to_string()),
+                LibError::from(Status::Timeout),
+            ],
+        };
+        assert_eq!(error.kind(), ErrorKind::Timeout);
+    }
 }
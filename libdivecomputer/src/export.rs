@@ -0,0 +1,221 @@
+use std::fmt::Write as _;
+use std::io::Write;
+
+use crate::error::Result;
+use crate::parser::{DecoKind, Dive, DiveSample};
+use crate::units::{Depth, Temperature, UnitSystem};
+
+#[cfg(feature = "arrow")]
+mod arrow;
+
+/// Layout of the CSV written by [`write_csv`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// Field separator. Use `;` for spreadsheets in locales that write
+    /// decimal commas. Defaults to `,`.
+    pub delimiter: char,
+    /// Units for depth, temperature and tank pressure columns. ppO2 is
+    /// always in bar.
+    pub units: UnitSystem,
+    /// Digits after the decimal point for measurements. Defaults to 2.
+    pub decimals: usize,
+    /// Write a header row naming the columns. Defaults to `true`.
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            units: UnitSystem::Metric,
+            decimals: 2,
+            header: true,
+        }
+    }
+}
+
+/// Write `dive`'s profile as CSV, one row per [`DiveSample`].
+///
+/// Columns are `time` (seconds), `depth`, `temperature`, one `pressure_N`
+/// per tank, `ppo2` (mean over the sensors), `cns` (fraction), `deco`
+/// (`ndl`, `deco`, `deep` or `safety`), `deco_depth`, `deco_time` and `tts`
+/// (seconds). Values a sample did not record are left empty.
+///
+/// ```no_run
+/// # use libdivecomputer::{Dive, UnitSystem};
+/// # use libdivecomputer::export::{CsvOptions, write_csv};
+/// # fn demo(dive: &Dive) -> libdivecomputer::Result<()> {
+/// let file = std::fs::File::create("dive.csv")?;
+/// let options = CsvOptions {
+///     units: UnitSystem::Imperial,
+///     ..Default::default()
+/// };
+/// write_csv(dive, std::io::BufWriter::new(file), &options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn write_csv(dive: &Dive, mut out: impl Write, options: &CsvOptions) -> Result<()> {
+    let tanks = dive
+        .samples
+        .iter()
+        .map(|s| s.pressure.len())
+        .max()
+        .unwrap_or(0)
+        .max(dive.tanks.len());
+    let mut row = String::new();
+
+    if options.header {
+        let (depth, temperature, pressure) = match options.units {
+            UnitSystem::Metric => ("m", "c", "bar"),
+            UnitSystem::Imperial => ("ft", "f", "psi"),
+        };
+        let mut columns = vec![
+            "time_s".to_string(),
+            format!("depth_{depth}"),
+            format!("temperature_{temperature}"),
+        ];
+        columns.extend((1..=tanks).map(|n| format!("pressure_{n}_{pressure}")));
+        columns.extend([
+            "ppo2_bar".into(),
+            "cns".into(),
+            "deco".into(),
+            format!("deco_depth_{depth}"),
+            "deco_time_s".into(),
+            "tts_s".into(),
+        ]);
+        writeln!(out, "{}", columns.join(&options.delimiter.to_string()))?;
+    }
+
+    for sample in &dive.samples {
+        row.clear();
+        format_row(&mut row, sample, tanks, options);
+        writeln!(out, "{row}")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn format_row(row: &mut String, sample: &DiveSample, tanks: usize, options: &CsvOptions) {
+    let CsvOptions {
+        delimiter,
+        units,
+        decimals,
+        ..
+    } = *options;
+    let mut field = |value: Option<f64>, decimals: usize| {
+        if !row.is_empty() {
+            row.push(delimiter);
+        }
+        if let Some(value) = value {
+            let _ = write!(row, "{value:.decimals$}");
+        }
+    };
+
+    field(Some(sample.time.as_secs_f64()), time_decimals(sample));
+    let depth = Depth::from_meters(sample.depth);
+    field(Some(depth.value_in(units)), decimals);
+    let temperature = sample.temperature.map(Temperature::from_celsius);
+    field(temperature.map(|t| t.value_in(units)), decimals);
+    for tank in 0..tanks {
+        let pressure = sample
+            .tank_pressure(tank)
+            .filter(|p| p.bar() > 0.0)
+            .map(|p| p.value_in(units));
+        field(pressure, decimals);
+    }
+    let ppo2 = (!sample.ppo2.is_empty())
+        .then(|| sample.ppo2.iter().map(|p| p.bar).sum::<f64>() / sample.ppo2.len() as f64);
+    field(ppo2, decimals);
+    field(Some(sample.cns), decimals);
+
+    let deco = sample.deco.filter(|deco| deco.kind != DecoKind::None);
+    let (kind, stop) = match deco.map(|deco| deco.kind) {
+        Some(DecoKind::NDL) => ("ndl", None),
+        Some(DecoKind::DecoStop { depth }) => ("deco", Some(depth)),
+        Some(DecoKind::DeepStop { depth }) => ("deep", Some(depth)),
+        Some(DecoKind::SafetyStop { depth }) => ("safety", Some(depth)),
+        Some(DecoKind::None) | None => ("", None),
+    };
+    row.push(delimiter);
+    row.push_str(kind);
+    let mut field = |value: Option<f64>, decimals: usize| {
+        row.push(delimiter);
+        if let Some(value) = value {
+            let _ = write!(row, "{value:.decimals$}");
+        }
+    };
+    let stop = stop.map(|depth| Depth::from_meters(depth).value_in(units));
+    field(stop, decimals);
+    field(deco.map(|deco| deco.time.as_secs_f64()), 0);
+    field(sample.tts.map(|tts| tts.as_secs_f64()), 0);
+}
+
+/// Whole seconds unless the sample falls between them, as with the
+/// sub-second sampling of freedive computers.
+fn time_decimals(sample: &DiveSample) -> usize {
+    if sample.time.subsec_millis() == 0 {
+        0
+    } else {
+        3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::parser::{Deco, Tank};
+
+    fn csv(dive: &Dive, options: &CsvOptions) -> String {
+        let mut out = Vec::new();
+        write_csv(dive, &mut out, options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_rows_per_sample() {
+        let dive = Dive {
+            tanks: vec![Tank::default(), Tank::default()],
+            samples: vec![
+                DiveSample {
+                    time: Duration::from_secs(10),
+                    depth: 3.048,
+                    temperature: Some(20.0),
+                    pressure: vec![200.0],
+                    deco: Some(Deco {
+                        kind: DecoKind::NDL,
+                        time: Duration::from_secs(600),
+                        tts: Duration::ZERO,
+                    }),
+                    ..Default::default()
+                },
+                DiveSample {
+                    time: Duration::from_millis(10_500),
+                    depth: 6.096,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            csv(&dive, &CsvOptions::default()),
+            "time_s,depth_m,temperature_c,pressure_1_bar,pressure_2_bar,ppo2_bar,cns,deco,\
+             deco_depth_m,deco_time_s,tts_s\n\
+             10,3.05,20.00,200.00,,,0.00,ndl,,600,\n\
+             10.500,6.10,,,,,0.00,,,,\n"
+        );
+
+        let imperial = CsvOptions {
+            delimiter: ';',
+            units: UnitSystem::Imperial,
+            decimals: 0,
+            header: false,
+        };
+        assert_eq!(
+            csv(&dive, &imperial),
+            "10;10;68;2901;;;0;ndl;;600;\n10.500;20;;;;;0;;;;\n"
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Float64Array, RecordBatch};
+use arrow_schema::{DataType, Field, Schema};
+
+use crate::error::{LibError, Result};
+use crate::parser::{Dive, DiveSample};
+
+impl Dive {
+    /// The dive's samples as an Arrow [`RecordBatch`], one row per
+    /// [`DiveSample`], ready for Polars, pandas (through `pyarrow`) or
+    /// DataFusion.
+    ///
+    /// Columns are all `Float64` and metric: `time_s`, `depth_m`,
+    /// `temperature_c`, one `pressure_N_bar` per tank, `ppo2_bar` (mean over
+    /// the sensors), `cns` (fraction) and `tts_s`. Values a sample did not
+    /// record are null, as are tank pressures of zero.
+    pub fn to_record_batch(&self) -> Result<RecordBatch> {
+        let tanks = self
+            .samples
+            .iter()
+            .map(|s| s.pressure.len())
+            .max()
+            .unwrap_or(0)
+            .max(self.tanks.len());
+
+        let mut fields = Vec::with_capacity(tanks + 6);
+        let mut columns: Vec<ArrayRef> = Vec::with_capacity(tanks + 6);
+        let mut column = |name: String, nullable: bool, values: Float64Array| {
+            fields.push(Field::new(name, DataType::Float64, nullable));
+            columns.push(Arc::new(values));
+        };
+        let values = |f: &dyn Fn(&DiveSample) -> f64| self.samples.iter().map(f).collect();
+        let optional =
+            |f: &dyn Fn(&DiveSample) -> Option<f64>| self.samples.iter().map(f).collect();
+
+        column("time_s".into(), false, values(&|s| s.time.as_secs_f64()));
+        column("depth_m".into(), false, values(&|s| s.depth));
+        column("temperature_c".into(), true, optional(&|s| s.temperature));
+        for tank in 0..tanks {
+            let pressure = optional(&|s| s.pressure.get(tank).copied().filter(|&p| p > 0.0));
+            column(format!("pressure_{}_bar", tank + 1), true, pressure);
+        }
+        let ppo2 = optional(&|s| {
+            (!s.ppo2.is_empty())
+                .then(|| s.ppo2.iter().map(|p| p.bar).sum::<f64>() / s.ppo2.len() as f64)
+        });
+        column("ppo2_bar".into(), true, ppo2);
+        column("cns".into(), false, values(&|s| s.cns));
+        let tts = optional(&|s| s.tts.map(|tts| tts.as_secs_f64()));
+        column("tts_s".into(), true, tts);
+
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+            .map_err(|e| LibError::Serialization(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use arrow_array::Array;
+
+    use super::*;
+    use crate::parser::Ppo2;
+
+    #[test]
+    fn record_batch_has_column_per_tank() {
+        let dive = Dive {
+            samples: vec![
+                DiveSample {
+                    time: Duration::from_secs(10),
+                    depth: 5.0,
+                    temperature: Some(20.0),
+                    pressure: vec![200.0, 180.0],
+                    ppo2: vec![
+                        Ppo2 {
+                            bar: 1.2,
+                            ..Default::default()
+                        },
+                        Ppo2 {
+                            bar: 1.4,
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                DiveSample {
+                    time: Duration::from_secs(20),
+                    depth: 10.0,
+                    pressure: vec![190.0],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let batch = dive.to_record_batch().unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let names: Vec<_> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "time_s",
+                "depth_m",
+                "temperature_c",
+                "pressure_1_bar",
+                "pressure_2_bar",
+                "ppo2_bar",
+                "cns",
+                "tts_s"
+            ]
+        );
+        let column = |name| {
+            batch
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(column("depth_m").values(), &[5.0, 10.0]);
+        assert!(column("temperature_c").is_null(1));
+        assert!(column("pressure_2_bar").is_null(1));
+        assert!((column("ppo2_bar").value(0) - 1.3).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{LibError, Result};
+use crate::parser::Fingerprint;
+
+/// Identifies one physical dive computer: its model plus the serial number it
+/// reports in [`DeviceEvent::DevInfo`](crate::DeviceEvent::DevInfo).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct FingerprintKey {
+    /// Vendor name, as in [`Descriptor::vendor`](crate::Descriptor::vendor).
+    pub vendor: String,
+    /// Product name, as in [`Descriptor::product`](crate::Descriptor::product).
+    pub product: String,
+    /// Device serial number.
+    pub serial: u32,
+}
+
+impl fmt::Display for FingerprintKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} #{}", self.vendor, self.product, self.serial)
+    }
+}
+
+/// Where [`Device::download_dives`](crate::Device::download_dives) keeps the
+/// fingerprint of the newest dive downloaded from each computer, set through
+/// [`DownloadOptions::fingerprint_store`](crate::DownloadOptions::fingerprint_store).
+///
+/// [`FileFingerprintStore`] covers the common case; implement this to keep
+/// fingerprints in an app's own database. Plain maps work as in-memory
+/// stores.
+pub trait FingerprintStore {
+    /// The fingerprint last stored for `key`, if any.
+    fn load(&self, key: &FingerprintKey) -> Result<Option<Fingerprint>>;
+
+    /// Remember `fingerprint` as the newest dive of `key`.
+    fn store(&mut self, key: &FingerprintKey, fingerprint: &Fingerprint) -> Result<()>;
+}
+
+impl FingerprintStore for HashMap<FingerprintKey, Fingerprint> {
+    fn load(&self, key: &FingerprintKey) -> Result<Option<Fingerprint>> {
+        Ok(self.get(key).cloned())
+    }
+
+    fn store(&mut self, key: &FingerprintKey, fingerprint: &Fingerprint) -> Result<()> {
+        self.insert(key.clone(), fingerprint.clone());
+        Ok(())
+    }
+}
+
+impl FingerprintStore for BTreeMap<FingerprintKey, Fingerprint> {
+    fn load(&self, key: &FingerprintKey) -> Result<Option<Fingerprint>> {
+        Ok(self.get(key).cloned())
+    }
+
+    fn store(&mut self, key: &FingerprintKey, fingerprint: &Fingerprint) -> Result<()> {
+        self.insert(key.clone(), fingerprint.clone());
+        Ok(())
+    }
+}
+
+/// What a [`MemoryFingerprintStore`] knows about one computer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FingerprintEntry {
+    /// Which computer this is.
+    #[serde(flatten)]
+    pub key: FingerprintKey,
+    /// Its newest downloaded dive.
+    pub fingerprint: Fingerprint,
+    /// When that fingerprint was stored, i.e. when new dives were last
+    /// downloaded from it.
+    pub last_download: jiff::Timestamp,
+}
+
+/// An in-memory [`FingerprintStore`] that also records when each computer
+/// last delivered new dives, for households with several computers of the
+/// same model.
+///
+/// Serializes as a list of [`FingerprintEntry`]s, so an app can persist it
+/// wherever it keeps its settings: deserialize it before a download and
+/// serialize it again afterwards, or whenever [`is_dirty`](Self::is_dirty)
+/// says it changed.
+///
+/// ```no_run
+/// # use libdivecomputer::{Device, DownloadOptions, fingerprint_store::MemoryFingerprintStore};
+/// # fn sync(device: Device, saved: MemoryFingerprintStore) {
+/// let mut store = saved;
+/// let _result = device.download_dives(DownloadOptions {
+///     fingerprint_store: Some(&mut store),
+///     ..Default::default()
+/// });
+/// if store.is_dirty() {
+///     // Save `store` with serde, then:
+///     store.mark_clean();
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<FingerprintEntry>", into = "Vec<FingerprintEntry>")]
+pub struct MemoryFingerprintStore {
+    entries: BTreeMap<FingerprintKey, (Fingerprint, jiff::Timestamp)>,
+    dirty: bool,
+}
+
+impl MemoryFingerprintStore {
+    /// An empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// When new dives were last downloaded from `key`, if ever.
+    pub fn last_download(&self, key: &FingerprintKey) -> Option<jiff::Timestamp> {
+        self.entries.get(key).map(|&(_, time)| time)
+    }
+
+    /// Every known computer, in key order.
+    pub fn entries(&self) -> impl Iterator<Item = FingerprintEntry> + '_ {
+        self.entries
+            .iter()
+            .map(|(key, (fingerprint, time))| FingerprintEntry {
+                key: key.clone(),
+                fingerprint: fingerprint.clone(),
+                last_download: *time,
+            })
+    }
+
+    /// Forget `key`, so its next download starts from its oldest dive.
+    pub fn remove(&mut self, key: &FingerprintKey) -> Option<FingerprintEntry> {
+        let (key, (fingerprint, last_download)) = self.entries.remove_entry(key)?;
+        self.dirty = true;
+        Some(FingerprintEntry {
+            key,
+            fingerprint,
+            last_download,
+        })
+    }
+
+    /// Whether the store changed since it was created, deserialized, or
+    /// last [marked clean](Self::mark_clean).
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Record that the store's contents have been saved.
+    pub fn mark_clean(&mut self) {
+        self.dirty = false;
+    }
+}
+
+impl FingerprintStore for MemoryFingerprintStore {
+    fn load(&self, key: &FingerprintKey) -> Result<Option<Fingerprint>> {
+        Ok(self.entries.get(key).map(|(fp, _)| fp.clone()))
+    }
+
+    fn store(&mut self, key: &FingerprintKey, fingerprint: &Fingerprint) -> Result<()> {
+        self.entries
+            .insert(key.clone(), (fingerprint.clone(), jiff::Timestamp::now()));
+        self.dirty = true;
+        Ok(())
+    }
+}
+
+impl From<Vec<FingerprintEntry>> for MemoryFingerprintStore {
+    fn from(entries: Vec<FingerprintEntry>) -> Self {
+        Self {
+            entries: entries
+                .into_iter()
+                .map(|entry| (entry.key, (entry.fingerprint, entry.last_download)))
+                .collect(),
+            dirty: false,
+        }
+    }
+}
+
+impl From<MemoryFingerprintStore> for Vec<FingerprintEntry> {
+    fn from(store: MemoryFingerprintStore) -> Self {
+        store.entries().collect()
+    }
+}
+
+/// A [`FingerprintStore`] in a small text file: one tab-separated
+/// `vendor`, `product`, `serial`, `fingerprint` line per computer.
+///
+/// The file is read once by [`open`](Self::open) and rewritten on every
+/// [`store`](FingerprintStore::store), through a temporary file so a crash
+/// mid-write does not lose the fingerprints already saved.
+#[derive(Debug)]
+pub struct FileFingerprintStore {
+    path: PathBuf,
+    entries: BTreeMap<FingerprintKey, Fingerprint>,
+}
+
+impl FileFingerprintStore {
+    /// Load the store at `path`. A missing file is an empty store; it is
+    /// created on the first [`store`](FingerprintStore::store).
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => parse_entries(&contents)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path, entries })
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn save(&self) -> Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        {
+            let mut file = io::BufWriter::new(fs::File::create(&tmp)?);
+            for (key, fingerprint) in &self.entries {
+                writeln!(
+                    file,
+                    "{}\t{}\t{}\t{fingerprint}",
+                    key.vendor, key.product, key.serial
+                )?;
+            }
+            file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        }
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl FingerprintStore for FileFingerprintStore {
+    fn load(&self, key: &FingerprintKey) -> Result<Option<Fingerprint>> {
+        self.entries.load(key)
+    }
+
+    fn store(&mut self, key: &FingerprintKey, fingerprint: &Fingerprint) -> Result<()> {
+        if key.vendor.contains(['\t', '\n']) || key.product.contains(['\t', '\n']) {
+            return Err(LibError::InvalidArguments(format!(
+                "fingerprint key contains a tab or newline: {key:?}"
+            )));
+        }
+        self.entries.store(key, fingerprint)?;
+        self.save()
+    }
+}
+
+fn parse_entries(contents: &str) -> Result<BTreeMap<FingerprintKey, Fingerprint>> {
+    let mut entries = BTreeMap::new();
+    for (idx, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = || LibError::InvalidArguments(format!("fingerprint store line {}", idx + 1));
+        let mut fields = line.split('\t');
+        let (Some(vendor), Some(product), Some(serial), Some(hex), None) = (
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+        ) else {
+            return Err(invalid());
+        };
+        let key = FingerprintKey {
+            vendor: vendor.to_string(),
+            product: product.to_string(),
+            serial: serial.parse().map_err(|_| invalid())?,
+        };
+        entries.insert(key, Fingerprint::from_hex(hex)?);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(serial: u32) -> FingerprintKey {
+        FingerprintKey {
+            vendor: "Shearwater".into(),
+            product: "Perdix".into(),
+            serial,
+        }
+    }
+
+    #[test]
+    fn file_store_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "libdivecomputer-fingerprints-{}.tsv",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let mut store = FileFingerprintStore::open(&path).unwrap();
+        assert_eq!(store.load(&key(1)).unwrap(), None);
+        let fp = Fingerprint::from(vec![0xDE, 0xAD, 0xBE, 0xEF]);
+        store.store(&key(1), &fp).unwrap();
+
+        let reopened = FileFingerprintStore::open(&path).unwrap();
+        assert_eq!(reopened.load(&key(1)).unwrap(), Some(fp));
+        assert_eq!(reopened.load(&key(2)).unwrap(), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn memory_store_tracks_last_download() {
+        let mut store = MemoryFingerprintStore::new();
+        let before = jiff::Timestamp::now();
+        let fp = Fingerprint::from(vec![0xDE, 0xAD]);
+        store.store(&key(1), &fp).unwrap();
+        assert!(store.is_dirty());
+        assert_eq!(store.load(&key(1)).unwrap(), Some(fp));
+        assert!(store.last_download(&key(1)).unwrap() >= before);
+        assert_eq!(store.last_download(&key(2)), None);
+
+        let restored = MemoryFingerprintStore::from(Vec::from(store.clone()));
+        assert!(!restored.is_dirty());
+        assert!(restored.entries().eq(store.entries()));
+    }
+
+    #[test]
+    fn parse_entries_rejects_malformed_lines() {
+        assert!(parse_entries("Shearwater\tPerdix\t1\tDEADBEEF\n\n").is_ok());
+        assert!(parse_entries("Shearwater\tPerdix\tDEADBEEF\n").is_err());
+        assert!(parse_entries("Shearwater\tPerdix\tx\tDEADBEEF\n").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::device::{Device, DeviceEvent};
+use crate::error::{LibError, Result};
+use crate::family::Family;
+use crate::status::Status;
+use crate::vendor;
+
+/// Options for [`Device::update_firmware`].
+#[derive(Default)]
+pub struct FirmwareOptions<'a> {
+    /// Receives [`DeviceEvent::Progress`] while the image is uploaded and
+    /// flashed, which takes several minutes on an OSTC.
+    pub on_event: Option<&'a mut dyn FnMut(DeviceEvent)>,
+    /// Flash even if the computer already runs this firmware. Only the OSTC 3
+    /// family driver checks; the first-generation OSTC always flashes.
+    pub force: bool,
+    /// CRC-32 (IEEE) the image must match, as published alongside the
+    /// download. Guards against truncated or corrupted files before anything
+    /// is sent to the computer.
+    pub expected_crc32: Option<u32>,
+    /// Validate the image and stop before touching the computer.
+    pub dry_run: bool,
+}
+
+/// Whether libdivecomputer can flash firmware on computers of `family`.
+///
+/// Only the Heinrichs Weikamp OSTC families. Shearwater computers are out
+/// of scope: libdivecomputer implements no Shearwater upload protocol, and
+/// the bootloader protocol Shearwater Cloud uses is undocumented, so
+/// [`Device::update_firmware`] rejects them with a dedicated message.
+pub fn supports_firmware_update(family: Family) -> bool {
+    matches!(family, Family::HwOstc | Family::HwOstc3)
+}
+
+impl Device {
+    /// Flash the firmware image at `path`, as distributed by the vendor
+    /// (`.hex` for the OSTC 2/Mk2, `.bin` for the OSTC 3/4/Plus/Sport).
+    ///
+    /// Saves OSTC owners from the vendor's Windows-only updater. The driver
+    /// verifies the image and the computer reboots into the new firmware
+    /// when done; the connection cannot be used afterwards.
+    ///
+    /// The image is read once and the driver is handed a private copy of
+    /// exactly the bytes that passed validation, so the file at `path`
+    /// changing in between cannot get an unchecked image flashed.
+    ///
+    /// # Errors
+    ///
+    /// [`Status::Unsupported`] for families without a firmware update path
+    /// (see [`supports_firmware_update`]; this includes Shearwater, whose
+    /// upload protocol libdivecomputer does not implement),
+    /// [`LibError::Io`] if the image cannot be read,
+    /// [`LibError::InvalidArguments`] if it is empty or fails the
+    /// [`expected_crc32`](FirmwareOptions::expected_crc32) check, and the
+    /// driver's status if the upload fails.
+    pub fn update_firmware(
+        &self,
+        path: impl AsRef<Path>,
+        options: FirmwareOptions<'_>,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        let family = self.family();
+        if !supports_firmware_update(family) {
+            let reason = match family {
+                Family::ShearwaterPredator | Family::ShearwaterPetrel => {
+                    format!("libdivecomputer has no firmware upload for {family}")
+                }
+                _ => format!("no firmware update support for {family}"),
+            };
+            return Err(LibError::Status(Status::Unsupported, Some(reason)));
+        }
+        // Fail here rather than after the driver has put the computer into
+        // bootloader mode.
+        let image = std::fs::read(path)?;
+        validate_image(&image, options.expected_crc32)?;
+
+        if options.dry_run {
+            tracing::info!(%family, path = %path.display(), "firmware image ok, dry run");
+            return Ok(());
+        }
+
+        // The drivers open the image by name; give them a copy nobody else
+        // writes to.
+        let staged = StagedImage::new(&image, path.extension())?;
+        let path_str = staged.path.to_str().ok_or_else(|| {
+            LibError::InvalidArguments(format!(
+                "firmware staging path is not valid UTF-8: {}",
+                staged.path.display()
+            ))
+        })?;
+
+        tracing::info!(%family, path = %path.display(), "updating firmware");
+        self.with_events(options.on_event, || match family {
+            Family::HwOstc => vendor::hw_ostc::firmware_update(self, path_str),
+            _ => vendor::hw_ostc3::firmware_update(self, path_str, options.force),
+        })
+    }
+}
+
+/// A validated firmware image copied to a fresh temporary file, removed
+/// again on drop.
+struct StagedImage {
+    path: PathBuf,
+}
+
+impl StagedImage {
+    fn new(image: &[u8], extension: Option<&std::ffi::OsStr>) -> Result<Self> {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        let mut path = std::env::temp_dir().join(format!(
+            "libdivecomputer-firmware-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        if let Some(extension) = extension {
+            path.set_extension(extension);
+        }
+
+        // `create_new` refuses to follow a planted file or symlink.
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&path)?;
+        let staged = Self { path };
+        file.write_all(image)?;
+        file.sync_all()?;
+        Ok(staged)
+    }
+}
+
+impl Drop for StagedImage {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn validate_image(image: &[u8], expected_crc32: Option<u32>) -> Result<()> {
+    if image.is_empty() {
+        return Err(LibError::InvalidArguments(
+            "firmware image is empty".to_string(),
+        ));
+    }
+    if let Some(expected) = expected_crc32 {
+        let actual = crc32(image);
+        if actual != expected {
+            return Err(LibError::InvalidArguments(format!(
+                "firmware image checksum mismatch: expected {expected:08x}, got {actual:08x}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// CRC-32 with the IEEE polynomial, as printed by `crc32`/`cksum -a crc32b`.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn firmware_update_families() {
+        assert!(supports_firmware_update(Family::HwOstc));
+        assert!(supports_firmware_update(Family::HwOstc3));
+        assert!(!supports_firmware_update(Family::HwFrog));
+        assert!(!supports_firmware_update(Family::ShearwaterPetrel));
+    }
+
+    #[test]
+    fn image_validation() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert!(validate_image(b"123456789", None).is_ok());
+        assert!(validate_image(b"123456789", Some(0xCBF4_3926)).is_ok());
+        assert!(matches!(
+            validate_image(b"123456789", Some(0)),
+            Err(LibError::InvalidArguments(_))
+        ));
+        assert!(validate_image(b"", None).is_err());
+    }
+
+    #[test]
+    fn staged_image_holds_validated_bytes() {
+        let staged = StagedImage::new(b"123456789", Some("hex".as_ref())).unwrap();
+        let path = staged.path.clone();
+        assert_eq!(path.extension(), Some("hex".as_ref()));
+        assert_eq!(std::fs::read(&path).unwrap(), b"123456789");
+        drop(staged);
+        assert!(!path.exists());
+    }
+}
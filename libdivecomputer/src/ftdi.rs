@@ -0,0 +1,433 @@
+use std::collections::VecDeque;
+use std::os::fd::RawFd;
+use std::time::{Duration, Instant};
+
+use libdivecomputer_sys as ffi;
+
+use crate::context::Context;
+use crate::custom::CustomTransport;
+use crate::error::{LibError, Result};
+use crate::iostream::{Direction, FlowControl, IoStream, Parity, SerialConfig, StopBits};
+use crate::status::Status;
+use crate::transport::Transport;
+use crate::usbfs::UsbFdTransport;
+
+/// FTDI's USB vendor ID, shared by all of its USB-serial bridges.
+pub const VENDOR_ID: u16 = 0x0403;
+
+const REQUEST_OUT: u8 = 0x40;
+const REQUEST_IN: u8 = 0xC0;
+const REQUEST_STANDARD_IN: u8 = 0x80;
+
+const GET_DESCRIPTOR: u8 = 6;
+const DESCRIPTOR_DEVICE: u16 = 0x0100;
+/// Offset of `bcdDevice` in the device descriptor.
+const BCD_DEVICE_OFFSET: usize = 12;
+
+/// `bcdDevice` of the chips whose `SIO_SET_BAUD_RATE` carries the port in
+/// the lower byte of `wIndex`: FT2232C/D, FT2232H, FT4232H and FT232H.
+const PORT_IN_BAUD_INDEX: [u16; 4] = [0x0500, 0x0700, 0x0800, 0x0900];
+
+const SIO_RESET: u8 = 0;
+const SIO_SET_MODEM_CTRL: u8 = 1;
+const SIO_SET_FLOW_CTRL: u8 = 2;
+const SIO_SET_BAUD_RATE: u8 = 3;
+const SIO_SET_DATA: u8 = 4;
+const SIO_POLL_MODEM_STATUS: u8 = 5;
+const SIO_SET_LATENCY_TIMER: u8 = 9;
+
+const RESET_SIO: u16 = 0;
+const PURGE_RX: u16 = 1;
+const PURGE_TX: u16 = 2;
+
+/// `SIO_SET_MODEM_CTRL` line selectors; the low byte carries the new state
+/// in the same bit position, one byte lower.
+const MODEM_DTR: u16 = 0x0100;
+const MODEM_RTS: u16 = 0x0200;
+
+const FLOW_RTS_CTS: u16 = 0x01;
+const FLOW_XON_XOFF: u16 = 0x04;
+const XON: u16 = 0x11;
+const XOFF: u16 = 0x13;
+
+const DATA_BREAK: u16 = 1 << 14;
+
+const STATUS_CTS: u8 = 0x10;
+const STATUS_DSR: u8 = 0x20;
+const STATUS_RI: u8 = 0x40;
+const STATUS_RLSD: u8 = 0x80;
+
+/// Every IN packet starts with the modem and line status bytes.
+const STATUS_LEN: usize = 2;
+
+/// Clock of the baud rate generator in the FT232BM and later chips.
+const BASE_CLOCK: u32 = 3_000_000;
+
+/// How long the chip holds back a partly filled packet. The 16 ms default
+/// adds a noticeable delay to every reply of a chatty protocol.
+const LATENCY_MS: u16 = 2;
+
+/// An FTDI USB-serial bridge (FT232R, FT231X and similar single-port chips)
+/// driven over usbfs, for serial computers whose cable has no kernel tty:
+/// Android, or Linux without `ftdi_sio`.
+///
+/// Implements the chip's vendor requests for line settings and the modem
+/// lines, and strips the status bytes the chip puts in front of every
+/// packet, so the driver sees a plain serial port. Report the stream as
+/// [`Transport::Serial`]; [`IoStream::ftdi_fd`] does.
+#[derive(Debug)]
+pub struct FtdiTransport {
+    usb: UsbFdTransport,
+    /// Interface number plus one, as the vendor requests address ports.
+    port: u16,
+    /// Whether the chip takes the port in the baud rate request as well.
+    port_in_baud_index: bool,
+    /// Current `SIO_SET_DATA` value, kept to toggle the break bit.
+    line: u16,
+    /// Bytes received beyond what earlier reads asked for.
+    pending: VecDeque<u8>,
+    timeout: Option<Duration>,
+    /// Keeps the usbfs node open when the transport opened it itself.
+    _device: Option<std::fs::File>,
+}
+
+impl FtdiTransport {
+    /// Take over the FTDI chip behind a usbfs file descriptor, e.g. one from
+    /// Android's `UsbDeviceConnection.getFileDescriptor()`. The descriptor
+    /// stays owned by the caller.
+    pub fn new(fd: RawFd) -> Result<Self> {
+        Self::with_usb(UsbFdTransport::new(fd)?, None)
+    }
+
+    /// Open the `index`-th attached FTDI chip with these IDs through its
+    /// `/dev/bus/usb` node, detaching `ftdi_sio` if it is bound.
+    #[cfg(target_os = "linux")]
+    pub fn open(vendor_id: u16, product_id: u16, index: usize) -> Result<Self> {
+        use std::os::fd::AsRawFd;
+
+        let device = crate::usbfs::open_device(vendor_id, product_id, index)?;
+        Self::with_usb(UsbFdTransport::new(device.as_raw_fd())?, Some(device))
+    }
+
+    fn with_usb(usb: UsbFdTransport, device: Option<std::fs::File>) -> Result<Self> {
+        let port = u16::from(usb.endpoints().interface) + 1;
+        let mut ftdi = Self {
+            usb,
+            port,
+            port_in_baud_index: false,
+            line: 8,
+            pending: VecDeque::new(),
+            timeout: None,
+            _device: device,
+        };
+        let mut device = [0u8; 18];
+        ftdi.usb.control(
+            REQUEST_STANDARD_IN,
+            GET_DESCRIPTOR,
+            DESCRIPTOR_DEVICE,
+            0,
+            &mut device,
+        )?;
+        let bcd_device =
+            u16::from_le_bytes([device[BCD_DEVICE_OFFSET], device[BCD_DEVICE_OFFSET + 1]]);
+        ftdi.port_in_baud_index = PORT_IN_BAUD_INDEX.contains(&bcd_device);
+        ftdi.request(SIO_RESET, RESET_SIO, 0)?;
+        ftdi.request(SIO_SET_LATENCY_TIMER, LATENCY_MS, 0)?;
+        Ok(ftdi)
+    }
+
+    /// A vendor request without data. `index_high` goes in the upper byte
+    /// of `wIndex`, next to the port.
+    fn request(&mut self, request: u8, value: u16, index_high: u16) -> Result<()> {
+        let index = (index_high << 8) | self.port;
+        self.usb
+            .control(REQUEST_OUT, request, value, index, &mut [])
+            .map(drop)
+    }
+
+    /// Run one bulk IN transfer of up to `timeout_ms`, moving its payload to
+    /// `pending`. A timeout is not an error here; the callers check their
+    /// own deadlines.
+    fn fill(&mut self, timeout_ms: i32) -> Result<()> {
+        let packet_size = usize::from(self.usb.endpoints().max_packet_size).max(STATUS_LEN + 1);
+        let mut buf = vec![0u8; packet_size * 8];
+        self.usb.set_timeout(timeout_ms)?;
+        match self.usb.read(&mut buf) {
+            Ok(n) => {
+                self.pending.extend(payload(&buf[..n], packet_size));
+                Ok(())
+            }
+            Err(LibError::Status(Status::Timeout, _)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// The data bytes of a bulk IN transfer, without each packet's status
+/// bytes.
+fn payload(data: &[u8], packet_size: usize) -> impl Iterator<Item = u8> + '_ {
+    data.chunks(packet_size)
+        .flat_map(|packet| packet.get(STATUS_LEN..).unwrap_or_default())
+        .copied()
+}
+
+/// Milliseconds left until `deadline`, at least 1, or `None` once it has
+/// passed.
+fn remaining_ms(deadline: Instant) -> Option<i32> {
+    let remaining = deadline.checked_duration_since(Instant::now())?;
+    (!remaining.is_zero()).then(|| {
+        i32::try_from(remaining.as_millis())
+            .unwrap_or(i32::MAX)
+            .max(1)
+    })
+}
+
+/// Timeout for the next [`FtdiTransport::fill`], -1 without a deadline, or
+/// `None` once `deadline` has passed. A deadline that is already due still
+/// gets one fill, so a zero timeout sees what the chip has buffered.
+fn fill_timeout(deadline: Option<Instant>, filled: bool) -> Option<i32> {
+    match deadline.map(remaining_ms) {
+        Some(Some(ms)) => Some(ms),
+        Some(None) if !filled => Some(0),
+        Some(None) => None,
+        None => Some(-1),
+    }
+}
+
+/// `wValue` and the upper byte of `wIndex` for `SIO_SET_BAUD_RATE`: a
+/// divisor of [`BASE_CLOCK`] in eighths, with the fraction encoded the way
+/// the chips expect.
+fn baud_divisor(baudrate: u32) -> Result<(u16, u16)> {
+    const FRACTION: [u32; 8] = [0, 3, 2, 4, 1, 5, 6, 7];
+
+    if baudrate == 0 {
+        return Err(LibError::InvalidArguments("baud rate 0".into()));
+    }
+    let encoded = if baudrate >= BASE_CLOCK {
+        0
+    } else if baudrate >= BASE_CLOCK * 2 / 3 {
+        1
+    } else if baudrate >= BASE_CLOCK / 2 {
+        2
+    } else {
+        // In sixteenths first, to round to the nearest eighth.
+        let sixteenths = BASE_CLOCK * 16 / baudrate;
+        let eighths = sixteenths.div_ceil(2).min(0x1FFFF);
+        (eighths >> 3) | (FRACTION[(eighths & 7) as usize] << 14)
+    };
+    Ok((encoded as u16, (encoded >> 16) as u16))
+}
+
+/// `wIndex` for `SIO_SET_BAUD_RATE`. Single-port chips take the divisor's
+/// high bits as is; the multi-port chips and the FT232H move them to the
+/// upper byte to make room for the port.
+fn baud_index(index_high: u16, port: Option<u16>) -> u16 {
+    match port {
+        Some(port) => (index_high << 8) | port,
+        None => index_high,
+    }
+}
+
+impl CustomTransport for FtdiTransport {
+    /// Fill `buf` completely, like a serial read; a short read within the
+    /// timeout is reported as [`Status::Timeout`].
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let deadline = self.timeout.map(|t| Instant::now() + t);
+        let mut filled = false;
+        while self.pending.len() < buf.len() {
+            let Some(timeout_ms) = fill_timeout(deadline, filled) else {
+                break;
+            };
+            self.fill(timeout_ms)?;
+            filled = true;
+        }
+        if self.pending.len() < buf.len() {
+            return Err(LibError::Status(Status::Timeout, None));
+        }
+        let len = buf.len();
+        for (byte, pending) in buf.iter_mut().zip(self.pending.drain(..len)) {
+            *byte = pending;
+        }
+        Ok(len)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize> {
+        let mut written = 0;
+        while written < data.len() {
+            written += self.usb.write(&data[written..])?;
+        }
+        Ok(written)
+    }
+
+    fn poll(&mut self, timeout_ms: i32) -> Result<bool> {
+        let deadline = u64::try_from(timeout_ms)
+            .ok()
+            .map(|ms| Instant::now() + Duration::from_millis(ms));
+        let mut filled = false;
+        while self.pending.is_empty() {
+            let Some(timeout_ms) = fill_timeout(deadline, filled) else {
+                return Ok(false);
+            };
+            // The chip sends a status-only packet every latency period, so
+            // a transfer returning says nothing about data having arrived.
+            self.fill(timeout_ms)?;
+            filled = true;
+        }
+        Ok(true)
+    }
+
+    fn set_timeout(&mut self, timeout_ms: i32) -> Result<()> {
+        self.timeout = u64::try_from(timeout_ms).ok().map(Duration::from_millis);
+        Ok(())
+    }
+
+    fn configure(&mut self, config: &SerialConfig) -> Result<()> {
+        let (value, index_high) = baud_divisor(config.baudrate)?;
+        let port = self.port_in_baud_index.then_some(self.port);
+        self.usb.control(
+            REQUEST_OUT,
+            SIO_SET_BAUD_RATE,
+            value,
+            baud_index(index_high, port),
+            &mut [],
+        )?;
+
+        let parity: u16 = match config.parity {
+            Parity::None => 0,
+            Parity::Odd => 1,
+            Parity::Even => 2,
+            Parity::Mark => 3,
+            Parity::Space => 4,
+        };
+        let stopbits: u16 = match config.stopbits {
+            StopBits::One => 0,
+            StopBits::OneAndHalf => 1,
+            StopBits::Two => 2,
+        };
+        let databits = u16::try_from(config.databits)
+            .ok()
+            .filter(|bits| (5..=8).contains(bits))
+            .ok_or_else(|| LibError::InvalidArguments(format!("{} data bits", config.databits)))?;
+        self.line = databits | (parity << 8) | (stopbits << 11);
+        self.request(SIO_SET_DATA, self.line, 0)?;
+
+        let (value, flow) = match config.flowcontrol {
+            FlowControl::None => (0, 0),
+            FlowControl::Hardware => (0, FLOW_RTS_CTS),
+            FlowControl::Software => (XON | (XOFF << 8), FLOW_XON_XOFF),
+        };
+        self.request(SIO_SET_FLOW_CTRL, value, flow)
+    }
+
+    fn set_break(&mut self, value: bool) -> Result<()> {
+        let line = if value {
+            self.line | DATA_BREAK
+        } else {
+            self.line
+        };
+        self.request(SIO_SET_DATA, line, 0)
+    }
+
+    fn set_dtr(&mut self, value: bool) -> Result<()> {
+        self.request(SIO_SET_MODEM_CTRL, MODEM_DTR | u16::from(value), 0)
+    }
+
+    fn set_rts(&mut self, value: bool) -> Result<()> {
+        self.request(SIO_SET_MODEM_CTRL, MODEM_RTS | (u16::from(value) << 1), 0)
+    }
+
+    fn get_lines(&mut self) -> Result<u32> {
+        let mut status = [0u8; 2];
+        self.usb
+            .control(REQUEST_IN, SIO_POLL_MODEM_STATUS, 0, self.port, &mut status)?;
+        Ok([
+            (STATUS_RLSD, ffi::DC_LINE_DCD),
+            (STATUS_CTS, ffi::DC_LINE_CTS),
+            (STATUS_DSR, ffi::DC_LINE_DSR),
+            (STATUS_RI, ffi::DC_LINE_RNG),
+        ]
+        .into_iter()
+        .filter(|(bit, _)| status[0] & bit != 0)
+        .fold(0, |lines, (_, line)| lines | line))
+    }
+
+    fn get_available(&mut self) -> Result<usize> {
+        Ok(self.pending.len())
+    }
+
+    fn purge(&mut self, direction: Direction) -> Result<()> {
+        if matches!(direction, Direction::Input | Direction::All) {
+            self.pending.clear();
+            self.request(SIO_RESET, PURGE_RX, 0)?;
+        }
+        if matches!(direction, Direction::Output | Direction::All) {
+            self.request(SIO_RESET, PURGE_TX, 0)?;
+        }
+        Ok(())
+    }
+}
+
+impl IoStream {
+    /// Open a serial dive computer behind an FTDI USB-serial cable from a
+    /// usbfs file descriptor, e.g. one from Android's `UsbDeviceConnection`.
+    /// See [`FtdiTransport`].
+    #[must_use = "the opened IoStream must be passed to Device::open"]
+    pub fn ftdi_fd(ctx: &Context, fd: RawFd) -> Result<Self> {
+        Self::custom(ctx, Transport::Serial, FtdiTransport::new(fd)?)
+    }
+
+    /// Open a serial dive computer behind the `index`-th attached FTDI
+    /// cable with these IDs, bypassing the kernel's tty. What
+    /// [`IoStream::open`] does for [`ConnectionInfo::Usb`](crate::ConnectionInfo::Usb)
+    /// entries with `ftdi` set.
+    #[cfg(target_os = "linux")]
+    #[must_use = "the opened IoStream must be passed to Device::open"]
+    pub fn ftdi(ctx: &Context, vendor_id: u16, product_id: u16, index: usize) -> Result<Self> {
+        Self::custom(
+            ctx,
+            Transport::Serial,
+            FtdiTransport::open(vendor_id, product_id, index)?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baud_divisors_match_ftdi_tables() {
+        assert_eq!(baud_divisor(9600).unwrap(), (0x4138, 0));
+        assert_eq!(baud_divisor(115_200).unwrap(), (0x001A, 0));
+        assert_eq!(baud_divisor(38_400).unwrap(), (0xC04E, 0));
+        assert_eq!(baud_divisor(3_000_000).unwrap(), (0, 0));
+        assert_eq!(baud_divisor(2_000_000).unwrap(), (1, 0));
+        assert_eq!(baud_divisor(300).unwrap(), (0x2710, 0));
+        assert!(baud_divisor(0).is_err());
+    }
+
+    #[test]
+    fn baud_index_carries_the_port_only_on_multi_port_chips() {
+        // 14400 baud has a fraction of 3/8, which needs the 17th bit.
+        let (value, high) = baud_divisor(14_400).unwrap();
+        assert_eq!((value, high), (0x00D0, 1));
+        assert_eq!(baud_index(high, None), 0x0001);
+        assert_eq!(baud_index(high, Some(2)), 0x0102);
+        assert_eq!(baud_index(0, Some(1)), 0x0001);
+    }
+
+    #[test]
+    fn zero_timeout_fills_once() {
+        let due = Some(Instant::now());
+        assert_eq!(fill_timeout(due, false), Some(0));
+        assert_eq!(fill_timeout(due, true), None);
+        assert_eq!(fill_timeout(None, true), Some(-1));
+    }
+
+    #[test]
+    fn payload_skips_status_bytes() {
+        let data = [0x31, 0x60, b'a', b'b', 0x31, 0x60, b'c'];
+        assert_eq!(payload(&data, 4).collect::<Vec<_>>(), b"abc");
+        assert_eq!(payload(&[0x31, 0x60], 64).count(), 0);
+    }
+}
@@ -0,0 +1,166 @@
+use hidapi::{HidApi, HidDevice, HidError};
+
+use crate::context::Context;
+use crate::custom::CustomTransport;
+use crate::descriptor::usb_device_name;
+use crate::device::{ConnectionInfo, DeviceInfo};
+use crate::error::{LibError, Result};
+use crate::iostream::IoStream;
+use crate::status::Status;
+use crate::transport::Transport;
+
+/// USB HID device driven from Rust through the `hidapi` crate instead of the
+/// C library's libusb/hidapi code.
+///
+/// Uses the platform's native HID stack (hidraw on Linux, IOKit on macOS,
+/// the HID class driver on Windows), so USB HID dive computers work without
+/// linking libusb — which is awkward on Android and in sandboxes such as
+/// flatpak.
+///
+/// Reads and writes are whole HID reports, exactly as the C backend passes
+/// them through: the first byte written is the report ID (`0` for devices
+/// with a single report), and each read returns one input report.
+#[derive(Debug)]
+pub struct HidTransport {
+    device: HidDevice,
+    timeout_ms: i32,
+}
+
+impl HidTransport {
+    /// Open the first HID device with the given vendor and product ID.
+    pub fn open(vendor_id: u16, product_id: u16) -> Result<Self> {
+        let api = HidApi::new().map_err(|e| hid_error(e, "failed to initialise hidapi"))?;
+        let device = api.open(vendor_id, product_id).map_err(|e| {
+            hid_error(
+                e,
+                &format!("failed to open USB HID device {vendor_id:04X}:{product_id:04X}"),
+            )
+        })?;
+        Ok(Self::from_device(device))
+    }
+
+    /// Wrap an already opened device.
+    pub fn from_device(device: HidDevice) -> Self {
+        Self {
+            device,
+            timeout_ms: -1,
+        }
+    }
+}
+
+impl CustomTransport for HidTransport {
+    /// Read one input report. No report within the timeout is reported as
+    /// [`Status::Timeout`].
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = self
+            .device
+            .read_timeout(buf, self.timeout_ms)
+            .map_err(|e| hid_error(e, "failed to read USB HID report"))?;
+        if n == 0 {
+            return Err(LibError::Status(Status::Timeout, None));
+        }
+        Ok(n)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize> {
+        let n = self
+            .device
+            .write(data)
+            .map_err(|e| hid_error(e, "failed to write USB HID report"))?;
+        // Windows pads writes to the full report size.
+        Ok(n.min(data.len()))
+    }
+
+    fn set_timeout(&mut self, timeout_ms: i32) -> Result<()> {
+        self.timeout_ms = timeout_ms.max(-1);
+        Ok(())
+    }
+}
+
+impl IoStream {
+    /// Open a USB HID device through the pure-Rust [`HidTransport`] rather
+    /// than the C library's USB HID backend.
+    #[must_use = "the opened IoStream must be passed to Device::open"]
+    pub fn hidapi(ctx: &Context, vendor_id: u16, product_id: u16) -> Result<Self> {
+        Self::custom(
+            ctx,
+            Transport::UsbHid,
+            HidTransport::open(vendor_id, product_id)?,
+        )
+    }
+}
+
+/// Enumerate HID devices with `hidapi`. Composite devices expose one entry
+/// per interface, so each vendor/product pair is listed once.
+pub(crate) fn scan_hidapi() -> Result<Vec<DeviceInfo>> {
+    let api = HidApi::new().map_err(|e| hid_error(e, "failed to initialise hidapi"))?;
+    let mut devices: Vec<DeviceInfo> = Vec::new();
+    for info in api.device_list() {
+        let (vendor_id, product_id) = (info.vendor_id(), info.product_id());
+        let seen = devices.iter().any(|d| {
+            matches!(d.connection, ConnectionInfo::UsbHid { vendor_id: v, product_id: p }
+                if v == vendor_id && p == product_id)
+        });
+        if seen {
+            continue;
+        }
+        let name = info
+            .product_string()
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| usb_device_name(Transport::UsbHid, vendor_id, product_id));
+        devices.push(DeviceInfo {
+            name,
+            transport: Transport::UsbHid,
+            connection: ConnectionInfo::UsbHid {
+                vendor_id,
+                product_id,
+            },
+            advertisement: None,
+        });
+    }
+    Ok(devices)
+}
+
+fn hid_error(err: HidError, context: &str) -> LibError {
+    match err {
+        HidError::IoError { error } => LibError::Io(std::io::Error::new(
+            error.kind(),
+            format!("{context}: {error}"),
+        )),
+        HidError::InitializationError => {
+            LibError::TransportNotSupported(format!("{context}: {err}"))
+        }
+        err => LibError::Status(Status::Io, Some(format!("{context}: {err}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hid_errors_keep_context() {
+        let err = hid_error(
+            HidError::HidApiError {
+                message: "broken pipe".into(),
+            },
+            "failed to read USB HID report",
+        );
+        match err {
+            LibError::Status(Status::Io, Some(msg)) => {
+                assert!(msg.starts_with("failed to read USB HID report"));
+                assert!(msg.contains("broken pipe"));
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+
+        let err = hid_error(
+            HidError::IoError {
+                error: std::io::ErrorKind::PermissionDenied.into(),
+            },
+            "failed to open",
+        );
+        assert!(matches!(err, LibError::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied));
+    }
+}
@@ -1,4 +1,4 @@
-use std::ffi::CString;
+use std::ffi::{CString, c_void};
 use std::ptr;
 
 use libdivecomputer_sys as ffi;
@@ -100,6 +100,9 @@ impl IoStream {
     #[must_use = "the opened IoStream must be passed to Device::open"]
     pub fn open(ctx: &Context, connection: &ConnectionInfo) -> Result<Self> {
         match connection {
+            #[cfg(feature = "serialport")]
+            ConnectionInfo::Serial { path, .. } => Self::serialport(ctx, path),
+            #[cfg(not(feature = "serialport"))]
             ConnectionInfo::Serial { path, .. } => Self::serial(ctx, path),
             ConnectionInfo::Bluetooth {
                 address,
@@ -115,29 +118,95 @@ impl IoStream {
                 }
                 #[cfg(not(all(target_os = "android", feature = "bluetooth")))]
                 {
-                    let _ = address_string; // unused on this path
-                    Self::bluetooth(ctx, *address, 0)
+                    // Entries rebuilt from a saved address string may not
+                    // carry the numeric form; recover it rather than dialing 0.
+                    let address = match *address {
+                        address if address.is_unknown() => address_string.parse()?,
+                        address => address,
+                    };
+                    // Port 0 lets the C library look up the RFCOMM channel
+                    // via SDP.
+                    Self::bluetooth(ctx, address.to_u64(), 0)
                 }
             }
+            #[cfg(feature = "irda")]
             ConnectionInfo::Irda { address, .. } => Self::irda(ctx, *address, 1),
+            #[cfg(not(feature = "irda"))]
+            ConnectionInfo::Irda { .. } => Err(LibError::TransportNotSupported("IrDA".into())),
             ConnectionInfo::UsbStorage { path, .. } => Self::usb_storage(ctx, path),
+            ConnectionInfo::Tcp { host, port } => Self::custom(
+                ctx,
+                Transport::Serial,
+                crate::tcp::TcpTransport::connect(host, *port)?,
+            ),
+            #[cfg(feature = "simulator")]
+            ConnectionInfo::Simulator { capture, transport } => Self::custom(
+                ctx,
+                *transport,
+                crate::capture::ReplayTransport::from_file(capture)?,
+            ),
+            #[cfg(not(feature = "simulator"))]
+            ConnectionInfo::Simulator { .. } => Err(LibError::TransportNotSupported(
+                "simulator (feature not enabled)".into(),
+            )),
             #[cfg(feature = "ble")]
             ConnectionInfo::Ble {
                 address_string,
                 service_name,
+                peripheral_id,
                 ..
-            } => crate::ble::ble_iostream_open(ctx, address_string, service_name),
+            } => match peripheral_id
+                .as_deref()
+                .and_then(crate::ble::parse_peripheral_id)
+            {
+                Some(id) => crate::ble::ble_iostream_open_peripheral(
+                    ctx,
+                    &id,
+                    service_name,
+                    &crate::ble::BleOptions::default(),
+                ),
+                None => crate::ble::ble_iostream_open(ctx, address_string, service_name),
+            },
             #[cfg(not(feature = "ble"))]
             ConnectionInfo::Ble { .. } => Err(LibError::TransportNotSupported("BLE".into())),
-            ConnectionInfo::Usb { .. } | ConnectionInfo::UsbHid { .. } => {
-                Err(LibError::TransportNotSupported(
-                    "USB/USB HID requires device handle from scanner".into(),
-                ))
+            #[cfg(feature = "hidapi")]
+            ConnectionInfo::UsbHid {
+                vendor_id,
+                product_id,
+            } => Self::hidapi(ctx, *vendor_id, *product_id),
+            #[cfg(not(feature = "hidapi"))]
+            ConnectionInfo::UsbHid {
+                vendor_id,
+                product_id,
+            } => Self::usbhid(ctx, *vendor_id, *product_id),
+            #[cfg(target_os = "linux")]
+            ConnectionInfo::Usb {
+                vendor_id,
+                product_id,
+                index,
+                ftdi: true,
+            } => Self::ftdi(ctx, *vendor_id, *product_id, *index),
+            // Android apps only get at USB devices through `UsbManager`, so
+            // they open FTDI cables with `IoStream::ftdi_fd`.
+            #[cfg(not(target_os = "linux"))]
+            ConnectionInfo::Usb { ftdi: true, .. } => {
+                Err(LibError::TransportNotSupported("FTDI".into()))
             }
+            ConnectionInfo::Usb {
+                vendor_id,
+                product_id,
+                index,
+                ftdi: false,
+            } => Self::usb(ctx, *vendor_id, *product_id, *index),
         }
     }
 
     /// Open a serial port iostream.
+    ///
+    /// Opening the port does not set its line parameters; [`Device::open`](crate::Device::open)
+    /// lets the family driver configure baud rate, framing and flow control
+    /// for the model being opened, so callers only need [`configure`](Self::configure)
+    /// when talking to the port directly.
     #[must_use = "the opened IoStream must be passed to Device::open"]
     pub fn serial(ctx: &Context, name: &str) -> Result<Self> {
         let mut ptr = ptr::null_mut();
@@ -147,8 +216,47 @@ impl IoStream {
         Ok(Self { ptr })
     }
 
+    /// Open a raw USB device by vendor and product ID. `index` picks among
+    /// several attached devices with the same IDs, in the order
+    /// [`scan`](crate::scan) reports them.
+    #[must_use = "the opened IoStream must be passed to Device::open"]
+    pub fn usb(ctx: &Context, vendor_id: u16, product_id: u16, index: usize) -> Result<Self> {
+        open_nth_matching(
+            |iter| unsafe { ffi::dc_usb_iterator_new(iter, ctx.ptr(), ptr::null_mut()) },
+            |device| unsafe {
+                (
+                    ffi::dc_usb_device_get_vid(device),
+                    ffi::dc_usb_device_get_pid(device),
+                )
+            },
+            |device| Self::usb_from_device(ctx, device),
+            |device| unsafe { ffi::dc_usb_device_free(device) },
+            (vendor_id, product_id, index),
+            Transport::Usb,
+        )
+    }
+
+    /// Open the first USB HID device with the given vendor and product ID,
+    /// as reported by [`scan`](crate::scan) (e.g. Suunto EON Steel/Core,
+    /// Scubapro G2).
+    #[must_use = "the opened IoStream must be passed to Device::open"]
+    pub fn usbhid(ctx: &Context, vendor_id: u16, product_id: u16) -> Result<Self> {
+        open_nth_matching(
+            |iter| unsafe { ffi::dc_usbhid_iterator_new(iter, ctx.ptr(), ptr::null_mut()) },
+            |device| unsafe {
+                (
+                    ffi::dc_usbhid_device_get_vid(device),
+                    ffi::dc_usbhid_device_get_pid(device),
+                )
+            },
+            |device| Self::usbhid_from_device(ctx, device),
+            |device| unsafe { ffi::dc_usbhid_device_free(device) },
+            (vendor_id, product_id, 0),
+            Transport::UsbHid,
+        )
+    }
+
     /// Open a USB iostream by device reference.
-    #[allow(dead_code)]
     pub(crate) fn usb_from_device(
         ctx: &Context,
         device: *mut ffi::dc_usb_device_t,
@@ -160,7 +268,6 @@ impl IoStream {
     }
 
     /// Open a USB HID iostream by device reference.
-    #[allow(dead_code)]
     pub(crate) fn usbhid_from_device(
         ctx: &Context,
         device: *mut ffi::dc_usbhid_device_t,
@@ -171,7 +278,9 @@ impl IoStream {
         Ok(Self { ptr })
     }
 
-    /// Open an IrDA iostream.
+    /// Open an IrDA iostream (Uwatec Aladin/Smart family).
+    /// [`IoStream::open`] uses LSAP 1, which those computers listen on.
+    #[cfg(feature = "irda")]
     #[must_use = "the opened IoStream must be passed to Device::open"]
     pub fn irda(ctx: &Context, address: u32, lsap: u32) -> Result<Self> {
         let mut ptr = ptr::null_mut();
@@ -180,7 +289,8 @@ impl IoStream {
         Ok(Self { ptr })
     }
 
-    /// Open a Bluetooth iostream.
+    /// Open a classic Bluetooth (RFCOMM) iostream. Pass `port` 0 to have the
+    /// RFCOMM channel discovered via SDP.
     #[must_use = "the opened IoStream must be passed to Device::open"]
     pub fn bluetooth(ctx: &Context, address: u64, port: u32) -> Result<Self> {
         let mut ptr = ptr::null_mut();
@@ -200,7 +310,6 @@ impl IoStream {
     }
 
     /// Wrap a raw `dc_iostream_t` pointer. Takes ownership.
-    #[allow(dead_code)]
     pub(crate) fn from_raw(ptr: *mut ffi::dc_iostream_t) -> Self {
         Self { ptr }
     }
@@ -334,6 +443,84 @@ impl IoStream {
         let status = unsafe { ffi::dc_iostream_sleep(self.ptr, milliseconds) };
         Status::check(status, "failed to sleep iostream")
     }
+
+    /// Close the transport now, reporting any error that dropping it would
+    /// swallow. For BLE this also stops the transport's event-loop thread.
+    pub fn close(mut self) -> Result<()> {
+        self.close_in_place()
+    }
+
+    /// Close the transport, leaving `self` closed so that dropping it is a
+    /// no-op.
+    pub(crate) fn close_in_place(&mut self) -> Result<()> {
+        let ptr = std::mem::replace(&mut self.ptr, ptr::null_mut());
+        if ptr.is_null() {
+            return Ok(());
+        }
+        let status = unsafe { ffi::dc_iostream_close(ptr) };
+        Status::check(status, "failed to close iostream")
+    }
+}
+
+/// Walk a libdivecomputer device iterator and open the `index`-th device
+/// whose vendor/product ID matches, freeing every iterated device handle.
+/// The opened stream holds its own reference to the device.
+fn open_nth_matching<T, FCreate, FIds, FOpen, FFree>(
+    create: FCreate,
+    ids: FIds,
+    open: FOpen,
+    free: FFree,
+    (vendor_id, product_id, index): (u16, u16, usize),
+    transport: Transport,
+) -> Result<IoStream>
+where
+    FCreate: FnOnce(&mut *mut ffi::dc_iterator_t) -> ffi::dc_status_t,
+    FIds: Fn(*mut T) -> (u32, u32),
+    FOpen: Fn(*mut T) -> Result<IoStream>,
+    FFree: Fn(*mut T),
+{
+    let mut iterator = ptr::null_mut();
+    let status = create(&mut iterator);
+    Status::check(status, &format!("failed to create {transport} iterator"))?;
+
+    let wanted = (u32::from(vendor_id), u32::from(product_id));
+    let mut seen = 0;
+    let result = loop {
+        let mut device: *mut T = ptr::null_mut();
+        let status =
+            unsafe { ffi::dc_iterator_next(iterator, &mut device as *mut _ as *mut c_void) };
+        match Status::check_done(status, &format!("failed to iterate {transport} devices")) {
+            Ok(true) => {}
+            Ok(false) => {
+                break Err(LibError::ConnectFailed {
+                    transport,
+                    reason: format!(
+                        "device {vendor_id:04X}:{product_id:04X} #{} not found",
+                        index + 1
+                    ),
+                });
+            }
+            Err(e) => break Err(e),
+        }
+        if device.is_null() {
+            continue;
+        }
+
+        let mut opened = None;
+        if ids(device) == wanted {
+            if seen == index {
+                opened = Some(open(device));
+            }
+            seen += 1;
+        }
+        free(device);
+        if let Some(result) = opened {
+            break result;
+        }
+    };
+
+    unsafe { ffi::dc_iterator_free(iterator) };
+    result
 }
 
 impl std::io::Read for IoStream {
@@ -362,10 +549,8 @@ impl std::fmt::Debug for IoStream {
 
 impl Drop for IoStream {
     fn drop(&mut self) {
-        unsafe {
-            if !self.ptr.is_null() {
-                ffi::dc_iostream_close(self.ptr);
-            }
+        if let Err(e) = self.close_in_place() {
+            tracing::warn!(error = %e, "closing dropped iostream");
         }
     }
 }
@@ -8,6 +8,8 @@
 //! - [`scan`] — enumerate devices reachable over a given [`Transport`]. Returns
 //!   a [`Vec<DeviceInfo>`](DeviceInfo) carrying the connection info needed to
 //!   open the device.
+//! - [`scan_all`] — scan for computers of any model, each returned with the
+//!   [`Descriptor`]s it may be, for users who do not know their exact model.
 //! - [`Descriptor::find_by_name`] / [`Descriptor::find`] — look up a specific
 //!   device model from libdivecomputer's built-in catalog.
 //! - [`IoStream::open`] + [`Device::open`] — open the physical I/O stream
@@ -18,9 +20,50 @@
 //!
 //! # Feature flags
 //!
-//! - `ble` (default on) — enable BLE transport via `btleplug`.
-//! - `bluetooth` — classic Bluetooth (Android only; desktop platforms use the
-//!   C library's built-in classic BT support).
+//! - `arrow` — [`Dive::to_record_batch`], a dive's samples as an Arrow
+//!   record batch for Polars, pandas or DataFusion.
+//! - `bindgen` (default on) — generate the native bindings with bindgen,
+//!   which needs libclang, at build time. Turn it off to use the bindings
+//!   shipped for the target instead.
+//! - `ble` (default on) — enable BLE transport via `btleplug`. This is the
+//!   only feature that pulls in tokio; without it the crate is blocking-only,
+//!   and [`ScanBuilder::stream`](scanner::ScanBuilder::stream) and
+//!   [`Device::into_dive_stream`] run on plain threads, which suits CLI tools
+//!   that only use serial or USB.
+//! - `bluetooth` (default on) — classic Bluetooth: the Android backend, and
+//!   the C library's own support, which links BlueZ on Linux.
+//! - `cbor` / `msgpack` — [`archive`] helpers that store dives as compact
+//!   CBOR or MessagePack instead of JSON.
+//! - `hidapi` — open USB HID devices through the `hidapi` crate and the
+//!   platform's native HID stack instead of the C library's libusb-based
+//!   code. [`scan`] and [`IoStream::open`] then use it for USB HID.
+//! - `irda` (default on) — IrDA transport for the Uwatec Aladin/Smart family.
+//!   Without it, the C library is built without IrDA, and scanning and
+//!   opening IrDA report [`LibError::TransportNotSupported`]; useful on
+//!   platforms with no IrDA stack.
+//! - `log` — send the C library's log output to the `log` crate instead
+//!   of stderr (see [`Context::new`]), and this crate's own `tracing`
+//!   diagnostics to `log` when no `tracing` subscriber is installed.
+//! - `logbook` — [`Logbook`](logbook::Logbook), an SQLite dive log with
+//!   devices, dives, samples and per-computer fingerprints. Bundles SQLite.
+//! - `parse-only` — build the C library without libusb, hidapi, BlueZ and
+//!   libmtp, and skip linking them. [`Parser`] works as usual; USB, USB HID
+//!   and Bluetooth transports report [`Status::Unsupported`].
+//! - `serialport` — open serial ports from Rust via the `serialport` crate
+//!   instead of the C library's serial code. [`IoStream::open`] then routes
+//!   serial connections through `IoStream::serialport`.
+//! - `simulator` — [`SimulatedDevice`](simulator::SimulatedDevice), a
+//!   stand-in computer that serves canned dive records for UI work and CI,
+//!   and [`ConnectionInfo::Simulator`], which replays a capture through the
+//!   usual scan, open and download steps.
+//! - `system` — link the libdivecomputer installed on the system, found
+//!   through pkg-config, instead of building the bundled copy.
+//! - `uddf` — [`uddf::parse`], reading dives from UDDF files exported by
+//!   desktop logbooks.
+//! - `usb` (default on) — USB, USB HID and USB storage in the C library,
+//!   linking libusb, hidapi and libmtp. Without it those transports report
+//!   [`Status::Unsupported`]; together with disabling `bluetooth`, this
+//!   leaves a serial- or BLE-only build free of those system libraries.
 //!
 //! # Errors
 //!
@@ -28,14 +71,27 @@
 //! type. FFI failures are mapped to [`LibError::Status`] carrying a
 //! [`Status`] code plus optional context; see [`error`] for the full variant
 //! list.
+//! [`LibError::kind`] sorts errors into broad [`ErrorKind`]s and
+//! [`LibError::is_retriable`] tells transient failures, worth another attempt,
+//! from permanent ones.
 //!
 //! [libdivecomputer]: https://github.com/libdivecomputer/libdivecomputer
 #![warn(missing_docs)]
 
+/// [`BtAddress`] — Bluetooth device addresses in every platform's spelling.
+pub mod address;
+/// Compact binary dive archives (CBOR, MessagePack).
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+pub mod archive;
 pub(crate) mod buffer;
+/// Capture a transport's traffic to a file and replay it without hardware.
+pub mod capture;
 pub(crate) mod common;
 /// libdivecomputer [`Context`] + logging configuration.
 pub mod context;
+/// [`CustomTransport`] — bring-your-own I/O for [`IoStream::custom`] and
+/// [`Device::open_custom`].
+pub mod custom;
 pub(crate) mod datetime;
 /// Descriptor catalog: look up device models by name, family, or model code.
 pub mod descriptor;
@@ -44,27 +100,67 @@ pub mod descriptor;
 pub mod device;
 /// Crate-wide error type [`LibError`] and the [`Result`] alias.
 pub mod error;
+/// Write parsed dives out for other tools: [`write_csv`](export::write_csv),
+/// and Arrow record batches with the `arrow` feature.
+pub mod export;
 /// Device [`Family`] enum — high-level grouping of vendor-specific protocols.
 pub mod family;
+/// [`FingerprintStore`] — per-computer persistence of the newest downloaded
+/// dive, consulted by [`Device::download_dives`].
+pub mod fingerprint_store;
+/// Firmware updates through [`Device::update_firmware`].
+pub mod firmware;
+/// [`FtdiTransport`](ftdi::FtdiTransport) — serial computers behind FTDI
+/// USB cables, driven over usbfs without a kernel tty.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub mod ftdi;
+/// [`HidTransport`](hid::HidTransport) — USB HID devices driven through
+/// `hidapi`.
+#[cfg(feature = "hidapi")]
+pub mod hid;
 /// [`IoStream`] — the transport-level I/O handle that sits between a connection
 /// and a [`Device`].
 pub mod iostream;
+/// [`Logbook`](logbook::Logbook) — dives, samples and fingerprints in SQLite.
+#[cfg(feature = "logbook")]
+pub mod logbook;
 /// Dive log [`Parser`] + the concrete dive data types (`Dive`, `DiveSample`,
 /// `Fingerprint`, …).
 pub mod parser;
 /// Device discovery — [`scan`] enumerates all devices reachable over a given
 /// [`Transport`].
 pub mod scanner;
+/// [`SerialPortTransport`](serial::SerialPortTransport) — serial ports
+/// driven from Rust.
+#[cfg(feature = "serialport")]
+pub mod serial;
+/// [`SimulatedDevice`](simulator::SimulatedDevice) — canned dives without
+/// hardware.
+#[cfg(feature = "simulator")]
+pub mod simulator;
 /// libdivecomputer [`Status`] enum and FFI-return-code checking helpers.
 pub mod status;
+/// [`TcpTransport`](tcp::TcpTransport) — serial port bridged over TCP.
+pub mod tcp;
 /// [`Transport`] enum and the [`TransportSet`] bitmask decoder.
 pub mod transport;
+/// UDDF import: read dives exported by other logbooks into [`Dive`]s.
+#[cfg(feature = "uddf")]
+pub mod uddf;
+/// Unit-aware [`Depth`], [`Pressure`] and [`Temperature`] with metric and
+/// imperial conversions.
+pub mod units;
+/// [`UsbFdTransport`](usbfs::UsbFdTransport) — USB devices opened by file
+/// descriptor, e.g. from Android's `UsbManager`.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub mod usbfs;
 /// Vendor-specific hooks for Oceanic, Reefnet, Suunto, and friends.
 pub mod vendor;
-/// Version string of the underlying C library.
+/// Version of the underlying C library, as a string and as a comparable
+/// [`LibraryVersion`] for checking upstream additions.
 pub mod version;
 
-/// Android JNI glue — guards, attach helpers, classic Bluetooth socket wrapper.
+/// Android JNI glue — guards, attach helpers, Bluetooth permission checks.
 #[cfg(target_os = "android")]
 pub mod android;
 
@@ -77,21 +173,32 @@ pub mod ble;
 pub mod bluetooth;
 
 // Re-exports for convenience.
-pub use common::{EventKind, SampleFlag, SampleKind};
-pub use context::{Context, ContextBuilder, LogLevel};
+pub use address::BtAddress;
+pub use common::{EventCategory, EventKind, SampleFlag, SampleKind, Severity};
+pub use context::{Context, ContextBuilder, LogLevel, LogRecord, LogRotation};
+pub use custom::CustomTransport;
 pub use descriptor::{Descriptor, DescriptorIter};
 pub use device::{
-    ConnectionInfo, Device, DeviceEvent, DeviceInfo, DownloadOptions, DownloadResult,
+    BatteryLevel, BleAdvertisement, ConnectionInfo, Device, DeviceClock, DeviceEvent,
+    DeviceIdentity, DeviceInfo, DiveStream, DownloadCheckpoint, DownloadOptions, DownloadProgress,
+    DownloadResult, DownloadSession, FailedDive, Next, PersistentConnection, SessionError,
+    SessionState, SessionStatus,
 };
-pub use error::{LibError, Result};
+pub use error::{ErrorKind, LibError, Result};
 pub use family::Family;
+pub use fingerprint_store::{
+    FileFingerprintStore, FingerprintKey, FingerprintStore, MemoryFingerprintStore,
+};
+pub use firmware::FirmwareOptions;
 pub use iostream::IoStream;
 pub use parser::{
-    Deco, DecoKind, DecoModel, Dive, DiveEvent, DiveMode, DiveSample, Fingerprint, GasUsage,
-    Gasmix, Location, O2Sensor, Parser, Ppo2, STRING_KEY_FIRMWARE_VERSION,
-    STRING_KEY_SERIAL_NUMBER, Salinity, SalinityKind, Sensor, Tank, TankKind, TankUsage,
+    Deco, DecoKind, DecoModel, Deduplicator, Dive, DiveEvent, DiveMode, DiveSample, DurationFormat,
+    EventMessage, Fingerprint, GasUsage, Gasmix, Location, O2Sensor, ParseOptions, Parser, Ppo2,
+    ProfilePoint, STRING_KEY_FIRMWARE_VERSION, STRING_KEY_SERIAL_NUMBER, Salinity, SalinityKind,
+    Sensor, StandaloneParser, Tank, TankKind, TankReport, TankUsage, TimezonePolicy, WithDurations,
 };
-pub use scanner::scan;
+pub use scanner::{ScanMatch, scan, scan_all};
 pub use status::Status;
-pub use transport::{Transport, TransportSet};
-pub use version::version;
+pub use transport::{Transport, TransportIter, TransportSet};
+pub use units::{Depth, Pressure, Temperature, UnitSystem};
+pub use version::{LibraryVersion, version};
@@ -0,0 +1,450 @@
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::types::{Type, Value};
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter};
+
+use crate::error::{LibError, Result};
+use crate::fingerprint_store::{FingerprintKey, FingerprintStore};
+use crate::parser::{Dive, DiveMode, DiveSample, Fingerprint};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS devices (
+    id      INTEGER PRIMARY KEY,
+    vendor  TEXT    NOT NULL,
+    product TEXT    NOT NULL,
+    serial  INTEGER NOT NULL,
+    UNIQUE (vendor, product, serial)
+);
+CREATE TABLE IF NOT EXISTS dives (
+    id                  INTEGER PRIMARY KEY,
+    device_id           INTEGER NOT NULL REFERENCES devices (id),
+    fingerprint         BLOB    NOT NULL,
+    start               INTEGER NOT NULL,
+    utc_offset          INTEGER,
+    duration            INTEGER NOT NULL,
+    max_depth           REAL    NOT NULL,
+    avg_depth           REAL,
+    temperature_minimum REAL,
+    dive_mode           TEXT    NOT NULL,
+    raw                 BLOB,
+    dive                TEXT    NOT NULL,
+    UNIQUE (device_id, fingerprint)
+);
+CREATE INDEX IF NOT EXISTS dives_start ON dives (start);
+CREATE TABLE IF NOT EXISTS samples (
+    dive_id     INTEGER NOT NULL REFERENCES dives (id) ON DELETE CASCADE,
+    idx         INTEGER NOT NULL,
+    time_ms     INTEGER NOT NULL,
+    depth       REAL    NOT NULL,
+    temperature REAL,
+    pressure    REAL,
+    PRIMARY KEY (dive_id, idx)
+);
+CREATE TABLE IF NOT EXISTS fingerprints (
+    device_id   INTEGER PRIMARY KEY REFERENCES devices (id),
+    fingerprint BLOB    NOT NULL
+);
+";
+
+/// A dive logbook in an SQLite database: the devices dives came from, the
+/// dives with their profiles, and the newest fingerprint per computer.
+///
+/// It doubles as a [`FingerprintStore`], so a download can both resume from
+/// and be saved into the same file:
+///
+/// ```no_run
+/// # use libdivecomputer::{Device, DownloadOptions, FingerprintKey};
+/// # use libdivecomputer::logbook::Logbook;
+/// # fn demo(device: &Device, key: &FingerprintKey) -> libdivecomputer::Result<()> {
+/// let mut logbook = Logbook::open("logbook.sqlite")?;
+/// let result = device.download_dives(DownloadOptions {
+///     fingerprint_store: Some(&mut logbook),
+///     ..Default::default()
+/// });
+/// logbook.insert_dives(key, &result.dives)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Logbook {
+    conn: Connection,
+}
+
+/// A dive as stored in a [`Logbook`]: the header fields, without samples.
+/// Fetch the profile with [`Logbook::samples`], or the whole dive with
+/// [`Logbook::dive`].
+#[derive(Debug, Clone)]
+pub struct LoggedDive {
+    /// Row id, for [`Logbook::samples`].
+    pub id: i64,
+    /// Computer the dive was downloaded from.
+    pub device: FingerprintKey,
+    /// See [`Dive::fingerprint`].
+    pub fingerprint: Fingerprint,
+    /// See [`Dive::start`].
+    pub start: jiff::Timestamp,
+    /// See [`Dive::utc_offset`].
+    pub utc_offset: Option<i32>,
+    /// See [`Dive::duration`].
+    pub duration: Duration,
+    /// See [`Dive::max_depth`].
+    pub max_depth: f64,
+    /// See [`Dive::avg_depth`].
+    pub avg_depth: Option<f64>,
+    /// See [`Dive::temperature_minimum`].
+    pub temperature_minimum: Option<f64>,
+    /// See [`Dive::dive_mode`].
+    pub dive_mode: DiveMode,
+}
+
+/// Filter for [`Logbook::find_dives`]. Unset fields match every dive.
+#[derive(Debug, Clone, Default)]
+pub struct DiveQuery<'a> {
+    /// Dives starting at or after this time.
+    pub from: Option<jiff::Timestamp>,
+    /// Dives starting before this time.
+    pub to: Option<jiff::Timestamp>,
+    /// Dives at least this deep, in metres.
+    pub min_depth: Option<f64>,
+    /// Dives at most this deep, in metres.
+    pub max_depth: Option<f64>,
+    /// Dives from this computer.
+    pub device: Option<&'a FingerprintKey>,
+}
+
+impl Logbook {
+    /// Open the logbook at `path`, creating the file and its tables if
+    /// needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// A logbook that lives in memory, for tests and throwaway imports.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Store `dives` downloaded from `device`, with their samples. Dives
+    /// already in the logbook (same computer and fingerprint) are skipped.
+    /// Returns how many were added.
+    ///
+    /// Besides the columns [`find_dives`](Self::find_dives) filters on, each
+    /// dive is kept whole as JSON, so [`dive`](Self::dive) gives back every
+    /// field.
+    pub fn insert_dives(&mut self, device: &FingerprintKey, dives: &[Dive]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let device_id = device_id(&tx, device)?;
+        let mut added = 0;
+        {
+            let mut insert_dive = tx.prepare_cached(
+                "INSERT OR IGNORE INTO dives (device_id, fingerprint, start, utc_offset, duration,
+                     max_depth, avg_depth, temperature_minimum, dive_mode, raw, dive)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            let mut insert_sample = tx.prepare_cached(
+                "INSERT INTO samples (dive_id, idx, time_ms, depth, temperature, pressure)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for dive in dives {
+                // The raw record has its own column.
+                let json = serde_json::to_string(&Dive {
+                    raw: Vec::new(),
+                    ..dive.clone()
+                })
+                .map_err(|e| LibError::Serialization(e.to_string()))?;
+                let inserted = insert_dive.execute(params![
+                    device_id,
+                    dive.fingerprint.as_bytes(),
+                    dive.start.as_second(),
+                    dive.utc_offset,
+                    i64::try_from(dive.duration.as_secs()).unwrap_or(i64::MAX),
+                    dive.max_depth,
+                    dive.avg_depth,
+                    dive.temperature_minimum,
+                    dive.dive_mode.to_string(),
+                    (!dive.raw.is_empty()).then_some(dive.raw.as_slice()),
+                    json,
+                ])?;
+                if inserted == 0 {
+                    continue;
+                }
+                let dive_id = tx.last_insert_rowid();
+                for (idx, sample) in dive.samples.iter().enumerate() {
+                    insert_sample.execute(params![
+                        dive_id,
+                        idx as i64,
+                        i64::try_from(sample.time.as_millis()).unwrap_or(i64::MAX),
+                        sample.depth,
+                        sample.temperature,
+                        sample.pressure.first().copied(),
+                    ])?;
+                }
+                added += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(added)
+    }
+
+    /// Dives matching `query`, oldest first.
+    pub fn find_dives(&self, query: &DiveQuery<'_>) -> Result<Vec<LoggedDive>> {
+        let mut sql = String::from(
+            "SELECT dives.id, vendor, product, serial, fingerprint, start, utc_offset, duration,
+                    max_depth, avg_depth, temperature_minimum, dive_mode
+             FROM dives JOIN devices ON devices.id = dives.device_id WHERE 1",
+        );
+        let mut args = Vec::new();
+        let mut filter = |clause: &str, value: Value| {
+            sql.push_str(" AND ");
+            sql.push_str(clause);
+            args.push(value);
+        };
+        if let Some(from) = query.from {
+            filter("start >= ?", from.as_second().into());
+        }
+        if let Some(to) = query.to {
+            filter("start < ?", to.as_second().into());
+        }
+        if let Some(depth) = query.min_depth {
+            filter("max_depth >= ?", depth.into());
+        }
+        if let Some(depth) = query.max_depth {
+            filter("max_depth <= ?", depth.into());
+        }
+        if let Some(device) = query.device {
+            filter("vendor = ?", device.vendor.clone().into());
+            filter("product = ?", device.product.clone().into());
+            filter("serial = ?", i64::from(device.serial).into());
+        }
+        sql.push_str(" ORDER BY start, dives.id");
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), |row| {
+            let start = jiff::Timestamp::from_second(row.get(5)?).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(5, Type::Integer, Box::new(e))
+            })?;
+            Ok(LoggedDive {
+                id: row.get(0)?,
+                device: FingerprintKey {
+                    vendor: row.get(1)?,
+                    product: row.get(2)?,
+                    serial: row.get(3)?,
+                },
+                fingerprint: Fingerprint::from(row.get::<_, Vec<u8>>(4)?),
+                start,
+                utc_offset: row.get(6)?,
+                duration: Duration::from_secs(row.get::<_, i64>(7)?.max(0) as u64),
+                max_depth: row.get(8)?,
+                avg_depth: row.get(9)?,
+                temperature_minimum: row.get(10)?,
+                dive_mode: DiveMode::from(row.get::<_, String>(11)?),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The stored profile of dive `dive_id`: time, depth, temperature and
+    /// first-tank pressure of each sample.
+    pub fn samples(&self, dive_id: i64) -> Result<Vec<DiveSample>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT time_ms, depth, temperature, pressure FROM samples
+             WHERE dive_id = ?1 ORDER BY idx",
+        )?;
+        let rows = stmt.query_map([dive_id], |row| {
+            Ok(DiveSample {
+                time: Duration::from_millis(row.get::<_, i64>(0)?.max(0) as u64),
+                depth: row.get(1)?,
+                temperature: row.get(2)?,
+                pressure: row.get::<_, Option<f64>>(3)?.into_iter().collect(),
+                ..Default::default()
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Dive `dive_id` as it was inserted: every field, the whole profile and
+    /// [`Dive::raw`]. `None` if there is no such dive.
+    pub fn dive(&self, dive_id: i64) -> Result<Option<Dive>> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT dive, raw FROM dives WHERE id = ?1",
+                [dive_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<Vec<u8>>>(1)?)),
+            )
+            .optional()?;
+        let Some((json, raw)) = row else {
+            return Ok(None);
+        };
+        let mut dive: Dive =
+            serde_json::from_str(&json).map_err(|e| LibError::Serialization(e.to_string()))?;
+        dive.raw = raw.unwrap_or_default();
+        Ok(Some(dive))
+    }
+
+    /// Fingerprint of the newest dive logged from `device`, for starting an
+    /// incremental download.
+    pub fn latest_fingerprint(&self, device: &FingerprintKey) -> Result<Option<Fingerprint>> {
+        let fingerprint = self
+            .conn
+            .query_row(
+                "SELECT fingerprint FROM dives JOIN devices ON devices.id = dives.device_id
+                 WHERE vendor = ?1 AND product = ?2 AND serial = ?3
+                 ORDER BY start DESC, dives.id DESC LIMIT 1",
+                params![device.vendor, device.product, device.serial],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?;
+        Ok(fingerprint.map(Fingerprint::from))
+    }
+}
+
+/// Loads the fingerprint last stored for the computer, falling back to its
+/// newest logged dive.
+impl FingerprintStore for Logbook {
+    fn load(&self, key: &FingerprintKey) -> Result<Option<Fingerprint>> {
+        let stored = self
+            .conn
+            .query_row(
+                "SELECT fingerprint FROM fingerprints JOIN devices ON devices.id = device_id
+                 WHERE vendor = ?1 AND product = ?2 AND serial = ?3",
+                params![key.vendor, key.product, key.serial],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?;
+        match stored {
+            Some(bytes) => Ok(Some(Fingerprint::from(bytes))),
+            None => self.latest_fingerprint(key),
+        }
+    }
+
+    fn store(&mut self, key: &FingerprintKey, fingerprint: &Fingerprint) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let device_id = device_id(&tx, key)?;
+        tx.execute(
+            "INSERT INTO fingerprints (device_id, fingerprint) VALUES (?1, ?2)
+             ON CONFLICT (device_id) DO UPDATE SET fingerprint = excluded.fingerprint",
+            params![device_id, fingerprint.as_bytes()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Row id of `device`, inserting it on first sight.
+fn device_id(conn: &Connection, device: &FingerprintKey) -> Result<i64> {
+    conn.execute(
+        "INSERT OR IGNORE INTO devices (vendor, product, serial) VALUES (?1, ?2, ?3)",
+        params![device.vendor, device.product, device.serial],
+    )?;
+    conn.query_row(
+        "SELECT id FROM devices WHERE vendor = ?1 AND product = ?2 AND serial = ?3",
+        params![device.vendor, device.product, device.serial],
+        |row| row.get(0),
+    )
+    .map_err(LibError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Salinity, SalinityKind};
+
+    fn key() -> FingerprintKey {
+        FingerprintKey {
+            vendor: "Shearwater".into(),
+            product: "Perdix".into(),
+            serial: 42,
+        }
+    }
+
+    fn dive(fingerprint: u8, start: &str, max_depth: f64) -> Dive {
+        Dive {
+            fingerprint: Fingerprint::from(vec![fingerprint]),
+            start: start.parse().unwrap(),
+            duration: Duration::from_secs(1800),
+            max_depth,
+            samples: vec![DiveSample {
+                time: Duration::from_secs(10),
+                depth: max_depth,
+                pressure: vec![200.0],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn insert_query_and_fingerprints() {
+        let mut logbook = Logbook::open_in_memory().unwrap();
+        let dives = [
+            dive(2, "2025-06-02T10:00:00Z", 30.0),
+            dive(1, "2025-06-01T10:00:00Z", 12.0),
+        ];
+        assert_eq!(logbook.insert_dives(&key(), &dives).unwrap(), 2);
+        assert_eq!(logbook.insert_dives(&key(), &dives).unwrap(), 0);
+
+        let deep = logbook
+            .find_dives(&DiveQuery {
+                min_depth: Some(20.0),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(deep.len(), 1);
+        assert_eq!(deep[0].start, dives[0].start);
+        assert_eq!(deep[0].device, key());
+        let samples = logbook.samples(deep[0].id).unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].pressure, vec![200.0]);
+
+        let june_first = logbook
+            .find_dives(&DiveQuery {
+                from: Some("2025-06-01T00:00:00Z".parse().unwrap()),
+                to: Some("2025-06-02T00:00:00Z".parse().unwrap()),
+                device: Some(&key()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(june_first.len(), 1);
+        assert_eq!(june_first[0].fingerprint, dives[1].fingerprint);
+
+        assert_eq!(
+            logbook.load(&key()).unwrap(),
+            Some(dives[0].fingerprint.clone())
+        );
+        let newer = Fingerprint::from(vec![3]);
+        logbook.store(&key(), &newer).unwrap();
+        assert_eq!(logbook.load(&key()).unwrap(), Some(newer));
+    }
+
+    #[test]
+    fn dives_round_trip_whole() {
+        let mut logbook = Logbook::open_in_memory().unwrap();
+        let mut logged = dive(1, "2025-06-01T10:00:00Z", 18.5);
+        logged.temperature_surface = Some(24.0);
+        logged.salinity = Some(Salinity {
+            kind: SalinityKind::Salt,
+            density: 1025.0,
+        });
+        logged.samples[0].heartbeat = Some(92);
+        logged.samples[0].pressure = vec![200.0, 180.0];
+        logged.raw = vec![0xDE, 0xAD, 0xBE, 0xEF];
+        logbook.insert_dives(&key(), &[logged.clone()]).unwrap();
+
+        let id = logbook.find_dives(&DiveQuery::default()).unwrap()[0].id;
+        let stored = logbook.dive(id).unwrap().unwrap();
+        assert_eq!(stored.raw, logged.raw);
+        assert_eq!(
+            serde_json::to_value(&stored).unwrap(),
+            serde_json::to_value(&logged).unwrap()
+        );
+        assert!(logbook.dive(id + 1).unwrap().is_none());
+    }
+}
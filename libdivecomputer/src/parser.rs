@@ -2,6 +2,27 @@
 /// `Gasmix`, `Tank`, `Fingerprint`, and friends.
 pub mod types;
 
+/// [`Deduplicator`](dedup::Deduplicator) and [`Dive::same_as`] — spotting
+/// dives that are already in a logbook.
+pub mod dedup;
+
+/// Gas-management calculations over a parsed [`Dive`]: per-tank consumption
+/// and dive-wide SAC.
+pub mod gas;
+
+/// `approx_eq` on [`Dive`], [`DiveSample`], [`Tank`] and [`Gasmix`]: equality
+/// with a tolerance on float fields, for values that went through unit
+/// conversion or a text format, where `==` is too strict.
+mod approx;
+
+/// Evenly spaced or thinned-out depth/temperature series for charting
+/// long dives: [`Dive::downsample`] and [`Dive::resample`].
+pub mod profile;
+
+/// [`StandaloneParser`](standalone::StandaloneParser) — bulk parsing of
+/// saved records from one model.
+pub mod standalone;
+
 use std::{
     ffi::{CStr, c_void},
     mem::MaybeUninit,
@@ -9,6 +30,10 @@ use std::{
     time::Duration,
 };
 
+pub use dedup::Deduplicator;
+pub use gas::TankReport;
+pub use profile::ProfilePoint;
+pub use standalone::StandaloneParser;
 pub use types::*;
 
 use libdivecomputer_sys as ffi;
@@ -28,9 +53,45 @@ pub const STRING_KEY_FIRMWARE_VERSION: &str = "FW Version";
 /// Well-known string key for serial number in `SAMPLE_EVENT_STRING` events.
 pub const STRING_KEY_SERIAL_NUMBER: &str = "Serial";
 
+/// How [`Parser`] reads dive start times from computers that keep local time
+/// on their clock and record no UTC offset.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TimezonePolicy {
+    /// Take the wall-clock time as UTC, leaving [`Dive::utc_offset`] unset.
+    /// [`Dive::start_local`] then still shows the time the computer did.
+    #[default]
+    Utc,
+    /// Take the wall-clock time as the host's system time zone.
+    System,
+    /// Take the wall-clock time in this time zone, e.g. where the dive
+    /// trip took place.
+    Zone(jiff::tz::TimeZone),
+}
+
+impl TimezonePolicy {
+    fn time_zone(&self) -> jiff::tz::TimeZone {
+        match self {
+            Self::Utc => jiff::tz::TimeZone::UTC,
+            Self::System => jiff::tz::TimeZone::system(),
+            Self::Zone(tz) => tz.clone(),
+        }
+    }
+}
+
+/// Options applied by [`Parser::parse`] and [`Parser::parse_header`]; set
+/// them with [`Parser::with_options`] or
+/// [`DownloadOptions::parse_options`](crate::DownloadOptions::parse_options).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseOptions {
+    /// Time zone for start times recorded without a UTC offset. Start times
+    /// that carry an offset are never reinterpreted.
+    pub timezone: TimezonePolicy,
+}
+
 /// Dive data parser. Wraps `dc_parser_t`.
 pub struct Parser {
     ptr: *mut ffi::dc_parser_t,
+    options: ParseOptions,
 }
 
 impl Parser {
@@ -50,7 +111,10 @@ impl Parser {
         let mut ptr = ptr::null_mut();
         let status = unsafe { ffi::dc_parser_new(&mut ptr, device_ptr, data.as_ptr(), data.len()) };
         Status::check(status, "failed to create parser from device")?;
-        Ok(Self { ptr })
+        Ok(Self {
+            ptr,
+            options: ParseOptions::default(),
+        })
     }
 
     /// Create a parser from a descriptor (for parsing saved dive data).
@@ -61,7 +125,50 @@ impl Parser {
             ffi::dc_parser_new2(&mut ptr, ctx.ptr(), desc.ptr, data.as_ptr(), data.len())
         };
         Status::check(status, "failed to create parser from descriptor")?;
-        Ok(Self { ptr })
+        Ok(Self {
+            ptr,
+            options: ParseOptions::default(),
+        })
+    }
+
+    /// Use `options` for subsequent parses.
+    #[must_use]
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Cheap sanity check that `data` looks like a dive for `desc`.
+    ///
+    /// Creating a parser and reading the header fields is enough for the
+    /// vendor backends to reject blobs of the wrong size or layout, without
+    /// walking the sample stream. Use it to skip files that were saved from a
+    /// different model instead of getting confusing partial results from
+    /// [`Parser::parse`]. A `true` result does not guarantee the samples parse.
+    pub fn can_parse(ctx: &Context, desc: &Descriptor, data: &[u8]) -> bool {
+        if data.is_empty() {
+            return false;
+        }
+        let Ok(parser) = Self::from_descriptor(ctx, desc, data) else {
+            return false;
+        };
+
+        let mut dt = MaybeUninit::<ffi::dc_datetime_t>::uninit();
+        let status = unsafe { ffi::dc_parser_get_datetime(parser.ptr, dt.as_mut_ptr()) };
+        match Status::check_unsupported(status, "failed to parse datetime") {
+            Ok(true) => {
+                // A mismatched model usually decodes the header into a
+                // nonsense date rather than failing outright.
+                let dt = unsafe { dt.assume_init() };
+                if crate::datetime::ffi_to_timestamp(&dt).is_err() {
+                    return false;
+                }
+            }
+            Ok(false) => {}
+            Err(_) => return false,
+        }
+
+        unsafe { get_field::<u32>(parser.ptr, ffi::DC_FIELD_DIVETIME, 0, "divetime") }.is_ok()
     }
 
     /// Set the device clock reference for datetime calculation.
@@ -88,19 +195,92 @@ impl Parser {
         crate::family::Family::from(raw)
     }
 
+    /// Parse the header fields into a `Dive`, leaving
+    /// [`samples`](Dive::samples) empty. Much cheaper than
+    /// [`parse`](Self::parse) when only a dive list is needed.
+    #[must_use = "parsed dive data should not be silently discarded"]
+    pub fn parse_header(&self, fingerprint: &Fingerprint) -> Result<Dive> {
+        Ok(Dive {
+            fingerprint: fingerprint.clone(),
+            ..parse_fields(self.ptr, &self.options)?
+        })
+    }
+
     /// Parse all fields and samples into a `Dive`.
     #[must_use = "parsed dive data should not be silently discarded"]
     pub fn parse(&self, fingerprint: &Fingerprint) -> Result<Dive> {
-        let mut dive = Dive {
-            fingerprint: fingerprint.clone(),
-            ..parse_fields(self.ptr)?
+        let mut dive = Dive::default();
+        self.parse_into(fingerprint, &mut dive)?;
+        Ok(dive)
+    }
+
+    /// Like [`parse`](Self::parse), but overwrites `dive` in place and reuses
+    /// its sample and fingerprint buffers. Re-parsing a large archive through
+    /// one `Dive` saves an allocation-heavy sample vector per dive:
+    ///
+    /// ```no_run
+    /// # use libdivecomputer::{Context, Descriptor, Dive, Fingerprint, Parser};
+    /// # fn reparse(ctx: &Context, desc: &Descriptor, archive: &[(Fingerprint, Vec<u8>)])
+    /// # -> libdivecomputer::Result<()> {
+    /// let mut dive = Dive::default();
+    /// for (fingerprint, data) in archive {
+    ///     Parser::from_descriptor(ctx, desc, data)?.parse_into(fingerprint, &mut dive)?;
+    ///     // use `dive` before the next iteration overwrites it
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// On error, the contents of `dive` are unspecified.
+    pub fn parse_into(&self, fingerprint: &Fingerprint, dive: &mut Dive) -> Result<()> {
+        let mut samples = std::mem::take(&mut dive.samples);
+        samples.clear();
+        let mut fp = std::mem::take(&mut dive.fingerprint);
+        fp.data.clear();
+        fp.data.extend_from_slice(fingerprint.as_bytes());
+        *dive = Dive {
+            fingerprint: fp,
+            samples,
+            ..parse_fields(self.ptr, &self.options)?
         };
+        // Most computers sample every 1 to 10 seconds, so this undershoots
+        // at worst and spares the first several reallocations of a long
+        // dive's samples.
+        let estimate = usize::try_from(dive.duration.as_secs() / 10).unwrap_or(0);
+        dive.samples.reserve(estimate);
+
+        self.parse_samples(ParseData {
+            dive,
+            sample: DiveSample::default(),
+            on_sample: None,
+        })
+    }
 
-        let mut parse_data = ParseData {
+    /// Parse the header fields into a `Dive` and hand each sample to
+    /// `on_sample` as soon as it is complete, instead of collecting them in
+    /// [`Dive::samples`], which stays empty.
+    ///
+    /// Memory stays bounded however long the dive: freedive sessions can run
+    /// to hundreds of thousands of samples. The header is fully parsed
+    /// before the first sample arrives, but `on_sample` only sees the
+    /// returned `Dive` once parsing is done; use [`parse_header`](Self::parse_header)
+    /// first if the samples need header context such as
+    /// [`gasmixes`](Dive::gasmixes).
+    pub fn parse_streaming(
+        &self,
+        fingerprint: &Fingerprint,
+        mut on_sample: impl FnMut(DiveSample),
+    ) -> Result<Dive> {
+        let mut dive = self.parse_header(fingerprint)?;
+        self.parse_samples(ParseData {
             dive: &mut dive,
             sample: DiveSample::default(),
-        };
+            on_sample: Some(&mut on_sample),
+        })?;
+        Ok(dive)
+    }
 
+    fn parse_samples(&self, mut parse_data: ParseData<'_>) -> Result<()> {
         unsafe {
             let status = ffi::dc_parser_samples_foreach(
                 self.ptr,
@@ -113,10 +293,10 @@ impl Parser {
         // Push the last sample if it has data.
         let last_sample = std::mem::take(&mut parse_data.sample);
         if last_sample.time.as_millis() > 0 {
-            parse_data.dive.samples.push(last_sample);
+            parse_data.emit(last_sample);
         }
 
-        Ok(dive)
+        Ok(())
     }
 }
 
@@ -142,6 +322,17 @@ impl Drop for Parser {
 struct ParseData<'a> {
     dive: &'a mut Dive,
     sample: DiveSample,
+    /// Receives finished samples instead of `dive.samples` when set.
+    on_sample: Option<&'a mut dyn FnMut(DiveSample)>,
+}
+
+impl ParseData<'_> {
+    fn emit(&mut self, sample: DiveSample) {
+        match self.on_sample.as_deref_mut() {
+            Some(on_sample) => on_sample(sample),
+            None => self.dive.samples.push(sample),
+        }
+    }
 }
 
 /// Read an arbitrary scalar/struct field from the parser.
@@ -163,8 +354,7 @@ unsafe fn get_field<T>(
     ctx: &str,
 ) -> Result<Option<T>> {
     let mut value = MaybeUninit::<T>::uninit();
-    let status =
-        unsafe { ffi::dc_parser_get_field(parser, field, idx, value.as_mut_ptr().cast()) };
+    let status = unsafe { ffi::dc_parser_get_field(parser, field, idx, value.as_mut_ptr().cast()) };
     if Status::check_unsupported(status, ctx)? {
         Ok(Some(unsafe { value.assume_init() }))
     } else {
@@ -172,7 +362,7 @@ unsafe fn get_field<T>(
     }
 }
 
-fn parse_fields(parser: *mut ffi::dc_parser_t) -> Result<Dive> {
+fn parse_fields(parser: *mut ffi::dc_parser_t, options: &ParseOptions) -> Result<Dive> {
     let mut dive = Dive::default();
 
     // Datetime (uses a dedicated FFI entry point, not dc_parser_get_field).
@@ -180,7 +370,12 @@ fn parse_fields(parser: *mut ffi::dc_parser_t) -> Result<Dive> {
     let status = unsafe { ffi::dc_parser_get_datetime(parser, dt.as_mut_ptr()) };
     if Status::check_unsupported(status, "failed to parse datetime")? {
         let dt = unsafe { dt.assume_init() };
-        dive.start = crate::datetime::ffi_to_timestamp(&dt)?;
+        let zoned = crate::datetime::ffi_to_zoned(&dt, &options.timezone.time_zone())?;
+        dive.start = zoned.timestamp();
+        dive.utc_offset = crate::datetime::ffi_utc_offset(&dt).or_else(|| {
+            // Record the assumed offset so `start_local` agrees with `start`.
+            (options.timezone != TimezonePolicy::Utc).then(|| zoned.offset().seconds())
+        });
     }
 
     // Required-ish scalar fields. If UNSUPPORTED, fall back to default.
@@ -221,19 +416,12 @@ fn parse_fields(parser: *mut ffi::dc_parser_t) -> Result<Dive> {
             "surface temperature",
         )?
     };
-    dive.atmospheric_pressure = unsafe {
-        get_field(
-            parser,
-            ffi::DC_FIELD_ATMOSPHERIC,
-            0,
-            "atmospheric pressure",
-        )?
-    };
+    dive.atmospheric_pressure =
+        unsafe { get_field(parser, ffi::DC_FIELD_ATMOSPHERIC, 0, "atmospheric pressure")? };
 
     // Gas mixes.
     let num_gases: u32 =
-        unsafe { get_field(parser, ffi::DC_FIELD_GASMIX_COUNT, 0, "gasmix count")? }
-            .unwrap_or(0);
+        unsafe { get_field(parser, ffi::DC_FIELD_GASMIX_COUNT, 0, "gasmix count")? }.unwrap_or(0);
     for i in 0..num_gases {
         if let Some(gm) =
             unsafe { get_field::<ffi::dc_gasmix_t>(parser, ffi::DC_FIELD_GASMIX, i, "gasmix")? }
@@ -255,10 +443,9 @@ fn parse_fields(parser: *mut ffi::dc_parser_t) -> Result<Dive> {
 
     // Dive mode — fall back to open-circuit if unsupported, matching the
     // previous behaviour.
-    let divemode = unsafe {
-        get_field::<ffi::dc_divemode_t>(parser, ffi::DC_FIELD_DIVEMODE, 0, "dive mode")?
-    }
-    .unwrap_or(ffi::DC_DIVEMODE_OC);
+    let divemode =
+        unsafe { get_field::<ffi::dc_divemode_t>(parser, ffi::DC_FIELD_DIVEMODE, 0, "dive mode")? }
+            .unwrap_or(ffi::DC_DIVEMODE_OC);
     dive.dive_mode = DiveMode::from(divemode);
 
     // Deco model.
@@ -269,25 +456,18 @@ fn parse_fields(parser: *mut ffi::dc_parser_t) -> Result<Dive> {
     }
 
     // Salinity and location.
-    dive.salinity = unsafe {
-        get_field::<ffi::dc_salinity_t>(parser, ffi::DC_FIELD_SALINITY, 0, "salinity")?
-    }
-    .map(Salinity::from);
-    dive.location = unsafe {
-        get_field::<ffi::dc_location_t>(parser, ffi::DC_FIELD_LOCATION, 0, "location")?
-    }
-    .map(Location::from);
+    dive.salinity =
+        unsafe { get_field::<ffi::dc_salinity_t>(parser, ffi::DC_FIELD_SALINITY, 0, "salinity")? }
+            .map(Salinity::from);
+    dive.location =
+        unsafe { get_field::<ffi::dc_location_t>(parser, ffi::DC_FIELD_LOCATION, 0, "location")? }
+            .map(Location::from);
 
     // String fields (metadata). Iterate until the C library reports
     // UNSUPPORTED or returns NULL description/value pointers.
     for idx in 0u32.. {
         let Some(field) = (unsafe {
-            get_field::<ffi::dc_field_string_t>(
-                parser,
-                ffi::DC_FIELD_STRING,
-                idx,
-                "string field",
-            )?
+            get_field::<ffi::dc_field_string_t>(parser, ffi::DC_FIELD_STRING, idx, "string field")?
         }) else {
             break;
         };
@@ -316,9 +496,11 @@ extern "C" fn sample_callback(
                 let prev = std::mem::take(&mut parse_data.sample);
                 parse_data.sample = DiveSample::carry_forward(&prev);
                 parse_data.sample.time = Duration::from_millis(value.time as u64);
+                // Tanks report in every sample, so size for them up front.
+                parse_data.sample.pressure = Vec::with_capacity(prev.pressure.len());
 
                 if prev.time.as_millis() > 0 {
-                    parse_data.dive.samples.push(prev);
+                    parse_data.emit(prev);
                 }
             }
 
@@ -328,16 +510,11 @@ extern "C" fn sample_callback(
 
             ffi::DC_SAMPLE_PRESSURE => {
                 let idx = value.pressure.tank as usize;
-                let val = value.pressure.value;
-                if let Some(p) = parse_data.sample.pressure.get_mut(idx) {
-                    *p = val;
-                } else {
-                    // Extend to fit
-                    while parse_data.sample.pressure.len() <= idx {
-                        parse_data.sample.pressure.push(0.0);
-                    }
-                    parse_data.sample.pressure[idx] = val;
+                let pressure = &mut parse_data.sample.pressure;
+                if pressure.len() <= idx {
+                    pressure.resize(idx + 1, 0.0);
                 }
+                pressure[idx] = value.pressure.value;
             }
 
             ffi::DC_SAMPLE_TEMPERATURE => {
@@ -368,7 +545,7 @@ extern "C" fn sample_callback(
 
             ffi::DC_SAMPLE_GASMIX => {
                 let idx = value.gasmix as usize;
-                parse_data.sample.gasmix = parse_data.dive.gasmixes.get(idx).cloned();
+                parse_data.sample.gasmix = parse_data.dive.gasmixes.get(idx).copied();
             }
 
             ffi::DC_SAMPLE_O2SENSOR => {
@@ -407,11 +584,26 @@ extern "C" fn sample_callback(
             }
 
             ffi::DC_SAMPLE_DECO => {
+                let tts = Duration::from_secs(value.deco.tts as u64);
+                let kind = DecoKind::new(value.deco.type_, value.deco.depth);
+                // `deco` still holds the previous sample's state here.
+                let state_changed = parse_data.sample.deco.is_none_or(|prev| {
+                    std::mem::discriminant(&prev.kind) != std::mem::discriminant(&kind)
+                });
                 parse_data.sample.deco = Some(Deco {
-                    kind: DecoKind::new(value.deco.type_, value.deco.depth),
+                    kind,
                     time: Duration::from_secs(value.deco.time as u64),
-                    tts: Duration::from_secs(value.deco.tts as u64),
+                    tts,
                 });
+                // Upstream libdivecomputer reports TTS only as part of the
+                // deco sample; DC_SAMPLE_TTS comes from the Subsurface fork.
+                // An estimate carried over from another deco state, e.g. the
+                // last stop before deco cleared, no longer applies.
+                if !tts.is_zero() {
+                    parse_data.sample.tts = Some(tts);
+                } else if state_changed {
+                    parse_data.sample.tts = None;
+                }
             }
 
             ffi::DC_SAMPLE_TTS => {
@@ -419,10 +611,107 @@ extern "C" fn sample_callback(
             }
 
             ffi::DC_SAMPLE_VENDOR => {
-                // Vendor samples are ignored for now.
+                let vendor = value.vendor;
+                let data = if vendor.data.is_null() {
+                    Vec::new()
+                } else {
+                    std::slice::from_raw_parts(vendor.data as *const u8, vendor.size as usize)
+                        .to_vec()
+                };
+                parse_data.sample.vendor.push(VendorData {
+                    kind: vendor.type_,
+                    data,
+                });
             }
 
             _ => {}
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ffi::dc_sample_value_t as Value;
+
+    #[test]
+    fn sample_callback_assembles_samples() {
+        let nitrox = Gasmix {
+            helium: 0.0,
+            oxygen: 0.32,
+            nitrogen: 0.68,
+            usage: GasUsage::None,
+        };
+        let mut dive = Dive {
+            gasmixes: vec![nitrox],
+            ..Default::default()
+        };
+        let mut parse_data = ParseData {
+            dive: &mut dive,
+            sample: DiveSample::default(),
+            on_sample: None,
+        };
+        let mut feed = |kind, value: Value| {
+            sample_callback(kind, &value, as_void_ptr(&mut parse_data));
+        };
+        let pressure = ffi::dc_sample_value_t__bindgen_ty_1 {
+            tank: 1,
+            value: 200.0,
+        };
+        for second in 1..=3 {
+            let time = second * 1000;
+            feed(ffi::DC_SAMPLE_TIME, Value { time });
+            feed(ffi::DC_SAMPLE_DEPTH, Value { depth: 10.0 });
+            feed(ffi::DC_SAMPLE_PRESSURE, Value { pressure });
+        }
+        feed(ffi::DC_SAMPLE_GASMIX, Value { gasmix: 0 });
+        let last = std::mem::take(&mut parse_data.sample);
+        parse_data.emit(last);
+
+        assert_eq!(dive.samples.len(), 3);
+        assert!(dive.samples.iter().all(|s| s.pressure == [0.0, 200.0]));
+        assert_eq!(dive.samples[2].time, Duration::from_secs(3));
+        assert_eq!(dive.samples[2].gasmix.map(|g| g.oxygen), Some(0.32));
+    }
+
+    #[test]
+    fn tts_resets_when_deco_clears() {
+        let mut dive = Dive::default();
+        let mut parse_data = ParseData {
+            dive: &mut dive,
+            sample: DiveSample::default(),
+            on_sample: None,
+        };
+        let mut feed = |kind, value: Value| {
+            sample_callback(kind, &value, as_void_ptr(&mut parse_data));
+        };
+        let deco = |type_, tts| Value {
+            deco: ffi::dc_sample_value_t__bindgen_ty_5 {
+                type_,
+                time: 60,
+                depth: 3.0,
+                tts,
+            },
+        };
+        feed(ffi::DC_SAMPLE_TIME, Value { time: 1000 });
+        feed(ffi::DC_SAMPLE_DECO, deco(ffi::DC_DECO_DECOSTOP, 300));
+        feed(ffi::DC_SAMPLE_TIME, Value { time: 2000 });
+        feed(ffi::DC_SAMPLE_DECO, deco(ffi::DC_DECO_DECOSTOP, 0));
+        feed(ffi::DC_SAMPLE_TIME, Value { time: 3000 });
+        feed(ffi::DC_SAMPLE_DECO, deco(ffi::DC_DECO_NDL, 0));
+        feed(ffi::DC_SAMPLE_TIME, Value { time: 4000 });
+        let last = std::mem::take(&mut parse_data.sample);
+        parse_data.emit(last);
+
+        let tts: Vec<_> = dive.samples.iter().map(|s| s.tts).collect();
+        assert_eq!(
+            tts,
+            [
+                Some(Duration::from_secs(300)),
+                Some(Duration::from_secs(300)),
+                None,
+                None,
+            ]
+        );
+    }
+}
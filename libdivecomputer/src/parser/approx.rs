@@ -0,0 +1,166 @@
+use super::types::{
+    Deco, DecoKind, Dive, DiveSample, Gasmix, Location, O2Sensor, Ppo2, Salinity, Tank,
+};
+
+fn close(a: f64, b: f64, epsilon: f64) -> bool {
+    a == b || (a - b).abs() <= epsilon
+}
+
+fn close_opt<T>(a: &Option<T>, b: &Option<T>, eq: impl Fn(&T, &T) -> bool) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn close_all<T>(a: &[T], b: &[T], eq: impl Fn(&T, &T) -> bool) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| eq(a, b))
+}
+
+impl Gasmix {
+    /// Whether the fractions match to within `epsilon` and the usage is the
+    /// same.
+    #[must_use]
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        close(self.helium, other.helium, epsilon)
+            && close(self.oxygen, other.oxygen, epsilon)
+            && close(self.nitrogen, other.nitrogen, epsilon)
+            && self.usage == other.usage
+    }
+}
+
+impl Tank {
+    /// Whether volume and pressures match to within `epsilon` and all other
+    /// fields are equal.
+    #[must_use]
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        self.gasmix_idx == other.gasmix_idx
+            && self.kind == other.kind
+            && close(self.volume, other.volume, epsilon)
+            && close(self.work_pressure, other.work_pressure, epsilon)
+            && close(self.begin_pressure, other.begin_pressure, epsilon)
+            && close(self.end_pressure, other.end_pressure, epsilon)
+            && self.usage == other.usage
+    }
+}
+
+impl DiveSample {
+    /// Whether every float reading matches to within `epsilon` and all other
+    /// fields are equal.
+    #[must_use]
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        let f = |a: &f64, b: &f64| close(*a, *b, epsilon);
+        let ppo2 = |a: &Ppo2, b: &Ppo2| a.sensor == b.sensor && f(&a.bar, &b.bar);
+        let o2_sensor = |a: &O2Sensor, b: &O2Sensor| {
+            a.sensor == b.sensor && f(&a.ppo2, &b.ppo2) && f(&a.millivolt, &b.millivolt)
+        };
+        let deco = |a: &Deco, b: &Deco| {
+            let kind = match (a.kind, b.kind) {
+                (DecoKind::DecoStop { depth: a }, DecoKind::DecoStop { depth: b })
+                | (DecoKind::DeepStop { depth: a }, DecoKind::DeepStop { depth: b })
+                | (DecoKind::SafetyStop { depth: a }, DecoKind::SafetyStop { depth: b }) => {
+                    f(&a, &b)
+                }
+                (a, b) => a == b,
+            };
+            kind && a.time == b.time && a.tts == b.tts
+        };
+
+        self.time == other.time
+            && f(&self.depth, &other.depth)
+            && close_opt(&self.gasmix, &other.gasmix, |a, b| a.approx_eq(b, epsilon))
+            && close_opt(&self.temperature, &other.temperature, f)
+            && self.events == other.events
+            && self.rbt == other.rbt
+            && self.heartbeat == other.heartbeat
+            && self.bearing == other.bearing
+            && close_opt(&self.setpoint, &other.setpoint, f)
+            && close_all(&self.ppo2, &other.ppo2, ppo2)
+            && close_all(&self.o2_sensor, &other.o2_sensor, o2_sensor)
+            && close_all(&self.pressure, &other.pressure, f)
+            && f(&self.cns, &other.cns)
+            && close_opt(&self.deco, &other.deco, deco)
+            && self.tts == other.tts
+            && self.vendor == other.vendor
+    }
+}
+
+impl Dive {
+    /// Whether every float field, including those of tanks, gas mixes and
+    /// samples, matches to within `epsilon` and all other fields
+    /// but [`schema_version`](Self::schema_version) are equal.
+    #[must_use]
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        let f = |a: &f64, b: &f64| close(*a, *b, epsilon);
+        let location = |a: &Location, b: &Location| {
+            f(&a.latitude, &b.latitude)
+                && f(&a.longitude, &b.longitude)
+                && f(&a.altitude, &b.altitude)
+        };
+        let salinity = |a: &Salinity, b: &Salinity| a.kind == b.kind && f(&a.density, &b.density);
+
+        self.fingerprint == other.fingerprint
+            && self.start == other.start
+            && self.utc_offset == other.utc_offset
+            && self.duration == other.duration
+            && f(&self.max_depth, &other.max_depth)
+            && close_opt(&self.avg_depth, &other.avg_depth, f)
+            && close_all(&self.gasmixes, &other.gasmixes, |a, b| {
+                a.approx_eq(b, epsilon)
+            })
+            && close_opt(&self.atmospheric_pressure, &other.atmospheric_pressure, f)
+            && close_opt(&self.temperature_surface, &other.temperature_surface, f)
+            && close_opt(&self.temperature_minimum, &other.temperature_minimum, f)
+            && close_opt(&self.temperature_maximum, &other.temperature_maximum, f)
+            && close_all(&self.tanks, &other.tanks, |a, b| a.approx_eq(b, epsilon))
+            && self.dive_mode == other.dive_mode
+            && self.deco_model == other.deco_model
+            && close_opt(&self.salinity, &other.salinity, salinity)
+            && close_opt(&self.location, &other.location, location)
+            && close_all(&self.samples, &other.samples, |a, b| {
+                a.approx_eq(b, epsilon)
+            })
+            && self.metadata == other.metadata
+            && self.raw == other.raw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn approx_eq_tolerates_float_noise_only() {
+        let dive = Dive {
+            max_depth: 18.5,
+            gasmixes: vec![Gasmix {
+                oxygen: 0.32,
+                nitrogen: 0.68,
+                ..Default::default()
+            }],
+            samples: vec![DiveSample {
+                time: Duration::from_secs(10),
+                depth: 3.2,
+                temperature: Some(21.0),
+                pressure: vec![208.5],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(dive, dive.clone());
+
+        let mut noisy = dive.clone();
+        noisy.max_depth += 1e-9;
+        noisy.samples[0].temperature = Some(21.0 - 1e-9);
+        noisy.gasmixes[0].oxygen = 0.1 + 0.22;
+        assert_ne!(dive, noisy);
+        assert!(dive.approx_eq(&noisy, 1e-6));
+        assert!(!dive.approx_eq(&noisy, 1e-12));
+
+        noisy.samples[0].time = Duration::from_secs(11);
+        assert!(!dive.approx_eq(&noisy, 1e-6));
+    }
+}
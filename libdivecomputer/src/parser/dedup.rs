@@ -0,0 +1,178 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use super::types::{Dive, Fingerprint};
+
+/// How far apart start times and durations may be for
+/// [`Dive::same_as`] to call two fingerprint-less dives the same.
+const DEFAULT_TOLERANCE: Duration = Duration::from_secs(60);
+
+impl Dive {
+    /// Whether `self` and `other` are the same dive, e.g. downloaded twice.
+    ///
+    /// Dives that both carry a fingerprint are compared by fingerprint
+    /// alone. Otherwise, as with dives imported from other logbooks, they
+    /// match when their start times and durations are each within a minute,
+    /// which absorbs rounding and small clock corrections without merging
+    /// back-to-back dives.
+    #[must_use]
+    pub fn same_as(&self, other: &Dive) -> bool {
+        if !self.fingerprint.is_empty() && !other.fingerprint.is_empty() {
+            return self.fingerprint == other.fingerprint;
+        }
+        close_in_time(self, other.start, other.duration, DEFAULT_TOLERANCE)
+    }
+}
+
+fn close_in_time(
+    a: &Dive,
+    start: jiff::Timestamp,
+    duration: Duration,
+    tolerance: Duration,
+) -> bool {
+    a.start.duration_since(start).unsigned_abs() <= tolerance
+        && a.duration.abs_diff(duration) <= tolerance
+}
+
+/// Filters out dives that are already in a logbook, so repeated downloads
+/// or imports do not add duplicates.
+///
+/// Seed it with the dives already stored, then pass new ones through
+/// [`is_new`](Self::is_new) or [`filter`](Self::filter):
+///
+/// ```
+/// # use libdivecomputer::Dive;
+/// # use libdivecomputer::parser::dedup::Deduplicator;
+/// # fn demo(logbook: &[Dive], downloaded: Vec<Dive>) {
+/// let mut dedup = Deduplicator::new().with_known(logbook);
+/// let fresh: Vec<Dive> = dedup.filter(downloaded).collect();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Deduplicator {
+    fingerprints: HashSet<Fingerprint>,
+    /// Start, duration and whether it had a fingerprint, of every dive
+    /// seen, for the time heuristic.
+    seen: Vec<(jiff::Timestamp, Duration, bool)>,
+    tolerance: Option<Duration>,
+}
+
+impl Default for Deduplicator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deduplicator {
+    /// A deduplicator that knows no dives yet and matches like
+    /// [`Dive::same_as`].
+    pub fn new() -> Self {
+        Self {
+            fingerprints: HashSet::new(),
+            seen: Vec::new(),
+            tolerance: Some(DEFAULT_TOLERANCE),
+        }
+    }
+
+    /// How close start times and durations must be for dives without a
+    /// fingerprint to match; `None` matches by fingerprint only. Defaults
+    /// to one minute.
+    #[must_use]
+    pub fn time_tolerance(mut self, tolerance: Option<Duration>) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Record `dives` as already known.
+    #[must_use]
+    pub fn with_known<'a>(mut self, dives: impl IntoIterator<Item = &'a Dive>) -> Self {
+        for dive in dives {
+            self.insert(dive);
+        }
+        self
+    }
+
+    /// `true` the first time a dive is seen; `false` for a duplicate of a
+    /// known dive or of one passed in earlier.
+    pub fn is_new(&mut self, dive: &Dive) -> bool {
+        if self.contains(dive) {
+            return false;
+        }
+        self.insert(dive);
+        true
+    }
+
+    /// Whether `dive` matches a known dive, without recording it.
+    #[must_use]
+    pub fn contains(&self, dive: &Dive) -> bool {
+        if !dive.fingerprint.is_empty() && self.fingerprints.contains(&dive.fingerprint) {
+            return true;
+        }
+        // As in `same_as`, two fingerprinted dives are never matched by time.
+        let fingerprinted = !dive.fingerprint.is_empty();
+        self.tolerance.is_some_and(|tolerance| {
+            self.seen
+                .iter()
+                .filter(|&&(_, _, had_fingerprint)| !(fingerprinted && had_fingerprint))
+                .any(|&(start, duration, _)| close_in_time(dive, start, duration, tolerance))
+        })
+    }
+
+    /// Keep only the new dives of `dives`, recording them as it goes.
+    pub fn filter<'a, I>(&'a mut self, dives: I) -> impl Iterator<Item = Dive> + 'a
+    where
+        I: IntoIterator<Item = Dive>,
+        I::IntoIter: 'a,
+    {
+        dives.into_iter().filter(move |dive| self.is_new(dive))
+    }
+
+    fn insert(&mut self, dive: &Dive) {
+        let fingerprinted = !dive.fingerprint.is_empty();
+        if fingerprinted {
+            self.fingerprints.insert(dive.fingerprint.clone());
+        }
+        self.seen.push((dive.start, dive.duration, fingerprinted));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dive(fingerprint: &[u8], start: &str, minutes: u64) -> Dive {
+        Dive {
+            fingerprint: Fingerprint::from(fingerprint),
+            start: start.parse().unwrap(),
+            duration: Duration::from_secs(minutes * 60),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn same_as_prefers_fingerprints() {
+        let a = dive(&[1], "2025-06-01T10:00:00Z", 40);
+        assert!(a.same_as(&dive(&[1], "2025-06-02T10:00:00Z", 10)));
+        assert!(!a.same_as(&dive(&[2], "2025-06-01T10:00:00Z", 40)));
+        assert!(a.same_as(&dive(&[], "2025-06-01T10:00:30Z", 40)));
+        assert!(!a.same_as(&dive(&[], "2025-06-01T11:00:00Z", 40)));
+    }
+
+    #[test]
+    fn deduplicator_filters_known_and_repeated_dives() {
+        let known = [dive(&[1], "2025-06-01T10:00:00Z", 40)];
+        let mut dedup = Deduplicator::new().with_known(&known);
+        let downloaded = vec![
+            dive(&[1], "2025-06-01T10:00:00Z", 40),
+            dive(&[], "2025-06-01T10:00:20Z", 41),
+            dive(&[2], "2025-06-01T12:00:00Z", 30),
+            dive(&[2], "2025-06-01T12:00:00Z", 30),
+        ];
+        let fresh: Vec<_> = dedup.filter(downloaded).collect();
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].fingerprint, Fingerprint::from(vec![2]));
+
+        let mut strict = Deduplicator::new().time_tolerance(None).with_known(&known);
+        assert!(strict.is_new(&dive(&[], "2025-06-01T10:00:00Z", 40)));
+    }
+}
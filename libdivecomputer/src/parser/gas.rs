@@ -0,0 +1,308 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::types::{Dive, Gasmix, Tank, TankKind};
+
+/// Standard atmosphere in bar, used when the dive carries no surface pressure.
+pub(crate) const STANDARD_ATMOSPHERE: f64 = 1.01325;
+
+/// Sea-water density in kg/m³; libdivecomputer assumes the same default.
+pub(crate) const DEFAULT_WATER_DENSITY: f64 = 1025.0;
+
+/// Gas-management summary for a single cylinder, produced by
+/// [`Dive::tank_report`].
+///
+/// Pressures and volumes are `None` when the dive computer did not record
+/// enough to derive them (e.g. no transmitter, or no tank size configured).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TankReport {
+    /// Index of the cylinder in [`Dive::tanks`] and in each sample's
+    /// [`pressure`](super::DiveSample::pressure) readings.
+    pub tank_idx: usize,
+    /// Gas breathed from this cylinder, resolved from [`Tank::gasmix_idx`].
+    pub gasmix: Option<Gasmix>,
+    /// Water capacity in litres.
+    pub water_capacity: Option<f64>,
+    /// Pressure at the start of the dive, in bar.
+    pub begin_pressure: Option<f64>,
+    /// Pressure at the end of the dive, in bar.
+    pub end_pressure: Option<f64>,
+    /// Rule-of-thirds turn pressure in bar: a third of the starting gas used.
+    pub turn_pressure: Option<f64>,
+    /// Gas used, as pressure drop times water capacity (bar·L).
+    pub consumed_bar_litres: Option<f64>,
+    /// Gas used, expanded to surface pressure (litres).
+    pub consumed_surface_litres: Option<f64>,
+    /// Surface air consumption while breathing this cylinder, in litres per
+    /// minute at surface pressure.
+    pub sac: Option<f64>,
+}
+
+impl Dive {
+    /// Per-cylinder gas consumption, joining [`Dive::tanks`],
+    /// [`Dive::gasmixes`] and the per-sample
+    /// [`pressure`](super::DiveSample::pressure) readings.
+    ///
+    /// Begin/end pressures come from the tank record and fall back to the
+    /// first/last sample reading for that cylinder, since many computers only
+    /// report one or the other. SAC is averaged over the samples in which the
+    /// cylinder reported a pressure, or over the whole dive if it never did.
+    #[must_use]
+    pub fn tank_report(&self) -> Vec<TankReport> {
+        let (surface, density) = self.surface_and_density();
+        self.tanks
+            .iter()
+            .enumerate()
+            .map(|(idx, tank)| self.report_for(idx, tank, surface, density))
+            .collect()
+    }
+
+    /// Surface air consumption over the whole dive, in litres per minute at
+    /// surface pressure: the gas used from every cylinder with a known size
+    /// and pressure drop (see [`tank_report`](Self::tank_report)), over the
+    /// dive's duration and average depth.
+    ///
+    /// Cylinders the computer could not account for are left out, so with
+    /// an unmonitored stage or bailout the figure undercounts.
+    #[must_use]
+    pub fn sac(&self) -> Option<f64> {
+        let litres = self
+            .tank_report()
+            .iter()
+            .filter_map(|tank| tank.consumed_surface_litres)
+            .reduce(|a, b| a + b)?;
+        let depth = self
+            .avg_depth
+            .or_else(|| mean(self.samples.iter().map(|s| s.depth)))?;
+        if self.duration.is_zero() {
+            return None;
+        }
+        let (surface, density) = self.surface_and_density();
+        let ambient = ambient_pressure(depth, surface, density);
+        Some(litres / (self.duration.as_secs_f64() / 60.0) / (ambient / surface))
+    }
+
+    fn surface_and_density(&self) -> (f64, f64) {
+        let surface = self.atmospheric_pressure.unwrap_or(STANDARD_ATMOSPHERE);
+        let density = self
+            .salinity
+            .map(|s| s.density)
+            .filter(|d| *d > 0.0)
+            .unwrap_or(DEFAULT_WATER_DENSITY);
+        (surface, density)
+    }
+
+    fn report_for(&self, idx: usize, tank: &Tank, surface: f64, density: f64) -> TankReport {
+        let readings: Vec<_> = self
+            .samples
+            .iter()
+            .filter_map(|s| match s.pressure.get(idx) {
+                Some(&p) if p > 0.0 => Some((s, p)),
+                _ => None,
+            })
+            .collect();
+
+        let begin_pressure =
+            positive(tank.begin_pressure).or_else(|| readings.first().map(|(_, p)| *p));
+        let end_pressure = positive(tank.end_pressure).or_else(|| readings.last().map(|(_, p)| *p));
+        let water_capacity = water_capacity(tank);
+
+        let consumed_bar_litres = match (begin_pressure, end_pressure, water_capacity) {
+            (Some(begin), Some(end), Some(capacity)) if begin >= end => {
+                Some((begin - end) * capacity)
+            }
+            _ => None,
+        };
+        let consumed_surface_litres = consumed_bar_litres.map(|bar_l| bar_l / surface);
+
+        // Average ambient pressure and elapsed time while this cylinder was
+        // in use; without per-tank readings, the whole dive is the best guess.
+        let (elapsed, mean_depth) = match (readings.first(), readings.last()) {
+            (Some((first, _)), Some((last, _))) if last.time > first.time => {
+                let depths = readings.iter().map(|(s, _)| s.depth);
+                (last.time - first.time, mean(depths))
+            }
+            _ => (
+                self.duration,
+                self.avg_depth
+                    .or_else(|| mean(self.samples.iter().map(|s| s.depth))),
+            ),
+        };
+        let sac = match (consumed_surface_litres, mean_depth) {
+            (Some(litres), Some(depth)) if elapsed > Duration::ZERO => {
+                let ambient = ambient_pressure(depth, surface, density);
+                Some(litres / (elapsed.as_secs_f64() / 60.0) / (ambient / surface))
+            }
+            _ => None,
+        };
+
+        TankReport {
+            tank_idx: idx,
+            gasmix: tank.gasmix_idx.and_then(|i| self.gasmixes.get(i)).copied(),
+            water_capacity,
+            begin_pressure,
+            end_pressure,
+            turn_pressure: begin_pressure.map(|begin| begin - begin / 3.0),
+            consumed_bar_litres,
+            consumed_surface_litres,
+            sac,
+        }
+    }
+}
+
+/// Absolute pressure in bar at `depth` metres below a surface at `surface`
+/// bar, for water of the given density.
+pub(crate) fn ambient_pressure(depth: f64, surface: f64, density: f64) -> f64 {
+    const GRAVITY: f64 = 9.806_65;
+    surface + depth * density * GRAVITY / 100_000.0
+}
+
+/// Water capacity of `tank` in litres, if it can be derived.
+fn water_capacity(tank: &Tank) -> Option<f64> {
+    let volume = positive(tank.volume)?;
+    match tank.kind {
+        // libdivecomputer reports litres for both; the kind only says how
+        // the cylinder was sized.
+        TankKind::Metric | TankKind::Imperial => Some(volume),
+        TankKind::None => None,
+    }
+}
+
+fn positive(value: f64) -> Option<f64> {
+    (value > 0.0).then_some(value)
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, n), v| (sum + v, n + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::DiveSample;
+
+    fn sample(minutes: u64, depth: f64, pressure: f64) -> DiveSample {
+        DiveSample {
+            time: Duration::from_secs(minutes * 60),
+            depth,
+            pressure: vec![pressure],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tank_report_from_samples() {
+        let dive = Dive {
+            duration: Duration::from_secs(40 * 60),
+            atmospheric_pressure: Some(1.0),
+            gasmixes: vec![Gasmix::default()],
+            tanks: vec![Tank {
+                gasmix_idx: Some(0),
+                kind: TankKind::Metric,
+                volume: 12.0,
+                ..Default::default()
+            }],
+            samples: vec![sample(0, 10.0, 200.0), sample(40, 10.0, 100.0)],
+            ..Default::default()
+        };
+
+        let report = dive.tank_report();
+        assert_eq!(report.len(), 1);
+        let tank = &report[0];
+        assert_eq!(tank.begin_pressure, Some(200.0));
+        assert_eq!(tank.end_pressure, Some(100.0));
+        assert_eq!(tank.consumed_bar_litres, Some(1200.0));
+        assert_eq!(tank.consumed_surface_litres, Some(1200.0));
+        assert!((tank.turn_pressure.unwrap() - 133.333).abs() < 0.01);
+
+        // 1200 L over 40 min at ~2 bar ambient.
+        let sac = tank.sac.unwrap();
+        assert!((sac - 14.96).abs() < 0.05, "sac = {sac}");
+        assert!((tank.gasmix.unwrap().oxygen - 0.21).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn tank_report_prefers_tank_pressures() {
+        let dive = Dive {
+            tanks: vec![Tank {
+                begin_pressure: 210.0,
+                end_pressure: 50.0,
+                ..Default::default()
+            }],
+            samples: vec![sample(0, 0.0, 190.0), sample(1, 0.0, 60.0)],
+            ..Default::default()
+        };
+
+        let tank = &dive.tank_report()[0];
+        assert_eq!(tank.begin_pressure, Some(210.0));
+        assert_eq!(tank.end_pressure, Some(50.0));
+        // No tank size, so no volume-derived figures.
+        assert_eq!(tank.water_capacity, None);
+        assert_eq!(tank.consumed_bar_litres, None);
+        assert_eq!(tank.sac, None);
+    }
+
+    #[test]
+    fn dive_sac_sums_cylinders() {
+        let tank = Tank {
+            kind: TankKind::Metric,
+            volume: 12.0,
+            begin_pressure: 200.0,
+            end_pressure: 150.0,
+            ..Default::default()
+        };
+        let dive = Dive {
+            duration: Duration::from_secs(30 * 60),
+            avg_depth: Some(0.0),
+            atmospheric_pressure: Some(1.0),
+            tanks: vec![tank.clone(), tank, Tank::default()],
+            ..Default::default()
+        };
+
+        // 2 × 600 L over 30 min at the surface.
+        let sac = dive.sac().unwrap();
+        assert!((sac - 40.0).abs() < 1e-9, "sac = {sac}");
+        assert_eq!(Dive::default().sac(), None);
+    }
+
+    #[test]
+    fn dive_sac_imperial_tank() {
+        let dive = Dive {
+            duration: Duration::from_secs(20 * 60),
+            avg_depth: Some(0.0),
+            atmospheric_pressure: Some(1.0),
+            tanks: vec![Tank {
+                kind: TankKind::Imperial,
+                volume: 11.1,
+                work_pressure: 207.0,
+                begin_pressure: 200.0,
+                end_pressure: 100.0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // 1110 L over 20 min at the surface, same as a metric 11.1 L tank.
+        let sac = dive.sac().unwrap();
+        assert!((sac - 55.5).abs() < 1e-9, "sac = {sac}");
+    }
+
+    #[test]
+    fn imperial_water_capacity() {
+        // An AL80 as libdivecomputer reports it: already in litres.
+        let al80 = Tank {
+            kind: TankKind::Imperial,
+            volume: 11.1,
+            work_pressure: 207.0,
+            ..Default::default()
+        };
+        assert_eq!(water_capacity(&al80), Some(11.1));
+        let no_kind = Tank {
+            volume: 11.1,
+            ..Default::default()
+        };
+        assert_eq!(water_capacity(&no_kind), None);
+    }
+}
@@ -0,0 +1,213 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::types::Dive;
+
+/// One point of a depth/temperature series for charting, produced by
+/// [`Dive::profile`], [`Dive::downsample`] and [`Dive::resample`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ProfilePoint {
+    /// Offset from dive start.
+    #[serde(with = "super::types::seconds")]
+    pub time: Duration,
+    /// Depth in metres.
+    pub depth: f64,
+    /// Water temperature in °C, carried forward from the last sample that
+    /// reported one, since most computers record it far less often than
+    /// depth.
+    pub temperature: Option<f64>,
+}
+
+impl Dive {
+    /// The dive's depth and temperature, one point per sample.
+    #[must_use]
+    pub fn profile(&self) -> Vec<ProfilePoint> {
+        let mut temperature = None;
+        self.samples
+            .iter()
+            .map(|sample| {
+                temperature = sample.temperature.or(temperature);
+                ProfilePoint {
+                    time: sample.time,
+                    depth: sample.depth,
+                    temperature,
+                }
+            })
+            .collect()
+    }
+
+    /// At most `max_points` points of the [`profile`](Self::profile) that
+    /// still look like it when plotted, for charts of dives with far more
+    /// samples than the chart has pixels (a long freedive session can have
+    /// tens of thousands).
+    ///
+    /// Uses Largest-Triangle-Three-Buckets on depth over time: the first and
+    /// last samples are always kept, and from each bucket in between the
+    /// sample that changes the shape most, so peaks such as the maximum
+    /// depth survive where averaging would flatten them. Points are actual
+    /// samples, not interpolated. Returns the whole profile if it is already
+    /// short enough.
+    #[must_use]
+    pub fn downsample(&self, max_points: usize) -> Vec<ProfilePoint> {
+        lttb(self.profile(), max_points)
+    }
+
+    /// The [`profile`](Self::profile) at evenly spaced times, `interval`
+    /// apart from the first sample to the last, with depth interpolated
+    /// linearly between samples. Suits charts or exports that want a fixed
+    /// time step whatever the computer's sample rate. A zero `interval`
+    /// returns the profile unchanged.
+    #[must_use]
+    pub fn resample(&self, interval: Duration) -> Vec<ProfilePoint> {
+        let profile = self.profile();
+        let (Some(first), Some(last)) = (profile.first(), profile.last()) else {
+            return profile;
+        };
+        if interval.is_zero() {
+            return profile;
+        }
+        let mut points = Vec::new();
+        let mut next = 0;
+        let mut time = first.time;
+        while time <= last.time {
+            // The first sample at or after `time`; `profile` is in time
+            // order.
+            while profile[next].time < time {
+                next += 1;
+            }
+            let after = profile[next];
+            let point = match next.checked_sub(1).map(|i| profile[i]) {
+                Some(before) if after.time > time => {
+                    let span = (after.time - before.time).as_secs_f64();
+                    let t = (time - before.time).as_secs_f64() / span;
+                    ProfilePoint {
+                        time,
+                        depth: before.depth + (after.depth - before.depth) * t,
+                        temperature: before.temperature,
+                    }
+                }
+                _ => ProfilePoint { time, ..after },
+            };
+            points.push(point);
+            time += interval;
+        }
+        points
+    }
+}
+
+/// Largest-Triangle-Three-Buckets (Steinarsson, 2013) over depth against
+/// time.
+fn lttb(points: Vec<ProfilePoint>, threshold: usize) -> Vec<ProfilePoint> {
+    let n = points.len();
+    if threshold >= n {
+        return points;
+    }
+    match threshold {
+        0 => return Vec::new(),
+        1 => return vec![points[0]],
+        2 => return vec![points[0], points[n - 1]],
+        _ => {}
+    }
+
+    let xy = |p: &ProfilePoint| (p.time.as_secs_f64(), p.depth);
+    // Buckets split the points between the first and the last.
+    let every = (n - 2) as f64 / (threshold - 2) as f64;
+    let bucket = |i: usize| {
+        let start = (i as f64 * every) as usize + 1;
+        let end = (((i + 1) as f64 * every) as usize + 1).min(n - 1);
+        start..end.max(start + 1)
+    };
+
+    let mut sampled = Vec::with_capacity(threshold);
+    sampled.push(points[0]);
+    let mut a = 0;
+    for i in 0..threshold - 2 {
+        // The next bucket's average stands in for the point not yet picked;
+        // the last bucket's neighbour is the last point.
+        let next = if i + 1 < threshold - 2 {
+            bucket(i + 1)
+        } else {
+            n - 1..n
+        };
+        let len = next.len() as f64;
+        let (avg_x, avg_y) = points[next]
+            .iter()
+            .map(xy)
+            .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x / len, sy + y / len));
+
+        let (ax, ay) = xy(&points[a]);
+        let range = bucket(i);
+        let picked = range
+            .clone()
+            .max_by(|&p, &q| {
+                let area = |j: usize| {
+                    let (x, y) = xy(&points[j]);
+                    ((ax - avg_x) * (y - ay) - (ax - x) * (avg_y - ay)).abs()
+                };
+                area(p).total_cmp(&area(q))
+            })
+            .unwrap_or(range.start);
+        sampled.push(points[picked]);
+        a = picked;
+    }
+    sampled.push(points[n - 1]);
+    sampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::DiveSample;
+
+    fn dive(depths: impl IntoIterator<Item = f64>, step: Duration) -> Dive {
+        Dive {
+            samples: (0u32..)
+                .zip(depths)
+                .map(|(i, depth)| DiveSample {
+                    time: step * i,
+                    depth,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn profile_carries_temperature_forward() {
+        let mut dive = dive([0.0, 5.0, 10.0], Duration::from_secs(10));
+        dive.samples[1].temperature = Some(18.0);
+        let temperatures: Vec<_> = dive.profile().iter().map(|p| p.temperature).collect();
+        assert_eq!(temperatures, [None, Some(18.0), Some(18.0)]);
+    }
+
+    #[test]
+    fn downsample_keeps_ends_and_peak() {
+        // Down to 40 m and back up over 50 000 one-second samples.
+        let depths = (0..50_000).map(|i| 40.0 - (f64::from(i) - 25_000.0).abs() / 625.0);
+        let dive = dive(depths, Duration::from_secs(1));
+
+        let points = dive.downsample(500);
+        assert_eq!(points.len(), 500);
+        assert_eq!(points[0].time, Duration::ZERO);
+        assert_eq!(points[499].time, Duration::from_secs(49_999));
+        assert!(points.windows(2).all(|w| w[0].time < w[1].time));
+        let max = points.iter().map(|p| p.depth).fold(0.0, f64::max);
+        assert_eq!(max, 40.0);
+
+        assert_eq!(dive.downsample(60_000).len(), 50_000);
+        assert_eq!(dive.downsample(2).len(), 2);
+    }
+
+    #[test]
+    fn resample_interpolates_depth() {
+        let dive = dive([0.0, 10.0, 0.0], Duration::from_secs(10));
+        let points = dive.resample(Duration::from_secs(5));
+        let depths: Vec<_> = points.iter().map(|p| p.depth).collect();
+        assert_eq!(depths, [0.0, 5.0, 10.0, 5.0, 0.0]);
+        assert_eq!(points[4].time, Duration::from_secs(20));
+        assert_eq!(dive.resample(Duration::ZERO).len(), 3);
+        assert!(Dive::default().resample(Duration::from_secs(1)).is_empty());
+    }
+}
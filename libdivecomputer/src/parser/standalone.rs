@@ -0,0 +1,113 @@
+use crate::context::Context;
+use crate::descriptor::Descriptor;
+use crate::error::Result;
+
+use super::types::{Dive, Fingerprint};
+use super::{ParseOptions, Parser};
+
+/// Parses saved dive records of one model, without looking the model up
+/// again for every record.
+///
+/// [`Descriptor::find_by_name`] walks the whole descriptor catalog, so
+/// looking it up per dive dominates bulk re-imports of thousands of raw
+/// records. A `StandaloneParser` finds it once and keeps the
+/// [`ParseOptions`] with it. libdivecomputer binds each `dc_parser_t` to one
+/// record, so a fresh one is still created per dive; pair
+/// [`parse_into`](Self::parse_into) with a reused [`Dive`] to save the
+/// sample allocations as well:
+///
+/// ```no_run
+/// # use libdivecomputer::{Context, Dive, Fingerprint, parser::StandaloneParser};
+/// # fn reimport(ctx: &Context, archive: &[(Fingerprint, Vec<u8>)]) -> libdivecomputer::Result<()> {
+/// let parser = StandaloneParser::for_model(ctx, "Shearwater Perdix")?;
+/// let mut dive = Dive::default();
+/// for (fingerprint, data) in archive {
+///     parser.parse_into(fingerprint, data, &mut dive)?;
+///     // use `dive` before the next iteration overwrites it
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct StandaloneParser<'c> {
+    ctx: &'c Context,
+    desc: Descriptor,
+    options: ParseOptions,
+}
+
+impl<'c> StandaloneParser<'c> {
+    /// A parser for records saved from `desc`'s model.
+    pub fn new(ctx: &'c Context, desc: Descriptor) -> Self {
+        Self {
+            ctx,
+            desc,
+            options: ParseOptions::default(),
+        }
+    }
+
+    /// A parser for the model `name`, as for [`Descriptor::find_by_name`].
+    pub fn for_model(ctx: &'c Context, name: &str) -> Result<Self> {
+        Ok(Self::new(ctx, Descriptor::find_by_name(name)?))
+    }
+
+    /// Use `options` for every record parsed.
+    #[must_use]
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The model this parser reads.
+    pub fn descriptor(&self) -> &Descriptor {
+        &self.desc
+    }
+
+    /// A [`Parser`] for one record, with this parser's options.
+    #[must_use = "the created Parser owns a C allocation"]
+    pub fn parser(&self, data: &[u8]) -> Result<Parser> {
+        Ok(Parser::from_descriptor(self.ctx, &self.desc, data)?.with_options(self.options.clone()))
+    }
+
+    /// Parse one record's fields and samples. See [`Parser::parse`].
+    #[must_use = "parsed dive data should not be silently discarded"]
+    pub fn parse(&self, fingerprint: &Fingerprint, data: &[u8]) -> Result<Dive> {
+        self.parser(data)?.parse(fingerprint)
+    }
+
+    /// Parse one record's header fields only. See [`Parser::parse_header`].
+    #[must_use = "parsed dive data should not be silently discarded"]
+    pub fn parse_header(&self, fingerprint: &Fingerprint, data: &[u8]) -> Result<Dive> {
+        self.parser(data)?.parse_header(fingerprint)
+    }
+
+    /// Parse one record into `dive`, reusing its buffers. See
+    /// [`Parser::parse_into`].
+    pub fn parse_into(
+        &self,
+        fingerprint: &Fingerprint,
+        data: &[u8],
+        dive: &mut Dive,
+    ) -> Result<()> {
+        self.parser(data)?.parse_into(fingerprint, dive)
+    }
+
+    /// Whether `data` looks like a record of this model. See
+    /// [`Parser::can_parse`].
+    pub fn can_parse(&self, data: &[u8]) -> bool {
+        Parser::can_parse(self.ctx, &self.desc, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standalone_parser_keeps_model() {
+        let ctx = Context::new().unwrap();
+        let parser = StandaloneParser::for_model(&ctx, "Suunto EON Steel").unwrap();
+        assert_eq!(parser.descriptor().product(), "EON Steel");
+        assert!(!parser.can_parse(&[]));
+        assert!(StandaloneParser::for_model(&ctx, "Nonexistent Device 9999").is_err());
+    }
+}
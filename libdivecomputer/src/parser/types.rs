@@ -8,20 +8,51 @@ use std::{
 use libdivecomputer_sys as ffi;
 use serde::{Deserialize, Serialize};
 
-use crate::{common::EventKind, error::LibError};
+use crate::{
+    common::{EventCategory, EventKind, SampleFlag, Severity},
+    error::LibError,
+    units::Pressure,
+};
 
 /// A parsed dive. Produced by [`Parser::parse`](crate::parser::Parser::parse)
 /// from the raw bytes the C library hands back for a single dive record.
 ///
 /// Most fields are `Option` or empty collections when the dive computer did
 /// not record that datum; defaults come from [`Default::default`].
+///
+/// # Serialization
+///
+/// Dives saved with serde by any earlier release deserialize with this one.
+/// Fields added since are filled from their defaults; a change that cannot
+/// be expressed that way bumps [`Dive::SCHEMA_VERSION`] and keeps reading
+/// the old form. The crate's `serde_compat` tests hold a dive saved by each
+/// version and check that it still loads.
+///
+/// Durations are written as seconds (`2400`, `10.5`) or, wrapped in
+/// [`WithDurations`], as ISO 8601 strings (`"PT40M"`). Both read back
+/// either way, as does the `{secs, nanos}` form written before schema
+/// versioning.
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Dive {
+    /// Version of the serialized form this dive was read from: 0 for data
+    /// written before versioning, and for dives that were not deserialized.
+    /// Always serialized as [`Dive::SCHEMA_VERSION`], since that is the form
+    /// written. Says where the data came from rather than what it is, so
+    /// equality ignores it.
+    #[serde(serialize_with = "serialize_schema_version")]
+    pub schema_version: u32,
     /// Opaque per-dive identifier; stable across downloads for the same dive.
     pub fingerprint: Fingerprint,
     /// Dive start time (UTC).
     pub start: jiff::Timestamp,
+    /// UTC offset of the dive computer's clock at dive start, in seconds, for
+    /// computers that record one, or the offset assumed by a
+    /// [`TimezonePolicy`](crate::parser::TimezonePolicy) other than `Utc`.
+    /// See [`Dive::start_local`].
+    pub utc_offset: Option<i32>,
     /// Total dive duration.
+    #[serde(with = "seconds")]
     pub duration: Duration,
     /// Maximum depth reached, in metres.
     pub max_depth: f64,
@@ -54,11 +85,106 @@ pub struct Dive {
     /// record (e.g. `STRING_KEY_SERIAL_NUMBER`,
     /// `STRING_KEY_FIRMWARE_VERSION`).
     pub metadata: HashMap<String, String>,
+    /// The dive record exactly as the computer sent it, when downloaded with
+    /// [`DownloadOptions::keep_raw`](crate::DownloadOptions::keep_raw).
+    /// Archive it to re-parse later with
+    /// [`Parser::from_descriptor`](crate::Parser::from_descriptor), e.g. after
+    /// a libdivecomputer upgrade decodes more fields. Serialized as hex.
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "hex_bytes")]
+    pub raw: Vec<u8>,
+}
+
+impl PartialEq for Dive {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            schema_version: _,
+            fingerprint,
+            start,
+            utc_offset,
+            duration,
+            max_depth,
+            avg_depth,
+            gasmixes,
+            atmospheric_pressure,
+            temperature_surface,
+            temperature_minimum,
+            temperature_maximum,
+            tanks,
+            dive_mode,
+            deco_model,
+            salinity,
+            location,
+            samples,
+            metadata,
+            raw,
+        } = self;
+        *fingerprint == other.fingerprint
+            && *start == other.start
+            && *utc_offset == other.utc_offset
+            && *duration == other.duration
+            && *max_depth == other.max_depth
+            && *avg_depth == other.avg_depth
+            && *gasmixes == other.gasmixes
+            && *atmospheric_pressure == other.atmospheric_pressure
+            && *temperature_surface == other.temperature_surface
+            && *temperature_minimum == other.temperature_minimum
+            && *temperature_maximum == other.temperature_maximum
+            && *tanks == other.tanks
+            && *dive_mode == other.dive_mode
+            && *deco_model == other.deco_model
+            && *salinity == other.salinity
+            && *location == other.location
+            && *samples == other.samples
+            && *metadata == other.metadata
+            && *raw == other.raw
+    }
+}
+
+fn serialize_schema_version<S: serde::Serializer>(
+    _: &u32,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u32(Dive::SCHEMA_VERSION)
+}
+
+impl Dive {
+    /// Version of the serialized form written by this release. See
+    /// [`Dive`]'s serialization notes.
+    pub const SCHEMA_VERSION: u32 = 1;
+
+    /// Dive start as the wall-clock time the dive computer displayed.
+    ///
+    /// Uses [`utc_offset`](Self::utc_offset) when the computer recorded one.
+    /// Computers without timezone support keep local time on their clock and,
+    /// under the default [`TimezonePolicy`](crate::parser::TimezonePolicy),
+    /// `start` holds that wall-clock time as if it were UTC, so the result is
+    /// returned in UTC — its civil date and time still match the display.
+    #[must_use]
+    pub fn start_local(&self) -> jiff::Zoned {
+        let tz = self
+            .utc_offset
+            .and_then(|secs| jiff::tz::Offset::from_seconds(secs).ok())
+            .map_or(jiff::tz::TimeZone::UTC, jiff::tz::TimeZone::fixed);
+        self.start.to_zoned(tz)
+    }
+
+    /// Set [`start`](Self::start) and [`utc_offset`](Self::utc_offset) from
+    /// a local start time, so [`start_local`](Self::start_local) shows the
+    /// same wall-clock time and offset back.
+    pub fn set_start_local(&mut self, start: &jiff::Zoned) {
+        self.start = start.timestamp();
+        self.utc_offset = Some(start.offset().seconds());
+    }
 }
 
 /// Opaque per-dive identifier as used by libdivecomputer's incremental
 /// download. Two dives with the same fingerprint are the same dive.
-#[derive(Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Serialized as the uppercase hex string of [`Display`](fmt::Display) and
+/// [`FromStr`], the form the download API takes; binary formats such as CBOR
+/// get the raw bytes. The `{"data": [..]}` form written before schema
+/// versioning still deserializes.
+#[derive(Default, Clone, PartialEq, Eq, Hash)]
 pub struct Fingerprint {
     pub(crate) data: Vec<u8>,
 }
@@ -108,6 +234,26 @@ impl Fingerprint {
     }
 }
 
+impl FromStr for Fingerprint {
+    type Err = LibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+impl Serialize for Fingerprint {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        hex_bytes::serialize(&self.data, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Fingerprint {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        hex_bytes::deserialize_any(deserializer).map(Self::from)
+    }
+}
+
 impl TryFrom<String> for Fingerprint {
     type Error = LibError;
 
@@ -162,7 +308,8 @@ impl fmt::Debug for Fingerprint {
 }
 
 /// Water salinity + density at dive start.
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Salinity {
     /// Salinity kind (fresh or salt).
     pub kind: SalinityKind,
@@ -190,7 +337,7 @@ impl Display for Salinity {
 }
 
 /// Water type for [`Salinity`].
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum SalinityKind {
     /// Fresh water.
@@ -210,7 +357,8 @@ impl Display for SalinityKind {
 }
 
 /// GPS location of the dive site, as tagged by the device.
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Location {
     /// Latitude in degrees (WGS-84).
     pub latitude: f64,
@@ -231,7 +379,7 @@ impl From<ffi::dc_location_t> for Location {
 }
 
 /// Dive mode — the high-level style of diving reported by the computer.
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum DiveMode {
     /// Mode not recorded or unknown.
@@ -300,7 +448,7 @@ impl fmt::Display for DiveMode {
 }
 
 /// Decompression model used by the dive computer, plus its parameters.
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum DecoModel {
     /// Model not recorded or unknown.
@@ -332,6 +480,41 @@ pub enum DecoModel {
     },
 }
 
+impl DecoModel {
+    /// Short human-readable label for the deco setting, e.g. `"GF 30/85"` or
+    /// `"VPM-B +3"`.
+    ///
+    /// The conservatism value means something different for every model and
+    /// vendor, so it is shown as a signed offset next to the model name
+    /// rather than translated; a zero offset is omitted.
+    pub fn description(&self) -> String {
+        let (name, conservatism) = match self {
+            Self::None => return "Unknown".to_string(),
+            Self::Buhlmann {
+                conservatism,
+                low,
+                high,
+            } => {
+                let name = if *low == 0 && *high == 0 {
+                    "Bühlmann".to_string()
+                } else {
+                    format!("GF {low}/{high}")
+                };
+                (name, *conservatism)
+            }
+            Self::Vpm { conservatism } => ("VPM-B".to_string(), *conservatism),
+            Self::Rgbm { conservatism } => ("RGBM".to_string(), *conservatism),
+            Self::Dciem { conservatism } => ("DCIEM".to_string(), *conservatism),
+        };
+
+        if conservatism == 0 {
+            name
+        } else {
+            format!("{name} {conservatism:+}")
+        }
+    }
+}
+
 impl From<ffi::dc_decomodel_t> for DecoModel {
     fn from(value: ffi::dc_decomodel_t) -> Self {
         unsafe {
@@ -357,15 +540,15 @@ impl From<ffi::dc_decomodel_t> for DecoModel {
 }
 
 /// A single cylinder used during a dive.
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Tank {
     /// Index into `Dive::gasmixes` for the gas in this tank; `None` if the
     /// device didn't associate a gas mix with the tank.
     pub gasmix_idx: Option<usize>,
-    /// Volume encoding (metric vs. imperial).
+    /// How the cylinder was sized (metric vs. imperial).
     pub kind: TankKind,
-    /// Cylinder volume. Units depend on [`TankKind`] — litres for metric,
-    /// cubic feet for imperial.
+    /// Water capacity in litres, whatever the [`TankKind`].
     pub volume: f64,
     /// Working pressure in bar (0 if not reported).
     pub work_pressure: f64,
@@ -397,17 +580,18 @@ impl From<ffi::dc_tank_t> for Tank {
     }
 }
 
-/// Volume encoding for a cylinder. Affects the interpretation of
-/// [`Tank::volume`].
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+/// How a cylinder's size was specified. [`Tank::volume`] is in litres
+/// either way.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum TankKind {
     /// Kind not recorded.
     #[default]
     None,
-    /// Volume is in litres (water capacity).
+    /// Sized by water capacity.
     Metric,
-    /// Volume is in cubic feet at working pressure.
+    /// Sized by free-gas volume at [`Tank::work_pressure`], as with US
+    /// cylinders.
     Imperial,
 }
 
@@ -422,7 +606,7 @@ impl From<u32> for TankKind {
 }
 
 /// How the cylinder is mounted/used during the dive.
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum TankUsage {
     /// Usage not recorded.
@@ -455,7 +639,7 @@ impl From<ffi::dc_usage_t> for GasUsage {
 
 /// Gas mix composition. Fractions are mole fractions in the range `[0.0, 1.0]`
 /// and should sum to 1.0 for a valid mix.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Gasmix {
     /// Helium fraction.
     pub helium: f64,
@@ -490,7 +674,7 @@ impl Default for Gasmix {
 }
 
 /// Role a [`Gasmix`] plays in the dive plan.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum GasUsage {
     /// Usage not specified.
@@ -539,9 +723,11 @@ impl From<String> for GasUsage {
 /// An event raised during a dive (deco violation, gas switch, ascent warning,
 /// …). The meaning of `flags` and `value` depends on [`kind`](Self::kind); see
 /// [`EventKind`] for the mapping.
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DiveEvent {
     /// Offset from dive start.
+    #[serde(with = "seconds")]
     pub time: Duration,
     /// Event classification — dictates the meaning of `flags` / `value`.
     pub kind: EventKind,
@@ -554,14 +740,51 @@ pub struct DiveEvent {
     pub name: Option<String>,
 }
 
+impl DiveEvent {
+    /// The alert text and classification of a [`EventKind::String`] event,
+    /// as logged by computers that record their on-screen messages
+    /// ("Low NDL", "High ppO2", …). `None` for other kinds.
+    #[must_use]
+    pub fn as_message(&self) -> Option<EventMessage<'_>> {
+        if self.kind != EventKind::String {
+            return None;
+        }
+        let flags = SampleFlag::from(self.flags);
+        Some(EventMessage {
+            text: self.name.as_deref().unwrap_or_default(),
+            severity: Severity::from(flags),
+            category: EventCategory::from(flags),
+            begin: flags.contains(SampleFlag::BEGIN),
+            end: flags.contains(SampleFlag::END),
+        })
+    }
+}
+
+/// A decoded string event; see [`DiveEvent::as_message`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventMessage<'a> {
+    /// The message as the computer displayed it.
+    pub text: &'a str,
+    /// How serious the computer considered it.
+    pub severity: Severity,
+    /// What it is about, for computers that tag messages.
+    pub category: EventCategory,
+    /// The condition started here.
+    pub begin: bool,
+    /// The condition cleared here.
+    pub end: bool,
+}
+
 /// A single sample in the dive's time series.
 ///
 /// Most fields are `Option` / `Vec` because dive computers differ widely in
 /// what they record per sample. [`DiveSample::carry_forward`] propagates the
 /// fields that are sampled sparsely (deco, CNS, …) from the previous sample.
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DiveSample {
     /// Offset from dive start.
+    #[serde(with = "seconds")]
     pub time: Duration,
     /// Depth in metres.
     pub depth: f64,
@@ -572,6 +795,7 @@ pub struct DiveSample {
     /// Events raised at this sample.
     pub events: Vec<DiveEvent>,
     /// Remaining bottom time computed by the computer.
+    #[serde(with = "seconds::option")]
     pub rbt: Option<Duration>,
     /// Heart rate in bpm, if the device records one.
     pub heartbeat: Option<u16>,
@@ -589,8 +813,13 @@ pub struct DiveSample {
     pub cns: f64,
     /// Current deco state (NDL remaining, deco stop, safety stop).
     pub deco: Option<Deco>,
-    /// Time-to-surface estimate from the deco model.
+    /// Time-to-surface estimate from the deco model, from `DC_SAMPLE_TTS`
+    /// or, for drivers that only report it there, [`Deco::tts`].
+    #[serde(with = "seconds::option")]
     pub tts: Option<Duration>,
+    /// Proprietary records the driver passed through undecoded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vendor: Vec<VendorData>,
 }
 
 impl DiveSample {
@@ -607,10 +836,308 @@ impl DiveSample {
             ..Default::default()
         }
     }
+
+    /// Pressure of tank `index` (see [`pressure`](Self::pressure)) as a
+    /// unit-aware [`Pressure`].
+    #[must_use]
+    pub fn tank_pressure(&self, index: usize) -> Option<Pressure> {
+        self.pressure.get(index).copied().map(Pressure::from_bar)
+    }
+}
+
+/// Proprietary sample data the driver does not decode (`DC_SAMPLE_VENDOR`),
+/// kept so importers that understand the vendor format lose nothing.
+/// Serialized with `data` as a hex string.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VendorData {
+    /// Vendor-specific record type, as tagged by the driver.
+    pub kind: u32,
+    /// Raw record bytes.
+    #[serde(with = "hex_bytes")]
+    pub data: Vec<u8>,
+}
+
+/// Serde helper writing bytes as an uppercase hex string, matching
+/// [`Fingerprint`]'s `Display`. Binary formats such as CBOR get the raw bytes
+/// instead.
+pub(crate) mod hex_bytes {
+    use std::fmt;
+
+    use serde::Serializer;
+    use serde::de::{self, Deserialize, Deserializer, Error, MapAccess, SeqAccess, Visitor};
+
+    use super::Fingerprint;
+
+    pub(crate) fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&Fingerprint::from(data))
+        } else {
+            serializer.serialize_bytes(data)
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        if !deserializer.is_human_readable() {
+            return deserializer.deserialize_byte_buf(BytesVisitor);
+        }
+        let hex = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Fingerprint::from_hex(&hex)
+            .map(|fp| fp.data)
+            .map_err(D::Error::custom)
+    }
+
+    /// Like [`deserialize`], but also takes a byte array and the
+    /// `{"data": [..]}` struct [`Fingerprint`] was written as before schema
+    /// versioning.
+    pub(crate) fn deserialize_any<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(BytesVisitor)
+        } else {
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a hex string or a byte string")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            Fingerprint::from_hex(v)
+                .map(|fp| fp.data)
+                .map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+            Ok(v)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut data = None;
+            while let Some(key) = map.next_key::<std::borrow::Cow<'de, str>>()? {
+                if key == "data" {
+                    data = Some(map.next_value()?);
+                } else {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+            data.ok_or_else(|| A::Error::missing_field("data"))
+        }
+    }
+}
+
+/// How serialized dives write durations and sample times. Only
+/// human-readable formats such as JSON are affected; binary ones keep
+/// their own encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DurationFormat {
+    /// Seconds: an integer when whole (`2400`), fractional otherwise.
+    #[default]
+    Seconds,
+    /// ISO 8601 strings such as `"PT40M"`.
+    Iso8601,
+}
+
+/// Serializes `T` — a [`Dive`], a slice of them, or anything holding them —
+/// with its durations written in the given [`DurationFormat`].
+///
+/// ```
+/// # use libdivecomputer::{Dive, DurationFormat, WithDurations};
+/// let dive = Dive {
+///     duration: std::time::Duration::from_secs(2400),
+///     ..Default::default()
+/// };
+/// let json = serde_json::to_value(WithDurations(&dive, DurationFormat::Iso8601)).unwrap();
+/// assert_eq!(json["duration"], "PT40M");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct WithDurations<T>(pub T, pub DurationFormat);
+
+impl<T: Serialize> Serialize for WithDurations<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        /// Puts the previous format back, even if serializing panics.
+        struct Restore(DurationFormat);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                seconds::FORMAT.set(self.0);
+            }
+        }
+
+        let _restore = Restore(seconds::FORMAT.replace(self.1));
+        self.0.serialize(serializer)
+    }
+}
+
+/// Serde helper writing a [`Duration`] as seconds: an integer when whole,
+/// fractional otherwise, or an ISO 8601 string such as `PT40M` inside
+/// [`WithDurations`]. Reads either, jiff's friendly form (`40m`) and the
+/// `{secs, nanos}` struct written before schema versioning. Binary formats
+/// such as CBOR keep that struct.
+pub(crate) mod seconds {
+    use std::borrow::Cow;
+    use std::cell::Cell;
+    use std::fmt;
+    use std::time::Duration;
+
+    use serde::de::{self, Deserializer, MapAccess, SeqAccess, Unexpected, Visitor};
+    use serde::ser::{Error as _, Serializer};
+    use serde::{Deserialize, Serialize};
+
+    use super::DurationFormat;
+
+    std::thread_local! {
+        /// The format of the innermost [`WithDurations`](super::WithDurations)
+        /// being serialized on this thread.
+        pub(super) static FORMAT: Cell<DurationFormat> = const { Cell::new(DurationFormat::Seconds) };
+    }
+
+    pub(crate) fn serialize<S: Serializer>(
+        duration: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            duration.serialize(serializer)
+        } else if FORMAT.get() == DurationFormat::Iso8601 {
+            let iso = jiff::SignedDuration::try_from(*duration).map_err(S::Error::custom)?;
+            serializer.collect_str(&iso)
+        } else if duration.subsec_nanos() == 0 {
+            serializer.serialize_u64(duration.as_secs())
+        } else {
+            serializer.serialize_f64(duration.as_secs_f64())
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(SecondsVisitor)
+        } else {
+            Duration::deserialize(deserializer)
+        }
+    }
+
+    /// The same, for `Option<Duration>` fields.
+    pub(crate) mod option {
+        use super::*;
+
+        pub(crate) fn serialize<S: Serializer>(
+            duration: &Option<Duration>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            duration.map(Seconds).serialize(serializer)
+        }
+
+        pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error> {
+            Ok(Option::<Seconds>::deserialize(deserializer)?.map(|Seconds(d)| d))
+        }
+    }
+
+    struct Seconds(Duration);
+
+    impl Serialize for Seconds {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize(&self.0, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Seconds {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize(deserializer).map(Seconds)
+        }
+    }
+
+    struct SecondsVisitor;
+
+    impl SecondsVisitor {
+        fn from_parts<E: de::Error>(secs: u64, nanos: u32) -> Result<Duration, E> {
+            Duration::from_secs(secs)
+                .checked_add(Duration::from_nanos(nanos.into()))
+                .ok_or_else(|| E::custom("duration overflow"))
+        }
+    }
+
+    impl<'de> Visitor<'de> for SecondsVisitor {
+        type Value = Duration;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a number of seconds or a duration string")
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(Duration::from_secs(v))
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+            u64::try_from(v)
+                .map(Duration::from_secs)
+                .map_err(|_| E::invalid_value(Unexpected::Signed(v), &self))
+        }
+
+        fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+            Duration::try_from_secs_f64(v)
+                .map_err(|_| E::invalid_value(Unexpected::Float(v), &self))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            let duration: jiff::SignedDuration = v.parse().map_err(E::custom)?;
+            Duration::try_from(duration).map_err(|_| E::invalid_value(Unexpected::Str(v), &self))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let secs = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            let nanos = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+            Self::from_parts(secs, nanos)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let (mut secs, mut nanos) = (None, None);
+            while let Some(key) = map.next_key::<Cow<'de, str>>()? {
+                match &*key {
+                    "secs" => secs = Some(map.next_value()?),
+                    "nanos" => nanos = Some(map.next_value()?),
+                    _ => {
+                        map.next_value::<de::IgnoredAny>()?;
+                    }
+                }
+            }
+            let secs = secs.ok_or_else(|| de::Error::missing_field("secs"))?;
+            Self::from_parts(secs, nanos.unwrap_or(0))
+        }
+    }
 }
 
 /// Partial pressure of O2 reading from a single CCR O2 sensor.
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Ppo2 {
     /// Sensor identifier (for multi-cell rebreathers).
     pub sensor: Sensor,
@@ -620,7 +1147,8 @@ pub struct Ppo2 {
 
 /// Raw O2 cell reading — the ppO2 the cell reports plus the underlying
 /// millivolt reading, useful for diagnosing failing cells.
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct O2Sensor {
     /// Sensor identifier.
     pub sensor: Sensor,
@@ -632,13 +1160,16 @@ pub struct O2Sensor {
 
 /// Deco state at a sample — either "no-decompression limit" with remaining
 /// NDL, or a required stop.
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Deco {
     /// Deco-state classification.
     pub kind: DecoKind,
     /// Remaining NDL (for `NDL`) or required stop duration.
+    #[serde(with = "seconds")]
     pub time: Duration,
     /// Total time-to-surface estimate.
+    #[serde(with = "seconds")]
     pub tts: Duration,
 }
 
@@ -676,7 +1207,7 @@ impl fmt::Display for DecoKind {
 
 /// Sensor identifier for readings that come from a specific physical sensor
 /// (e.g. a particular O2 cell on a rebreather).
-#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[non_exhaustive]
 pub enum Sensor {
     /// No sensor identifier attached.
@@ -758,6 +1289,57 @@ impl DecoKind {
 mod tests {
     use super::*;
 
+    #[test]
+    fn dive_event_as_message() {
+        let event = DiveEvent {
+            kind: EventKind::String,
+            flags: (SampleFlag::BEGIN | SampleFlag::SEVERITY_WARN).bits(),
+            name: Some("Low NDL".into()),
+            ..Default::default()
+        };
+        let message = event.as_message().unwrap();
+        assert_eq!(message.text, "Low NDL");
+        assert_eq!(message.severity, Severity::Warning);
+        assert!(message.begin && !message.end);
+
+        let event = DiveEvent {
+            kind: EventKind::Ascent,
+            ..Default::default()
+        };
+        assert!(event.as_message().is_none());
+    }
+
+    #[test]
+    fn dives_equal_whatever_schema_they_were_read_from() {
+        let dive = Dive {
+            max_depth: 18.5,
+            ..Default::default()
+        };
+        let read = Dive {
+            schema_version: Dive::SCHEMA_VERSION,
+            ..dive.clone()
+        };
+        assert_eq!(read, dive);
+        assert!(read.approx_eq(&dive, 0.0));
+        assert_ne!(
+            read,
+            Dive {
+                max_depth: 18.0,
+                ..dive
+            }
+        );
+    }
+
+    #[test]
+    fn vendor_data_hex_round_trip() {
+        use serde::de::value::{Error, StrDeserializer};
+
+        let bytes = hex_bytes::deserialize(StrDeserializer::<Error>::new("01aBff")).unwrap();
+        assert_eq!(bytes, [0x01, 0xAB, 0xFF]);
+        assert_eq!(Fingerprint::from(bytes).to_string(), "01ABFF");
+        assert!(hex_bytes::deserialize(StrDeserializer::<Error>::new("0")).is_err());
+    }
+
     #[test]
     fn fingerprint_from_hex_valid() {
         let fp = Fingerprint::from_hex("DEADBEEF").unwrap();
@@ -784,7 +1366,7 @@ mod tests {
     fn fingerprint_from_hex_empty() {
         let fp = Fingerprint::from_hex("").unwrap();
         assert!(fp.is_empty());
-        assert_eq!(fp.as_bytes(), &[]);
+        assert!(fp.as_bytes().is_empty());
     }
 
     #[test]
@@ -827,6 +1409,13 @@ mod tests {
         assert_eq!(fp.as_bytes(), &[0xCC, 0xDD]);
     }
 
+    #[test]
+    fn fingerprint_from_str() {
+        let fp: Fingerprint = "deadBEEF".parse().unwrap();
+        assert_eq!(fp.as_bytes(), &[0xDE, 0xAD, 0xBE, 0xEF]);
+        assert!("DEA".parse::<Fingerprint>().is_err());
+    }
+
     #[test]
     fn fingerprint_display() {
         let fp = Fingerprint::from(vec![0xDE, 0xAD]);
@@ -886,6 +1475,66 @@ mod tests {
         assert!((air.helium - 0.0).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn dive_start_local_uses_recorded_offset() {
+        let dive = Dive {
+            start: "2025-06-15T12:30:00Z".parse().unwrap(),
+            utc_offset: Some(5 * 3600 + 30 * 60),
+            ..Default::default()
+        };
+        let local = dive.start_local();
+        assert_eq!(local.time(), jiff::civil::time(18, 0, 0, 0));
+        assert_eq!(local.offset().seconds(), 19800);
+        assert_eq!(local.timestamp(), dive.start);
+
+        let dive = Dive {
+            utc_offset: None,
+            ..dive
+        };
+        assert_eq!(dive.start_local().time(), jiff::civil::time(12, 30, 0, 0));
+    }
+
+    #[test]
+    fn dive_set_start_local_round_trips() {
+        let zoned: jiff::Zoned = "2025-06-15T08:00:00-04:00[America/New_York]"
+            .parse()
+            .unwrap();
+        let mut dive = Dive::default();
+        dive.set_start_local(&zoned);
+        assert_eq!(dive.start.to_string(), "2025-06-15T12:00:00Z");
+        assert_eq!(dive.utc_offset, Some(-4 * 3600));
+        assert_eq!(dive.start_local().datetime(), zoned.datetime());
+    }
+
+    #[test]
+    fn deco_model_description() {
+        assert_eq!(DecoModel::None.description(), "Unknown");
+        assert_eq!(
+            DecoModel::Buhlmann {
+                conservatism: 0,
+                low: 30,
+                high: 85,
+            }
+            .description(),
+            "GF 30/85"
+        );
+        assert_eq!(
+            DecoModel::Buhlmann {
+                conservatism: 1,
+                low: 0,
+                high: 0,
+            }
+            .description(),
+            "Bühlmann +1"
+        );
+        assert_eq!(DecoModel::Vpm { conservatism: 3 }.description(), "VPM-B +3");
+        assert_eq!(
+            DecoModel::Rgbm { conservatism: -2 }.description(),
+            "RGBM -2"
+        );
+        assert_eq!(DecoModel::Dciem { conservatism: 0 }.description(), "DCIEM");
+    }
+
     #[test]
     fn deco_kind_display() {
         use std::time::Duration;
@@ -1,13 +1,19 @@
 use std::ffi::{CStr, c_char, c_void};
 use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
 use std::time::Duration;
 
 use libdivecomputer_sys as ffi;
 use tracing::instrument;
 
+use crate::address::BtAddress;
 use crate::context::Context;
-use crate::device::{ConnectionInfo, DeviceInfo};
-#[cfg(not(feature = "ble"))]
+use crate::descriptor::{Descriptor, usb_device_name};
+use crate::device::{ConnectionInfo, DeviceInfo, Next};
+#[cfg(any(not(feature = "ble"), not(feature = "irda")))]
 use crate::error::LibError;
 use crate::error::Result;
 use crate::status::Status;
@@ -22,11 +28,52 @@ fn cstr_or_default(ptr: *const c_char, default: &str) -> String {
     }
 }
 
+/// How a [`ScanStream`] treats a device it has already reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ScanDedup {
+    /// Report each device once, the first time it is seen.
+    #[default]
+    Once,
+    /// Report a device again whenever its [`DeviceInfo`] changes, e.g. when
+    /// the advertised local name arrives in a later advertisement.
+    Updates,
+}
+
+/// Advertised-name prefix accepted as a BLE dive computer when the service
+/// UUID is missing from its advertisements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(feature = "ble"), allow(dead_code))]
+pub(crate) struct NamePrefix {
+    pub(crate) prefix: String,
+    /// Vendor the prefix belongs to, when known. Matches get the vendor's
+    /// catalog entry as their [`ConnectionInfo::Ble`] service name so vendor
+    /// quirks still apply; the advertised name is kept as the local name.
+    pub(crate) vendor: Option<String>,
+}
+
+/// First of `prefixes` that `name` starts with, ignoring ASCII case.
+#[cfg_attr(not(feature = "ble"), allow(dead_code))]
+pub(crate) fn match_name_prefix<'p>(
+    name: &str,
+    prefixes: &'p [NamePrefix],
+) -> Option<&'p NamePrefix> {
+    prefixes.iter().find(|p| {
+        name.get(..p.prefix.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(&p.prefix))
+    })
+}
+
 /// Builder for scanning for dive computer devices.
 pub struct ScanBuilder<'a> {
     ctx: &'a Context,
     transport: Transport,
     timeout: Duration,
+    exhaustive: bool,
+    continuous: bool,
+    dedup: ScanDedup,
+    name_prefixes: Vec<NamePrefix>,
+    simulated: Vec<DeviceInfo>,
 }
 
 impl<'a> ScanBuilder<'a> {
@@ -36,18 +83,160 @@ impl<'a> ScanBuilder<'a> {
         self
     }
 
+    /// Keep scanning for the whole timeout instead of returning as soon as
+    /// the first device is seen. Use this when several computers may be in
+    /// range and the user picks one from the list; pass the chosen
+    /// [`DeviceInfo`] to [`DeviceInfo::open`] to connect to exactly that one.
+    ///
+    /// Only affects BLE; the other transports enumerate synchronously.
+    pub fn exhaustive(mut self, exhaustive: bool) -> Self {
+        self.exhaustive = exhaustive;
+        self
+    }
+
+    /// Also accept BLE devices whose advertised name starts with `prefix`
+    /// (case-insensitive), for computers that don't advertise their service
+    /// UUID — older Shearwater firmware, some Cressi units.
+    ///
+    /// With any prefix set, the BLE scan can no longer ask the OS to filter
+    /// by service, so it sees (and discards) every advertising peripheral.
+    /// Matches carry no service name; use [`descriptor`](Self::descriptor)
+    /// when vendor quirks must apply on connect.
+    pub fn name_prefix(mut self, prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into();
+        if !prefix.is_empty() {
+            self.name_prefixes.push(NamePrefix {
+                prefix,
+                vendor: None,
+            });
+        }
+        self
+    }
+
+    /// Accept BLE devices advertising under the product name of `desc`
+    /// (e.g. "Perdix 2", or just "Perdix"), as with
+    /// [`name_prefix`](Self::name_prefix). Matches are recorded with the
+    /// vendor's service name next to their advertised name, so vendor
+    /// quirks still apply when connecting.
+    pub fn descriptor(mut self, desc: &Descriptor) -> Self {
+        let product = desc.product();
+        let first_word = product.split_whitespace().next().unwrap_or_default();
+        for prefix in [product, first_word] {
+            if !prefix.is_empty() && !self.name_prefixes.iter().any(|p| p.prefix == prefix) {
+                self.name_prefixes.push(NamePrefix {
+                    prefix: prefix.to_string(),
+                    vendor: Some(desc.vendor().to_string()),
+                });
+            }
+        }
+        self
+    }
+
+    /// Report a simulated computer instead of scanning: the capture at
+    /// `capture`, written by a
+    /// [`RecordingTransport`](crate::capture::RecordingTransport) on this
+    /// transport, shows up as `name` and opens as a
+    /// [`ConnectionInfo::Simulator`] that replays it to the real driver.
+    ///
+    /// A scan with simulated computers reports only those and leaves the
+    /// hardware alone, so UIs and CI runs go through the usual scan, open
+    /// and download steps without adapters or computers attached.
+    #[cfg(feature = "simulator")]
+    pub fn simulated(mut self, name: impl Into<String>, capture: impl Into<String>) -> Self {
+        self.simulated.push(DeviceInfo {
+            name: name.into(),
+            transport: self.transport,
+            connection: ConnectionInfo::Simulator {
+                capture: capture.into(),
+                transport: self.transport,
+            },
+            advertisement: None,
+        });
+        self
+    }
+
+    /// Let [`stream`](Self::stream) run until cancelled instead of stopping
+    /// after the timeout. Slow-advertising computers may take well over the
+    /// default 5 seconds to show up.
+    pub fn continuous(mut self, continuous: bool) -> Self {
+        self.continuous = continuous;
+        self
+    }
+
+    /// How [`stream`](Self::stream) treats devices it has already reported.
+    pub fn dedup(mut self, dedup: ScanDedup) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Start scanning in the background and yield devices as they are
+    /// discovered, rather than waiting for the scan to finish.
+    ///
+    /// BLE keeps scanning until the timeout elapses — or, with
+    /// [`continuous`](Self::continuous), until the stream is cancelled or
+    /// dropped. The other transports enumerate once and the stream ends
+    /// after the devices present at that moment.
+    #[must_use = "the scan stops when the stream is dropped"]
+    #[instrument(skip(self), fields(transport = ?self.transport, continuous = self.continuous))]
+    pub fn stream(self) -> Result<ScanStream> {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        #[cfg(feature = "ble")]
+        if self.transport == Transport::Ble && self.simulated.is_empty() {
+            let timeout = (!self.continuous).then_some(self.timeout);
+            let dedup = self.dedup;
+            let name_prefixes = self.name_prefixes;
+            let worker_cancel = Arc::clone(&cancel);
+            let worker = std::thread::Builder::new()
+                .name("ble-scan".into())
+                .spawn(move || {
+                    crate::ble::stream_ble(timeout, dedup, &name_prefixes, &tx, &worker_cancel)
+                })?;
+            return Ok(ScanStream {
+                rx,
+                cancel,
+                worker: Some(worker),
+            });
+        }
+
+        match self.execute() {
+            Ok(devices) => devices.into_iter().for_each(|d| {
+                let _ = tx.send(Ok(d));
+            }),
+            Err(e) => {
+                let _ = tx.send(Err(e));
+            }
+        }
+        Ok(ScanStream {
+            rx,
+            cancel,
+            worker: None,
+        })
+    }
+
     /// Execute the scan and return discovered devices.
     #[must_use = "discovered devices should be inspected"]
     #[instrument(skip(self), fields(transport = ?self.transport, timeout_ms = self.timeout.as_millis() as u64))]
     pub fn execute(self) -> Result<Vec<DeviceInfo>> {
+        if !self.simulated.is_empty() {
+            return Ok(self.simulated);
+        }
         match self.transport {
             Transport::Serial => scan_serial(self.ctx),
             Transport::Usb => scan_usb(self.ctx),
             Transport::UsbHid => scan_usbhid(self.ctx),
             Transport::Bluetooth => scan_bluetooth(self.ctx),
+            #[cfg(feature = "irda")]
             Transport::Irda => scan_irda(self.ctx),
+            #[cfg(not(feature = "irda"))]
+            Transport::Irda => Err(LibError::TransportNotSupported(
+                "IrDA (feature not enabled)".into(),
+            )),
             #[cfg(feature = "ble")]
-            Transport::Ble => crate::ble::scan_ble(self.timeout),
+            Transport::Ble => {
+                crate::ble::scan_ble_with(self.timeout, self.exhaustive, &self.name_prefixes)
+            }
             #[cfg(not(feature = "ble"))]
             Transport::Ble => Err(LibError::TransportNotSupported(
                 "BLE (feature not enabled)".into(),
@@ -69,6 +258,162 @@ pub fn scan(ctx: &Context, transport: Transport) -> ScanBuilder<'_> {
         ctx,
         transport,
         timeout: Duration::from_secs(5),
+        exhaustive: false,
+        continuous: false,
+        dedup: ScanDedup::default(),
+        name_prefixes: Vec::new(),
+        simulated: Vec::new(),
+    }
+}
+
+/// A device found by [`scan_all`], with the models it may be.
+#[derive(Debug)]
+pub struct ScanMatch {
+    /// The device as the scan reported it.
+    pub device: DeviceInfo,
+    /// Models the device may be, most likely first. See [`candidates`].
+    pub candidates: Vec<Descriptor>,
+}
+
+/// Scan `transport` for dive computers of any model, for users who do not
+/// know exactly which computer they have.
+///
+/// Every model reachable over `transport` is registered with
+/// [`ScanBuilder::descriptor`], so BLE devices advertising any known product
+/// name are found, and the scan is [exhaustive](ScanBuilder::exhaustive).
+/// Each device comes back with its [`candidates`].
+pub fn scan_all(ctx: &Context, transport: Transport) -> Result<Vec<ScanMatch>> {
+    let builder = Descriptor::by_transport(transport)?.iter().fold(
+        scan(ctx, transport).exhaustive(true),
+        ScanBuilder::descriptor,
+    );
+    builder
+        .execute()?
+        .into_iter()
+        .map(|device| {
+            Ok(ScanMatch {
+                candidates: candidates(&device)?,
+                device,
+            })
+        })
+        .collect()
+}
+
+/// Models `device` may be, most likely first.
+///
+/// A model is a candidate if [`Descriptor::matches`] accepts the device's
+/// USB ID or advertised name. Models whose product name the device advertises come first, then
+/// those of the vendor its BLE service belongs to. Serial ports and other
+/// devices that do not identify themselves match every model reachable over
+/// their transport.
+pub fn candidates(device: &DeviceInfo) -> Result<Vec<Descriptor>> {
+    let mut ranked: Vec<_> = Descriptor::by_transport(device.transport)?
+        .into_iter()
+        .filter(|desc| desc.matches(device))
+        .map(|desc| (match_rank(device, desc.vendor(), desc.product()), desc))
+        .collect();
+    // Stable, so models of equal rank keep the catalog's order.
+    ranked.sort_by_key(|(rank, _)| *rank);
+    Ok(ranked.into_iter().map(|(_, desc)| desc).collect())
+}
+
+/// How well `device` matches the model `vendor` `product`, lower is better.
+/// See [`candidates`].
+pub(crate) fn match_rank(device: &DeviceInfo, vendor: &str, product: &str) -> u8 {
+    let (name, service) = match &device.connection {
+        ConnectionInfo::Ble {
+            local_name,
+            service_name,
+            ..
+        } => (
+            local_name.as_deref().unwrap_or(&device.name),
+            service_name.as_str(),
+        ),
+        ConnectionInfo::Bluetooth { .. } => (device.name.as_str(), ""),
+        _ => return 3,
+    };
+    let starts_with = |prefix: &str| {
+        !prefix.is_empty()
+            && name
+                .get(..prefix.len())
+                .is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+    };
+    let first_word = product.split_whitespace().next().unwrap_or_default();
+    if starts_with(product) {
+        0
+    } else if starts_with(first_word) {
+        1
+    } else if !vendor.is_empty()
+        && service
+            .to_ascii_lowercase()
+            .contains(&vendor.to_ascii_lowercase())
+    {
+        2
+    } else {
+        3
+    }
+}
+
+/// Devices discovered by a background scan, started with
+/// [`ScanBuilder::stream`]. Iterating blocks until the next device is found
+/// and ends when the scan finishes or is cancelled.
+///
+/// Dropping the stream cancels the scan and waits for it to stop.
+pub struct ScanStream {
+    rx: mpsc::Receiver<Result<DeviceInfo>>,
+    cancel: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ScanStream {
+    /// Stop scanning. Devices already found are still yielded.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Handle for cancelling the scan from another thread while this one is
+    /// blocked iterating.
+    pub fn cancel_handle(&self) -> ScanCancel {
+        ScanCancel(Arc::clone(&self.cancel))
+    }
+
+    /// Wait at most `timeout` for the next device. See
+    /// [`DiveStream::next_timeout`](crate::DiveStream::next_timeout).
+    pub fn next_timeout(&mut self, timeout: Duration) -> Next<Result<DeviceInfo>> {
+        Next::recv(&self.rx, timeout)
+    }
+}
+
+impl Iterator for ScanStream {
+    type Item = Result<DeviceInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+impl Drop for ScanStream {
+    fn drop(&mut self) {
+        self.cancel();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Cancels the [`ScanStream`] it was taken from. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct ScanCancel(Arc<AtomicBool>);
+
+impl ScanCancel {
+    /// Stop the scan.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the scan has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
     }
 }
 
@@ -123,12 +468,14 @@ fn scan_serial(ctx: &Context) -> Result<Vec<DeviceInfo>> {
         |iter| unsafe { ffi::dc_serial_iterator_new(iter, ctx.ptr(), ptr::null_mut()) },
         |iter, device| unsafe { ffi::dc_iterator_next(iter, device as *mut _ as *mut c_void) },
         |device| {
-            let path = cstr_or_default(unsafe { ffi::dc_serial_device_get_name(device) }, "Unknown");
+            let path =
+                cstr_or_default(unsafe { ffi::dc_serial_device_get_name(device) }, "Unknown");
             let name = extract_device_name(&path);
             DeviceInfo {
                 name,
                 transport: Transport::Serial,
                 connection: ConnectionInfo::Serial { path },
+                advertisement: None,
             }
         },
         |device| unsafe { ffi::dc_serial_device_free(device) },
@@ -137,27 +484,60 @@ fn scan_serial(ctx: &Context) -> Result<Vec<DeviceInfo>> {
 }
 
 fn scan_usb(ctx: &Context) -> Result<Vec<DeviceInfo>> {
-    scan_with_iterator(
+    let mut devices = scan_with_iterator(
         |iter| unsafe { ffi::dc_usb_iterator_new(iter, ctx.ptr(), ptr::null_mut()) },
         |iter, device| unsafe { ffi::dc_iterator_next(iter, device as *mut _ as *mut c_void) },
         |device| {
             let vid = unsafe { ffi::dc_usb_device_get_vid(device) } as u16;
             let pid = unsafe { ffi::dc_usb_device_get_pid(device) } as u16;
-            let name = format!("USB Device {vid:04X}:{pid:04X}");
+            let name = usb_device_name(Transport::Usb, vid, pid);
             DeviceInfo {
                 name,
                 transport: Transport::Usb,
                 connection: ConnectionInfo::Usb {
                     vendor_id: vid,
                     product_id: pid,
+                    index: 0,
+                    ftdi: false,
                 },
+                advertisement: None,
             }
         },
         |device| unsafe { ffi::dc_usb_device_free(device) },
         "USB",
-    )
+    )?;
+    number_identical_usb_devices(&mut devices);
+    Ok(devices)
+}
+
+/// Fill in [`ConnectionInfo::Usb::index`] so that identical devices get
+/// distinct, openable entries, and label the duplicates accordingly.
+fn number_identical_usb_devices(devices: &mut [DeviceInfo]) {
+    let mut seen: Vec<(u16, u16)> = Vec::new();
+    for device in devices {
+        if let ConnectionInfo::Usb {
+            vendor_id,
+            product_id,
+            index,
+            ..
+        } = &mut device.connection
+        {
+            let key = (*vendor_id, *product_id);
+            *index = seen.iter().filter(|k| **k == key).count();
+            seen.push(key);
+            if *index > 0 {
+                device.name = device.connection.display_name().into_owned();
+            }
+        }
+    }
+}
+
+#[cfg(feature = "hidapi")]
+fn scan_usbhid(_ctx: &Context) -> Result<Vec<DeviceInfo>> {
+    crate::hid::scan_hidapi()
 }
 
+#[cfg(not(feature = "hidapi"))]
 fn scan_usbhid(ctx: &Context) -> Result<Vec<DeviceInfo>> {
     scan_with_iterator(
         |iter| unsafe { ffi::dc_usbhid_iterator_new(iter, ctx.ptr(), ptr::null_mut()) },
@@ -165,7 +545,7 @@ fn scan_usbhid(ctx: &Context) -> Result<Vec<DeviceInfo>> {
         |device| {
             let vid = unsafe { ffi::dc_usbhid_device_get_vid(device) } as u16;
             let pid = unsafe { ffi::dc_usbhid_device_get_pid(device) } as u16;
-            let name = format!("USB HID Device {vid:04X}:{pid:04X}");
+            let name = usb_device_name(Transport::UsbHid, vid, pid);
             DeviceInfo {
                 name,
                 transport: Transport::UsbHid,
@@ -173,6 +553,7 @@ fn scan_usbhid(ctx: &Context) -> Result<Vec<DeviceInfo>> {
                     vendor_id: vid,
                     product_id: pid,
                 },
+                advertisement: None,
             }
         },
         |device| unsafe { ffi::dc_usbhid_device_free(device) },
@@ -193,16 +574,15 @@ fn scan_bluetooth(ctx: &Context) -> Result<Vec<DeviceInfo>> {
     {
         scan_with_iterator(
             |iter| unsafe { ffi::dc_bluetooth_iterator_new(iter, ctx.ptr(), ptr::null_mut()) },
-            |iter, device| unsafe {
-                ffi::dc_iterator_next(iter, device as *mut _ as *mut c_void)
-            },
+            |iter, device| unsafe { ffi::dc_iterator_next(iter, device as *mut _ as *mut c_void) },
             |device| {
-                let address = unsafe { ffi::dc_bluetooth_device_get_address(device) };
+                let address =
+                    BtAddress::from_u64(unsafe { ffi::dc_bluetooth_device_get_address(device) });
                 let name = cstr_or_default(
                     unsafe { ffi::dc_bluetooth_device_get_name(device) },
                     "Unknown Bluetooth Device",
                 );
-                let address_string = format_bluetooth_address(address);
+                let address_string = address.to_string();
                 DeviceInfo {
                     name,
                     transport: Transport::Bluetooth,
@@ -210,6 +590,7 @@ fn scan_bluetooth(ctx: &Context) -> Result<Vec<DeviceInfo>> {
                         address,
                         address_string,
                     },
+                    advertisement: None,
                 }
             },
             |device| unsafe { ffi::dc_bluetooth_device_free(device) },
@@ -218,6 +599,7 @@ fn scan_bluetooth(ctx: &Context) -> Result<Vec<DeviceInfo>> {
     }
 }
 
+#[cfg(feature = "irda")]
 fn scan_irda(ctx: &Context) -> Result<Vec<DeviceInfo>> {
     scan_with_iterator(
         |iter| unsafe { ffi::dc_irda_iterator_new(iter, ctx.ptr(), ptr::null_mut()) },
@@ -232,6 +614,7 @@ fn scan_irda(ctx: &Context) -> Result<Vec<DeviceInfo>> {
                 name,
                 transport: Transport::Irda,
                 connection: ConnectionInfo::Irda { address },
+                advertisement: None,
             }
         },
         |device| unsafe { ffi::dc_irda_device_free(device) },
@@ -239,81 +622,181 @@ fn scan_irda(ctx: &Context) -> Result<Vec<DeviceInfo>> {
     )
 }
 
-/// Format a Bluetooth address as a colon-separated hex string.
-pub fn format_bluetooth_address(address: u64) -> String {
-    format!(
-        "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
-        (address >> 40) & 0xFF,
-        (address >> 32) & 0xFF,
-        (address >> 24) & 0xFF,
-        (address >> 16) & 0xFF,
-        (address >> 8) & 0xFF,
-        address & 0xFF
-    )
-}
-
 /// Extract a friendly device name from a path.
 fn extract_device_name(path: &str) -> String {
     path.split('/').next_back().unwrap_or(path).to_string()
 }
 
+/// Format a Bluetooth address as a colon-separated hex string.
+#[deprecated(since = "0.2.0", note = "Use BtAddress's Display instead")]
+pub fn format_bluetooth_address(address: u64) -> String {
+    BtAddress::from_u64(address).to_string()
+}
+
 /// Convert a MAC address string to a u64.
+#[deprecated(since = "0.2.0", note = "Use BtAddress's FromStr instead")]
 pub fn mac_string_to_u64(mac: &str) -> Option<u64> {
-    let parts: Vec<&str> = mac.split(':').collect();
-    if parts.len() != 6 {
-        return None;
-    }
-    let mut address: u64 = 0;
-    for (i, part) in parts.iter().enumerate() {
-        let byte = u8::from_str_radix(part, 16).ok()?;
-        address |= (byte as u64) << (40 - i * 8);
-    }
-    Some(address)
+    mac.parse::<BtAddress>().ok().map(BtAddress::to_u64)
 }
 
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn format_bluetooth_address_known() {
-        let addr: u64 = 0xAABBCCDDEEFF;
-        assert_eq!(format_bluetooth_address(addr), "AA:BB:CC:DD:EE:FF");
+    fn ble_device(local_name: &str, service_name: &str) -> DeviceInfo {
+        DeviceInfo {
+            name: local_name.to_string(),
+            transport: Transport::Ble,
+            connection: ConnectionInfo::Ble {
+                address: BtAddress::UNKNOWN,
+                local_name: Some(local_name.to_string()),
+                service_name: service_name.to_string(),
+                address_string: String::new(),
+                peripheral_id: None,
+            },
+            advertisement: None,
+        }
     }
 
     #[test]
-    fn format_bluetooth_address_zero() {
-        assert_eq!(format_bluetooth_address(0), "00:00:00:00:00:00");
+    fn match_rank_prefers_advertised_product() {
+        let device = ble_device("Perdix 2 1234", "Shearwater (Perdix/Teric/Peregrine/Tern)");
+        assert_eq!(match_rank(&device, "Shearwater", "Perdix 2"), 0);
+        assert_eq!(match_rank(&device, "Shearwater", "Perdix AI"), 1);
+        assert_eq!(match_rank(&device, "Shearwater", "Teric"), 2);
+        assert_eq!(match_rank(&device, "Suunto", "EON Steel"), 3);
+
+        let serial = DeviceInfo {
+            name: "ttyUSB0".to_string(),
+            transport: Transport::Serial,
+            connection: ConnectionInfo::Serial {
+                path: "/dev/ttyUSB0".to_string(),
+            },
+            advertisement: None,
+        };
+        assert_eq!(match_rank(&serial, "Suunto", "Vyper"), 3);
     }
 
     #[test]
-    fn mac_string_to_u64_valid() {
-        let addr = mac_string_to_u64("AA:BB:CC:DD:EE:FF").unwrap();
-        assert_eq!(addr, 0xAABBCCDDEEFF);
+    fn scan_stream_yields_until_sender_drops() {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker = std::thread::spawn({
+            let cancel = Arc::clone(&cancel);
+            move || {
+                let device = DeviceInfo {
+                    name: "Perdix".into(),
+                    transport: Transport::Ble,
+                    connection: ConnectionInfo::Ble {
+                        address: BtAddress::UNKNOWN,
+                        address_string: "AA:BB:CC:DD:EE:FF".into(),
+                        service_name: String::new(),
+                        local_name: None,
+                        peripheral_id: None,
+                    },
+                    advertisement: None,
+                };
+                tx.send(Ok(device)).unwrap();
+                while !cancel.load(Ordering::Relaxed) {
+                    std::thread::yield_now();
+                }
+            }
+        });
+        let mut stream = ScanStream {
+            rx,
+            cancel,
+            worker: Some(worker),
+        };
+
+        assert_eq!(stream.next().unwrap().unwrap().name, "Perdix");
+        let handle = stream.cancel_handle();
+        assert!(!handle.is_cancelled());
+        handle.cancel();
+        assert!(handle.is_cancelled());
+        assert!(stream.next().is_none());
     }
 
     #[test]
-    fn mac_string_to_u64_lowercase() {
-        let addr = mac_string_to_u64("aa:bb:cc:dd:ee:ff").unwrap();
-        assert_eq!(addr, 0xAABBCCDDEEFF);
+    fn name_prefix_matching() {
+        let prefixes = [
+            NamePrefix {
+                prefix: "Perdix 2".into(),
+                vendor: Some("Shearwater".into()),
+            },
+            NamePrefix {
+                prefix: "OSTC".into(),
+                vendor: None,
+            },
+        ];
+        let matched = |name| match_name_prefix(name, &prefixes).map(|p| p.prefix.as_str());
+
+        assert_eq!(matched("Perdix 2 0a1b"), Some("Perdix 2"));
+        assert_eq!(matched("ostc4 1234"), Some("OSTC"));
+        assert_eq!(matched("Perdix"), None);
+        assert_eq!(matched("Petrel"), None);
+        // A prefix longer than the name, or one that splits a multi-byte
+        // character, simply doesn't match.
+        assert_eq!(matched("OST"), None);
+        assert_eq!(matched("Ö"), None);
     }
 
+    #[cfg(feature = "simulator")]
     #[test]
-    fn mac_string_to_u64_wrong_octets() {
-        assert!(mac_string_to_u64("AA:BB:CC").is_none());
-        assert!(mac_string_to_u64("AA:BB:CC:DD:EE:FF:00").is_none());
+    fn simulated_devices_replace_the_hardware_scan() {
+        let ctx = Context::new().unwrap();
+        let devices = scan(&ctx, Transport::Ble)
+            .simulated("Perdix 2", "captures/perdix.txt")
+            .execute()
+            .unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].name, "Perdix 2");
+        assert_eq!(devices[0].transport, Transport::Ble);
+        assert_eq!(
+            devices[0].connection,
+            ConnectionInfo::Simulator {
+                capture: "captures/perdix.txt".into(),
+                transport: Transport::Ble,
+            }
+        );
+        assert_eq!(Transport::from(&devices[0].connection), Transport::Ble);
     }
 
     #[test]
-    fn mac_string_to_u64_invalid_hex() {
-        assert!(mac_string_to_u64("GG:HH:II:JJ:KK:LL").is_none());
+    fn identical_usb_devices_are_numbered() {
+        let usb = |vendor_id, product_id| DeviceInfo {
+            name: format!("USB Device {vendor_id:04X}:{product_id:04X}"),
+            transport: Transport::Usb,
+            connection: ConnectionInfo::Usb {
+                vendor_id,
+                product_id,
+                index: 0,
+                ftdi: false,
+            },
+            advertisement: None,
+        };
+        let mut devices = vec![usb(1, 2), usb(3, 4), usb(1, 2)];
+        number_identical_usb_devices(&mut devices);
+
+        let indices: Vec<_> = devices
+            .iter()
+            .map(|d| match d.connection {
+                ConnectionInfo::Usb { index, .. } => index,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(indices, [0, 0, 1]);
+        assert_eq!(devices[0].name, "USB Device 0001:0002");
+        assert_eq!(devices[2].name, "USB Device 0001:0002 #2");
     }
 
     #[test]
-    fn mac_round_trip() {
-        let mac = "AA:BB:CC:DD:EE:FF";
-        let addr = mac_string_to_u64(mac).unwrap();
-        let recovered = format_bluetooth_address(addr);
-        assert_eq!(recovered, mac);
+    fn deprecated_address_helpers_still_work() {
+        assert_eq!(
+            format_bluetooth_address(0xAABBCCDDEEFF),
+            "AA:BB:CC:DD:EE:FF"
+        );
+        assert_eq!(mac_string_to_u64("AA:BB:CC:DD:EE:FF"), Some(0xAABBCCDDEEFF));
+        assert_eq!(mac_string_to_u64("AA:BB:CC"), None);
     }
 }
@@ -0,0 +1,294 @@
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use libdivecomputer_sys as ffi;
+use serialport::{ClearBuffer, DataBits, SerialPort};
+
+use crate::context::Context;
+use crate::custom::{CustomTransport, read_full};
+use crate::error::{LibError, Result};
+use crate::iostream::{Direction, FlowControl, IoStream, Parity, SerialConfig, StopBits};
+use crate::status::Status;
+use crate::transport::Transport;
+
+/// Interval between checks of the receive queue while polling.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Serial port driven from Rust through the `serialport` crate instead of the
+/// C library's own serial code.
+///
+/// Useful where the bundled implementation is unavailable or unsuitable,
+/// e.g. Windows builds without the C serial backend or sandboxed
+/// environments that only expose ports through the platform APIs
+/// `serialport` uses.
+pub struct SerialPortTransport {
+    port: Box<dyn SerialPort>,
+    timeout: Option<Duration>,
+    /// Bytes from a read that timed out, handed out first by the next one.
+    pending: Vec<u8>,
+}
+
+impl SerialPortTransport {
+    /// Open the serial port at `path` (e.g. `/dev/ttyUSB0` or `COM3`). The
+    /// driver sets the line parameters when the device is opened.
+    pub fn open(path: &str) -> Result<Self> {
+        let port = serialport::new(path, 9600)
+            .open()
+            .map_err(|e| serial_error(e, "failed to open serial port"))?;
+        Ok(Self::from_port(port))
+    }
+
+    /// Wrap an already opened port.
+    pub fn from_port(port: Box<dyn SerialPort>) -> Self {
+        Self {
+            port,
+            timeout: None,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Debug for SerialPortTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SerialPortTransport")
+            .field("port", &self.port.name())
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl CustomTransport for SerialPortTransport {
+    /// Fill `buf` completely, matching the C serial backend; a short read
+    /// within the timeout is reported as [`Status::Timeout`].
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let port = &mut self.port;
+        read_full(&mut self.pending, buf, self.timeout, |buf, remaining| {
+            // `serialport` has no "block forever"; re-arm a long timeout
+            // instead when the driver asked for one.
+            port.set_timeout(remaining.unwrap_or(Duration::from_secs(3600)))
+                .map_err(std::io::Error::from)?;
+            port.read(buf)
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize> {
+        self.port.write_all(data)?;
+        Ok(data.len())
+    }
+
+    fn poll(&mut self, timeout_ms: i32) -> Result<bool> {
+        let deadline = u64::try_from(timeout_ms)
+            .ok()
+            .map(|ms| Instant::now() + Duration::from_millis(ms));
+        loop {
+            if self.get_available()? > 0 {
+                return Ok(true);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Ok(false);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn set_timeout(&mut self, timeout_ms: i32) -> Result<()> {
+        self.timeout = u64::try_from(timeout_ms).ok().map(Duration::from_millis);
+        Ok(())
+    }
+
+    fn configure(&mut self, config: &SerialConfig) -> Result<()> {
+        let settings = port_settings(config)?;
+        let port = &mut self.port;
+        port.set_baud_rate(config.baudrate)
+            .and_then(|()| port.set_data_bits(settings.0))
+            .and_then(|()| port.set_parity(settings.1))
+            .and_then(|()| port.set_stop_bits(settings.2))
+            .and_then(|()| port.set_flow_control(settings.3))
+            .map_err(|e| serial_error(e, "failed to configure serial port"))
+    }
+
+    fn set_break(&mut self, value: bool) -> Result<()> {
+        let result = if value {
+            self.port.set_break()
+        } else {
+            self.port.clear_break()
+        };
+        result.map_err(|e| serial_error(e, "failed to set break"))
+    }
+
+    fn set_dtr(&mut self, value: bool) -> Result<()> {
+        self.port
+            .write_data_terminal_ready(value)
+            .map_err(|e| serial_error(e, "failed to set DTR"))
+    }
+
+    fn set_rts(&mut self, value: bool) -> Result<()> {
+        self.port
+            .write_request_to_send(value)
+            .map_err(|e| serial_error(e, "failed to set RTS"))
+    }
+
+    fn get_lines(&mut self) -> Result<u32> {
+        let port = &mut self.port;
+        let lines = [
+            (port.read_carrier_detect(), ffi::DC_LINE_DCD),
+            (port.read_clear_to_send(), ffi::DC_LINE_CTS),
+            (port.read_data_set_ready(), ffi::DC_LINE_DSR),
+            (port.read_ring_indicator(), ffi::DC_LINE_RNG),
+        ];
+        lines.into_iter().try_fold(0u32, |mask, (state, bit)| {
+            let state = state.map_err(|e| serial_error(e, "failed to read modem lines"))?;
+            Ok(if state { mask | bit } else { mask })
+        })
+    }
+
+    fn get_available(&mut self) -> Result<usize> {
+        let available = self
+            .port
+            .bytes_to_read()
+            .map_err(|e| serial_error(e, "failed to query receive queue"))?;
+        Ok(self.pending.len() + available as usize)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.port.flush()?;
+        Ok(())
+    }
+
+    fn purge(&mut self, direction: Direction) -> Result<()> {
+        if direction != Direction::Output {
+            self.pending.clear();
+        }
+        let buffer = match direction {
+            Direction::Input => ClearBuffer::Input,
+            Direction::Output => ClearBuffer::Output,
+            Direction::All => ClearBuffer::All,
+        };
+        self.port
+            .clear(buffer)
+            .map_err(|e| serial_error(e, "failed to purge serial port"))
+    }
+}
+
+impl IoStream {
+    /// Open a serial port through the pure-Rust [`SerialPortTransport`]
+    /// rather than the C library's serial backend. Line settings are left
+    /// to the driver, as with [`IoStream::serial`].
+    #[must_use = "the opened IoStream must be passed to Device::open"]
+    pub fn serialport(ctx: &Context, path: &str) -> Result<Self> {
+        Self::custom(ctx, Transport::Serial, SerialPortTransport::open(path)?)
+    }
+}
+
+/// Map the driver's line settings onto `serialport`'s, rejecting the ones
+/// it cannot express.
+fn port_settings(
+    config: &SerialConfig,
+) -> Result<(
+    DataBits,
+    serialport::Parity,
+    serialport::StopBits,
+    serialport::FlowControl,
+)> {
+    let databits = u8::try_from(config.databits)
+        .ok()
+        .and_then(|bits| DataBits::try_from(bits).ok())
+        .ok_or_else(|| {
+            LibError::InvalidArguments(format!("unsupported data bits: {}", config.databits))
+        })?;
+    let parity = match config.parity {
+        Parity::None => serialport::Parity::None,
+        Parity::Odd => serialport::Parity::Odd,
+        Parity::Even => serialport::Parity::Even,
+        other => {
+            return Err(LibError::Status(
+                Status::Unsupported,
+                Some(format!("{other:?} parity is not supported by serialport")),
+            ));
+        }
+    };
+    let stopbits = match config.stopbits {
+        StopBits::One => serialport::StopBits::One,
+        StopBits::Two => serialport::StopBits::Two,
+        other => {
+            return Err(LibError::Status(
+                Status::Unsupported,
+                Some(format!(
+                    "{other:?} stop bits are not supported by serialport"
+                )),
+            ));
+        }
+    };
+    let flowcontrol = match config.flowcontrol {
+        FlowControl::None => serialport::FlowControl::None,
+        FlowControl::Hardware => serialport::FlowControl::Hardware,
+        FlowControl::Software => serialport::FlowControl::Software,
+    };
+    Ok((databits, parity, stopbits, flowcontrol))
+}
+
+fn serial_error(err: serialport::Error, context: &str) -> LibError {
+    match err.kind() {
+        serialport::ErrorKind::Io(kind) => LibError::Io(std::io::Error::new(
+            kind,
+            format!("{context}: {}", err.description),
+        )),
+        serialport::ErrorKind::NoDevice => LibError::Status(
+            Status::NoDevice,
+            Some(format!("{context}: {}", err.description)),
+        ),
+        serialport::ErrorKind::InvalidInput => {
+            LibError::InvalidArguments(format!("{context}: {}", err.description))
+        }
+        serialport::ErrorKind::Unknown => LibError::TransportIo {
+            transport: Transport::Serial,
+            reason: format!("{context}: {}", err.description),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(databits: u32, parity: Parity, stopbits: StopBits) -> SerialConfig {
+        SerialConfig {
+            baudrate: 9600,
+            databits,
+            parity,
+            stopbits,
+            flowcontrol: FlowControl::None,
+        }
+    }
+
+    #[test]
+    fn maps_line_settings() {
+        let (databits, parity, stopbits, flowcontrol) =
+            port_settings(&config(7, Parity::Even, StopBits::Two)).unwrap();
+        assert_eq!(databits, DataBits::Seven);
+        assert_eq!(parity, serialport::Parity::Even);
+        assert_eq!(stopbits, serialport::StopBits::Two);
+        assert_eq!(flowcontrol, serialport::FlowControl::None);
+    }
+
+    #[test]
+    fn rejects_settings_serialport_cannot_express() {
+        assert!(matches!(
+            port_settings(&config(8, Parity::Mark, StopBits::One)),
+            Err(LibError::Status(Status::Unsupported, _))
+        ));
+        assert!(matches!(
+            port_settings(&config(8, Parity::None, StopBits::OneAndHalf)),
+            Err(LibError::Status(Status::Unsupported, _))
+        ));
+        assert!(matches!(
+            port_settings(&config(9, Parity::None, StopBits::One)),
+            Err(LibError::InvalidArguments(_))
+        ));
+    }
+
+    #[test]
+    fn missing_port_fails_to_open() {
+        assert!(SerialPortTransport::open("/dev/does-not-exist-divecomputer").is_err());
+    }
+}
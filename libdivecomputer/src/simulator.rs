@@ -0,0 +1,252 @@
+use std::cell::RefCell;
+use std::time::Duration;
+
+use crate::context::Context;
+use crate::descriptor::Descriptor;
+use crate::device::{
+    DeviceEvent, DiveSource, DownloadOptions, DownloadResult, ForeachData, download_from,
+};
+use crate::error::{LibError, Result};
+use crate::parser::{Dive, Fingerprint, Parser};
+use crate::status::Status;
+
+/// A stand-in for a connected dive computer that serves canned dive records,
+/// for building UIs and running CI without hardware.
+///
+/// Records are parsed by the real libdivecomputer parser for `desc`, and
+/// [`download_dives`](Self::download_dives) takes the same
+/// [`DownloadOptions`] as [`Device::download_dives`](crate::Device::download_dives):
+/// fingerprints, checkpoints, limits, events and cancellation all behave as
+/// with a physical computer. Dives downloaded with
+/// [`DownloadOptions::keep_raw`] make good fixtures:
+///
+/// ```no_run
+/// # use libdivecomputer::{Context, Descriptor, DownloadOptions, Dive};
+/// # use libdivecomputer::simulator::SimulatedDevice;
+/// # fn demo(ctx: &Context, saved: &[Dive]) -> libdivecomputer::Result<()> {
+/// let desc = Descriptor::find_by_name("Shearwater Perdix")?;
+/// let device = SimulatedDevice::new(ctx, &desc)
+///     .with_dives(saved)
+///     .dive_delay(std::time::Duration::from_millis(200));
+/// let result = device.download_dives(DownloadOptions::default());
+/// # Ok(())
+/// # }
+/// ```
+pub struct SimulatedDevice<'a> {
+    ctx: &'a Context,
+    desc: &'a Descriptor,
+    serial: u32,
+    firmware: u32,
+    dives: Vec<(Fingerprint, Vec<u8>)>,
+    dive_delay: Duration,
+    fingerprint: RefCell<Option<Fingerprint>>,
+}
+
+impl<'a> SimulatedDevice<'a> {
+    /// A simulated `desc` computer holding no dives.
+    pub fn new(ctx: &'a Context, desc: &'a Descriptor) -> Self {
+        Self {
+            ctx,
+            desc,
+            serial: 1,
+            firmware: 0,
+            dives: Vec::new(),
+            dive_delay: Duration::ZERO,
+            fingerprint: RefCell::new(None),
+        }
+    }
+
+    /// Serial number and firmware word to report in
+    /// [`DeviceEvent::DevInfo`].
+    #[must_use]
+    pub fn identity(mut self, serial: u32, firmware: u32) -> Self {
+        self.serial = serial;
+        self.firmware = firmware;
+        self
+    }
+
+    /// Add a raw dive record. Add dives newest first, the order computers
+    /// hand them over in.
+    #[must_use]
+    pub fn dive(mut self, fingerprint: Fingerprint, data: Vec<u8>) -> Self {
+        self.dives.push((fingerprint, data));
+        self
+    }
+
+    /// Add dives downloaded earlier with [`DownloadOptions::keep_raw`].
+    /// Dives without a raw record are skipped.
+    #[must_use]
+    pub fn with_dives(mut self, dives: &[Dive]) -> Self {
+        self.dives.extend(
+            dives
+                .iter()
+                .filter(|dive| !dive.raw.is_empty())
+                .map(|dive| (dive.fingerprint.clone(), dive.raw.clone())),
+        );
+        self
+    }
+
+    /// Pause before each dive, to mimic a slow link. Defaults to none.
+    #[must_use]
+    pub fn dive_delay(mut self, delay: Duration) -> Self {
+        self.dive_delay = delay;
+        self
+    }
+
+    /// Download and parse the simulated dives. See
+    /// [`Device::download_dives`](crate::Device::download_dives).
+    #[must_use = "downloaded dives and errors should not be silently discarded"]
+    pub fn download_dives(&self, options: DownloadOptions<'_>) -> DownloadResult {
+        download_from(self, options)
+    }
+}
+
+impl DiveSource for SimulatedDevice<'_> {
+    fn vendor(&self) -> &str {
+        self.desc.vendor()
+    }
+
+    fn product(&self) -> &str {
+        self.desc.product()
+    }
+
+    fn set_fingerprint(&self, fingerprint: &Fingerprint) -> Result<()> {
+        *self.fingerprint.borrow_mut() = (!fingerprint.is_empty()).then(|| fingerprint.clone());
+        Ok(())
+    }
+
+    fn parser(&self, data: &[u8]) -> Result<Parser> {
+        Parser::from_descriptor(self.ctx, self.desc, data)
+    }
+
+    fn set_timeout(&self, _timeout: Duration) -> Result<()> {
+        Ok(())
+    }
+
+    fn run_foreach(&self, data: ForeachData<'_, '_, '_>) -> Result<()> {
+        let ForeachData {
+            dive_cb,
+            mut event_cb,
+            cancel_cb,
+        } = data;
+        let mut emit = |event| {
+            if let Some(cb) = event_cb.as_deref_mut() {
+                cb(event);
+            }
+        };
+
+        emit(DeviceEvent::DevInfo {
+            model: self.desc.model(),
+            firmware: self.firmware,
+            serial: self.serial,
+        });
+        // Read after DEVINFO: a fingerprint store sets it from there.
+        let since = self.fingerprint.borrow().clone();
+        let maximum = u32::try_from(self.dives.len()).unwrap_or(u32::MAX);
+        emit(DeviceEvent::Progress {
+            current: 0,
+            maximum,
+        });
+
+        for (current, (fingerprint, data)) in (1..).zip(&self.dives) {
+            if since.as_ref() == Some(fingerprint) {
+                break;
+            }
+            if cancel_cb.is_some_and(|cancel| cancel()) {
+                return Err(LibError::Status(
+                    Status::Cancelled,
+                    Some("simulated download cancelled".into()),
+                ));
+            }
+            if !self.dive_delay.is_zero() {
+                std::thread::sleep(self.dive_delay);
+            }
+            emit(DeviceEvent::Progress { current, maximum });
+            if !dive_cb(data, fingerprint) {
+                break;
+            }
+        }
+        emit(DeviceEvent::Progress {
+            current: maximum,
+            maximum,
+        });
+        Ok(())
+    }
+
+    fn dive_count(&self) -> Option<u32> {
+        let since = self.fingerprint.borrow();
+        let count = self
+            .dives
+            .iter()
+            .take_while(|(fingerprint, _)| since.as_ref() != Some(fingerprint))
+            .count();
+        u32::try_from(count).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::fingerprint_store::{FingerprintKey, FingerprintStore};
+
+    #[test]
+    fn simulated_download_honours_stored_fingerprint() {
+        let ctx = Context::new().unwrap();
+        let desc = Descriptor::find_by_name("Shearwater Perdix").unwrap();
+        let newest = Fingerprint::from(vec![2]);
+        let device = SimulatedDevice::new(&ctx, &desc)
+            .identity(1234, 0x5a)
+            .dive(newest.clone(), vec![0; 16])
+            .dive(Fingerprint::from(vec![1]), vec![0; 16]);
+
+        let mut store = HashMap::new();
+        let key = FingerprintKey {
+            vendor: desc.vendor().to_string(),
+            product: desc.product().to_string(),
+            serial: 1234,
+        };
+        store.store(&key, &newest).unwrap();
+        let mut events = Vec::new();
+        let result = device.download_dives(DownloadOptions {
+            on_event: Some(&mut |event| events.push(event)),
+            fingerprint_store: Some(&mut store),
+            ..Default::default()
+        });
+
+        assert!(result.is_ok());
+        assert!(result.dives.is_empty());
+        let identity = result.identity.unwrap();
+        assert_eq!((identity.serial, identity.firmware), (1234, 0x5a));
+        assert!(matches!(events[0], DeviceEvent::DevInfo { .. }));
+        assert!(matches!(
+            events.last(),
+            Some(DeviceEvent::Progress {
+                current: 2,
+                maximum: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn simulated_download_counts_dives() {
+        let ctx = Context::new().unwrap();
+        let desc = Descriptor::find_by_name("Shearwater Perdix").unwrap();
+        let device = SimulatedDevice::new(&ctx, &desc)
+            .dive(Fingerprint::from(vec![3]), vec![0; 16])
+            .dive(Fingerprint::from(vec![2]), vec![0; 16])
+            .dive(Fingerprint::from(vec![1]), vec![0; 16]);
+
+        let mut reports = Vec::new();
+        let _ = device.download_dives(DownloadOptions {
+            fingerprint: Some(&Fingerprint::from(vec![1])),
+            on_progress: Some(&mut |progress| reports.push(progress)),
+            ..Default::default()
+        });
+
+        let last = reports.last().unwrap();
+        assert_eq!((last.dives_done, last.dives_total), (2, Some(2)));
+        assert!(reports.iter().any(|p| p.dives_done == 1));
+    }
+}
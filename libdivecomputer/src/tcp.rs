@@ -0,0 +1,200 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::custom::{CustomTransport, read_full};
+use crate::error::{LibError, Result};
+use crate::iostream::Direction;
+
+/// How long to wait for the TCP handshake with the bridge.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Raw TCP byte stream to a serial-over-network bridge such as `ser2net` or a
+/// Raspberry Pi sitting next to the dive computer.
+///
+/// The bridge is expected to forward bytes verbatim (ser2net's `raw` mode),
+/// with the port's line settings configured on the bridge side; requests from
+/// the driver to change baud rate or flow control are accepted and ignored.
+#[derive(Debug)]
+pub struct TcpTransport {
+    stream: TcpStream,
+    timeout: Option<Duration>,
+    /// Bytes from a read that timed out, handed out first by the next one.
+    pending: Vec<u8>,
+}
+
+impl TcpTransport {
+    /// Connect to `host:port`, trying each resolved address in turn.
+    pub fn connect(host: &str, port: u16) -> Result<Self> {
+        let mut last_err = None;
+        for addr in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                Ok(stream) => return Self::from_stream(stream),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.map(LibError::from).unwrap_or_else(|| {
+            LibError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{host}:{port} did not resolve to any address"),
+            ))
+        }))
+    }
+
+    /// Wrap an already connected stream.
+    pub fn from_stream(stream: TcpStream) -> Result<Self> {
+        // Dive computer protocols are chatty request/response exchanges of a
+        // few bytes; Nagle would add a round trip of latency to each.
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            timeout: None,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Drop whatever the bridge has already delivered.
+    fn discard_input(&mut self) -> Result<()> {
+        self.pending.clear();
+        self.stream.set_nonblocking(true)?;
+        let mut scratch = [0u8; 256];
+        let result = loop {
+            match self.stream.read(&mut scratch) {
+                Ok(0) => break Ok(()),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => break Err(e.into()),
+            }
+        };
+        self.stream.set_nonblocking(false)?;
+        result
+    }
+}
+
+impl CustomTransport for TcpTransport {
+    /// Fill `buf` completely, like a serial read; a short read within the
+    /// timeout is reported as [`Status::Timeout`](crate::Status::Timeout).
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let stream = &mut self.stream;
+        read_full(&mut self.pending, buf, self.timeout, |buf, remaining| {
+            // A zero read timeout is rejected by std; 1 ms is the closest
+            // thing.
+            stream.set_read_timeout(remaining.map(|t| t.max(Duration::from_millis(1))))?;
+            match stream.read(buf)? {
+                0 => Err(std::io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "TCP bridge closed the connection",
+                )),
+                n => Ok(n),
+            }
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize> {
+        self.stream.write_all(data)?;
+        Ok(data.len())
+    }
+
+    fn poll(&mut self, timeout_ms: i32) -> Result<bool> {
+        if !self.pending.is_empty() {
+            return Ok(true);
+        }
+        // A zero read timeout is rejected by std; 1 ms is the closest thing.
+        let timeout = u64::try_from(timeout_ms)
+            .ok()
+            .map(|ms| Duration::from_millis(ms.max(1)));
+        self.stream.set_read_timeout(timeout)?;
+        match self.stream.peek(&mut [0u8; 1]) {
+            Ok(0) => Err(LibError::Io(std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "TCP bridge closed the connection",
+            ))),
+            Ok(_) => Ok(true),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set_timeout(&mut self, timeout_ms: i32) -> Result<()> {
+        self.timeout = u64::try_from(timeout_ms).ok().map(Duration::from_millis);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    fn purge(&mut self, direction: Direction) -> Result<()> {
+        match direction {
+            Direction::Input | Direction::All => self.discard_input(),
+            // Written bytes are already in the kernel's socket buffer.
+            Direction::Output => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::status::Status;
+
+    fn pair() -> (TcpTransport, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = TcpTransport::connect("127.0.0.1", port).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn round_trip_through_bridge() {
+        let (mut client, mut server) = pair();
+
+        assert_eq!(client.write(b"hello").unwrap(), 5);
+        let mut received = [0u8; 5];
+        server.read_exact(&mut received).unwrap();
+        assert_eq!(&received, b"hello");
+
+        server.write_all(b"world").unwrap();
+        assert!(client.poll(1000).unwrap());
+        let mut buf = [0u8; 5];
+        assert_eq!(client.read(&mut buf).unwrap(), 5);
+        assert_eq!(&buf, b"world");
+    }
+
+    #[test]
+    fn short_read_times_out() {
+        let (mut client, mut server) = pair();
+        client.set_timeout(50).unwrap();
+
+        server.write_all(b"ab").unwrap();
+        let mut buf = [0u8; 4];
+        assert!(matches!(
+            client.read(&mut buf),
+            Err(LibError::Status(Status::Timeout, _))
+        ));
+        assert!(client.poll(10).unwrap());
+
+        // The bytes that arrived before the timeout are not lost.
+        client.set_timeout(0).unwrap();
+        server.write_all(b"cd").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(client.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"abcd");
+        assert!(!client.poll(10).unwrap());
+    }
+
+    #[test]
+    fn purge_discards_pending_input() {
+        let (mut client, mut server) = pair();
+        server.write_all(b"stale").unwrap();
+        assert!(client.poll(1000).unwrap());
+
+        client.purge(Direction::Input).unwrap();
+        assert!(!client.poll(10).unwrap());
+    }
+}
@@ -79,17 +79,51 @@ impl FromStr for Transport {
     }
 }
 
-/// A set of transport flags, decoded from a C bitfield.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// A set of [`Transport`]s, stored as the C library's `DC_TRANSPORT_*`
+/// bitfield.
+///
+/// Copyable and compared without allocating; [`iter`](Self::iter) walks the
+/// members in enum declaration order. Combine sets and transports with `|`
+/// and intersect them with `&`. Serialized as a list of transports, e.g.
+/// `["Serial", "Ble"]`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "Vec<Transport>", into = "Vec<Transport>")]
 pub struct TransportSet {
     bits: u32,
 }
 
+impl Transport {
+    /// Every transport, in declaration order.
+    pub const ALL: [Self; 7] = [
+        Self::Serial,
+        Self::Usb,
+        Self::UsbHid,
+        Self::Irda,
+        Self::Bluetooth,
+        Self::Ble,
+        Self::UsbStorage,
+    ];
+}
+
 impl TransportSet {
-    /// Decode a bitfield from the C library into a `TransportSet`.
+    /// The set with no transports.
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self { bits: 0 }
+    }
+
+    /// The set of every [`Transport`].
+    #[must_use]
+    pub fn all() -> Self {
+        Transport::ALL.into_iter().collect()
+    }
+
+    /// Decode a bitfield from the C library into a `TransportSet`. Bits the
+    /// crate does not know are dropped, so sets compare by their members.
     #[must_use]
     pub fn from_bits(bits: u32) -> Self {
-        Self { bits }
+        let known = Transport::ALL.iter().fold(0, |acc, &t| acc | t as u32);
+        Self { bits: bits & known }
     }
 
     /// Check if a specific transport is present.
@@ -98,57 +132,174 @@ impl TransportSet {
         self.bits & (transport as u32) != 0
     }
 
+    /// Whether no known transport is present.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Number of known transports present.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Add `transport` to the set.
+    pub fn insert(&mut self, transport: Transport) {
+        self.bits |= transport as u32;
+    }
+
+    /// Remove `transport` from the set.
+    pub fn remove(&mut self, transport: Transport) {
+        self.bits &= !(transport as u32);
+    }
+
+    /// The transports present, in enum declaration order.
+    pub fn iter(&self) -> TransportIter {
+        TransportIter {
+            set: *self,
+            next: 0,
+        }
+    }
+
     /// Expand the bitfield into a vector of transports, in enum declaration
     /// order.
     #[must_use]
     pub fn to_vec(&self) -> Vec<Transport> {
-        let all = [
-            Transport::Serial,
-            Transport::Usb,
-            Transport::UsbHid,
-            Transport::Irda,
-            Transport::Bluetooth,
-            Transport::Ble,
-            Transport::UsbStorage,
-        ];
-        all.iter().filter(|t| self.contains(**t)).copied().collect()
-    }
-
-    /// Raw underlying bitfield value as returned by the C library.
+        self.iter().collect()
+    }
+
+    /// The set as a `DC_TRANSPORT_*` bitfield for the C library.
     #[must_use]
     pub fn bits(&self) -> u32 {
         self.bits
     }
 }
 
+/// Iterator over a [`TransportSet`], from [`TransportSet::iter`].
+#[derive(Debug, Clone)]
+pub struct TransportIter {
+    set: TransportSet,
+    next: usize,
+}
+
+impl Iterator for TransportIter {
+    type Item = Transport;
+
+    fn next(&mut self) -> Option<Transport> {
+        while let Some(&transport) = Transport::ALL.get(self.next) {
+            self.next += 1;
+            if self.set.contains(transport) {
+                return Some(transport);
+            }
+        }
+        None
+    }
+}
+
 impl From<u32> for TransportSet {
     fn from(bits: u32) -> Self {
         Self::from_bits(bits)
     }
 }
 
+impl From<Transport> for TransportSet {
+    fn from(transport: Transport) -> Self {
+        Self::from_bits(transport as u32)
+    }
+}
+
+impl From<Vec<Transport>> for TransportSet {
+    fn from(transports: Vec<Transport>) -> Self {
+        transports.into_iter().collect()
+    }
+}
+
+impl From<TransportSet> for Vec<Transport> {
+    fn from(set: TransportSet) -> Self {
+        set.to_vec()
+    }
+}
+
+impl FromIterator<Transport> for TransportSet {
+    fn from_iter<I: IntoIterator<Item = Transport>>(iter: I) -> Self {
+        let mut set = Self::empty();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Transport> for TransportSet {
+    fn extend<I: IntoIterator<Item = Transport>>(&mut self, iter: I) {
+        for transport in iter {
+            self.insert(transport);
+        }
+    }
+}
+
+impl<T: Into<TransportSet>> std::ops::BitOr<T> for TransportSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: T) -> Self {
+        Self::from_bits(self.bits | rhs.into().bits)
+    }
+}
+
+impl<T: Into<TransportSet>> std::ops::BitOrAssign<T> for TransportSet {
+    fn bitor_assign(&mut self, rhs: T) {
+        self.bits |= rhs.into().bits;
+    }
+}
+
+impl<T: Into<TransportSet>> std::ops::BitAnd<T> for TransportSet {
+    type Output = Self;
+
+    fn bitand(self, rhs: T) -> Self {
+        Self::from_bits(self.bits & rhs.into().bits)
+    }
+}
+
+impl std::ops::BitOr for Transport {
+    type Output = TransportSet;
+
+    fn bitor(self, rhs: Self) -> TransportSet {
+        TransportSet::from(self) | rhs
+    }
+}
+
 impl IntoIterator for TransportSet {
     type Item = Transport;
-    type IntoIter = std::vec::IntoIter<Transport>;
+    type IntoIter = TransportIter;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.to_vec().into_iter()
+        self.iter()
     }
 }
 
 impl IntoIterator for &TransportSet {
     type Item = Transport;
-    type IntoIter = std::vec::IntoIter<Transport>;
+    type IntoIter = TransportIter;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.to_vec().into_iter()
+        self.iter()
+    }
+}
+
+impl fmt::Debug for TransportSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
     }
 }
 
 impl fmt::Display for TransportSet {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let names: Vec<String> = self.to_vec().iter().map(|t| t.to_string()).collect();
-        write!(f, "{}", names.join(", "))
+        for (i, transport) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{transport}")?;
+        }
+        Ok(())
     }
 }
 
@@ -199,16 +350,7 @@ mod tests {
 
     #[test]
     fn display_round_trip() {
-        let all = [
-            Transport::Serial,
-            Transport::Usb,
-            Transport::UsbHid,
-            Transport::Irda,
-            Transport::Bluetooth,
-            Transport::Ble,
-            Transport::UsbStorage,
-        ];
-        for t in all {
+        for t in Transport::ALL {
             let s = t.to_string();
             let parsed: Transport = s.parse().unwrap();
             assert_eq!(parsed, t);
@@ -263,4 +405,35 @@ mod tests {
         assert!(set.contains(Transport::Usb));
         assert_eq!(set.bits(), Transport::Usb as u32);
     }
+
+    #[test]
+    fn transport_set_operations() {
+        let mut set = Transport::Serial | Transport::Ble;
+        assert_eq!(set.len(), 2);
+        set |= Transport::Usb;
+        set.remove(Transport::Serial);
+        assert_eq!(set.to_vec(), vec![Transport::Usb, Transport::Ble]);
+        assert_eq!(set & Transport::Ble, TransportSet::from(Transport::Ble));
+        assert!((set & Transport::Irda).is_empty());
+        assert_eq!(TransportSet::all().len(), Transport::ALL.len());
+        assert!(TransportSet::from_bits(1 << 31).is_empty());
+        assert_eq!(
+            TransportSet::from_bits(Transport::Usb as u32 | 1 << 31),
+            TransportSet::from(Transport::Usb)
+        );
+        assert_eq!(format!("{set:?}"), "{Usb, Ble}");
+        let collected: TransportSet = [Transport::Ble, Transport::Usb].into_iter().collect();
+        assert_eq!(collected, set);
+    }
+
+    #[test]
+    fn transport_set_serde_as_list() {
+        use serde::de::IntoDeserializer;
+        use serde::de::value::{Error, SeqDeserializer};
+
+        let names = ["Serial", "Ble"].into_iter().map(|n| n.into_deserializer());
+        let set = TransportSet::deserialize(SeqDeserializer::<_, Error>::new(names)).unwrap();
+        assert_eq!(set, Transport::Serial | Transport::Ble);
+        assert_eq!(Vec::from(set), vec![Transport::Serial, Transport::Ble]);
+    }
 }
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use roxmltree::{Document, Node};
+
+use crate::error::{LibError, Result};
+use crate::parser::{Dive, DiveSample, Gasmix, Tank, TankKind};
+
+/// Kelvin of 0 °C; UDDF stores temperatures in kelvin.
+const ZERO_CELSIUS: f64 = 273.15;
+/// Pascal per bar; UDDF stores pressures in pascal.
+const PASCAL_PER_BAR: f64 = 100_000.0;
+
+/// Read the dives of a UDDF (Universal Dive Data Format) document, as
+/// written by most desktop logbooks, into [`Dive`]s.
+///
+/// Reads each `<dive>`'s start time, depths, duration and temperatures, its
+/// gas mixes and tanks, and the waypoint profile (depth, temperature, tank
+/// pressures and gas switches). Values are converted from UDDF's SI units to
+/// the metres, bar and °C used throughout this crate. UDDF carries no
+/// libdivecomputer fingerprint, so [`Dive::fingerprint`] is left empty.
+///
+/// Start times with a UTC offset keep it in [`Dive::utc_offset`], so
+/// [`Dive::start_local`] shows the time the diver saw. Start times without
+/// one are taken as UTC, like
+/// [`TimezonePolicy::Utc`](crate::parser::TimezonePolicy::Utc).
+pub fn parse(xml: &str) -> Result<Vec<Dive>> {
+    let doc = Document::parse(xml).map_err(|e| LibError::ParseError(format!("UDDF: {e}")))?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "uddf" {
+        return Err(LibError::ParseError(format!(
+            "UDDF: unexpected root element <{}>",
+            root.tag_name().name()
+        )));
+    }
+
+    let mut mixes = HashMap::new();
+    for mix in root.descendants().filter(|n| n.has_tag_name("mix")) {
+        if let Some(id) = mix.attribute("id") {
+            let helium = number(mix, "he").unwrap_or(0.0);
+            let oxygen = number(mix, "o2").unwrap_or(0.21);
+            let gasmix = Gasmix {
+                helium,
+                oxygen,
+                nitrogen: number(mix, "n2").unwrap_or(1.0 - oxygen - helium),
+                ..Default::default()
+            };
+            mixes.insert(id, gasmix);
+        }
+    }
+
+    root.descendants()
+        .filter(|n| {
+            n.has_tag_name("dive")
+                && n.parent_element()
+                    .is_some_and(|p| p.has_tag_name("repetitiongroup"))
+        })
+        .map(|dive| parse_dive(dive, &mixes))
+        .collect()
+}
+
+/// Read the UDDF file at `path`. See [`parse`].
+pub fn read_file(path: impl AsRef<Path>) -> Result<Vec<Dive>> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+fn parse_dive(node: Node<'_, '_>, mixes: &HashMap<&str, Gasmix>) -> Result<Dive> {
+    let mut dive = Dive::default();
+    let before = child(node, "informationbeforedive");
+    let after = child(node, "informationafterdive");
+
+    if let Some(datetime) = before.and_then(|n| text(n, "datetime")) {
+        let (start, utc_offset) = parse_datetime(datetime)?;
+        dive.start = start;
+        dive.utc_offset = utc_offset;
+    }
+
+    // Gas mixes in the order the dive first references them.
+    let mut mix_ids: Vec<&str> = Vec::new();
+    let mut mix_idx = |id: &str, dive: &mut Dive| -> Option<usize> {
+        let gasmix = mixes.get(id)?;
+        Some(match mix_ids.iter().position(|known| *known == id) {
+            Some(idx) => idx,
+            None => {
+                mix_ids.push(mixes.get_key_value(id)?.0);
+                dive.gasmixes.push(*gasmix);
+                dive.gasmixes.len() - 1
+            }
+        })
+    };
+
+    let mut tank_ids = HashMap::new();
+    for (idx, tankdata) in node
+        .children()
+        .filter(|n| n.has_tag_name("tankdata"))
+        .enumerate()
+    {
+        if let Some(id) = tankdata.attribute("id") {
+            tank_ids.insert(id, idx);
+        }
+        let gasmix_idx = child(tankdata, "link")
+            .and_then(|link| link.attribute("ref"))
+            .and_then(|id| mix_idx(id, &mut dive));
+        let volume = number(tankdata, "tankvolume").map(|m3| m3 * 1000.0);
+        dive.tanks.push(Tank {
+            gasmix_idx,
+            kind: if volume.is_some() {
+                TankKind::Metric
+            } else {
+                TankKind::None
+            },
+            volume: volume.unwrap_or(0.0),
+            begin_pressure: number(tankdata, "tankpressurebegin").map_or(0.0, bar),
+            end_pressure: number(tankdata, "tankpressureend").map_or(0.0, bar),
+            ..Default::default()
+        });
+    }
+
+    let waypoints = child(node, "samples")
+        .into_iter()
+        .flat_map(|samples| samples.children().filter(|n| n.has_tag_name("waypoint")));
+    for waypoint in waypoints {
+        let mut sample = DiveSample {
+            time: seconds(number(waypoint, "divetime").unwrap_or(0.0)),
+            depth: number(waypoint, "depth").unwrap_or(0.0),
+            temperature: number(waypoint, "temperature").map(celsius),
+            ..Default::default()
+        };
+        if let Some(id) = child(waypoint, "switchmix").and_then(|n| n.attribute("ref")) {
+            sample.gasmix = mix_idx(id, &mut dive).map(|idx| dive.gasmixes[idx]);
+        }
+        for pressure in waypoint
+            .children()
+            .filter(|n| n.has_tag_name("tankpressure"))
+        {
+            let Some(value) = pressure.text().and_then(|t| t.trim().parse::<f64>().ok()) else {
+                continue;
+            };
+            let idx = pressure
+                .attribute("ref")
+                .and_then(|id| tank_ids.get(id).copied())
+                .unwrap_or(0);
+            if sample.pressure.len() <= idx {
+                sample.pressure.resize(idx + 1, 0.0);
+            }
+            sample.pressure[idx] = bar(value);
+        }
+        if let Some(cns) = number(waypoint, "cns") {
+            sample.cns = cns;
+        }
+        dive.samples.push(sample);
+    }
+
+    dive.max_depth = after
+        .and_then(|n| number(n, "greatestdepth"))
+        .unwrap_or_else(|| dive.samples.iter().map(|s| s.depth).fold(0.0, f64::max));
+    dive.avg_depth = after.and_then(|n| number(n, "averagedepth"));
+    dive.duration = after
+        .and_then(|n| number(n, "diveduration"))
+        .map(seconds)
+        .or_else(|| dive.samples.last().map(|s| s.time))
+        .unwrap_or_default();
+    dive.temperature_minimum = after
+        .and_then(|n| number(n, "lowesttemperature"))
+        .map(celsius);
+    dive.temperature_surface = before
+        .and_then(|n| number(n, "airtemperature"))
+        .map(celsius);
+    Ok(dive)
+}
+
+/// The start time and, when `value` carries a numeric one, its UTC offset
+/// in seconds. `Z` says the local time is unknown (RFC 9557), so it is taken
+/// as UTC like a missing offset.
+fn parse_datetime(value: &str) -> Result<(jiff::Timestamp, Option<i32>)> {
+    use jiff::fmt::temporal::{Pieces, PiecesOffset};
+    use jiff::tz::{Offset, TimeZone};
+
+    let pieces = Pieces::parse(value)?;
+    let civil = pieces.date().to_datetime(pieces.time().unwrap_or_default());
+    let offset = match pieces.offset() {
+        Some(PiecesOffset::Numeric(numeric)) => Some(numeric.offset()),
+        _ => None,
+    };
+    let tz = TimeZone::fixed(offset.unwrap_or(Offset::UTC));
+    Ok((civil.to_zoned(tz)?.timestamp(), offset.map(Offset::seconds)))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)?.text().map(str::trim)
+}
+
+fn number(node: Node<'_, '_>, name: &str) -> Option<f64> {
+    text(node, name)?.parse().ok()
+}
+
+fn seconds(value: f64) -> Duration {
+    Duration::try_from_secs_f64(value).unwrap_or_default()
+}
+
+fn celsius(kelvin: f64) -> f64 {
+    kelvin - ZERO_CELSIUS
+}
+
+fn bar(pascal: f64) -> f64 {
+    pascal / PASCAL_PER_BAR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<uddf version="3.2.0">
+  <gasdefinitions>
+    <mix id="ean32"><name>EAN32</name><o2>0.32</o2><he>0.0</he></mix>
+  </gasdefinitions>
+  <profiledata>
+    <repetitiongroup id="rg1">
+      <dive id="d1">
+        <informationbeforedive><datetime>2025-06-01T10:30:00</datetime></informationbeforedive>
+        <tankdata id="t1">
+          <link ref="ean32"/>
+          <tankvolume>0.012</tankvolume>
+          <tankpressurebegin>20000000</tankpressurebegin>
+          <tankpressureend>5000000</tankpressureend>
+        </tankdata>
+        <samples>
+          <waypoint><divetime>0</divetime><depth>0</depth><switchmix ref="ean32"/></waypoint>
+          <waypoint>
+            <divetime>60</divetime><depth>18.5</depth><temperature>293.15</temperature>
+            <tankpressure ref="t1">19000000</tankpressure>
+          </waypoint>
+        </samples>
+        <informationafterdive>
+          <greatestdepth>18.5</greatestdepth><diveduration>2400</diveduration>
+        </informationafterdive>
+      </dive>
+    </repetitiongroup>
+  </profiledata>
+</uddf>"#;
+
+    #[test]
+    fn parse_uddf_keeps_utc_offset() {
+        let xml = SAMPLE.replace("2025-06-01T10:30:00<", "2025-06-01T10:30:00+02:00<");
+        let dive = &parse(&xml).unwrap()[0];
+        assert_eq!(dive.start.to_string(), "2025-06-01T08:30:00Z");
+        assert_eq!(dive.utc_offset, Some(7200));
+        assert_eq!(dive.start_local().time(), jiff::civil::time(10, 30, 0, 0));
+    }
+
+    #[test]
+    fn parse_uddf_dive() {
+        let dives = parse(SAMPLE).unwrap();
+        assert_eq!(dives.len(), 1);
+        let dive = &dives[0];
+        assert_eq!(dive.start.to_string(), "2025-06-01T10:30:00Z");
+        assert_eq!(dive.utc_offset, None);
+        assert_eq!(dive.duration, Duration::from_secs(2400));
+        assert_eq!(dive.max_depth, 18.5);
+        assert_eq!(dive.gasmixes.len(), 1);
+        assert!((dive.gasmixes[0].oxygen - 0.32).abs() < 1e-9);
+
+        let tank = &dive.tanks[0];
+        assert_eq!(tank.gasmix_idx, Some(0));
+        assert!((tank.volume - 12.0).abs() < 1e-9);
+        assert_eq!((tank.begin_pressure, tank.end_pressure), (200.0, 50.0));
+
+        assert_eq!(dive.samples.len(), 2);
+        assert!(dive.samples[0].gasmix.is_some());
+        let sample = &dive.samples[1];
+        assert_eq!(sample.time, Duration::from_secs(60));
+        assert!((sample.temperature.unwrap() - 20.0).abs() < 1e-9);
+        assert_eq!(sample.pressure, vec![190.0]);
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(parse("<logbook/>").is_err());
+        assert!(parse("not xml").is_err());
+    }
+}
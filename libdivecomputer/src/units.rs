@@ -0,0 +1,177 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+const METERS_PER_FOOT: f64 = 0.3048;
+const BAR_PER_PSI: f64 = 0.068_947_572_931_683_6;
+
+/// Which units to present measurements in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UnitSystem {
+    /// Metres, bar and °C.
+    #[default]
+    Metric,
+    /// Feet, psi and °F.
+    Imperial,
+}
+
+/// A depth, stored in metres like the rest of this crate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Depth(f64);
+
+impl Depth {
+    /// A depth of `meters` metres.
+    #[must_use]
+    pub const fn from_meters(meters: f64) -> Self {
+        Self(meters)
+    }
+
+    /// A depth of `feet` feet.
+    #[must_use]
+    pub fn from_feet(feet: f64) -> Self {
+        Self(feet * METERS_PER_FOOT)
+    }
+
+    /// In metres.
+    #[must_use]
+    pub const fn meters(self) -> f64 {
+        self.0
+    }
+
+    /// In feet.
+    #[must_use]
+    pub fn feet(self) -> f64 {
+        self.0 / METERS_PER_FOOT
+    }
+
+    /// In metres or feet.
+    #[must_use]
+    pub fn value_in(self, units: UnitSystem) -> f64 {
+        match units {
+            UnitSystem::Metric => self.meters(),
+            UnitSystem::Imperial => self.feet(),
+        }
+    }
+}
+
+impl fmt::Display for Depth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)?;
+        f.write_str(" m")
+    }
+}
+
+/// A pressure (tank, partial or atmospheric), stored in bar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Pressure(f64);
+
+impl Pressure {
+    /// A pressure of `bar` bar.
+    #[must_use]
+    pub const fn from_bar(bar: f64) -> Self {
+        Self(bar)
+    }
+
+    /// A pressure of `psi` pounds per square inch.
+    #[must_use]
+    pub fn from_psi(psi: f64) -> Self {
+        Self(psi * BAR_PER_PSI)
+    }
+
+    /// In bar.
+    #[must_use]
+    pub const fn bar(self) -> f64 {
+        self.0
+    }
+
+    /// In pounds per square inch.
+    #[must_use]
+    pub fn psi(self) -> f64 {
+        self.0 / BAR_PER_PSI
+    }
+
+    /// In bar or psi.
+    #[must_use]
+    pub fn value_in(self, units: UnitSystem) -> f64 {
+        match units {
+            UnitSystem::Metric => self.bar(),
+            UnitSystem::Imperial => self.psi(),
+        }
+    }
+}
+
+impl fmt::Display for Pressure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)?;
+        f.write_str(" bar")
+    }
+}
+
+/// A temperature, stored in degrees Celsius.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Temperature(f64);
+
+impl Temperature {
+    /// A temperature of `celsius` °C.
+    #[must_use]
+    pub const fn from_celsius(celsius: f64) -> Self {
+        Self(celsius)
+    }
+
+    /// A temperature of `fahrenheit` °F.
+    #[must_use]
+    pub fn from_fahrenheit(fahrenheit: f64) -> Self {
+        Self((fahrenheit - 32.0) * 5.0 / 9.0)
+    }
+
+    /// In °C.
+    #[must_use]
+    pub const fn celsius(self) -> f64 {
+        self.0
+    }
+
+    /// In °F.
+    #[must_use]
+    pub fn fahrenheit(self) -> f64 {
+        self.0 * 9.0 / 5.0 + 32.0
+    }
+
+    /// In °C or °F.
+    #[must_use]
+    pub fn value_in(self, units: UnitSystem) -> f64 {
+        match units {
+            UnitSystem::Metric => self.celsius(),
+            UnitSystem::Imperial => self.fahrenheit(),
+        }
+    }
+}
+
+impl fmt::Display for Temperature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)?;
+        f.write_str(" °C")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn conversions() {
+        assert!(close(Depth::from_meters(30.48).feet(), 100.0));
+        assert!(close(Depth::from_feet(100.0).meters(), 30.48));
+        assert!(close(Pressure::from_bar(200.0).psi(), 2_900.754_754_6));
+        assert!(close(Pressure::from_psi(3000.0).bar(), 206.842_718_8));
+        assert!(close(Temperature::from_celsius(20.0).fahrenheit(), 68.0));
+        assert!(close(Temperature::from_fahrenheit(50.0).celsius(), 10.0));
+        assert_eq!(format!("{:.1}", Depth::from_meters(18.25)), "18.2 m");
+    }
+}
@@ -0,0 +1,387 @@
+use std::ffi::{c_int, c_uint, c_void};
+use std::io::ErrorKind;
+use std::os::fd::RawFd;
+
+use crate::context::Context;
+use crate::custom::CustomTransport;
+use crate::error::{LibError, Result};
+use crate::iostream::IoStream;
+use crate::status::Status;
+use crate::transport::Transport;
+
+/// `struct usbdevfs_bulktransfer` from `<linux/usbdevice_fs.h>`.
+#[repr(C)]
+struct BulkTransfer {
+    ep: c_uint,
+    len: c_uint,
+    timeout: c_uint,
+    data: *mut c_void,
+}
+
+/// `struct usbdevfs_ctrltransfer` from `<linux/usbdevice_fs.h>`.
+#[repr(C)]
+struct CtrlTransfer {
+    request_type: u8,
+    request: u8,
+    value: u16,
+    index: u16,
+    length: u16,
+    timeout: u32,
+    data: *mut c_void,
+}
+
+/// `struct usbdevfs_ioctl` from `<linux/usbdevice_fs.h>`.
+#[repr(C)]
+struct InterfaceIoctl {
+    interface: c_int,
+    code: c_int,
+    data: *mut c_void,
+}
+
+/// `_IOC(dir, 'U', nr, size)`, the way the kernel header builds the usbfs
+/// request numbers.
+const fn usbdevfs_ioc(dir: u64, nr: u64, size: usize) -> u64 {
+    (dir << 30) | ((size as u64) << 16) | ((b'U' as u64) << 8) | nr
+}
+
+const IOC_READ: u64 = 2;
+const IOC_WRITE: u64 = 1;
+const USBDEVFS_CONTROL: u64 =
+    usbdevfs_ioc(IOC_READ | IOC_WRITE, 0, std::mem::size_of::<CtrlTransfer>());
+const USBDEVFS_BULK: u64 =
+    usbdevfs_ioc(IOC_READ | IOC_WRITE, 2, std::mem::size_of::<BulkTransfer>());
+const USBDEVFS_IOCTL: u64 = usbdevfs_ioc(
+    IOC_READ | IOC_WRITE,
+    18,
+    std::mem::size_of::<InterfaceIoctl>(),
+);
+const USBDEVFS_DISCONNECT: u64 = usbdevfs_ioc(0, 22, 0);
+const USBDEVFS_CLAIMINTERFACE: u64 = usbdevfs_ioc(IOC_READ, 15, std::mem::size_of::<c_uint>());
+const USBDEVFS_RELEASEINTERFACE: u64 = usbdevfs_ioc(IOC_READ, 16, std::mem::size_of::<c_uint>());
+
+const DESCRIPTOR_INTERFACE: u8 = 4;
+const DESCRIPTOR_ENDPOINT: u8 = 5;
+const ENDPOINT_DIR_IN: u8 = 0x80;
+const TRANSFER_TYPE_MASK: u8 = 0x03;
+const TRANSFER_TYPE_BULK: u8 = 2;
+const TRANSFER_TYPE_INTERRUPT: u8 = 3;
+
+/// Timeout for control transfers, which devices answer right away.
+const CONTROL_TIMEOUT_MS: u32 = 1000;
+
+/// Interface and endpoints a [`UsbFdTransport`] talks through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsbEndpoints {
+    /// `bInterfaceNumber` of the interface to claim.
+    pub interface: u8,
+    /// Address of the bulk or interrupt IN endpoint, high bit set.
+    pub endpoint_in: u8,
+    /// Address of the bulk or interrupt OUT endpoint.
+    pub endpoint_out: u8,
+    /// `wMaxPacketSize` of the IN endpoint.
+    pub max_packet_size: u16,
+}
+
+impl UsbEndpoints {
+    /// The first interface with both an IN and an OUT bulk or interrupt
+    /// endpoint, from raw device and configuration descriptors as read from
+    /// a usbfs file descriptor. Covers USB HID computers and the bulk pipes
+    /// of plain USB ones.
+    pub fn find(descriptors: &[u8]) -> Option<Self> {
+        // Interface number, IN endpoint with its packet size, OUT endpoint.
+        type Candidate = (u8, Option<(u8, u16)>, Option<u8>);
+        let mut current: Option<Candidate> = None;
+        let mut rest = descriptors;
+        while let [len, kind, ..] = *rest {
+            let len = usize::from(len);
+            if len < 2 || len > rest.len() {
+                break;
+            }
+            let descriptor = &rest[..len];
+            match kind {
+                DESCRIPTOR_INTERFACE if len >= 3 => {
+                    current = Some((descriptor[2], None, None));
+                }
+                DESCRIPTOR_ENDPOINT if len >= 6 => {
+                    let transfer = descriptor[3] & TRANSFER_TYPE_MASK;
+                    if let Some((_, ep_in, ep_out)) = current.as_mut()
+                        && matches!(transfer, TRANSFER_TYPE_BULK | TRANSFER_TYPE_INTERRUPT)
+                    {
+                        let address = descriptor[2];
+                        let max_packet_size = u16::from_le_bytes([descriptor[4], descriptor[5]]);
+                        if address & ENDPOINT_DIR_IN != 0 {
+                            ep_in.get_or_insert((address, max_packet_size));
+                        } else {
+                            ep_out.get_or_insert(address);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            if let Some((interface, Some((endpoint_in, max_packet_size)), Some(endpoint_out))) =
+                current
+            {
+                return Some(Self {
+                    interface,
+                    endpoint_in,
+                    endpoint_out,
+                    max_packet_size,
+                });
+            }
+            rest = &rest[len..];
+        }
+        None
+    }
+}
+
+/// A USB device driven through a usbfs file descriptor, for Android, where
+/// apps can't enumerate or open USB devices themselves.
+///
+/// The app asks `UsbManager` for permission, opens the device, and passes
+/// `UsbDeviceConnection.getFileDescriptor()` here over JNI. Each
+/// [`read`](CustomTransport::read) and [`write`](CustomTransport::write) is
+/// one transfer, as with the C library's USB and USB HID backends. The
+/// descriptor stays owned by the Java connection, which must stay open until
+/// the transport is dropped.
+#[derive(Debug)]
+pub struct UsbFdTransport {
+    fd: RawFd,
+    endpoints: UsbEndpoints,
+    /// Per-transfer timeout in milliseconds; 0 waits forever.
+    timeout_ms: c_uint,
+}
+
+impl UsbFdTransport {
+    /// Claim the first interface with IN and OUT endpoints (see
+    /// [`UsbEndpoints::find`]) on the device behind `fd`.
+    pub fn new(fd: RawFd) -> Result<Self> {
+        let mut descriptors = vec![0u8; 4096];
+        let n = unsafe { libc::pread(fd, descriptors.as_mut_ptr().cast(), descriptors.len(), 0) };
+        let n = usize::try_from(n).map_err(|_| std::io::Error::last_os_error())?;
+        let endpoints =
+            UsbEndpoints::find(&descriptors[..n]).ok_or_else(|| LibError::ConnectFailed {
+                transport: Transport::Usb,
+                reason: "no interface with IN and OUT endpoints".into(),
+            })?;
+        Self::with_endpoints(fd, endpoints)
+    }
+
+    /// Claim `endpoints.interface` on the device behind `fd` and talk
+    /// through the given endpoints. A kernel driver bound to the interface,
+    /// such as Linux's `ftdi_sio`, is detached first.
+    pub fn with_endpoints(fd: RawFd, endpoints: UsbEndpoints) -> Result<Self> {
+        let mut interface = c_uint::from(endpoints.interface);
+        if let Err(e) = usbfs_ioctl(fd, USBDEVFS_CLAIMINTERFACE, &mut interface) {
+            if !matches!(&e, LibError::Io(io) if io.raw_os_error() == Some(libc::EBUSY)) {
+                return Err(e);
+            }
+            let mut request = InterfaceIoctl {
+                interface: c_int::from(endpoints.interface),
+                code: USBDEVFS_DISCONNECT as c_int,
+                data: std::ptr::null_mut(),
+            };
+            usbfs_ioctl(fd, USBDEVFS_IOCTL, &mut request)?;
+            usbfs_ioctl(fd, USBDEVFS_CLAIMINTERFACE, &mut interface)?;
+        }
+        Ok(Self {
+            fd,
+            endpoints,
+            timeout_ms: 0,
+        })
+    }
+
+    /// The interface and endpoints in use.
+    pub fn endpoints(&self) -> UsbEndpoints {
+        self.endpoints
+    }
+
+    /// A control transfer on the default endpoint; the direction bit of
+    /// `request_type` decides whether `data` is sent or filled in. Returns
+    /// the number of bytes transferred.
+    pub fn control(
+        &mut self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &mut [u8],
+    ) -> Result<usize> {
+        let length = u16::try_from(data.len()).map_err(|_| {
+            LibError::InvalidArguments(format!("{} byte control transfer is too large", data.len()))
+        })?;
+        let mut transfer = CtrlTransfer {
+            request_type,
+            request,
+            value,
+            index,
+            length,
+            timeout: CONTROL_TIMEOUT_MS,
+            data: data.as_mut_ptr().cast(),
+        };
+        usbfs_ioctl(self.fd, USBDEVFS_CONTROL, &mut transfer)
+    }
+
+    fn transfer(&mut self, endpoint: u8, data: *mut c_void, len: usize) -> Result<usize> {
+        let len = c_uint::try_from(len)
+            .map_err(|_| LibError::InvalidArguments(format!("{len} byte transfer is too large")))?;
+        let mut request = BulkTransfer {
+            ep: c_uint::from(endpoint),
+            len,
+            timeout: self.timeout_ms,
+            data,
+        };
+        usbfs_ioctl(self.fd, USBDEVFS_BULK, &mut request)
+    }
+}
+
+impl CustomTransport for UsbFdTransport {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.transfer(
+            self.endpoints.endpoint_in,
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+        )
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize> {
+        // OUT transfers only read from `data`; the kernel API just isn't
+        // const-correct.
+        self.transfer(
+            self.endpoints.endpoint_out,
+            data.as_ptr().cast_mut().cast(),
+            data.len(),
+        )
+    }
+
+    fn set_timeout(&mut self, timeout_ms: i32) -> Result<()> {
+        // usbfs has no non-blocking transfer; 1 ms is the closest thing.
+        self.timeout_ms = match timeout_ms {
+            ..0 => 0,
+            0 => 1,
+            ms => ms.unsigned_abs(),
+        };
+        Ok(())
+    }
+}
+
+impl Drop for UsbFdTransport {
+    fn drop(&mut self) {
+        let mut interface = c_uint::from(self.endpoints.interface);
+        // The device may already be gone; nothing to do about it here.
+        let _ = usbfs_ioctl(self.fd, USBDEVFS_RELEASEINTERFACE, &mut interface);
+    }
+}
+
+/// Issue a usbfs request, mapping a timeout to [`Status::Timeout`].
+fn usbfs_ioctl<T>(fd: RawFd, request: u64, arg: &mut T) -> Result<usize> {
+    let rc = unsafe { libc::ioctl(fd, request as _, std::ptr::from_mut(arg)) };
+    match usize::try_from(rc) {
+        Ok(n) => Ok(n),
+        Err(_) => {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == ErrorKind::TimedOut {
+                Err(LibError::Status(Status::Timeout, None))
+            } else {
+                Err(err.into())
+            }
+        }
+    }
+}
+
+/// Open the usbfs node (`/dev/bus/usb/BBB/DDD`) of the `index`-th attached
+/// device with these IDs, found through sysfs and counted in bus and device
+/// number order.
+#[cfg(target_os = "linux")]
+pub(crate) fn open_device(vendor_id: u16, product_id: u16, index: usize) -> Result<std::fs::File> {
+    let attribute = |dir: &std::path::Path, name: &str| {
+        std::fs::read_to_string(dir.join(name))
+            .ok()
+            .map(|value| value.trim().to_string())
+    };
+    let mut nodes = Vec::new();
+    for entry in std::fs::read_dir("/sys/bus/usb/devices")? {
+        let dir = entry?.path();
+        let ids = (attribute(&dir, "idVendor"), attribute(&dir, "idProduct"));
+        let (Some(vid), Some(pid)) = ids else {
+            continue;
+        };
+        if u16::from_str_radix(&vid, 16) != Ok(vendor_id)
+            || u16::from_str_radix(&pid, 16) != Ok(product_id)
+        {
+            continue;
+        }
+        let number = |name| attribute(&dir, name).and_then(|n| n.parse::<u16>().ok());
+        if let (Some(bus), Some(device)) = (number("busnum"), number("devnum")) {
+            nodes.push((bus, device));
+        }
+    }
+    nodes.sort_unstable();
+    let (bus, device) = *nodes.get(index).ok_or_else(|| LibError::ConnectFailed {
+        transport: Transport::Usb,
+        reason: format!(
+            "no USB device {vendor_id:04X}:{product_id:04X} #{}",
+            index + 1
+        ),
+    })?;
+    Ok(std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(format!("/dev/bus/usb/{bus:03}/{device:03}"))?)
+}
+
+impl IoStream {
+    /// Open a USB or USB HID dive computer from a usbfs file descriptor,
+    /// e.g. one from Android's `UsbDeviceConnection`. See
+    /// [`UsbFdTransport`]; `transport` is [`Transport::Usb`] or
+    /// [`Transport::UsbHid`], whichever the computer's descriptor lists.
+    #[must_use = "the opened IoStream must be passed to Device::open"]
+    pub fn usb_fd(ctx: &Context, transport: Transport, fd: RawFd) -> Result<Self> {
+        Self::custom(ctx, transport, UsbFdTransport::new(fd)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_numbers_match_kernel_header() {
+        assert_eq!(USBDEVFS_CLAIMINTERFACE, 0x8004_550f);
+        assert_eq!(USBDEVFS_RELEASEINTERFACE, 0x8004_5510);
+        assert_eq!(USBDEVFS_DISCONNECT, 0x5516);
+        #[cfg(target_pointer_width = "64")]
+        {
+            assert_eq!(USBDEVFS_CONTROL, 0xc018_5500);
+            assert_eq!(USBDEVFS_BULK, 0xc018_5502);
+            assert_eq!(USBDEVFS_IOCTL, 0xc010_5512);
+        }
+    }
+
+    #[test]
+    fn finds_hid_interrupt_endpoints() {
+        #[rustfmt::skip]
+        let descriptors = [
+            // Device.
+            18, 1, 0x00, 0x02, 0, 0, 0, 64, 0x93, 0x14, 0x30, 0x00, 0, 1, 1, 2, 3, 1,
+            // Configuration.
+            9, 2, 41, 0, 1, 1, 0, 0x80, 50,
+            // Interface 0, HID, two endpoints.
+            9, 4, 0, 0, 2, 3, 0, 0, 0,
+            // HID class descriptor.
+            9, 0x21, 0x11, 0x01, 0, 1, 0x22, 34, 0,
+            // Interrupt IN 0x81, interrupt OUT 0x02.
+            7, 5, 0x81, 3, 64, 0, 1,
+            7, 5, 0x02, 3, 64, 0, 1,
+        ];
+        assert_eq!(
+            UsbEndpoints::find(&descriptors),
+            Some(UsbEndpoints {
+                interface: 0,
+                endpoint_in: 0x81,
+                endpoint_out: 0x02,
+                max_packet_size: 64,
+            })
+        );
+        assert_eq!(UsbEndpoints::find(&descriptors[..52]), None);
+    }
+}
@@ -1,6 +1,10 @@
 use std::ffi::CStr;
+use std::fmt;
 
-use libdivecomputer_sys::dc_version;
+use libdivecomputer_sys::{dc_version, dc_version_t};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{LibError, Result};
 
 /// Returns the libdivecomputer version.
 pub fn version() -> String {
@@ -12,3 +16,86 @@ pub fn version() -> String {
         CStr::from_ptr(res).to_string_lossy().into_owned()
     }
 }
+
+/// Numeric version of the linked libdivecomputer.
+///
+/// The crate can be built against newer upstream releases, forks, or a
+/// system library (the `system` feature), so APIs and devices added upstream
+/// are not always there. Compare against the release that introduced them
+/// before relying on them:
+///
+/// ```no_run
+/// # use libdivecomputer::LibraryVersion;
+/// if LibraryVersion::current().at_least(0, 9, 0) {
+///     // use something added in 0.9
+/// }
+/// ```
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub struct LibraryVersion {
+    /// Major version.
+    pub major: u32,
+    /// Minor version.
+    pub minor: u32,
+    /// Micro (patch) version.
+    pub micro: u32,
+}
+
+impl LibraryVersion {
+    /// A version from its parts.
+    pub const fn new(major: u32, minor: u32, micro: u32) -> Self {
+        Self {
+            major,
+            minor,
+            micro,
+        }
+    }
+
+    /// The version of the linked C library.
+    pub fn current() -> Self {
+        let mut v = dc_version_t::default();
+        unsafe { dc_version(&mut v) };
+        Self::new(v.major, v.minor, v.micro)
+    }
+
+    /// Whether this version is `major.minor.micro` or newer.
+    pub fn at_least(&self, major: u32, minor: u32, micro: u32) -> bool {
+        *self >= Self::new(major, minor, micro)
+    }
+
+    /// Fail with [`LibError::InvalidVersion`] unless the linked C library is
+    /// `minimum` or newer.
+    pub fn require(minimum: LibraryVersion) -> Result<()> {
+        let found = Self::current();
+        if found >= minimum {
+            Ok(())
+        } else {
+            Err(LibError::InvalidVersion {
+                expected: format!(">= {minimum}"),
+                found: found.to_string(),
+            })
+        }
+    }
+}
+
+impl fmt::Display for LibraryVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.micro)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn library_version_orders_by_part() {
+        let v = LibraryVersion::new(0, 9, 2);
+        assert!(v.at_least(0, 9, 0));
+        assert!(v.at_least(0, 9, 2));
+        assert!(!v.at_least(0, 10, 0));
+        assert!(!v.at_least(1, 0, 0));
+        assert_eq!(v.to_string(), "0.9.2");
+    }
+}
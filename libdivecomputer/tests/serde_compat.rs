@@ -0,0 +1,127 @@
+//! Dives serialized by earlier releases must keep deserializing.
+//!
+//! `fixtures/dive-vN.json` is the same dive as written by the release whose
+//! [`Dive::SCHEMA_VERSION`] was `N` (`v0` predates versioning). Never edit
+//! a fixture: when the serialized form changes, bump `SCHEMA_VERSION`, add
+//! the new fixture and its entry in `FIXTURES`.
+
+use std::time::Duration;
+
+use libdivecomputer::{
+    DecoModel, Dive, DiveMode, DiveSample, DurationFormat, Fingerprint, WithDurations,
+};
+
+const FIXTURES: &[(u32, &str)] = &[
+    (0, include_str!("fixtures/dive-v0.json")),
+    (1, include_str!("fixtures/dive-v1.json")),
+];
+
+#[test]
+fn every_fixture_deserializes() {
+    for &(version, json) in FIXTURES {
+        let dive: Dive = serde_json::from_str(json)
+            .unwrap_or_else(|e| panic!("dive-v{version}.json no longer loads: {e}"));
+        assert_eq!(dive.schema_version, version);
+        assert_eq!(dive.fingerprint.to_string(), "DEADBEEF");
+        assert_eq!(dive.start.to_string(), "2024-06-01T09:30:00Z");
+        assert_eq!(dive.duration, Duration::from_secs(2400));
+        assert_eq!(dive.max_depth, 18.5);
+        assert_eq!(dive.dive_mode, DiveMode::OC);
+        assert!(matches!(
+            dive.deco_model,
+            DecoModel::Buhlmann {
+                low: 30,
+                high: 85,
+                ..
+            }
+        ));
+        assert_eq!(dive.tanks[0].begin_pressure, 210.0);
+        assert_eq!(dive.samples.len(), 2);
+        assert_eq!(dive.samples[1].time, Duration::from_secs(20));
+        assert_eq!(dive.samples[1].events[0].name.as_deref(), Some("ascent"));
+        assert_eq!(dive.metadata["Serial"], "12345");
+    }
+}
+
+#[test]
+fn current_version_has_a_fixture() {
+    let &(version, json) = FIXTURES.last().unwrap();
+    assert_eq!(
+        version,
+        Dive::SCHEMA_VERSION,
+        "add a fixture for the new schema version"
+    );
+
+    // Catches changes to the serialized form that forgot to bump the
+    // version.
+    let dive: Dive = serde_json::from_str(json).unwrap();
+    let written = serde_json::to_value(&dive).unwrap();
+    let fixture: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(written, fixture);
+}
+
+#[test]
+fn fingerprint_is_a_hex_string() {
+    let fingerprint: Fingerprint = "DEADBEEF".parse().unwrap();
+    let json = serde_json::to_string(&fingerprint).unwrap();
+    assert_eq!(json, r#""DEADBEEF""#);
+    assert_eq!(
+        serde_json::from_str::<Fingerprint>(&json).unwrap(),
+        fingerprint
+    );
+    assert_eq!(
+        serde_json::from_str::<Fingerprint>(r#"{"data": [222, 173, 190, 239]}"#).unwrap(),
+        fingerprint
+    );
+    assert!(serde_json::from_str::<Fingerprint>(r#""XYZ""#).is_err());
+}
+
+#[test]
+fn missing_fields_take_defaults() {
+    let dive: Dive =
+        serde_json::from_str(r#"{"max_depth": 12.0, "samples": [{"depth": 3.0}]}"#).unwrap();
+    assert_eq!(dive.schema_version, 0);
+    assert_eq!(dive.max_depth, 12.0);
+    assert_eq!(dive.samples[0].depth, 3.0);
+    assert!(dive.samples[0].temperature.is_none());
+}
+
+#[test]
+fn durations_round_trip() {
+    let dive = Dive {
+        duration: Duration::from_secs(2400),
+        samples: (0..1000)
+            .map(|i| DiveSample {
+                time: Duration::from_millis(i * 1001),
+                tts: Some(Duration::from_millis(i)),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let seconds = serde_json::to_value(&dive).unwrap();
+    assert_eq!(seconds["duration"], 2400);
+    assert_eq!(seconds["samples"][1]["time"], 1.001);
+    let iso = serde_json::to_value(WithDurations(&dive, DurationFormat::Iso8601)).unwrap();
+    assert_eq!(iso["duration"], "PT40M");
+    assert_eq!(iso["samples"][1]["time"], "PT1.001S");
+    // The format only lasts for the wrapped value.
+    assert_eq!(serde_json::to_value(&dive).unwrap(), seconds);
+
+    for json in [seconds, iso] {
+        let back: Dive = serde_json::from_value(json).unwrap();
+        for (a, b) in dive.samples.iter().zip(&back.samples) {
+            assert_eq!((a.time, a.tts), (b.time, b.tts));
+        }
+    }
+}
+
+#[test]
+fn durations_read_every_form() {
+    for form in [r#"2400"#, r#"2400.0"#, r#""PT40M""#, r#""40m""#] {
+        let json = format!(r#"{{"duration": {form}}}"#);
+        let dive: Dive = serde_json::from_str(&json).unwrap();
+        assert_eq!(dive.duration, Duration::from_secs(2400), "{form}");
+    }
+    assert!(serde_json::from_str::<Dive>(r#"{"duration": -1}"#).is_err());
+}